@@ -0,0 +1,110 @@
+// Reader for Android's combined tzdata file.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::path::{Path, PathBuf};
+
+// Android doesn't have a zoneinfo directory. Instead every zone's TZif data is concatenated into one `tzdata`
+// file, which starts with this header (all numbers are big endian i32s):
+//
+//   char tzdata_version[12];  // "tzdata2023c\0"
+//   i32  index_offset;        // Where the index starts
+//   i32  data_offset;         // Where the TZif data starts (and the index ends)
+//   i32  final_offset;        // Where the zone.tab data starts (unused here)
+//
+// The index is a list of 52 byte entries, sorted by name:
+//
+//   char name[40];            // "America/Los_Angeles\0..."
+//   i32  start;               // Relative to data_offset
+//   i32  length;
+//   i32  unused;              // Was the raw GMT offset
+const HEADER_LEN: usize = 24;
+const ENTRY_LEN: usize = 52;
+const NAME_LEN: usize = 40;
+
+/// Where Android keeps its tzdata file, in the order bionic checks them: timezone updates first, then the
+/// tzdata APEX module, then the copy in the system image.
+pub(crate) fn tzdata_paths() -> Vec<PathBuf> {
+    let env = |var: &str, default: &str| std::env::var_os(var).map_or_else(|| PathBuf::from(default), PathBuf::from);
+    vec![env("ANDROID_DATA", "/data").join("misc/zoneinfo/current/tzdata"),
+         env("ANDROID_TZDATA_ROOT", "/apex/com.android.tzdata").join("etc/tz/tzdata"),
+         env("ANDROID_ROOT", "/system").join("usr/share/zoneinfo/tzdata")]
+}
+
+/// Find the first tzdata file that has the zone `name`.
+pub(crate) fn find(name: &str) -> Option<PathBuf> {
+    find_in(&tzdata_paths(), name)
+}
+
+pub(crate) fn find_in(paths: &[PathBuf], name: &str) -> Option<PathBuf> {
+    paths.iter().find(|path| std::fs::read(path).is_ok_and(|data| lookup(&data, name).is_ok_and(|zone| zone.is_some()))).cloned()
+}
+
+/// Read the TZif data for the zone `name` out of the tzdata file at `path`.
+pub(crate) fn read(path: &Path, name: &str) -> Result<Vec<u8>, String> {
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    trace_event!(DEBUG, path = %path.display(), bytes = data.len(), zone = name, "loaded android tzdata");
+    lookup(&data, name).map_err(|e| format!("{}: {}", path.display(), e))?
+                       .map(<[u8]>::to_vec)
+                       .ok_or_else(|| format!("{}: no zone named {}", path.display(), name))
+}
+
+fn lookup<'a>(data: &'a [u8], name: &str) -> Result<Option<&'a [u8]>, String> {
+    if data.len() < HEADER_LEN || !data.starts_with(b"tzdata") {
+        return Err("not an Android tzdata file".to_string());
+    }
+    let offset = |at: usize| usize::try_from(i32::from_be_bytes(data[at..at + 4].try_into().unwrap())).map_err(|_| "negative offset".to_string());
+    let (index, zones) = (offset(12)?, offset(16)?);
+    let entries = data.get(index..zones).ok_or("index out of range")?;
+    for entry in entries.chunks_exact(ENTRY_LEN) {
+        let entry_name = entry[..NAME_LEN].split(|c| *c == 0).next().unwrap();
+        if entry_name != name.as_bytes() {
+            continue;
+        }
+        let field = |at: usize| usize::try_from(i32::from_be_bytes(entry[at..at + 4].try_into().unwrap())).map_err(|_| format!("{}: negative offset", name));
+        let start = zones + field(NAME_LEN)?;
+        return Ok(Some(data.get(start..start + field(NAME_LEN + 4)?).ok_or_else(|| format!("{}: data out of range", name))?));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Build a tzdata file like Android's out of some of the system's zones.
+    pub(crate) fn build(zones: &[&str]) -> Vec<u8> {
+        let mut zones: Vec<(&str, Vec<u8>)> = zones.iter().map(|name| (*name, std::fs::read(Path::new("/usr/share/zoneinfo").join(name)).expect("read"))).collect();
+        zones.sort();
+        let data_offset = HEADER_LEN + zones.len() * ENTRY_LEN;
+        let mut out = b"tzdata2023c\0".to_vec();
+        for n in [HEADER_LEN, data_offset, data_offset + zones.iter().map(|z| z.1.len()).sum::<usize>()] {
+            out.extend((n as i32).to_be_bytes());
+        }
+        let mut start = 0;
+        for (name, data) in &zones {
+            let mut entry = name.as_bytes().to_vec();
+            entry.resize(NAME_LEN, 0);
+            out.extend(entry);
+            for n in [start, data.len(), 0] {
+                out.extend((n as i32).to_be_bytes());
+            }
+            start += data.len();
+        }
+        zones.iter().for_each(|(_, data)| out.extend(data));
+        out
+    }
+
+    #[test]
+    fn tzdata() {
+        let path = std::env::temp_dir().join(format!("libtz-android-test-{}", std::process::id()));
+        std::fs::write(&path, build(&["Europe/London", "Asia/Tokyo", "America/Los_Angeles"])).expect("write");
+        assert_eq!(read(&path, "Asia/Tokyo"), Ok(std::fs::read("/usr/share/zoneinfo/Asia/Tokyo").expect("read")));
+        assert!(read(&path, "Asia/Toky").is_err());
+        assert_eq!(find_in(&[PathBuf::from("/nonexistent"), path.clone()], "Europe/London"), Some(path.clone()));
+        assert_eq!(find_in(std::slice::from_ref(&path), "Europe/Paris"), None);
+        std::fs::remove_file(&path).expect("remove");
+        assert!(lookup(b"TZif2", "UTC").is_err());
+    }
+}
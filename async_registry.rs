@@ -0,0 +1,184 @@
+// Loading zones from asynchronous sources.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use crate::{Timezone, ZoneRegistry};
+
+/// What an [`AsyncZoneResolver`] returns: the contents of a TZif file, or `None` if it doesn't know the zone.
+pub type ZoneFuture<'a> = Pin<Box<dyn Future<Output = Result<Option<Vec<u8>>, String>> + Send + 'a>>;
+
+/// The `async` version of [`ZoneResolver`](crate::ZoneResolver), for zone data that comes over the network, so
+/// fetching it doesn't block the runtime's threads.
+///
+/// Closures taking the zone's name as a `String` and returning a future are resolvers.
+pub trait AsyncZoneResolver: Send + Sync {
+    /// Fetch the TZif data for the zone `name`, or `None` if it's unknown (so it's looked up the usual way).
+    fn resolve<'a>(&'a self, name: &'a str) -> ZoneFuture<'a>;
+}
+
+impl<F, Fut> AsyncZoneResolver for F where F: Fn(String) -> Fut + Send + Sync,
+                                           Fut: Future<Output = Result<Option<Vec<u8>>, String>> + Send + 'static {
+    fn resolve<'a>(&'a self, name: &'a str) -> ZoneFuture<'a> {
+        Box::pin(self(name.to_string()))
+    }
+}
+
+/// A [`ZoneRegistry`] that fetches zones it doesn't have with an [`AsyncZoneResolver`]. Only loading is
+/// `async`: the zones it hands out are ordinary [`Timezone`]s, so conversions stay synchronous. It doesn't depend
+/// on any particular runtime (it works with tokio, async-std or anything else), but with the `tokio` feature the
+/// blocking parts of loading a zone run on tokio's blocking thread pool (see [`AsyncZoneRegistry::get_zone`]).
+///
+/// The registry's lock isn't held while the resolver runs, so if several tasks ask for the same new zone at once
+/// they may each fetch it; the first one loaded is kept and the others get that one.
+///
+/// ```
+/// use libtz::{AsyncZoneRegistry, ZoneRegistry};
+/// async fn fetch(name: String) -> Result<Option<Vec<u8>>, String> {
+///     // Ask a config service. This just reads a file under a different name.
+///     Ok((name == "Office/Main").then(|| std::fs::read("/usr/share/zoneinfo/Europe/Oslo").unwrap()))
+/// }
+/// async fn handle_request(zones: &AsyncZoneRegistry) -> String {
+///     let tz = zones.get_zone("Office/Main").await.unwrap();
+///     tz.localtime(1700000000).unwrap().strftime("%H:%M %Z")
+/// }
+/// let zones = AsyncZoneRegistry::new(ZoneRegistry::new(100), fetch);
+/// # #[cfg(feature = "tokio")]
+/// # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+/// #     tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(future)
+/// # }
+/// # #[cfg(not(feature = "tokio"))]
+/// # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+/// #     let mut future = std::pin::pin!(future);
+/// #     let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+/// #     loop { if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) { return output } }
+/// # }
+/// assert_eq!(block_on(handle_request(&zones)), "23:13 CET"); // Or however your runtime runs it
+/// ```
+pub struct AsyncZoneRegistry {
+    registry: Arc<ZoneRegistry>,
+    resolver: Box<dyn AsyncZoneResolver>,
+}
+
+impl AsyncZoneRegistry {
+    /// Cache zones in `registry` (with its capacity, time to live and so on), fetching new ones with `resolver`.
+    /// Names the resolver doesn't know are loaded by the registry's own loader (see [`AsyncZoneRegistry::get_zone`]).
+    pub fn new(registry: ZoneRegistry, resolver: impl AsyncZoneResolver + 'static) -> AsyncZoneRegistry {
+        AsyncZoneRegistry{ registry: Arc::new(registry), resolver: Box::new(resolver) }
+    }
+
+    /// The zone `name`, fetching it if it isn't in the registry (or has expired). Errors aren't cached.
+    ///
+    /// If the resolver returns `None` the zone is loaded by the registry's own loader (the zoneinfo directory or
+    /// its [`ZoneResolver`](crate::ZoneResolver)). Loading the resolver's data blocks too: with the C backend,
+    /// [`Timezone::from_tzif`] writes it to a temporary file for libtz to read. Without the `tokio` feature both
+    /// happen right here and block the task, which is a small local file read or write and usually fine. With
+    /// it they run on tokio's blocking thread pool instead, so this has to be called from within a tokio runtime.
+    pub async fn get_zone(&self, name: &str) -> Result<Arc<Timezone>, String> {
+        if let Some(found) = self.registry.get_cached(name) {
+            return found;
+        }
+        let owned = name.to_string();
+        match self.resolver.resolve(name).await? {
+            Some(data) => {
+                trace_event!(DEBUG, zone = name, bytes = data.len(), "zone from async resolver");
+                let tz = blocking(move || Timezone::from_tzif(&owned, &data)).await?.map_err(|e| format!("{}: {}", name, e))?;
+                self.registry.put(name, Arc::new(tz))
+            },
+            None => {
+                let registry = self.registry.clone();
+                blocking(move || registry.get(&owned)).await?
+            },
+        }
+    }
+
+    /// The underlying registry, for zones that are already loaded or don't need the resolver.
+    pub fn registry(&self) -> &ZoneRegistry {
+        &self.registry
+    }
+}
+
+// Run `f`, which may block, on tokio's blocking thread pool (with the `tokio` feature) or right here.
+#[cfg(feature = "tokio")]
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> Result<T, String> {
+    tokio::task::spawn_blocking(f).await.map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "tokio"))]
+async fn blocking<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    Ok(f())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll};
+
+    #[cfg(feature = "tokio")]
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().build().expect("runtime").block_on(future)
+    }
+
+    // Just enough of an executor to run a future to completion.
+    #[cfg(not(feature = "tokio"))]
+    fn block_on<F: Future>(future: F) -> F::Output {
+        use std::task::{Wake, Waker};
+        struct Unpark(std::thread::Thread);
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) { self.0.unpark() }
+        }
+        let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+        let mut future = std::pin::pin!(future);
+        loop {
+            match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                Poll::Ready(output) => return output,
+                Poll::Pending       => std::thread::park(),
+            }
+        }
+    }
+
+    // Pending the first time it's polled, like a network request.
+    struct YieldOnce(bool);
+    impl Future for YieldOnce {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 { return Poll::Ready(()) }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn async_registry() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let count = fetches.clone();
+        let zones = AsyncZoneRegistry::new(ZoneRegistry::new(10), move |name: String| {
+            count.fetch_add(1, Ordering::Relaxed);
+            async move {
+                YieldOnce(false).await;
+                match name.as_str() {
+                    "Remote/Tokyo" => Ok(Some(std::fs::read("/usr/share/zoneinfo/Asia/Tokyo").map_err(|e| e.to_string())?)),
+                    "Remote/Down"  => Err("service unavailable".to_string()),
+                    "Remote/Bad"   => Ok(Some(b"not tzif".to_vec())),
+                    _              => Ok(None),
+                }
+            }
+        });
+        let tokyo = block_on(zones.get_zone("Remote/Tokyo")).expect("get_zone");
+        assert_eq!(tokyo.localtime(0).map(|tm| tm.tm_hour), Ok(9));
+        assert!(Arc::ptr_eq(&tokyo, &block_on(zones.get_zone("Remote/Tokyo")).expect("get_zone")));
+        assert_eq!(fetches.load(Ordering::Relaxed), 1); // The second came from the registry
+        assert!(Arc::ptr_eq(&tokyo, &zones.registry().get("Remote/Tokyo").expect("get")));
+
+        // Unknown to the resolver, so loaded from the filesystem.
+        assert_eq!(block_on(zones.get_zone("Europe/Paris")).map(|tz| tz.provenance().name().to_string()), Ok("Europe/Paris".to_string()));
+        assert_eq!(block_on(zones.get_zone("Remote/Down")).err(), Some("service unavailable".to_string()));
+        assert!(block_on(zones.get_zone("Remote/Bad")).is_err());
+        assert_eq!(zones.registry().len(), 2);
+    }
+}
@@ -0,0 +1,104 @@
+// Command line zone queries.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use libtz::{Timezone, TimeT, Clock, SystemClock, OffsetFormat, format_offset};
+
+const USAGE: &str = "\
+Usage:
+  tz convert <time> [--zone <zone>]       Show a system time (seconds since the epoch) as local time
+  tz transitions <zone> [--year <year>]   List a zone's transitions in a year (default: this year)
+  tz list [--country <code>]              List zones, optionally just those for an ISO 3166 country code
+  tz codegen <zone>...                    Print Rust source with the zones as static tables (see libtz::StaticZone)
+  tz install-tzdata <release> <dir>       Download a tzdata release (like 2024a, or latest) and install it in <dir>";
+
+// Positional arguments and `--name value` options.
+type Args = (Vec<String>, Vec<(String, String)>);
+
+fn parse_args(args: &[String], options: &[&str]) -> Result<Args, String> {
+    let (mut positional, mut named) = (vec![], vec![]);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.strip_prefix("--") {
+            Some(name) if options.contains(&name) => named.push((name.to_string(), args.next().ok_or(format!("--{} needs a value", name))?.clone())),
+            Some(name) => return Err(format!("Unknown option --{}", name)),
+            None       => positional.push(arg.clone()),
+        }
+    }
+    Ok((positional, named))
+}
+
+fn option<'a>(named: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    named.iter().rev().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let Some(command) = args.first() else { return Err(USAGE.to_string()) };
+    match command.as_str() {
+        "convert" => {
+            let (positional, named) = parse_args(&args[1..], &["zone"])?;
+            let [time] = &positional[..] else { return Err(USAGE.to_string()) };
+            let time: TimeT = time.parse().map_err(|_| format!("Invalid time {:?}", time))?;
+            let tz = match option(&named, "zone") { Some(zone) => Timezone::new(zone)?, None => Timezone::from_env()? };
+            let tm = tz.localtime(time)?;
+            println!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} {} ({}){}", tm.tm_year as i64 + 1900, tm.tm_mon + 1, tm.tm_mday,
+                     tm.tm_hour, tm.tm_min, tm.tm_sec, tm.tm_zone, format_offset(tm.tm_gmtoff, OffsetFormat::Basic), if tm.tm_isdst > 0 { " DST" } else { "" });
+        },
+        "transitions" => {
+            let (positional, named) = parse_args(&args[1..], &["year"])?;
+            let [zone] = &positional[..] else { return Err(USAGE.to_string()) };
+            let tz = Timezone::new(zone)?;
+            let year = match option(&named, "year") {
+                Some(year) => year.parse::<i32>().map_err(|_| format!("Invalid year {:?}", year))?,
+                None => libtz::gmtime(SystemClock.now())?.tm_year + 1900,
+            };
+            let start = libtz::Tm{ tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 1, tm_mon: 0, tm_year: year - 1900, tm_wday: 0, tm_yday: 0,
+                                   tm_isdst: 0, tm_gmtoff: 0, tm_zone: "".into() };
+            let end = libtz::Tm{ tm_year: year - 1900 + 1, ..start.clone() };
+            // Pad by a day either side so transitions near New Year's in far off zones aren't missed.
+            let range = libtz::timegm(&start)? - 86400..libtz::timegm(&end)? + 86400;
+            let dump = tz.dump(range)?;
+            let entries = dump.entries.into_iter().filter(|e| e.local.ends_with(&year.to_string())).collect();
+            print!("{}", libtz::ZoneDump{ entries, ..dump });
+        },
+        "list" => {
+            let (positional, named) = parse_args(&args[1..], &["country"])?;
+            if !positional.is_empty() {
+                return Err(USAGE.to_string());
+            }
+            let country = option(&named, "country").map(str::to_ascii_uppercase);
+            let mut zones: Vec<String> = libtz::zone_tab()?.into_iter()
+                .filter(|e| country.as_ref().is_none_or(|c| e.countries.contains(c)))
+                .map(|e| e.zone).collect();
+            zones.sort();
+            for zone in zones {
+                println!("{}", zone);
+            }
+        },
+        "codegen" => {
+            let (positional, _) = parse_args(&args[1..], &[])?;
+            if positional.is_empty() {
+                return Err(USAGE.to_string());
+            }
+            print!("{}", libtz::generate_static_zones(&positional.iter().map(String::as_str).collect::<Vec<_>>())?);
+        },
+        "install-tzdata" => {
+            let (positional, _) = parse_args(&args[1..], &[])?;
+            let [release, dir] = &positional[..] else { return Err(USAGE.to_string()) };
+            let zones = libtz::download_and_install_tzdata(release, dir.as_ref())?;
+            println!("Installed {} zones in {}", zones, dir);
+        },
+        "-h" | "--help" | "help" => println!("{}", USAGE),
+        _ => return Err(USAGE.to_string()),
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&args) {
+        eprintln!("tz: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,79 @@
+// How the time conversion code was built.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use crate::TimeT;
+use crate::timezone::{TZDIR, TZDEFAULT};
+
+/// Which implementation does the conversions. See the crate's `c-backend` and `rust-backend` features.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// IANA's C code, via libtz-sys.
+    C,
+    /// The pure Rust implementation.
+    Rust,
+}
+
+/// How the conversion code was built, from [`build_info`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// This crate's version.
+    pub version: &'static str,
+    /// Which implementation does the conversions.
+    pub backend: Backend,
+    /// The width of `time_t` in bits. libtz-sys always builds libtz with a 64 bit `time_t`, whatever the
+    /// system's is.
+    pub time_t_bits: u32,
+    /// Whether the `NETBSD_INSPIRED` functions (`tzalloc()`, `localtime_rz()`, `mktime_z()`) are there, so
+    /// zones can be used side by side without touching `TZ`.
+    pub netbsd_inspired: bool,
+    /// Whether the `STD_INSPIRED` functions (`time2posix_z()`, `posix2time_z()`) are there, for leap second
+    /// conversions.
+    pub std_inspired: bool,
+    /// Whether `struct tm` has `tm_gmtoff` and `tm_zone`, so local times carry their offset and abbreviation.
+    pub tm_gmtoff: bool,
+    /// Whether the code locks its shared state, so it can be called from several threads at once.
+    pub thread_safe: bool,
+    /// The directory relative zone names are looked up in (before any added with
+    /// [`set_zone_dirs`](crate::set_zone_dirs)).
+    pub tzdir: &'static str,
+    /// The file loaded when no zone is given and `TZ` isn't set.
+    pub tzdefault: &'static str,
+}
+
+/// How the underlying time conversion code was built, for bug reports and for checking for features at run
+/// time.
+///
+/// ```
+/// let info = libtz::build_info();
+/// assert_eq!(info.time_t_bits, 64);
+/// println!("{:?} backend, zones from {}", info.backend, info.tzdir);
+/// ```
+pub fn build_info() -> BuildInfo {
+    BuildInfo{
+        version: env!("CARGO_PKG_VERSION"),
+        backend: if cfg!(feature = "rust-backend") { Backend::Rust } else { Backend::C },
+        time_t_bits: TimeT::BITS,
+        // These match the defines in libtz-sys's build.rs, which the Rust backend implements too.
+        netbsd_inspired: true,
+        std_inspired: true,
+        tm_gmtoff: true,
+        thread_safe: true,
+        tzdir: TZDIR,
+        tzdefault: TZDEFAULT,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_info() {
+        let info = super::build_info();
+        assert_eq!(info.backend, if cfg!(feature = "rust-backend") { Backend::Rust } else { Backend::C });
+        assert_eq!((info.time_t_bits, info.tzdir, info.tzdefault), (64, "/usr/share/zoneinfo", "/etc/localtime"));
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    }
+}
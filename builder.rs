@@ -0,0 +1,194 @@
+// Configurable zone loading.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use crate::{Timezone, Provenance, ZoneResolver};
+
+/// What [`TimezoneBuilder::build`] does with a name that isn't a zone file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Fallback {
+    /// Parse it as a POSIX TZ rule string (like `EST5EDT,M3.2.0,M11.1.0`), failing if it isn't one. This is what
+    /// libtz (and [`Timezone::new`]) does.
+    #[default]
+    PosixRule,
+    /// Fail. Only names of zone files are accepted.
+    Error,
+    /// Use UTC instead.
+    Utc,
+}
+
+/// Options for loading a [`Timezone`]. Create one with [`Timezone::builder`].
+///
+/// ```
+/// use libtz::{Timezone, Fallback, Provenance};
+/// let tz = Timezone::builder().leap_seconds(true).fallback(Fallback::Error).build("Europe/Lisbon").expect("build");
+/// assert_eq!(tz.provenance(), &Provenance::File("/usr/share/zoneinfo/right/Europe/Lisbon".into()));
+/// assert!(Timezone::builder().fallback(Fallback::Error).build("EST5EDT,M3.2.0,M11.1.0").is_err());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TimezoneBuilder {
+    tzdir: Option<PathBuf>,
+    leap_seconds: Option<bool>,
+    fallback: Fallback,
+    preload: bool,
+    resolver: Option<Arc<dyn ZoneResolver>>,
+}
+
+impl Timezone {
+    /// Start building a [`Timezone`] with non-default options.
+    pub fn builder() -> TimezoneBuilder {
+        TimezoneBuilder::default()
+    }
+}
+
+impl TimezoneBuilder {
+    /// Look up relative zone names in `dir` instead of `/usr/share/zoneinfo` (see [`Timezone::new_in`]).
+    pub fn tzdir(mut self, dir: impl Into<PathBuf>) -> TimezoneBuilder {
+        self.tzdir = Some(dir.into());
+        self
+    }
+
+    /// Choose between the `right/` variant of a zone, which counts leap seconds, and the plain (POSIX) variant,
+    /// which doesn't. A `right/` or `posix/` prefix on the name is replaced. By default the name is used as is.
+    pub fn leap_seconds(mut self, leap_seconds: bool) -> TimezoneBuilder {
+        self.leap_seconds = Some(leap_seconds);
+        self
+    }
+
+    /// Choose what happens to names that aren't zone files. The default is [`Fallback::PosixRule`].
+    pub fn fallback(mut self, fallback: Fallback) -> TimezoneBuilder {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Load the zone's compiled data ([`Timezone::zone_info`]) while building instead of the first time it's
+    /// needed, so problems with it show up right away and later calls never go back to the filesystem.
+    pub fn preload(mut self, preload: bool) -> TimezoneBuilder {
+        self.preload = preload;
+        self
+    }
+
+    /// Ask `resolver` for zones' data before looking for their files. Names it doesn't know (and the empty
+    /// string, absolute paths and names starting with `:`) are looked up as usual.
+    pub fn resolver(mut self, resolver: impl ZoneResolver + 'static) -> TimezoneBuilder {
+        self.resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// Load the zone `name`.
+    pub fn build(&self, name: &str) -> Result<Timezone, String> {
+        let name = match self.leap_seconds {
+            Some(leap_seconds) if !name.is_empty() && !name.starts_with('/') && !name.starts_with(':') => {
+                let zone = name.strip_prefix("right/").or_else(|| name.strip_prefix("posix/")).unwrap_or(name);
+                if leap_seconds { format!("right/{}", zone) } else { zone.to_string() }
+            },
+            _ => name.to_string(),
+        };
+        if let Some(ref resolver) = self.resolver {
+            if !name.is_empty() && !name.starts_with('/') && !name.starts_with(':') {
+                if let Some(data) = resolver.resolve(&name)? {
+                    trace_event!(DEBUG, zone = %name, bytes = data.len(), "zone from resolver");
+                    return Timezone::from_tzif(&name, &data).map_err(|e| format!("{}: {}", name, e));
+                }
+            }
+        }
+        let tz = match self.tzdir {
+            Some(ref dir) => Timezone::new_in(dir, &name),
+            None          => Timezone::new(&name),
+        };
+        let tz = match (tz, self.fallback) {
+            (Ok(tz), Fallback::PosixRule)                                         => tz,
+            (Ok(tz), _) if !matches!(tz.provenance(), Provenance::PosixRule(_)) => tz,
+            (_, Fallback::Utc)                                                    => {
+                trace_event!(WARN, zone = %name, "unknown zone, falling back to UTC");
+                Timezone::new("")?
+            },
+            (Ok(_), Fallback::Error)                                              => return Err(format!("Unknown zone {:?}", name)),
+            (Err(e), _)                                                           => return Err(e),
+        };
+        if self.preload {
+            tz.zone_info()?;
+        }
+        Ok(tz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build() {
+        let tz = Timezone::builder().leap_seconds(true).build("posix/Europe/Lisbon").expect("build");
+        assert_eq!(tz.provenance(), &Provenance::File(PathBuf::from("/usr/share/zoneinfo/right/Europe/Lisbon")));
+        let tz = Timezone::builder().leap_seconds(false).preload(true).build("right/Europe/Lisbon").expect("build");
+        assert_eq!(tz.provenance().name(), "Europe/Lisbon");
+        assert!(tz.zone_info().expect("zone_info").leap_seconds.is_empty());
+
+        let rule = "EST5EDT,M3.2.0,M11.1.0";
+        assert_eq!(Timezone::builder().build(rule).expect("build").provenance(), &Provenance::PosixRule(rule.to_string()));
+        assert!(Timezone::builder().fallback(Fallback::Error).build(rule).is_err());
+        assert_eq!(Timezone::builder().fallback(Fallback::Utc).build(rule).expect("build").provenance(), &Provenance::Utc);
+        assert_eq!(Timezone::builder().fallback(Fallback::Utc).build("Not/A_Zone").expect("build").provenance(), &Provenance::Utc);
+        assert!(Timezone::builder().build("Not/A_Zone").is_err());
+
+        let tz = Timezone::builder().tzdir("/usr/share/zoneinfo/right").fallback(Fallback::Error).build("Asia/Tokyo").expect("build");
+        assert_eq!(tz.provenance(), &Provenance::File(PathBuf::from("/usr/share/zoneinfo/right/Asia/Tokyo")));
+    }
+
+    #[test]
+    fn resolver() {
+        let zones = std::collections::HashMap::from([("Test/Tokyo".to_string(), std::fs::read("/usr/share/zoneinfo/Asia/Tokyo").expect("read")),
+                                                     ("Test/Broken".to_string(), b"TZif".to_vec())]);
+        let builder = Timezone::builder().resolver(zones).fallback(Fallback::Error);
+        let tz = builder.build("Test/Tokyo").expect("build");
+        assert_eq!((tz.provenance(), tz.localtime(0).expect("localtime").tm_hour), (&Provenance::Synthesized("Test/Tokyo".to_string()), 9));
+        assert_eq!(builder.build("Europe/Paris").expect("build").provenance().name(), "Europe/Paris"); // Not in the map
+        assert!(builder.build("Test/Broken").is_err());
+        assert!(builder.build("Test/Missing").is_err());
+        let failing = Timezone::builder().resolver(|_: &str| Err("store is down".to_string()));
+        assert_eq!(failing.build("Europe/Paris").err(), Some("store is down".to_string()));
+        assert!(format!("{:?}", failing).contains("resolver: Some(ZoneResolver)"));
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing() {
+        use std::sync::{Arc, Mutex};
+        use tracing::{Event, Metadata, span, subscriber::Subscriber};
+
+        // Just enough of a subscriber to see which events fire.
+        #[derive(Clone, Default)]
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+        struct Message<'a>(&'a mut String);
+        impl tracing::field::Visit for Message<'_> {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                if field.name() == "message" { *self.0 = format!("{:?}", value) }
+            }
+        }
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+            fn new_span(&self, _: &span::Attributes<'_>) -> span::Id { span::Id::from_u64(1) }
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, event: &Event<'_>) {
+                let mut message = String::new();
+                event.record(&mut Message(&mut message));
+                self.0.lock().unwrap().push(format!("{} {}", event.metadata().level(), message));
+            }
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            Timezone::builder().fallback(Fallback::Utc).build("Not/A_Zone").expect("build");
+        });
+        let events = recorder.0.lock().unwrap();
+        assert!(events.iter().any(|e| e == "WARN unknown zone, falling back to UTC"), "{:?}", events);
+        assert!(events.iter().any(|e| e == "DEBUG zone allocated"), "{:?}", events);
+    }
+}
@@ -0,0 +1,161 @@
+// Proleptic Gregorian calendar arithmetic.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+pub(crate) const WEEKDAY_ABBRS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+pub(crate) const MONTH_ABBRS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+pub(crate) const WEEKDAY_NAMES: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+pub(crate) const MONTH_NAMES: [&str; 12] = ["January", "February", "March", "April", "May", "June", "July", "August",
+                                            "September", "October", "November", "December"];
+
+/// Which side of year 1 a year is on, for writing years the way historians do. See [`Tm::era_year`].
+///
+/// [`Tm::era_year`]: crate::Tm::era_year
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Era {
+    /// Before the Common Era (BC). There's no year 0: 1 BCE is followed by 1 CE.
+    Bce,
+    /// The Common Era (AD).
+    Ce,
+}
+
+impl core::fmt::Display for Era {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self { Era::Bce => "BCE", Era::Ce => "CE" })
+    }
+}
+
+/// Convert an astronomical year number (where year 0 is 1 BCE, -1 is 2 BCE, and so on) to an era and a year
+/// counted from 1 within that era.
+pub fn astronomical_to_era(year: i64) -> (Era, i64) {
+    if year > 0 { (Era::Ce, year) } else { (Era::Bce, 1 - year) }
+}
+
+/// Convert an era and a year within it to an astronomical year number. Years are counted from 1, so this is
+/// `None` for years less than 1 (or too big to convert).
+pub fn era_to_astronomical(era: Era, year: i64) -> Option<i64> {
+    match era {
+        _ if year < 1 => None,
+        Era::Ce       => Some(year),
+        Era::Bce      => 1i64.checked_sub(year),
+    }
+}
+
+/// Whether astronomical `year` (year 0 is 1 BCE) is a leap year in the proleptic Gregorian calendar: years
+/// divisible by 4 are, except for those divisible by 100 but not 400 (so 2000 was and 1900 wasn't).
+///
+/// ```
+/// assert!(libtz::is_leap_year(2024) && libtz::is_leap_year(2000) && !libtz::is_leap_year(1900));
+/// ```
+pub const fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+/// The number of days in astronomical `year`: 366 in leap years (see [`is_leap_year`]), 365 otherwise.
+pub const fn days_in_year(year: i64) -> i32 {
+    if is_leap_year(year) { 366 } else { 365 }
+}
+
+/// The number of days in `month` [1, 12] of astronomical `year`, or `None` if the month is out of range. See
+/// also [`Month::days`](crate::Month::days).
+///
+/// ```
+/// assert_eq!((libtz::days_in_month(2024, 2), libtz::days_in_month(2023, 2), libtz::days_in_month(2023, 4)), (Some(29), Some(28), Some(30)));
+/// assert_eq!(libtz::days_in_month(2023, 13), None);
+/// ```
+pub const fn days_in_month(year: i64, month: i32) -> Option<i32> {
+    if month < 1 || month > 12 {
+        return None;
+    }
+    Some(month_days(year, month - 1))
+}
+
+// `month` is 0 based, like `tm_mon`.
+pub(crate) const fn month_days(year: i64, month: i32) -> i32 {
+    match month {
+        1 if is_leap_year(year) => 29,
+        1                       => 28,
+        3 | 5 | 8 | 10          => 30,
+        _                       => 31,
+    }
+}
+
+// Days since 1970-01-01. `month` is 0 based and must be in [0, 11]; `day` is 1 based but may be out of range.
+// This is Howard Hinnant's `days_from_civil` (<https://howardhinnant.github.io/date_algorithms.html>).
+pub(crate) const fn days_from_civil(year: i64, month: i32, day: i64) -> i64 {
+    let y = if month < 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (month as i64 + 10) % 12; // March = 0
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+// The inverse of `days_from_civil()`: (year, month [0, 11], day [1, 31]).
+pub(crate) const fn civil_from_days(days: i64) -> (i64, i32, i32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 2 } else { mp - 10 };
+    let year = yoe + era * 400 + if month < 2 { 1 } else { 0 };
+    (year, month as i32, day as i32)
+}
+
+// Day of the week (Sunday = 0) for days since 1970-01-01 (which was a Thursday).
+pub(crate) const fn weekday_from_days(days: i64) -> i32 {
+    (days + 4).rem_euclid(7) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn civil_days() {
+        assert_eq!(days_from_civil(1970, 0, 1), 0);
+        assert_eq!(days_from_civil(2000, 2, 1), 11017);
+        assert_eq!(days_from_civil(1969, 11, 31), -1);
+        assert_eq!(days_from_civil(2024, 0, 32), days_from_civil(2024, 1, 1));
+        for days in [-719468 - 400, -1, 0, 11016, 11017, 2932896] {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d as i64), days);
+        }
+        assert_eq!(civil_from_days(-1), (1969, 11, 31));
+        assert_eq!(weekday_from_days(0), 4);
+        assert_eq!(weekday_from_days(-1), 3);
+        assert!(is_leap_year(0) && is_leap_year(-4) && !is_leap_year(-100));
+    }
+
+    #[test]
+    fn month_and_year_lengths() {
+        for year in [-401, -400, -100, -1, 0, 1, 1900, 1970, 2000, 2023, 2024, 2100, 2400] {
+            let days: i32 = (1..=12).map(|m| days_in_month(year, m).expect("days_in_month")).sum();
+            assert_eq!(days, days_in_year(year), "{}", year);
+            assert_eq!(days_from_civil(year + 1, 0, 1) - days_from_civil(year, 0, 1), days_in_year(year) as i64, "{}", year);
+            assert_eq!(is_leap_year(year), days_in_month(year, 2) == Some(29), "{}", year);
+        }
+        assert_eq!((days_in_year(1900), days_in_year(2000), days_in_year(2023)), (365, 366, 365));
+        let lengths: [i32; 12] = core::array::from_fn(|m| days_in_month(2023, m as i32 + 1).expect("days_in_month"));
+        assert_eq!(lengths, [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31]);
+        assert_eq!((days_in_month(2023, 0), days_in_month(2023, 13), days_in_month(2023, -1)), (None, None, None));
+    }
+
+    #[test]
+    fn era() {
+        assert_eq!(astronomical_to_era(2024), (Era::Ce, 2024));
+        assert_eq!(astronomical_to_era(1), (Era::Ce, 1));
+        assert_eq!(astronomical_to_era(0), (Era::Bce, 1));
+        assert_eq!(astronomical_to_era(-43), (Era::Bce, 44));
+        for year in [-10000, -1, 0, 1, 10000] {
+            let (era, y) = astronomical_to_era(year);
+            assert_eq!(era_to_astronomical(era, y), Some(year));
+        }
+        assert_eq!(era_to_astronomical(Era::Bce, 0), None);
+        assert_eq!(era_to_astronomical(Era::Ce, -5), None);
+    }
+}
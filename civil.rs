@@ -0,0 +1,459 @@
+// Calendar dates and times of day without the rest of a `Tm`.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use core::fmt;
+use alloc::{format, string::String};
+use crate::{Tm, calendar};
+
+/// A day of the week. The discriminants are `tm_wday`'s numbers (Sunday = 0), so `weekday as i32` gives one.
+///
+/// ```
+/// use libtz::Weekday;
+/// let tm = libtz::gmtime(1700000000).unwrap();
+/// assert_eq!(tm.weekday(), Weekday::Tuesday);
+/// assert_eq!(Weekday::from(tm.tm_wday + 5), Weekday::Sunday);
+/// assert_eq!(tm.weekday().to_string(), "Tuesday");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Weekday {
+    Sunday = 0,
+    Monday = 1,
+    Tuesday = 2,
+    Wednesday = 3,
+    Thursday = 4,
+    Friday = 5,
+    Saturday = 6,
+}
+
+const WEEKDAYS: [Weekday; 7] = [Weekday::Sunday, Weekday::Monday, Weekday::Tuesday, Weekday::Wednesday,
+                                Weekday::Thursday, Weekday::Friday, Weekday::Saturday];
+
+/// From `tm_wday`'s numbering. Numbers outside [0, 6] wrap around, so 7 is Sunday and -1 is Saturday.
+impl From<i32> for Weekday {
+    fn from(wday: i32) -> Weekday {
+        WEEKDAYS[wday.rem_euclid(7) as usize]
+    }
+}
+
+/// The English name (`Tuesday`).
+impl fmt::Display for Weekday {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(calendar::WEEKDAY_NAMES[*self as usize])
+    }
+}
+
+/// A month of the year. The discriminants are `tm_mon`'s numbers (January = 0), so `month as i32` gives one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Month {
+    January = 0,
+    February = 1,
+    March = 2,
+    April = 3,
+    May = 4,
+    June = 5,
+    July = 6,
+    August = 7,
+    September = 8,
+    October = 9,
+    November = 10,
+    December = 11,
+}
+
+const MONTHS: [Month; 12] = [Month::January, Month::February, Month::March, Month::April, Month::May, Month::June,
+                             Month::July, Month::August, Month::September, Month::October, Month::November, Month::December];
+
+impl Month {
+    /// The number of days in the month in astronomical `year`.
+    pub fn days(self, year: i64) -> i32 {
+        calendar::month_days(year, self as i32)
+    }
+}
+
+/// From `tm_mon`'s numbering. Numbers outside [0, 11] wrap around, so 12 is January and -1 is December.
+impl From<i32> for Month {
+    fn from(mon: i32) -> Month {
+        MONTHS[mon.rem_euclid(12) as usize]
+    }
+}
+
+/// The English name (`November`).
+impl fmt::Display for Month {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(calendar::MONTH_NAMES[*self as usize])
+    }
+}
+
+impl Tm {
+    /// The day of the week from `tm_wday`.
+    pub fn weekday(&self) -> Weekday {
+        Weekday::from(self.tm_wday)
+    }
+
+    /// The month from `tm_mon`.
+    pub fn month(&self) -> Month {
+        Month::from(self.tm_mon)
+    }
+
+    /// Midnight UTC at the start of the `day`th day [1, 366] of `year` (see [`Date::from_ordinal`]). `%j`
+    /// formats the day back out.
+    ///
+    /// ```
+    /// let tm = libtz::Tm::from_ordinal(2024, 79).unwrap();
+    /// assert_eq!(tm.strftime("%F (%Y-%j)"), "2024-03-19 (2024-079)");
+    /// ```
+    pub fn from_ordinal(year: i32, day: i32) -> Result<Tm, String> {
+        Ok(Tm::from(Date::from_ordinal(year, day)?))
+    }
+}
+
+/// A proleptic Gregorian calendar date, always a day that exists.
+///
+/// ```
+/// use libtz::{Date, Tm};
+/// let date = Date::new(2024, 2, 29).expect("valid date");
+/// assert_eq!(date.to_string(), "2024-02-29");
+/// assert_eq!(date.weekday(), libtz::Weekday::Thursday);
+/// assert!(Date::new(2023, 2, 29).is_err());
+/// assert_eq!(Date::try_from(&Tm::from(date)), Ok(date));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    year  : i32,
+    month : u8,
+    day   : u8,
+}
+
+impl Date {
+    /// The date `day` of `month` (both starting at 1) in astronomical `year` (year 0 is 1 BCE). This fails if
+    /// the day doesn't exist or the year doesn't fit in a [`Tm`].
+    pub fn new(year: i32, month: i32, day: i32) -> Result<Date, String> {
+        if year.checked_sub(1900).is_none() {
+            return Err(format!("Year {} is out of range", year));
+        }
+        if !(1..=12).contains(&month) {
+            return Err(format!("Month {} is out of range", month));
+        }
+        if day < 1 || day > calendar::month_days(year as i64, month - 1) {
+            return Err(format!("Day {} is out of range for {}-{:02}", day, year, month));
+        }
+        Ok(Date{ year, month: month as u8, day: day as u8 })
+    }
+
+    /// Like [`Date::new`] but usable in `const` contexts, returning `None` instead of an error.
+    ///
+    /// ```
+    /// const LAUNCH: libtz::Date = libtz::Date::from_ymd(2024, 2, 29).unwrap();
+    /// assert_eq!(LAUNCH.to_string(), "2024-02-29");
+    /// ```
+    pub const fn from_ymd(year: i32, month: i32, day: i32) -> Option<Date> {
+        if year.checked_sub(1900).is_none() || month < 1 || month > 12 || day < 1 || day > calendar::month_days(year as i64, month - 1) {
+            return None;
+        }
+        Some(Date{ year, month: month as u8, day: day as u8 })
+    }
+
+    /// The date `days` days after 1970-01-01 (before it, if negative).
+    pub fn from_days_since_epoch(days: i64) -> Result<Date, String> {
+        if days.unsigned_abs() > 1 << 40 {
+            return Err("Date out of range".into());
+        }
+        let (year, month, day) = calendar::civil_from_days(days);
+        let year = i32::try_from(year).map_err(|_| "Date out of range")?;
+        Date::new(year, month + 1, day)
+    }
+
+    /// The number of days since 1970-01-01 (negative before it).
+    pub fn days_since_epoch(&self) -> i64 {
+        calendar::days_from_civil(self.year as i64, self.month as i32 - 1, self.day as i64)
+    }
+
+    /// Astronomical year (year 0 is 1 BCE).
+    pub const fn year(&self) -> i32 { self.year }
+    /// Month [1, 12]
+    pub const fn month(&self) -> i32 { self.month as i32 }
+    /// Day of the month [1, 31]
+    pub const fn day(&self) -> i32 { self.day as i32 }
+
+    /// The day of the week.
+    pub fn weekday(&self) -> Weekday {
+        Weekday::from(calendar::weekday_from_days(self.days_since_epoch()))
+    }
+
+    /// The `n`th `weekday` of `month` [1, 12] in `year`, or counting back from the end of the month if `n` is
+    /// negative (-1 is the last one). This fails if there isn't one (like the 5th Monday of most months).
+    ///
+    /// ```
+    /// use libtz::{Date, Weekday};
+    /// // US DST starts on the second Sunday in March and ends on the first Sunday in November.
+    /// assert_eq!(Date::nth_weekday_of_month(2024, 3, 2, Weekday::Sunday), Date::new(2024, 3, 10));
+    /// assert_eq!(Date::nth_weekday_of_month(2024, 11, 1, Weekday::Sunday), Date::new(2024, 11, 3));
+    /// // EU DST ends on the last Sunday in October.
+    /// assert_eq!(Date::nth_weekday_of_month(2024, 10, -1, Weekday::Sunday), Date::new(2024, 10, 27));
+    /// ```
+    pub fn nth_weekday_of_month(year: i32, month: i32, n: i32, weekday: Weekday) -> Result<Date, String> {
+        let first = Date::new(year, month, 1)?;
+        let last = calendar::month_days(year as i64, month - 1);
+        let first_match = 1 + (weekday as i32 - first.weekday() as i32).rem_euclid(7);
+        let day = match n {
+            1..=5   => first_match + (n - 1) * 7,
+            -5..=-1 => first_match + ((last - first_match) / 7 + n + 1) * 7,
+            _       => return Err(format!("Invalid weekday number {}", n)),
+        };
+        if day < 1 || day > last {
+            return Err(format!("{}-{:02} doesn't have {} {:?}s", year, month, n.abs(), weekday));
+        }
+        Date::new(year, month, day)
+    }
+
+    /// Day of the year [0, 365] (Jan/01 = 0), like `tm_yday`.
+    pub fn day_of_year(&self) -> i32 {
+        (self.days_since_epoch() - calendar::days_from_civil(self.year as i64, 0, 1)) as i32
+    }
+
+    /// The `day`th day [1, 366] of astronomical `year`: the ISO 8601 ordinal date `2024-079` is
+    /// `Date::from_ordinal(2024, 79)`, March 19th. This fails if the year doesn't have that many days.
+    pub fn from_ordinal(year: i32, day: i32) -> Result<Date, String> {
+        if year.checked_sub(1900).is_none() {
+            return Err(format!("Year {} is out of range", year));
+        }
+        if day < 1 || day > calendar::days_in_year(year as i64) {
+            return Err(format!("Day {} is out of range for {}", day, year));
+        }
+        Date::from_days_since_epoch(calendar::days_from_civil(year as i64, 0, 1) + day as i64 - 1)
+    }
+
+    /// Parse an ISO 8601 ordinal date, as used in aerospace and meteorological data: the year and the day of
+    /// the year [1, 366], either extended (`2024-079`) or basic (`2024079`). Years outside 0 to 9999 need a sign
+    /// (`+12345-001`), as in [`Date`]'s `Display`.
+    ///
+    /// ```
+    /// use libtz::Date;
+    /// let date = Date::parse_ordinal("2024-079").unwrap();
+    /// assert_eq!(date, Date::new(2024, 3, 19).unwrap());
+    /// assert_eq!(date.ordinal_string(), "2024-079");
+    /// assert!(Date::parse_ordinal("2023-366").is_err());
+    /// ```
+    pub fn parse_ordinal(s: &str) -> Result<Date, String> {
+        let bad = || format!("Invalid ordinal date {:?}", s);
+        if !s.is_ascii() || s.len() < 7 {
+            return Err(bad());
+        }
+        let (year, day) = match s.rfind('-').filter(|&i| i > 0) {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None    => s.split_at(s.len() - 3),
+        };
+        let digits = year.trim_start_matches(['+', '-']);
+        let signed = year.len() - digits.len();
+        let valid = signed <= 1 && digits.bytes().all(|b| b.is_ascii_digit()) && (digits.len() == 4 || signed == 1 && digits.len() > 4)
+                    && day.len() == 3 && day.bytes().all(|b| b.is_ascii_digit());
+        if !valid {
+            return Err(bad());
+        }
+        Date::from_ordinal(year.parse().map_err(|_| bad())?, day.parse().map_err(|_| bad())?)
+    }
+
+    /// The date as an ISO 8601 ordinal date (`2024-079`), like `strftime("%Y-%j")`.
+    pub fn ordinal_string(&self) -> String {
+        let year = match self.year {
+            0..=9999 => format!("{:04}", self.year),
+            _        => format!("{:+05}", self.year),
+        };
+        format!("{}-{:03}", year, self.day_of_year() + 1)
+    }
+}
+
+/// ISO 8601 (`2023-11-14`). Years before 0 or after 9999 get a sign, as in ISO 8601's expanded years.
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.year {
+            0..=9999 => write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day),
+            _        => write!(f, "{:+05}-{:02}-{:02}", self.year, self.month, self.day),
+        }
+    }
+}
+
+/// A time of day on a 24 hour clock. The seconds go up to 60, for leap seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimeOfDay {
+    hour   : u8,
+    minute : u8,
+    second : u8,
+}
+
+impl TimeOfDay {
+    /// Midnight, at the start of the day.
+    pub const MIDNIGHT: TimeOfDay = TimeOfDay{ hour: 0, minute: 0, second: 0 };
+
+    /// The time `hour` [0, 23], `minute` [0, 59], `second` [0, 60]. This fails if any are out of range.
+    pub fn new(hour: i32, minute: i32, second: i32) -> Result<TimeOfDay, String> {
+        for (name, value, max) in [("Hour", hour, 23), ("Minute", minute, 59), ("Second", second, 60)] {
+            if !(0..=max).contains(&value) {
+                return Err(format!("{} {} is out of range", name, value));
+            }
+        }
+        Ok(TimeOfDay{ hour: hour as u8, minute: minute as u8, second: second as u8 })
+    }
+
+    /// Like [`TimeOfDay::new`] but usable in `const` contexts, returning `None` instead of an error.
+    pub const fn from_hms(hour: i32, minute: i32, second: i32) -> Option<TimeOfDay> {
+        if hour < 0 || hour > 23 || minute < 0 || minute > 59 || second < 0 || second > 60 {
+            return None;
+        }
+        Some(TimeOfDay{ hour: hour as u8, minute: minute as u8, second: second as u8 })
+    }
+
+    /// Hour [0, 23]
+    pub fn hour(&self) -> i32 { self.hour as i32 }
+    /// Minute [0, 59]
+    pub fn minute(&self) -> i32 { self.minute as i32 }
+    /// Second [0, 60]
+    pub fn second(&self) -> i32 { self.second as i32 }
+
+    /// Seconds since midnight (a leap second counts as the 86400th).
+    pub const fn seconds_since_midnight(&self) -> i32 {
+        self.hour as i32 * 3600 + self.minute as i32 * 60 + self.second as i32
+    }
+}
+
+/// `22:13:20`
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)
+    }
+}
+
+/// A date and time of day on a wall clock, with no time zone.
+///
+/// ```
+/// use libtz::{CivilDateTime, Date, TimeOfDay, Timezone, Tm};
+/// let tz = Timezone::new("EST5EDT,M3.2.0,M11.1.0")?;
+/// let dt = CivilDateTime::new(Date::new(2023, 11, 14)?, TimeOfDay::new(17, 13, 20)?);
+/// assert_eq!(dt.to_string(), "2023-11-14T17:13:20");
+/// assert_eq!(tz.mktime(&Tm::from(dt)), Ok(1700000000));
+/// # Ok::<(), String>(())
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CivilDateTime {
+    /** The calendar date */                        pub date : Date,
+    /** The time of day */                          pub time : TimeOfDay,
+}
+
+impl CivilDateTime {
+    /// The time `time` on `date`.
+    pub const fn new(date: Date, time: TimeOfDay) -> CivilDateTime {
+        CivilDateTime{ date, time }
+    }
+}
+
+/// ISO 8601 (`2023-11-14T22:13:20`).
+impl fmt::Display for CivilDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}T{}", self.date, self.time)
+    }
+}
+
+/// The date's fields, checked (not normalized like [`Timezone::mktime`][crate::Timezone::mktime] would).
+impl TryFrom<&Tm> for Date {
+    type Error = String;
+    fn try_from(tm: &Tm) -> Result<Date, String> {
+        Date::new(tm.tm_year.checked_add(1900).ok_or("Year out of range")?, tm.tm_mon.saturating_add(1), tm.tm_mday)
+    }
+}
+
+/// The time's fields, checked (not normalized like [`Timezone::mktime`][crate::Timezone::mktime] would).
+impl TryFrom<&Tm> for TimeOfDay {
+    type Error = String;
+    fn try_from(tm: &Tm) -> Result<TimeOfDay, String> {
+        TimeOfDay::new(tm.tm_hour, tm.tm_min, tm.tm_sec)
+    }
+}
+
+impl TryFrom<&Tm> for CivilDateTime {
+    type Error = String;
+    fn try_from(tm: &Tm) -> Result<CivilDateTime, String> {
+        Ok(CivilDateTime{ date: Date::try_from(tm)?, time: TimeOfDay::try_from(tm)? })
+    }
+}
+
+/// Midnight UTC at the start of the date, like [`Tm::from_ymd_hms`].
+impl From<Date> for Tm {
+    fn from(date: Date) -> Tm {
+        Tm::from(CivilDateTime{ date, time: TimeOfDay::MIDNIGHT })
+    }
+}
+
+/// In UTC, like [`Tm::from_ymd_hms`]. [`Timezone::mktime`][crate::Timezone::mktime] ignores the offset, so
+/// the result works as a local time too.
+impl From<CivilDateTime> for Tm {
+    fn from(dt: CivilDateTime) -> Tm {
+        Tm::from_ymd_hms(dt.date.year, dt.date.month(), dt.date.day(), dt.time.hour(), dt.time.minute(), dt.time.second())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn civil() {
+        let date = Date::new(2023, 11, 14).expect("date");
+        assert_eq!((date.year(), date.month(), date.day(), date.weekday(), date.day_of_year()), (2023, 11, 14, Weekday::Tuesday, 317));
+        assert_eq!(Date::from_days_since_epoch(date.days_since_epoch()), Ok(date));
+        assert_eq!(Date::from_days_since_epoch(-1).map(|d| d.to_string()), Ok("1969-12-31".to_string()));
+        assert!(Date::from_days_since_epoch(i64::MAX).is_err());
+        assert!(Date::new(2023, 13, 1).is_err());
+        assert!(Date::new(2023, 4, 31).is_err());
+        assert!(Date::new(i32::MIN, 1, 1).is_err());
+        assert_eq!(Date::new(-43, 3, 15).expect("date").to_string(), "-0043-03-15");
+        assert_eq!(Date::new(12345, 1, 1).expect("date").to_string(), "+12345-01-01");
+
+        let time = TimeOfDay::new(23, 59, 60).expect("leap second");
+        assert_eq!((time.to_string(), time.seconds_since_midnight()), ("23:59:60".to_string(), 86400));
+        assert!(TimeOfDay::new(24, 0, 0).is_err());
+        assert!(TimeOfDay::new(0, -1, 0).is_err());
+
+        let tm = crate::Tm::from_ymd_hms(2023, 11, 14, 22, 13, 20);
+        let dt = CivilDateTime::try_from(&tm).expect("from tm");
+        assert_eq!(dt, CivilDateTime::new(date, TimeOfDay::new(22, 13, 20).expect("time")));
+        assert_eq!(Tm::from(dt), tm);
+        assert_eq!(Tm::from(date), crate::Tm::from_ymd_hms(2023, 11, 14, 0, 0, 0));
+        assert!(dt < CivilDateTime::new(Date::new(2023, 11, 15).expect("date"), TimeOfDay::MIDNIGHT));
+
+        assert_eq!(Date::nth_weekday_of_month(2023, 11, 2, Weekday::Tuesday), Ok(date));
+        assert_eq!(Date::nth_weekday_of_month(2023, 11, -3, Weekday::Tuesday), Ok(date));
+        assert_eq!(Date::nth_weekday_of_month(2024, 2, 5, Weekday::Thursday), Date::new(2024, 2, 29));
+        assert_eq!(Date::nth_weekday_of_month(2024, 2, -5, Weekday::Thursday), Date::new(2024, 2, 1));
+        assert!(Date::nth_weekday_of_month(2023, 2, 5, Weekday::Thursday).is_err());
+        assert!(Date::nth_weekday_of_month(2023, 2, 0, Weekday::Thursday).is_err());
+
+        for wday in -14..14 {
+            assert_eq!(Weekday::from(wday) as i32, wday.rem_euclid(7));
+            assert_eq!(Month::from(wday) as i32, wday.rem_euclid(12));
+        }
+        assert_eq!((tm.weekday(), tm.month()), (Weekday::Tuesday, Month::November));
+        assert_eq!((Month::February.days(2024), Month::February.days(2100), Month::December.to_string()), (29, 28, "December".to_string()));
+
+        assert_eq!(Date::from_ordinal(2023, 318), Ok(Date::new(2023, 11, 14).expect("date")));
+        assert_eq!(Date::from_ordinal(2024, 366), Date::new(2024, 12, 31));
+        assert!(Date::from_ordinal(2023, 366).is_err() && Date::from_ordinal(2024, 0).is_err());
+        assert_eq!(Date::parse_ordinal("2023318"), Ok(date));
+        assert_eq!(Date::parse_ordinal("+12345-001"), Date::new(12345, 1, 1));
+        assert_eq!(Date::parse_ordinal("-0043-074"), Date::new(-43, 3, 15));
+        for ordinal in ["2023-11-14", "23-318", "2023-31", "2023-0318", "12345-001", "+-2023-001", "2023-３18", "", "-001"] {
+            assert!(Date::parse_ordinal(ordinal).is_err(), "{:?}", ordinal);
+        }
+        for date in [date, Date::new(-43, 3, 15).expect("date"), Date::new(12345, 12, 31).expect("date")] {
+            assert_eq!(Date::parse_ordinal(&date.ordinal_string()), Ok(date));
+        }
+        assert_eq!(Tm::from_ordinal(2023, 318).map(|tm| tm.strftime("%Y-%j")), Ok("2023-318".to_string()));
+
+        let mut bad = tm.clone();
+        bad.tm_mday = 31;
+        assert!(Date::try_from(&bad).is_err());
+        bad.tm_mon = -1;
+        assert!(Date::try_from(&bad).is_err());
+    }
+}
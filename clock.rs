@@ -0,0 +1,111 @@
+// Sources of the current time.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use crate::{Tm, TimeT, Timezone};
+
+/// Where "now" comes from. Functions that need the current time take a `Clock` so tests can pin it down with a
+/// [`FixedClock`] or [`SteppingClock`] instead of depending on when they run.
+pub trait Clock {
+    /// The current system time.
+    fn now(&self) -> TimeT;
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> TimeT { (**self).now() }
+}
+
+/// The real time, from [`std::time::SystemTime::now`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> TimeT {
+        match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+            Ok(d)  => d.as_secs() as TimeT,
+            Err(e) => -(e.duration().as_secs() as TimeT) - (e.duration().subsec_nanos() > 0) as TimeT,
+        }
+    }
+}
+
+/// A clock that's stopped at one time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedClock(pub TimeT);
+
+impl Clock for FixedClock {
+    fn now(&self) -> TimeT { self.0 }
+}
+
+/// A clock that starts at one time and moves forward by `step` seconds every time it's read.
+#[derive(Debug)]
+pub struct SteppingClock {
+    next: AtomicI64,
+    step: TimeT,
+}
+
+impl SteppingClock {
+    /// Make a clock whose first reading is `start`.
+    pub fn new(start: TimeT, step: TimeT) -> SteppingClock {
+        SteppingClock{ next: AtomicI64::new(start), step }
+    }
+
+    /// Move the clock by `secs` seconds without reading it.
+    pub fn advance(&self, secs: TimeT) {
+        self.next.fetch_add(secs, Ordering::Relaxed);
+    }
+}
+
+impl Clock for SteppingClock {
+    fn now(&self) -> TimeT {
+        self.next.fetch_add(self.step, Ordering::Relaxed)
+    }
+}
+
+impl Timezone {
+    /// The current local time in this zone, according to `clock`.
+    ///
+    /// ```
+    /// use libtz::{Timezone, FixedClock};
+    /// let tz = Timezone::new("America/New_York").unwrap();
+    /// let tm = tz.now_local(&FixedClock(1700000000)).unwrap();
+    /// assert_eq!((tm.tm_hour, tm.tm_min), (17, 13));
+    /// ```
+    pub fn now_local(&self, clock: &impl Clock) -> Result<Tm, String> {
+        self.localtime(clock.now())
+    }
+
+    /// Like [`Timezone::deadline`], but reading `now` from `clock`.
+    pub fn deadline_from(&self, clock: &impl Clock, target: &Tm) -> Result<std::time::Duration, String> {
+        self.deadline(clock.now(), target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clocks() {
+        assert_eq!(FixedClock(42).now(), 42);
+
+        let clock = SteppingClock::new(100, 10);
+        assert_eq!([clock.now(), clock.now(), clock.now()], [100, 110, 120]);
+        clock.advance(-30);
+        assert_eq!(clock.now(), 100);
+
+        let before = SystemClock.now();
+        assert!(before > 1700000000);
+        assert!(SystemClock.now() >= before);
+
+        let tz = Timezone::new("Europe/London").expect("timezone alloc");
+        let clock = SteppingClock::new(1700000000, 3600); // 2023-11-14 22:13:20 GMT
+        assert_eq!(tz.now_local(&clock).map(|tm| tm.tm_hour), Ok(22));
+        // The second reading is an hour later.
+        let wake = tz.deadline_from(&clock, &Tm::from_ymd_hms(2023, 11, 15, 7, 0, 0)).expect("deadline_from");
+        assert_eq!(wake.as_secs(), 7 * 3600 + 46 * 60 + 40);
+        let dyn_clock: &dyn Clock = &FixedClock(1700000000);
+        assert_eq!(tz.now_local(&dyn_clock).map(|tm| tm.tm_min), Ok(13));
+    }
+}
@@ -0,0 +1,161 @@
+// An allocation free broken down time.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::ffi::CStr;
+use crate::{Tm, TimeT, Timezone};
+
+/// The longest abbreviation a [`CompactTm`] can hold. Real zones use at most 6 characters, but POSIX TZ rule
+/// strings can specify longer ones.
+pub const COMPACT_ZONE_MAX: usize = 15;
+
+/// A [`Tm`] that stores its abbreviation inline instead of in a `String`, so it's `Copy` and creating one never
+/// allocates. Use it in hot paths and convert to a `Tm` when needed.
+///
+/// The fields are the same as [`Tm`]'s except that the abbreviation is read with [`tm_zone()`][CompactTm::tm_zone].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CompactTm {
+    /** Seconds          [0, 60] */                 pub tm_sec    : i32,
+    /** Minutes          [0, 59] */                 pub tm_min    : i32,
+    /** Hour             [0, 23] */                 pub tm_hour   : i32,
+    /** Day of the month [1, 31] */                 pub tm_mday   : i32,
+    /** Month            [0, 11]  (January = 0) */  pub tm_mon    : i32,
+    /** Year minus 1900 */                          pub tm_year   : i32,
+    /** Day of the week  [0, 6]   (Sunday = 0) */   pub tm_wday   : i32,
+    /** Day of the year  [0, 365] (Jan/01 = 0) */   pub tm_yday   : i32,
+    /** Daylight savings flag */                    pub tm_isdst  : i32,
+
+    /** Seconds East of UTC */                      pub tm_gmtoff : i64,
+    tm_zone_len : u8,
+    tm_zone     : [u8; COMPACT_ZONE_MAX],
+}
+
+impl CompactTm {
+    /// Timezone abbreviation
+    pub fn tm_zone(&self) -> &str {
+        // Only ever filled from a &str, so this can't fail.
+        std::str::from_utf8(&self.tm_zone[..self.tm_zone_len as usize]).unwrap_or("")
+    }
+
+    fn set_zone(&mut self, zone: &[u8]) -> Result<(), String> {
+        std::str::from_utf8(zone).map_err(|_| "Invalid utf8")?;
+        if zone.len() > COMPACT_ZONE_MAX {
+            return Err(format!("Abbreviation is longer than {} bytes", COMPACT_ZONE_MAX));
+        }
+        self.tm_zone[..zone.len()].copy_from_slice(zone);
+        self.tm_zone_len = zone.len() as u8;
+        Ok(())
+    }
+}
+
+impl TryFrom<&crate::sys::Tm> for CompactTm {
+    type Error = String;
+    fn try_from(tztm: &crate::sys::Tm) -> Result<Self, Self::Error> {
+        let mut tm = CompactTm{
+            tm_sec      : tztm.tm_sec,
+            tm_min      : tztm.tm_min,
+            tm_hour     : tztm.tm_hour,
+            tm_mday     : tztm.tm_mday,
+            tm_mon      : tztm.tm_mon,
+            tm_year     : tztm.tm_year,
+            tm_wday     : tztm.tm_wday,
+            tm_yday     : tztm.tm_yday,
+            tm_isdst    : tztm.tm_isdst,
+            tm_gmtoff   : tztm.tm_gmtoff,
+            tm_zone_len : 0,
+            tm_zone     : [0; COMPACT_ZONE_MAX],
+        };
+        tm.set_zone(unsafe { CStr::from_ptr(tztm.tm_zone) }.to_bytes())?;
+        Ok(tm)
+    }
+}
+
+impl TryFrom<&Tm> for CompactTm {
+    type Error = String;
+    fn try_from(from: &Tm) -> Result<Self, Self::Error> {
+        let mut tm = CompactTm{
+            tm_sec      : from.tm_sec,
+            tm_min      : from.tm_min,
+            tm_hour     : from.tm_hour,
+            tm_mday     : from.tm_mday,
+            tm_mon      : from.tm_mon,
+            tm_year     : from.tm_year,
+            tm_wday     : from.tm_wday,
+            tm_yday     : from.tm_yday,
+            tm_isdst    : from.tm_isdst,
+            tm_gmtoff   : from.tm_gmtoff,
+            tm_zone_len : 0,
+            tm_zone     : [0; COMPACT_ZONE_MAX],
+        };
+        tm.set_zone(from.tm_zone.as_bytes())?;
+        Ok(tm)
+    }
+}
+
+impl From<&CompactTm> for Tm {
+    fn from(from: &CompactTm) -> Tm {
+        Tm{
+            tm_sec    : from.tm_sec,
+            tm_min    : from.tm_min,
+            tm_hour   : from.tm_hour,
+            tm_mday   : from.tm_mday,
+            tm_mon    : from.tm_mon,
+            tm_year   : from.tm_year,
+            tm_wday   : from.tm_wday,
+            tm_yday   : from.tm_yday,
+            tm_isdst  : from.tm_isdst,
+            tm_gmtoff : from.tm_gmtoff,
+            tm_zone   : from.tm_zone().into(),
+        }
+    }
+}
+
+impl From<&CompactTm> for crate::sys::Tm {
+    fn from(tm: &CompactTm) -> crate::sys::Tm {
+        crate::sys::Tm{
+            tm_sec    : tm.tm_sec,
+            tm_min    : tm.tm_min,
+            tm_hour   : tm.tm_hour,
+            tm_mday   : tm.tm_mday,
+            tm_mon    : tm.tm_mon,
+            tm_year   : tm.tm_year,
+            tm_wday   : tm.tm_wday,
+            tm_yday   : tm.tm_yday,
+            tm_isdst  : tm.tm_isdst,
+            tm_gmtoff : tm.tm_gmtoff,
+            tm_zone   : std::ptr::null_mut(),
+        }
+    }
+}
+
+impl Timezone {
+    /// Convert system time to a local time [`CompactTm`]. This is [`localtime`][Timezone::localtime] without
+    /// the allocation.
+    pub fn localtime_compact(&self, time: TimeT) -> Result<CompactTm, String> {
+        CompactTm::try_from(&self.localtime_raw(time)?)
+    }
+
+    /// Convert a local time [`CompactTm`] to system time. See [`mktime`][Timezone::mktime].
+    pub fn mktime_compact(&self, tm: &CompactTm) -> Result<TimeT, String> {
+        crate::checked_mktime(tm, |tztm| unsafe { crate::sys::mktime_z(self.tz, tztm) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn compact() {
+        let tz = Timezone::new("Asia/Kathmandu").expect("timezone alloc");
+        let tm = tz.localtime_compact(1700000000).expect("localtime_compact");
+        assert_eq!(tm.tm_zone(), "+0545");
+        assert_eq!(Tm::from(&tm), tz.localtime(1700000000).expect("localtime"));
+        assert_eq!(CompactTm::try_from(&Tm::from(&tm)), Ok(tm));
+        assert_eq!(tz.mktime_compact(&tm), Ok(1700000000));
+
+        let mut long = Tm::from(&tm);
+        long.tm_zone = "A_VERY_LONG_ABBREVIATION".into();
+        assert!(CompactTm::try_from(&long).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+// Golden test vectors for checking a libtz build and its zone data.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+//! Check that libtz and the installed zone data produce known-good results.
+//!
+//! This module embeds a set of golden vectors: instants and their expected local rendering (wall clock time,
+//! offset, DST flag and abbreviation) in a variety of zones. They mostly sit on either side of historical
+//! transitions, including odd ones like half hour and 2 hour DST, 45 minute offsets, a skipped day
+//! (`Pacific/Apia` in 2011), and leap seconds (the `right/` zones). They only cover the past, so they shouldn't
+//! change when new tzdata releases change future rules.
+//!
+//! Use [`run`] at startup (or in a test) to verify the platform's zone database:
+//!
+//! ```
+//! if let Err(mismatches) = libtz::conformance::run() {
+//!     for m in mismatches {
+//!         eprintln!("{}", m);
+//!     }
+//! #   panic!("conformance failed");
+//! }
+//! ```
+
+use crate::{TimeT, Timezone};
+
+/// One golden vector: `time` in `zone` should render as `local` (formatted as `YYYY-MM-DD hh:mm:ss`) with
+/// the given offset, DST flag and abbreviation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Vector {
+    /** Zone name */                                pub zone   : &'static str,
+    /** The instant being converted */              pub time   : TimeT,
+    /** Expected wall clock time */                 pub local  : &'static str,
+    /** Expected `tm_gmtoff` */                     pub gmtoff : i64,
+    /** Expected `tm_isdst != 0` */                 pub isdst  : bool,
+    /** Expected `tm_zone` */                       pub abbr   : &'static str,
+}
+
+/// A [`Vector`] that didn't produce the expected result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    /** The failing vector */                       pub vector  : &'static Vector,
+    /** What went wrong */                          pub problem : String,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} @ {}: {}", self.vector.zone, self.vector.time, self.problem)
+    }
+}
+
+static VECTORS: &[Vector] = &[
+    Vector{ zone: "America/New_York",        time:   1143961199, local: "2006-04-02 01:59:59", gmtoff: -18000, isdst: false, abbr: "EST"   },
+    Vector{ zone: "America/New_York",        time:   1143961200, local: "2006-04-02 03:00:00", gmtoff: -14400, isdst: true, abbr: "EDT"   },
+    Vector{ zone: "America/New_York",        time:   1162101599, local: "2006-10-29 01:59:59", gmtoff: -14400, isdst: true, abbr: "EDT"   },
+    Vector{ zone: "America/New_York",        time:   1162101600, local: "2006-10-29 01:00:00", gmtoff: -18000, isdst: false, abbr: "EST"   },
+    Vector{ zone: "America/New_York",        time:   1173596399, local: "2007-03-11 01:59:59", gmtoff: -18000, isdst: false, abbr: "EST"   },
+    Vector{ zone: "America/New_York",        time:   1173596400, local: "2007-03-11 03:00:00", gmtoff: -14400, isdst: true, abbr: "EDT"   },
+    Vector{ zone: "America/New_York",        time:   1194155999, local: "2007-11-04 01:59:59", gmtoff: -14400, isdst: true, abbr: "EDT"   },
+    Vector{ zone: "America/New_York",        time:   1194156000, local: "2007-11-04 01:00:00", gmtoff: -18000, isdst: false, abbr: "EST"   },
+    Vector{ zone: "Europe/London",           time:    828233999, local: "1996-03-31 00:59:59", gmtoff:      0, isdst: false, abbr: "GMT"   },
+    Vector{ zone: "Europe/London",           time:    828234000, local: "1996-03-31 02:00:00", gmtoff:   3600, isdst: true, abbr: "BST"   },
+    Vector{ zone: "Europe/London",           time:    846377999, local: "1996-10-27 01:59:59", gmtoff:   3600, isdst: true, abbr: "BST"   },
+    Vector{ zone: "Europe/London",           time:    846378000, local: "1996-10-27 01:00:00", gmtoff:      0, isdst: false, abbr: "GMT"   },
+    Vector{ zone: "Australia/Sydney",        time:    953999999, local: "2000-03-26 02:59:59", gmtoff:  39600, isdst: true, abbr: "AEDT"  },
+    Vector{ zone: "Australia/Sydney",        time:    954000000, local: "2000-03-26 02:00:00", gmtoff:  36000, isdst: false, abbr: "AEST"  },
+    Vector{ zone: "Australia/Sydney",        time:    967305599, local: "2000-08-27 01:59:59", gmtoff:  36000, isdst: false, abbr: "AEST"  },
+    Vector{ zone: "Australia/Sydney",        time:    967305600, local: "2000-08-27 03:00:00", gmtoff:  39600, isdst: true, abbr: "AEDT"  },
+    Vector{ zone: "Asia/Kathmandu",          time:    504901799, local: "1985-12-31 23:59:59", gmtoff:  19800, isdst: false, abbr: "+0530" },
+    Vector{ zone: "Asia/Kathmandu",          time:    504901800, local: "1986-01-01 00:15:00", gmtoff:  20700, isdst: false, abbr: "+0545" },
+    Vector{ zone: "Pacific/Chatham",         time:   1270303199, local: "2010-04-04 03:44:59", gmtoff:  49500, isdst: true, abbr: "+1345" },
+    Vector{ zone: "Pacific/Chatham",         time:   1270303200, local: "2010-04-04 02:45:00", gmtoff:  45900, isdst: false, abbr: "+1245" },
+    Vector{ zone: "Pacific/Chatham",         time:   1285423199, local: "2010-09-26 02:44:59", gmtoff:  45900, isdst: false, abbr: "+1245" },
+    Vector{ zone: "Pacific/Chatham",         time:   1285423200, local: "2010-09-26 03:45:00", gmtoff:  49500, isdst: true, abbr: "+1345" },
+    Vector{ zone: "America/St_Johns",        time:   1268537459, local: "2010-03-14 00:00:59", gmtoff: -12600, isdst: false, abbr: "NST"   },
+    Vector{ zone: "America/St_Johns",        time:   1268537460, local: "2010-03-14 01:01:00", gmtoff:  -9000, isdst: true, abbr: "NDT"   },
+    Vector{ zone: "America/St_Johns",        time:   1289097059, local: "2010-11-07 00:00:59", gmtoff:  -9000, isdst: true, abbr: "NDT"   },
+    Vector{ zone: "America/St_Johns",        time:   1289097060, local: "2010-11-06 23:01:00", gmtoff: -12600, isdst: false, abbr: "NST"   },
+    Vector{ zone: "Pacific/Apia",            time:   1301752799, local: "2011-04-02 03:59:59", gmtoff: -36000, isdst: true, abbr: "-10"   },
+    Vector{ zone: "Pacific/Apia",            time:   1301752800, local: "2011-04-02 03:00:00", gmtoff: -39600, isdst: false, abbr: "-11"   },
+    Vector{ zone: "Pacific/Apia",            time:   1316872799, local: "2011-09-24 02:59:59", gmtoff: -39600, isdst: false, abbr: "-11"   },
+    Vector{ zone: "Pacific/Apia",            time:   1316872800, local: "2011-09-24 04:00:00", gmtoff: -36000, isdst: true, abbr: "-10"   },
+    Vector{ zone: "Pacific/Apia",            time:   1325239199, local: "2011-12-29 23:59:59", gmtoff: -36000, isdst: true, abbr: "-10"   },
+    Vector{ zone: "Pacific/Apia",            time:   1325239200, local: "2011-12-31 00:00:00", gmtoff:  50400, isdst: true, abbr: "+14"   },
+    Vector{ zone: "America/Sao_Paulo",       time:   1518919199, local: "2018-02-17 23:59:59", gmtoff:  -7200, isdst: true, abbr: "-02"   },
+    Vector{ zone: "America/Sao_Paulo",       time:   1518919200, local: "2018-02-17 23:00:00", gmtoff: -10800, isdst: false, abbr: "-03"   },
+    Vector{ zone: "America/Sao_Paulo",       time:   1541300399, local: "2018-11-03 23:59:59", gmtoff: -10800, isdst: false, abbr: "-03"   },
+    Vector{ zone: "America/Sao_Paulo",       time:   1541300400, local: "2018-11-04 01:00:00", gmtoff:  -7200, isdst: true, abbr: "-02"   },
+    Vector{ zone: "Europe/Moscow",           time:   1301180399, local: "2011-03-27 01:59:59", gmtoff:  10800, isdst: false, abbr: "MSK"   },
+    Vector{ zone: "Europe/Moscow",           time:   1301180400, local: "2011-03-27 03:00:00", gmtoff:  14400, isdst: false, abbr: "MSK"   },
+    Vector{ zone: "Europe/Moscow",           time:   1414274399, local: "2014-10-26 01:59:59", gmtoff:  14400, isdst: false, abbr: "MSK"   },
+    Vector{ zone: "Europe/Moscow",           time:   1414274400, local: "2014-10-26 01:00:00", gmtoff:  10800, isdst: false, abbr: "MSK"   },
+    Vector{ zone: "Antarctica/Troll",        time:   1269737999, local: "2010-03-28 00:59:59", gmtoff:      0, isdst: false, abbr: "+00"   },
+    Vector{ zone: "Antarctica/Troll",        time:   1269738000, local: "2010-03-28 03:00:00", gmtoff:   7200, isdst: true, abbr: "+02"   },
+    Vector{ zone: "Antarctica/Troll",        time:   1288486799, local: "2010-10-31 02:59:59", gmtoff:   7200, isdst: true, abbr: "+02"   },
+    Vector{ zone: "Antarctica/Troll",        time:   1288486800, local: "2010-10-31 01:00:00", gmtoff:      0, isdst: false, abbr: "+00"   },
+    Vector{ zone: "Australia/Lord_Howe",     time:   1270306799, local: "2010-04-04 01:59:59", gmtoff:  39600, isdst: true, abbr: "+11"   },
+    Vector{ zone: "Australia/Lord_Howe",     time:   1270306800, local: "2010-04-04 01:30:00", gmtoff:  37800, isdst: false, abbr: "+1030" },
+    Vector{ zone: "Australia/Lord_Howe",     time:   1286033399, local: "2010-10-03 01:59:59", gmtoff:  37800, isdst: false, abbr: "+1030" },
+    Vector{ zone: "Australia/Lord_Howe",     time:   1286033400, local: "2010-10-03 02:30:00", gmtoff:  39600, isdst: true, abbr: "+11"   },
+    Vector{ zone: "right/UTC",               time:     78796800, local: "1972-06-30 23:59:60", gmtoff:      0, isdst: false, abbr: "UTC"   },
+    Vector{ zone: "right/UTC",               time:     78796801, local: "1972-07-01 00:00:00", gmtoff:      0, isdst: false, abbr: "UTC"   },
+    Vector{ zone: "right/America/New_York",  time:   1457852425, local: "2016-03-13 01:59:59", gmtoff: -18000, isdst: false, abbr: "EST"   },
+    Vector{ zone: "right/America/New_York",  time:   1457852426, local: "2016-03-13 03:00:00", gmtoff: -14400, isdst: true, abbr: "EDT"   },
+    Vector{ zone: "right/America/New_York",  time:   1478412025, local: "2016-11-06 01:59:59", gmtoff: -14400, isdst: true, abbr: "EDT"   },
+    Vector{ zone: "right/America/New_York",  time:   1478412026, local: "2016-11-06 01:00:00", gmtoff: -18000, isdst: false, abbr: "EST"   },
+    Vector{ zone: "right/UTC",               time:   1483228826, local: "2016-12-31 23:59:60", gmtoff:      0, isdst: false, abbr: "UTC"   },
+    Vector{ zone: "right/UTC",               time:   1483228827, local: "2017-01-01 00:00:00", gmtoff:      0, isdst: false, abbr: "UTC"   },
+    Vector{ zone: "Asia/Kolkata",            time:    946684800, local: "2000-01-01 05:30:00", gmtoff:  19800, isdst: false, abbr: "IST"   },
+    Vector{ zone: "Asia/Tokyo",              time:            0, local: "1970-01-01 09:00:00", gmtoff:  32400, isdst: false, abbr: "JST"   },
+    Vector{ zone: "UTC",                     time:           -1, local: "1969-12-31 23:59:59", gmtoff:      0, isdst: false, abbr: "UTC"   },
+];
+
+/// All the embedded golden vectors.
+pub fn vectors() -> &'static [Vector] {
+    VECTORS
+}
+
+/// Check `tz` against the vectors for the zone `name`. The name is only used to select vectors, so this can
+/// check a `Timezone` loaded some other way (from a different directory, say).
+pub fn check(tz: &Timezone, name: &str) -> Vec<Mismatch> {
+    VECTORS.iter().filter(|v| v.zone == name).filter_map(|vector| {
+        let problem = match tz.localtime(vector.time) {
+            Err(e) => e,
+            Ok(tm) => {
+                let local = format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", tm.tm_year as i64 + 1900, tm.tm_mon + 1, tm.tm_mday,
+                                    tm.tm_hour, tm.tm_min, tm.tm_sec);
+                if (local.as_str(), tm.tm_gmtoff, tm.tm_isdst != 0, &*tm.tm_zone) == (vector.local, vector.gmtoff, vector.isdst, vector.abbr) {
+                    return None;
+                }
+                format!("expected {} {} isdst={} gmtoff={}, got {} {} isdst={} gmtoff={}",
+                        vector.local, vector.abbr, vector.isdst as i32, vector.gmtoff, local, tm.tm_zone, tm.tm_isdst, tm.tm_gmtoff)
+            },
+        };
+        Some(Mismatch{ vector, problem })
+    }).collect()
+}
+
+/// Load each zone with [`Timezone::new`] and check it against all the vectors.
+pub fn run() -> Result<(), Vec<Mismatch>> {
+    let mut zones: Vec<&str> = VECTORS.iter().map(|v| v.zone).collect();
+    zones.sort_unstable();
+    zones.dedup();
+    let mut mismatches = vec![];
+    for zone in zones {
+        match Timezone::new(zone) {
+            Ok(tz) => mismatches.extend(check(&tz, zone)),
+            Err(e) => mismatches.extend(VECTORS.iter().filter(|v| v.zone == zone)
+                                                      .map(|vector| Mismatch{ vector, problem: format!("Couldn't load zone: {}", e) })),
+        }
+    }
+    if mismatches.is_empty() { Ok(()) } else { Err(mismatches) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn golden() {
+        assert_eq!(run(), Ok(()));
+        let wrong = Timezone::new("Europe/Paris").expect("timezone alloc");
+        let mismatches = check(&wrong, "Europe/London");
+        assert_eq!(mismatches.len(), VECTORS.iter().filter(|v| v.zone == "Europe/London").count());
+        assert_eq!(mismatches[0].to_string(),
+                   "Europe/London @ 828233999: expected 1996-03-31 00:59:59 GMT isdst=0 gmtoff=0, got 1996-03-31 01:59:59 CET isdst=0 gmtoff=3600");
+    }
+}
@@ -0,0 +1,143 @@
+// A `struct tm` for passing across other C interfaces.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::collections::BTreeSet;
+use std::ffi::{CStr, CString, c_char, c_int, c_long};
+use std::sync::Mutex;
+use crate::Tm;
+
+// Every abbreviation a `CTm` has pointed at. They're never freed, so a `CTm` can be copied anywhere (including
+// into C code that holds on to it) without its `tm_zone` dangling. Zones only use a few dozen abbreviations.
+static ABBREVIATIONS: Mutex<BTreeSet<&'static CStr>> = Mutex::new(BTreeSet::new());
+
+fn intern(abbr: &str) -> Result<*const c_char, String> {
+    let abbr = CString::new(abbr).map_err(|_| "Abbreviation contains a NUL")?;
+    let mut abbrs = ABBREVIATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(interned) = abbrs.get(abbr.as_c_str()) {
+        return Ok(interned.as_ptr());
+    }
+    let interned: &'static CStr = Box::leak(abbr.into_boxed_c_str());
+    abbrs.insert(interned);
+    Ok(interned.as_ptr())
+}
+
+/// A [`Tm`] laid out like C's `struct tm` (with the BSD/GNU `tm_gmtoff` and `tm_zone` fields), for handing to
+/// C code of your own.
+///
+/// `tm_zone` points at an abbreviation libtz keeps for the life of the process, so a `CTm` is `Copy` and C code
+/// can keep the pointer as long as it likes. It's set from Rust with [`set_tm_zone()`][CTm::set_tm_zone]. When
+/// C code fills in a `CTm`, its `tm_zone` must be null or point at a NUL terminated string that outlives the
+/// `CTm`, as usual for a `struct tm`.
+///
+/// ```
+/// let tm = libtz::gmtime(1700000000).expect("gmtime");
+/// let ctm = libtz::CTm::try_from(&tm).expect("CTm");
+/// assert_eq!(ctm.tm_zone().to_str(), Ok("UTC"));
+/// // `&ctm` can be passed to C as a `const struct tm *`.
+/// assert_eq!(libtz::Tm::try_from(&ctm), Ok(tm));
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CTm {
+    /** Seconds          [0, 60] */                 pub tm_sec    : c_int,
+    /** Minutes          [0, 59] */                 pub tm_min    : c_int,
+    /** Hour             [0, 23] */                 pub tm_hour   : c_int,
+    /** Day of the month [1, 31] */                 pub tm_mday   : c_int,
+    /** Month            [0, 11]  (January = 0) */  pub tm_mon    : c_int,
+    /** Year minus 1900 */                          pub tm_year   : c_int,
+    /** Day of the week  [0, 6]   (Sunday = 0) */   pub tm_wday   : c_int,
+    /** Day of the year  [0, 365] (Jan/01 = 0) */   pub tm_yday   : c_int,
+    /** Daylight savings flag */                    pub tm_isdst  : c_int,
+
+    /** Seconds East of UTC */                      pub tm_gmtoff : c_long,
+    tm_zone   : *const c_char,
+}
+
+// `tm_zone` is either null or one of the never freed abbreviations, which any thread can read.
+unsafe impl Send for CTm {}
+unsafe impl Sync for CTm {}
+
+impl CTm {
+    /// Timezone abbreviation (empty if `tm_zone` is null).
+    pub fn tm_zone(&self) -> &CStr {
+        if self.tm_zone.is_null() {
+            return c"";
+        }
+        unsafe { CStr::from_ptr(self.tm_zone) }
+    }
+
+    /// Point `tm_zone` at `abbr`. This fails if `abbr` contains a NUL.
+    pub fn set_tm_zone(&mut self, abbr: &str) -> Result<(), String> {
+        self.tm_zone = intern(abbr)?;
+        Ok(())
+    }
+}
+
+impl TryFrom<&Tm> for CTm {
+    type Error = String;
+    #[allow(clippy::useless_conversion)] // c_long is only 32 bits on some targets
+    fn try_from(tm: &Tm) -> Result<Self, Self::Error> {
+        Ok(CTm{
+            tm_sec    : tm.tm_sec,
+            tm_min    : tm.tm_min,
+            tm_hour   : tm.tm_hour,
+            tm_mday   : tm.tm_mday,
+            tm_mon    : tm.tm_mon,
+            tm_year   : tm.tm_year,
+            tm_wday   : tm.tm_wday,
+            tm_yday   : tm.tm_yday,
+            tm_isdst  : tm.tm_isdst,
+            tm_gmtoff : tm.tm_gmtoff.try_into().map_err(|_| "Offset out of range")?,
+            tm_zone   : intern(&tm.tm_zone)?,
+        })
+    }
+}
+
+impl TryFrom<&CTm> for Tm {
+    type Error = String;
+    #[allow(clippy::useless_conversion)] // c_long is only 32 bits on some targets
+    fn try_from(tm: &CTm) -> Result<Self, Self::Error> {
+        Ok(Tm{
+            tm_sec    : tm.tm_sec,
+            tm_min    : tm.tm_min,
+            tm_hour   : tm.tm_hour,
+            tm_mday   : tm.tm_mday,
+            tm_mon    : tm.tm_mon,
+            tm_year   : tm.tm_year,
+            tm_wday   : tm.tm_wday,
+            tm_yday   : tm.tm_yday,
+            tm_isdst  : tm.tm_isdst,
+            tm_gmtoff : tm.tm_gmtoff.into(),
+            tm_zone   : tm.tm_zone().to_str().map_err(|_| "Invalid utf8")?.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Timezone;
+
+    #[test]
+    fn ctm() {
+        let tz = Timezone::new("Asia/Kathmandu").expect("timezone alloc");
+        let tm = tz.localtime(1700000000).expect("localtime");
+        let mut ctm = CTm::try_from(&tm).expect("CTm");
+        assert_eq!(ctm.tm_zone().to_str(), Ok("+0545"));
+        assert_eq!(Tm::try_from(&ctm), Ok(tm.clone()));
+        // Interned, so the same abbreviation is the same pointer.
+        assert_eq!(CTm::try_from(&tm).expect("CTm").tm_zone, ctm.tm_zone);
+
+        // Laid out the way libtz's `struct tm` is.
+        assert_eq!(std::mem::size_of::<CTm>(), std::mem::size_of::<crate::sys::Tm>());
+        assert_eq!(unsafe { crate::sys::mktime_z(tz.as_raw(), &mut ctm as *mut CTm as *mut crate::sys::Tm) }, 1700000000);
+
+        ctm.set_tm_zone("XYZ").expect("set_tm_zone");
+        assert_eq!(Tm::try_from(&ctm).expect("Tm").tm_zone.as_ref(), "XYZ");
+        assert!(ctm.set_tm_zone("A\0B").is_err());
+        ctm.tm_zone = std::ptr::null();
+        assert_eq!(Tm::try_from(&ctm).expect("Tm").tm_zone.as_ref(), "");
+    }
+}
@@ -0,0 +1,164 @@
+// A process-wide default timezone.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::cell::RefCell;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::Timezone;
+
+struct DefaultZone {
+    tz: Arc<Timezone>,
+    set: bool, // By set_default_timezone() (as opposed to loaded from the system)
+}
+
+static DEFAULT: RwLock<Option<DefaultZone>> = RwLock::new(None);
+
+// Bumped every time DEFAULT changes, so each thread can keep its own copy and only take the lock when it's stale.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static CACHE: RefCell<Option<(u64, Arc<Timezone>)>> = const { RefCell::new(None) };
+    // From with_default_timezone(), innermost last.
+    static OVERRIDES: RefCell<Vec<Arc<Timezone>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Make `tz` the zone [`default_timezone`] returns, for the whole process.
+///
+/// This is a thread safe alternative to setting the `TZ` environment variable (which isn't safe to change
+/// while other threads might be reading the environment). It doesn't affect [`Timezone::from_env`], which
+/// always looks at `TZ` and `/etc/localtime`.
+pub fn set_default_timezone(tz: Timezone) {
+    *DEFAULT.write().unwrap() = Some(DefaultZone{ tz: Arc::new(tz), set: true });
+    GENERATION.fetch_add(1, Ordering::Release);
+}
+
+/// Forget the default zone loaded from the system by [`default_timezone`] so the next call loads it again, for
+/// instance after `/etc/localtime` has been changed. A zone given to [`set_default_timezone`] is kept.
+pub fn invalidate_default_timezone() {
+    let mut default = DEFAULT.write().unwrap();
+    if default.as_ref().is_some_and(|default| !default.set) {
+        *default = None;
+        GENERATION.fetch_add(1, Ordering::Release);
+    }
+}
+
+/// The process-wide default zone: the one last given to [`set_default_timezone`] or, if there hasn't been
+/// one, [`Timezone::from_env`] (loaded the first time this is called). Inside [`with_default_timezone`] it's
+/// the zone given to that instead. If that can't be loaded the default is
+/// UTC, like C's `localtime()` does.
+///
+/// The zone is cached per thread, so this is cheap enough to call for every conversion.
+pub fn default_timezone() -> Arc<Timezone> {
+    if let Some(tz) = OVERRIDES.with_borrow(|overrides| overrides.last().cloned()) {
+        return tz;
+    }
+    let generation = GENERATION.load(Ordering::Acquire);
+    if let Some(tz) = CACHE.with_borrow(|cache| cache.as_ref().filter(|(g, _)| *g == generation).map(|(_, tz)| tz.clone())) {
+        trace_event!(TRACE, "default zone cache hit");
+        return tz;
+    }
+    trace_event!(TRACE, generation, "default zone cache miss");
+    let tz = shared_default();
+    CACHE.set(Some((generation, tz.clone())));
+    tz
+}
+
+/// Run `f` with `tz` as the [`default_timezone`] on this thread, then put the default back (even if `f`
+/// panics). Other threads (including ones `f` spawns) don't see it, so tests of code that uses the default zone
+/// can each pick one and still run in parallel, without touching `TZ` or [`set_default_timezone`]. Calls can be
+/// nested.
+///
+/// ```
+/// use libtz::{Timezone, default_timezone, with_default_timezone};
+/// let hour = with_default_timezone(Timezone::new("Asia/Tokyo").unwrap(), || default_timezone().localtime(0).unwrap().tm_hour);
+/// assert_eq!(hour, 9);
+/// ```
+pub fn with_default_timezone<R>(tz: Timezone, f: impl FnOnce() -> R) -> R {
+    struct Restore;
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            OVERRIDES.with_borrow_mut(|overrides| overrides.pop());
+        }
+    }
+    OVERRIDES.with_borrow_mut(|overrides| overrides.push(Arc::new(tz)));
+    let _restore = Restore;
+    f()
+}
+
+/// Convert from leap-second to POSIX `time_t`s using the [`default_timezone`]. See [`Timezone::time2posix`].
+///
+/// Unless the default zone has a leap second table (one of the `right/` zones) this returns `time` unchanged.
+pub fn time2posix(time: crate::TimeT) -> crate::TimeT {
+    default_timezone().time2posix(time)
+}
+
+/// Convert from POSIX to leap-second `time_t`s using the [`default_timezone`]. See [`Timezone::posix2time`].
+///
+/// Unless the default zone has a leap second table (one of the `right/` zones) this returns `time` unchanged.
+pub fn posix2time(time: crate::TimeT) -> crate::TimeT {
+    default_timezone().posix2time(time)
+}
+
+fn shared_default() -> Arc<Timezone> {
+    if let Some(default) = DEFAULT.read().unwrap().as_ref() {
+        return default.tz.clone();
+    }
+    DEFAULT.write().unwrap().get_or_insert_with(|| {
+        let tz = Timezone::from_env().or_else(|_e| {
+            trace_event!(WARN, error = %_e, "can't load the system zone, falling back to UTC");
+            Timezone::new("")
+        });
+        DefaultZone{ tz: Arc::new(tz.expect("UTC always loads")), set: false }
+    }).tz.clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_zone() {
+        let _env = crate::tests::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let system = Timezone::from_env().map(|tz| tz.localtime(1690000000).expect("localtime"));
+        let first = default_timezone();
+        if let Ok(system) = system {
+            assert_eq!(first.localtime(1690000000), Ok(system));
+        }
+        assert!(Arc::ptr_eq(&first, &default_timezone()));
+        invalidate_default_timezone();
+        let reloaded = default_timezone();
+        assert!(!Arc::ptr_eq(&first, &reloaded));
+        assert_eq!(first.provenance(), reloaded.provenance());
+
+        set_default_timezone(Timezone::new("Asia/Tokyo").expect("timezone alloc"));
+        let tokyo = std::thread::spawn(default_timezone).join().expect("join");
+        assert_eq!(tokyo.localtime(0).expect("localtime").tm_gmtoff, 9 * 3600);
+        assert!(Arc::ptr_eq(&tokyo, &default_timezone()), "stale thread cache");
+        invalidate_default_timezone();
+        assert!(Arc::ptr_eq(&tokyo, &default_timezone()), "set zone must survive invalidation");
+        assert_eq!(first.provenance(), Timezone::from_env().unwrap_or_else(|_| Timezone::new("").expect("utc")).provenance());
+        assert_eq!((time2posix(1700000000), posix2time(1700000000)), (1700000000, 1700000000));
+
+        set_default_timezone(Timezone::new("right/UTC").expect("timezone alloc"));
+        assert_eq!(time2posix(1700000027), 1700000000);
+        assert_eq!(posix2time(1700000000), 1700000027);
+    }
+
+    #[test]
+    fn with_default() {
+        // The process default is changed by other tests, so this only looks at the overrides.
+        let offset = || default_timezone().localtime(0).expect("localtime").tm_gmtoff;
+        let inner = with_default_timezone(Timezone::new("Asia/Kolkata").expect("timezone alloc"), || {
+            let nested = with_default_timezone(Timezone::new("America/Chicago").expect("timezone alloc"), offset);
+            let other_thread = std::thread::spawn(offset).join().expect("join");
+            (nested, offset(), other_thread != 19800)
+        });
+        assert_eq!(inner, (-6 * 3600, 19800, true));
+        assert!(OVERRIDES.with_borrow(Vec::is_empty));
+        let panicked = std::panic::catch_unwind(|| with_default_timezone(Timezone::new("Asia/Tokyo").expect("timezone alloc"), || panic!("oops")));
+        assert!(panicked.is_err());
+        assert!(OVERRIDES.with_borrow(Vec::is_empty));
+    }
+}
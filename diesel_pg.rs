@@ -0,0 +1,100 @@
+// Postgres TIMESTAMPTZ and TIMESTAMP support for Diesel.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::{self, BigInt};
+use crate::{TimeSpec, Timestamp};
+
+// Postgres counts microseconds from 2000-01-01 00:00:00 UTC.
+const PG_EPOCH_MICROS: i64 = 946684800 * 1_000_000;
+
+fn to_pg_micros(unix_micros: Option<i64>) -> Result<i64, String> {
+    unix_micros.and_then(|micros| micros.checked_sub(PG_EPOCH_MICROS)).ok_or_else(|| "timestamp out of range for Postgres".to_string())
+}
+
+fn from_pg_micros(pg_micros: i64) -> Result<i64, String> {
+    if pg_micros == i64::MAX || pg_micros == i64::MIN {
+        return Err("infinite timestamps aren't supported".to_string());
+    }
+    pg_micros.checked_add(PG_EPOCH_MICROS).ok_or_else(|| "timestamp out of range".to_string())
+}
+
+/// Sub-second parts of stored values are dropped when they're read back.
+impl ToSql<sql_types::Timestamptz, Pg> for Timestamp {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let micros = to_pg_micros(self.0.checked_mul(1_000_000))?;
+        ToSql::<BigInt, Pg>::to_sql(&micros, &mut out.reborrow())
+    }
+}
+
+impl FromSql<sql_types::Timestamptz, Pg> for Timestamp {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Timestamp> {
+        let micros = from_pg_micros(<i64 as FromSql<BigInt, Pg>>::from_sql(bytes)?)?;
+        Ok(Timestamp(micros.div_euclid(1_000_000)))
+    }
+}
+
+/// A `TIMESTAMP` (without time zone) column holds UTC.
+impl ToSql<sql_types::Timestamp, Pg> for Timestamp {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        ToSql::<sql_types::Timestamptz, Pg>::to_sql(self, out)
+    }
+}
+
+impl FromSql<sql_types::Timestamp, Pg> for Timestamp {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Timestamp> {
+        <Timestamp as FromSql<sql_types::Timestamptz, Pg>>::from_sql(bytes)
+    }
+}
+
+/// Postgres has microsecond precision, so nanoseconds are dropped.
+impl ToSql<sql_types::Timestamptz, Pg> for TimeSpec {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let micros = to_pg_micros(self.to_unix_micros())?;
+        ToSql::<BigInt, Pg>::to_sql(&micros, &mut out.reborrow())
+    }
+}
+
+impl FromSql<sql_types::Timestamptz, Pg> for TimeSpec {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<TimeSpec> {
+        Ok(TimeSpec::from_unix_micros(from_pg_micros(<i64 as FromSql<BigInt, Pg>>::from_sql(bytes)?)?))
+    }
+}
+
+/// A `TIMESTAMP` (without time zone) column holds UTC.
+impl ToSql<sql_types::Timestamp, Pg> for TimeSpec {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        ToSql::<sql_types::Timestamptz, Pg>::to_sql(self, out)
+    }
+}
+
+impl FromSql<sql_types::Timestamp, Pg> for TimeSpec {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<TimeSpec> {
+        <TimeSpec as FromSql<sql_types::Timestamptz, Pg>>::from_sql(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::IntoSql;
+
+    #[test]
+    fn micros() {
+        assert_eq!(to_pg_micros(Some(PG_EPOCH_MICROS)), Ok(0));
+        assert_eq!(to_pg_micros(TimeSpec{ sec: 1700000000, nsec: 500_000_999 }.to_unix_micros()), Ok(753_315_200_500_000));
+        assert!(to_pg_micros(Timestamp::MIN.0.checked_mul(1_000_000)).is_err());
+        assert!(to_pg_micros(Some(i64::MIN)).is_err());
+        assert_eq!(from_pg_micros(-1), Ok(PG_EPOCH_MICROS - 1));
+        assert!(from_pg_micros(i64::MAX).is_err());
+        assert!(from_pg_micros(i64::MIN).is_err());
+
+        let query = diesel::select((Timestamp(1700000000).into_sql::<sql_types::Timestamptz>(),
+                                    TimeSpec{ sec: 0, nsec: 0 }.into_sql::<sql_types::Timestamp>()));
+        assert!(diesel::debug_query::<Pg, _>(&query).to_string().starts_with("SELECT $1, $2"));
+    }
+}
@@ -0,0 +1,99 @@
+// zdump-style listings of a zone's transitions.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::fmt;
+use std::ops::Range;
+use crate::TimeT;
+use crate::{Tm, Timezone, calendar};
+
+/// The transitions of a zone over a range of time, as listed by `zdump -v`. Create one with
+/// [`Timezone::dump`].
+///
+/// Its [`Display`][fmt::Display] output matches `zdump -v` line for line (without the `NULL` lines `zdump`
+/// prints for the extremes of `time_t`), so it can be diffed against the reference tool. With the `serde`
+/// feature it can also be serialized, for instance to JSON.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ZoneDump {
+    /** The zone name */                            pub zone    : String,
+    /** Two entries per transition */               pub entries : Vec<DumpEntry>,
+}
+
+/// One line of a [`ZoneDump`]: the last second before a transition or the first second of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DumpEntry {
+    /** System time */                              pub time   : TimeT,
+    /** UT, like `Sun Mar 11 06:59:59 2007` */      pub ut     : String,
+    /** Local time, like `Sun Mar 11 01:59:59 2007` */ pub local : String,
+    /** Daylight saving time flag */                pub isdst  : bool,
+    /** Seconds east of UT */                       pub gmtoff : i64,
+    /** Abbreviation */                             pub abbr   : String,
+}
+
+// asctime() style, but without the newline: "Sun Nov  4 01:59:59 2007"
+fn zdump_time(tm: &Tm) -> String {
+    format!("{} {} {:2} {:02}:{:02}:{:02} {}", calendar::WEEKDAY_ABBRS[tm.tm_wday as usize], calendar::MONTH_ABBRS[tm.tm_mon as usize],
+            tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec, tm.tm_year as i64 + 1900)
+}
+
+impl DumpEntry {
+    fn new(time: TimeT, tm: &Tm) -> Result<DumpEntry, String> {
+        Ok(DumpEntry{ time,
+                      ut: zdump_time(&crate::gmtime(time)?),
+                      local: zdump_time(tm),
+                      isdst: tm.tm_isdst > 0,
+                      gmtoff: tm.tm_gmtoff,
+                      abbr: tm.tm_zone.to_string() })
+    }
+}
+
+impl Timezone {
+    /// List the zone's transitions in `range` like `zdump -v` does: for each one, the last second before it
+    /// and the first second of it.
+    pub fn dump(&self, range: Range<TimeT>) -> Result<ZoneDump, String> {
+        let mut entries = vec![];
+        for (at, before, after) in self.transitions_between(range.start, range.end)? {
+            entries.push(DumpEntry::new(at - 1, &before)?);
+            entries.push(DumpEntry::new(at, &after)?);
+        }
+        Ok(ZoneDump{ zone: self.provenance().name(), entries })
+    }
+}
+
+impl fmt::Display for ZoneDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for e in self.entries.iter() {
+            writeln!(f, "{}  {} UT = {} {} isdst={} gmtoff={}", self.zone, e.ut, e.local, e.abbr, e.isdst as i32, e.gmtoff)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn zdump() {
+        let dump = Timezone::new("America/New_York").expect("timezone alloc").dump(1167609600..1199145600).expect("dump"); // 2007
+        assert_eq!(dump.to_string(), "\
+America/New_York  Sun Mar 11 06:59:59 2007 UT = Sun Mar 11 01:59:59 2007 EST isdst=0 gmtoff=-18000
+America/New_York  Sun Mar 11 07:00:00 2007 UT = Sun Mar 11 03:00:00 2007 EDT isdst=1 gmtoff=-14400
+America/New_York  Sun Nov  4 05:59:59 2007 UT = Sun Nov  4 01:59:59 2007 EDT isdst=1 gmtoff=-14400
+America/New_York  Sun Nov  4 06:00:00 2007 UT = Sun Nov  4 01:00:00 2007 EST isdst=0 gmtoff=-18000
+");
+        assert_eq!(dump.entries[1].time, 1173596400);
+        assert!(Timezone::new("UTC").expect("timezone alloc").dump(0..1000000000).expect("dump").entries.is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json() {
+        let dump = Timezone::new("Asia/Tokyo").expect("timezone alloc").dump(-620300000..-610000000).expect("dump"); // 1950
+        let json = serde_json::to_string(&dump).expect("serialize");
+        assert!(json.starts_with(r#"{"zone":"Asia/Tokyo","entries":[{"time":-620298001,"ut":"Sat May  6 14:59:59 1950","local":"Sat May  6 23:59:59 1950","isdst":false,"gmtoff":32400,"abbr":"JST"}"#), "{}", json);
+        assert_eq!(serde_json::from_str::<ZoneDump>(&json).expect("deserialize"), dump);
+    }
+}
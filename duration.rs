@@ -0,0 +1,148 @@
+// Durations expressed in calendar units.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use core::fmt;
+use alloc::{format, string::String};
+
+/// A duration made of calendar and clock units, like "1 month, 2 days and 3 hours".
+///
+/// Unlike a number of seconds, the length of a `CivilDuration` depends on where it's applied: a month can be
+/// 28 to 31 days and a day can be 23 or 25 hours when the clocks change. Each unit is kept separately so it can
+/// be applied to a local time correctly.
+///
+/// It can be converted to and from [ISO 8601](https://en.wikipedia.org/wiki/ISO_8601#Durations) duration
+/// strings:
+///
+/// ```
+/// use libtz::CivilDuration;
+/// let d: CivilDuration = "P1Y2M3DT4H5M6S".parse().expect("parse");
+/// assert_eq!(d, CivilDuration{ years: 1, months: 2, days: 3, hours: 4, minutes: 5, seconds: 6, ..Default::default() });
+/// assert_eq!(d.format(), "P1Y2M3DT4H5M6S");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CivilDuration {
+    /** Years */                                    pub years   : i32,
+    /** Months */                                   pub months  : i32,
+    /** Weeks */                                    pub weeks   : i32,
+    /** Days */                                     pub days    : i32,
+    /** Hours */                                    pub hours   : i32,
+    /** Minutes */                                  pub minutes : i32,
+    /** Seconds */                                  pub seconds : i32,
+}
+
+impl CivilDuration {
+    fn fields(&self) -> [i32; 7] {
+        [self.years, self.months, self.weeks, self.days, self.hours, self.minutes, self.seconds]
+    }
+
+    /// Format as an ISO 8601 duration string, like `P1Y2M3DT4H5M6S`. Zero units are left out (a zero duration
+    /// is `PT0S`).
+    ///
+    /// ISO 8601 has no way to give units different signs. If every non-zero unit is negative the result has a
+    /// leading minus sign (`-P1D`, as in ISO 8601-2 and RFC 5545). If the signs are mixed, each negative unit
+    /// gets its own minus sign (`P1M-1D`), which [`parse`][CivilDuration::parse] accepts but other parsers
+    /// might not.
+    pub fn format(&self) -> String {
+        let negative = self.fields().iter().any(|f| *f < 0) && self.fields().iter().all(|f| *f <= 0);
+        let sign = if negative { -1 } else { 1 };
+        let mut s = String::from(if negative { "-P" } else { "P" });
+        for (value, unit) in [(self.years, 'Y'), (self.months, 'M'), (self.weeks, 'W'), (self.days, 'D')] {
+            if value != 0 {
+                s += &format!("{}{}", value as i64 * sign, unit);
+            }
+        }
+        if self.hours != 0 || self.minutes != 0 || self.seconds != 0 || s.len() <= 2 {
+            s.push('T');
+            for (value, unit) in [(self.hours, 'H'), (self.minutes, 'M'), (self.seconds, 'S')] {
+                if value != 0 {
+                    s += &format!("{}{}", value as i64 * sign, unit);
+                }
+            }
+            if s.ends_with('T') {
+                s += "0S";
+            }
+        }
+        s
+    }
+
+    /// Parse an ISO 8601 duration string like `P1Y2M3DT4H5M6S`, `P2W` or `-PT90M`.
+    ///
+    /// Units must be in order and at least one is required. Fractional values aren't supported.
+    pub fn parse(s: &str) -> Result<CivilDuration, String> {
+        let err = |why: &str| Err(format!("Invalid ISO 8601 duration {:?}: {}", s, why));
+        let (sign, rest) = match s.as_bytes().first() {
+            Some(b'-') => (-1, &s[1..]),
+            Some(b'+') => (1, &s[1..]),
+            _          => (1, s),
+        };
+        let Some(rest) = rest.strip_prefix('P') else { return err("missing 'P'") };
+        let (date, time) = match rest.split_once('T') {
+            Some((date, time)) => (date, Some(time)),
+            None               => (rest, None),
+        };
+        let mut d = CivilDuration::default();
+        let mut any = false;
+        for (part, units) in [(Some(date), "YMWD"), (time, "HMS")] {
+            let Some(mut part) = part else { continue };
+            if units == "HMS" && part.is_empty() {
+                return err("no units after 'T'");
+            }
+            let mut allowed = units;
+            while !part.is_empty() {
+                let digits = part.find(|c: char| !c.is_ascii_digit() && c != '-').unwrap_or(part.len());
+                let Some(unit) = part[digits..].chars().next() else { return err("number without a unit") };
+                let Some(pos) = allowed.find(unit) else { return err(&format!("unexpected '{}'", unit)) };
+                let Ok(value) = part[..digits].parse::<i32>() else { return err("bad number") };
+                let field = match (units, unit) {
+                    ("YMWD", 'Y') => &mut d.years,
+                    ("YMWD", 'M') => &mut d.months,
+                    ("YMWD", 'W') => &mut d.weeks,
+                    ("YMWD", 'D') => &mut d.days,
+                    (_,      'H') => &mut d.hours,
+                    (_,      'M') => &mut d.minutes,
+                    _             => &mut d.seconds,
+                };
+                let Some(value) = value.checked_mul(sign) else { return err("out of range") };
+                *field = value;
+                allowed = &allowed[pos + 1..];
+                part = &part[digits + 1..];
+                any = true;
+            }
+        }
+        if !any {
+            return err("no units");
+        }
+        Ok(d)
+    }
+}
+
+impl fmt::Display for CivilDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format())
+    }
+}
+
+impl core::str::FromStr for CivilDuration {
+    type Err = String;
+    fn from_str(s: &str) -> Result<CivilDuration, String> {
+        CivilDuration::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn iso8601() {
+        for s in ["P1Y2M3DT4H5M6S", "P2W", "PT0S", "-P1D", "PT36H", "P1M-1D", "P1Y1W", "-P1YT1S"] {
+            assert_eq!(CivilDuration::parse(s).expect(s).format(), s);
+        }
+        assert_eq!("-PT90M".parse(), Ok(CivilDuration{ minutes: -90, ..Default::default() }));
+        assert_eq!("+P1D".parse::<CivilDuration>().expect("parse").to_string(), "P1D");
+        for bad in ["", "P", "PT", "1D", "P1H", "PT1D", "P1D1Y", "P1.5D", "P1DT", "PD", "P1D2", "-P-2147483648D"] {
+            assert!(CivilDuration::parse(bad).is_err(), "{:?} should fail", bad);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+// Timezone for no_std builds.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use core::ffi::CStr;
+use core::mem::MaybeUninit;
+use alloc::{format, vec, vec::Vec, string::{String, ToString}, sync::Arc};
+use crate::sys::{TimezoneT, tzfree, localtime_rz, mktime_z, posix2time_z, time2posix_z};
+use crate::{Tm, TimeT, ZoneInfo, StaticZone};
+
+/// A timezone, for `no_std` builds (the `std` feature turned off).
+///
+/// This has the same conversion methods as the `std` version but without a filesystem there's no zoneinfo
+/// directory to load from: zones come from TZif data compiled into the program (with [`Timezone::from_tzif`]),
+/// from static tables compiled into the program (with [`Timezone::from_static`]) or from POSIX TZ rule strings
+/// (with [`Timezone::new`]).
+///
+/// ```ignore
+/// let berlin = libtz::Timezone::from_tzif("Europe/Berlin", include_bytes!("zoneinfo/Europe/Berlin"))?;
+/// let tm = berlin.localtime(1700000000)?;
+/// ```
+pub struct Timezone {
+    tz: TimezoneT,
+    name: String,
+    data: ZoneData,
+    abbrs: Vec<Arc<str>>,
+}
+
+// The zone's tables: parsed at run time, or compiled into the program (which aren't copied).
+enum ZoneData {
+    Parsed(ZoneInfo),
+    Static(&'static StaticZone),
+}
+
+impl Timezone {
+    /// Create a [`Timezone`] from a POSIX TZ rule string (like `CET-1CEST,M3.5.0,M10.5.0/3`). The empty string
+    /// is UTC.
+    pub fn new(name: &str) -> Result<Timezone, String> {
+        let zone_info = match name {
+            "" => ZoneInfo::from_posix(&"UTC0".parse()?),
+            _  => ZoneInfo::from_posix(&name.parse()?),
+        };
+        Timezone::from_zone_info(name, zone_info)
+    }
+
+    /// Create a [`Timezone`] named `name` from the contents of a TZif file.
+    pub fn from_tzif(name: &str, data: &[u8]) -> Result<Timezone, String> {
+        Timezone::from_zone_info(name, ZoneInfo::parse(data)?)
+    }
+
+    pub(crate) fn from_zone_info(name: &str, zone_info: ZoneInfo) -> Result<Timezone, String> {
+        let mut abbrs: Vec<Arc<str>> = zone_info.abbreviations().into_iter().map(Arc::from).collect();
+        if let Some(footer) = zone_info.footer.as_deref().map(str::parse::<crate::posix::PosixTz>).transpose()? {
+            abbrs.push(footer.std_abbr.as_str().into());
+            abbrs.extend(footer.dst.map(|dst| dst.abbr.as_str().into()));
+        }
+        Ok(Timezone{ tz: crate::sys::tzalloc_zone_info(zone_info.clone())?, name: name.to_string(), data: ZoneData::Parsed(zone_info), abbrs })
+    }
+
+    pub(crate) fn from_static_zone(zone: &'static StaticZone) -> Result<Timezone, String> {
+        let mut abbrs: Vec<Arc<str>> = vec![];
+        let footer = zone.footer.iter().flat_map(|f| core::iter::once(f.std_abbr).chain(f.dst.map(|dst| dst.abbr)));
+        for abbr in zone.types.iter().map(|t| t.abbr).chain(footer) {
+            if !abbrs.iter().any(|a| **a == *abbr) {
+                abbrs.push(abbr.into());
+            }
+        }
+        Ok(Timezone{ tz: crate::sys::tzalloc_static(zone)?, name: zone.name.to_string(), data: ZoneData::Static(zone), abbrs })
+    }
+
+    /// The name the zone was created with.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The zone's compiled data: its transitions, local time types and leap second table. Zones from
+    /// [`Timezone::from_static`] don't have one (their tables aren't copied), so this is an error: use
+    /// [`Timezone::static_zone`] instead.
+    pub fn zone_info(&self) -> Result<&ZoneInfo, String> {
+        match self.data {
+            ZoneData::Parsed(ref zone_info) => Ok(zone_info),
+            ZoneData::Static(_)             => Err(format!("{}: zone is compiled in, see Timezone::static_zone()", self.name)),
+        }
+    }
+
+    /// The tables of a zone from [`Timezone::from_static`].
+    pub fn static_zone(&self) -> Option<&'static StaticZone> {
+        match self.data {
+            ZoneData::Parsed(_)    => None,
+            ZoneData::Static(zone) => Some(zone),
+        }
+    }
+
+    /// The earliest and latest times covered by the zone's transition data, or `None` if it has none (see
+    /// [`ZoneInfo::data_range`]). Outside of it answers are extrapolated, so they may be less trustworthy the further
+    /// out they are.
+    pub fn data_range(&self) -> Result<Option<(TimeT, TimeT)>, String> {
+        match self.data {
+            ZoneData::Parsed(ref zone_info) => Ok(zone_info.data_range()),
+            ZoneData::Static(zone)          => Ok(zone.data_range()),
+        }
+    }
+
+    /// Every abbreviation the zone has used or will use, in the order they first come into use. This is handy
+    /// for deciding which abbreviations to accept when parsing times in the zone.
+    ///
+    /// ```
+    /// let tz = libtz::Timezone::new("EST5EDT,M3.2.0,M11.1.0").expect("timezone alloc");
+    /// assert_eq!(tz.abbreviations(), Ok(vec!["EST".to_string(), "EDT".to_string()]));
+    /// ```
+    pub fn abbreviations(&self) -> Result<Vec<String>, String> {
+        let abbrs = match self.data {
+            ZoneData::Parsed(ref zone_info) => zone_info.abbreviations_chronological(),
+            ZoneData::Static(zone)          => zone.abbreviations_chronological(),
+        };
+        Ok(abbrs.into_iter().map(|abbr| abbr.to_string()).collect())
+    }
+
+    /// Convert system time to a local time [`Tm`].
+    pub fn localtime(&self, time: TimeT) -> Result<Tm, String> {
+        let tztm = self.localtime_raw(time)?;
+        Ok(Tm::with_zone(&tztm, self.abbr(&tztm)?))
+    }
+
+    /// Convert system time to local time, overwriting `tm`.
+    pub fn localtime_into(&self, time: TimeT, tm: &mut Tm) -> Result<(), String> {
+        let tztm = self.localtime_raw(time)?;
+        let same = tm.tm_zone.as_bytes() == unsafe { CStr::from_ptr(tztm.tm_zone) }.to_bytes();
+        *tm = Tm::with_zone(&tztm, if same { tm.tm_zone.clone() } else { self.abbr(&tztm)? });
+        Ok(())
+    }
+
+    // The abbreviations were all made when the zone was loaded, so this never allocates.
+    fn abbr(&self, tztm: &crate::sys::Tm) -> Result<Arc<str>, String> {
+        let abbr = unsafe { CStr::from_ptr(tztm.tm_zone) }.to_str().map_err(|_| "Invalid utf8")?;
+        Ok(self.abbrs.iter().find(|a| ***a == *abbr).cloned().unwrap_or_else(|| abbr.into()))
+    }
+
+    fn localtime_raw(&self, time: TimeT) -> Result<crate::sys::Tm, String> {
+        let mut tztm = MaybeUninit::<crate::sys::Tm>::uninit();
+        let ret = unsafe { localtime_rz(self.tz, &time, tztm.as_mut_ptr()) };
+        if ret.is_null() {
+            return Err(format!("{} out of range", time));
+        }
+        Ok(unsafe { tztm.assume_init() })
+    }
+
+    /// Convert local time [`Tm`] to system time.
+    pub fn mktime(&self, tm: &Tm) -> Result<TimeT, String> {
+        crate::checked_mktime(tm, |tztm| unsafe { mktime_z(self.tz, tztm) })
+    }
+
+    /// Convert from leap-second to POSIX `time_t`s.
+    pub fn time2posix(&self, time: TimeT) -> TimeT {
+        unsafe { time2posix_z(self.tz, time) }
+    }
+
+    /// Convert from POSIX to leap-second `time_t`s.
+    pub fn posix2time(&self, time: TimeT) -> TimeT {
+        unsafe { posix2time_z(self.tz, time) }
+    }
+}
+
+/// Parse a zone name with [`Timezone::new`], so `"Europe/Berlin".parse::<Timezone>()` works.
+impl core::str::FromStr for Timezone {
+    type Err = String;
+    fn from_str(name: &str) -> Result<Timezone, String> {
+        Timezone::new(name)
+    }
+}
+
+unsafe impl Send for Timezone {}
+unsafe impl Sync for Timezone {}
+
+impl Drop for Timezone {
+    fn drop(&mut self) {
+        unsafe { tzfree(self.tz) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded() {
+        let data = std::fs::read("/usr/share/zoneinfo/Europe/Berlin").expect("read");
+        let berlin = Timezone::from_tzif("Europe/Berlin", &data).expect("from_tzif");
+        assert_eq!(berlin.name(), "Europe/Berlin");
+        let tm = berlin.localtime(1700000000).expect("localtime"); // 2023-11-14 22:13:20 UTC
+        assert_eq!((tm.tm_hour, tm.tm_min, tm.tm_gmtoff, &*tm.tm_zone), (23, 13, 3600, "CET"));
+        assert_eq!(berlin.mktime(&tm), Ok(1700000000));
+
+        let mut summer = tm.clone();
+        berlin.localtime_into(1690000000, &mut summer).expect("localtime_into");
+        assert_eq!((summer.tm_isdst, &*summer.tm_zone), (1, "CEST"));
+
+        let rule = Timezone::new("CET-1CEST,M3.5.0,M10.5.0/3").expect("new");
+        for time in [1690000000, 1700000000, 4102444800] {
+            assert_eq!(rule.localtime(time), berlin.localtime(time));
+        }
+        assert_eq!(&*Timezone::new("").expect("utc").localtime(0).expect("localtime").tm_zone, "UTC");
+        assert!(Timezone::from_tzif("bad", b"not tzif").is_err());
+
+        static JUMP: StaticZone = StaticZone{ name: "Example/Jump", version: 2, transitions: &[crate::Transition{ time: 1325239200, local_time_type: 1 }],
+                                              types: &[crate::StaticLocalTimeType{ utoff: -36000, isdst: false, abbr: "-10", is_std: false, is_ut: false },
+                                                       crate::StaticLocalTimeType{ utoff: 50400, isdst: false, abbr: "+14", is_std: false, is_ut: false }],
+                                              leap_seconds: &[], footer: Some(crate::StaticPosixTz{ std_abbr: "+14", std_offset: 50400, dst: None }) };
+        let jump = Timezone::from_static(&JUMP).expect("from_static");
+        assert_eq!((jump.name(), jump.static_zone()), ("Example/Jump", Some(&JUMP)));
+        assert!(jump.zone_info().is_err());
+        assert_eq!(jump.data_range(), Ok(Some((1325239200, 1325239200))));
+        assert_eq!(jump.abbreviations(), Ok(vec!["-10".to_string(), "+14".to_string()]));
+        assert_eq!(&*jump.localtime(1325239199).expect("localtime").tm_zone, "-10");
+        assert_eq!(jump.localtime(2000000000).expect("localtime").tm_gmtoff, 50400);
+        assert_eq!(berlin.static_zone(), None);
+    }
+}
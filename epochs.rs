@@ -0,0 +1,267 @@
+// Timestamps with other epochs and units.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use alloc::{format, string::String};
+use crate::{TimeSpec, TimeT, Tm, calendar};
+
+// Seconds from 1900-01-01 (the NTP epoch) to 1970-01-01.
+const NTP_OFFSET: i64 = 2_208_988_800;
+const ERA: i64 = 1 << 32;
+// Seconds from 1601-01-01 (the Windows FILETIME epoch) to 1970-01-01.
+const FILETIME_OFFSET: i64 = 11_644_473_600;
+const TICKS_PER_SEC: u64 = 10_000_000;
+// Seconds from 1970-01-01 to 2001-01-01 (the Cocoa and Core Foundation reference date).
+const COCOA_OFFSET: i64 = 978_307_200;
+// Far beyond any real date, but small enough that seconds convert to and from `f64` without trouble.
+const COCOA_MAX: f64 = 1e15;
+
+// The nanoseconds in an NTP timestamp's fraction, rounded down.
+fn ntp_nanos(timestamp: u64) -> u32 {
+    (((timestamp & 0xffff_ffff) * 1_000_000_000) >> 32) as u32
+}
+
+impl TimeSpec {
+    /// Convert a 64 bit NTP timestamp (32 bits of seconds since 1900 and 32 bits of fraction) in NTP era `era`.
+    /// Era 0 started in 1900, era 1 starts in 2036. Returns `None` if the time doesn't fit in a [`TimeT`] (only
+    /// possible in the most extreme eras).
+    pub fn from_ntp_era(era: i32, timestamp: u64) -> Option<TimeSpec> {
+        let sec = (era as i64 * ERA).checked_add((timestamp >> 32) as i64)?.checked_sub(NTP_OFFSET)?;
+        Some(TimeSpec{ sec, nsec: ntp_nanos(timestamp) })
+    }
+
+    /// Convert a 64 bit NTP timestamp, which doesn't say what era it's in, to the time closest to `pivot`
+    /// (usually the current time). That's correct as long as the timestamp is within 68 years of `pivot`.
+    /// Returns `None` if that time doesn't fit in a [`TimeT`] (with a `pivot` near the ends of its range).
+    ///
+    /// ```
+    /// use libtz::TimeSpec;
+    /// let ts = TimeSpec::from_ntp(0xe8fe_6f80_8000_0000, 1700000000);
+    /// assert_eq!(ts, Some(TimeSpec{ sec: 1700000000, nsec: 500_000_000 }));
+    /// ```
+    pub fn from_ntp(timestamp: u64, pivot: TimeT) -> Option<TimeSpec> {
+        let base = pivot.saturating_add(NTP_OFFSET).saturating_sub(ERA / 2);
+        let secs = base.checked_add(((timestamp >> 32) as i128 - base as i128).rem_euclid(ERA as i128) as i64)?;
+        Some(TimeSpec{ sec: secs.checked_sub(NTP_OFFSET)?, nsec: ntp_nanos(timestamp) })
+    }
+
+    /// Convert to a 64 bit NTP timestamp and the NTP era it's in. The fraction is rounded up so that
+    /// [`TimeSpec::from_ntp_era`] gives back the same nanoseconds. Returns `None` if the era doesn't fit in an
+    /// `i32` (only for times near the ends of [`TimeT`]'s range).
+    pub fn to_ntp(&self) -> Option<(i32, u64)> {
+        let ntp = self.sec as i128 + NTP_OFFSET as i128;
+        let frac = ((self.nsec as u64) << 32).div_ceil(1_000_000_000);
+        Some((i32::try_from(ntp.div_euclid(ERA as i128)).ok()?, (ntp.rem_euclid(ERA as i128) as u64) << 32 | frac))
+    }
+
+    /// Convert a Windows `FILETIME` (100 nanosecond ticks since 1601-01-01 UTC, as found in NTFS metadata and
+    /// Windows event logs).
+    pub fn from_filetime(ticks: u64) -> TimeSpec {
+        TimeSpec{ sec: (ticks / TICKS_PER_SEC) as i64 - FILETIME_OFFSET, nsec: (ticks % TICKS_PER_SEC) as u32 * 100 }
+    }
+
+    /// Convert to a Windows `FILETIME`. Nanoseconds are rounded down to the 100ns tick. Returns `None` for
+    /// times before 1601 or too far in the future (after the year 60056).
+    pub fn to_filetime(&self) -> Option<u64> {
+        let secs = u64::try_from(self.sec.checked_add(FILETIME_OFFSET)?).ok()?;
+        secs.checked_mul(TICKS_PER_SEC)?.checked_add(self.nsec as u64 / 100)
+    }
+
+    /// Convert a Cocoa absolute time (`CFAbsoluteTime`, or `NSDate`'s `timeIntervalSinceReferenceDate`):
+    /// floating point seconds since 2001-01-01 00:00:00 UTC, as found in Apple plists and Core Data stores. The
+    /// fraction is rounded to the nearest nanosecond, which is more precision than a `f64` has for recent dates.
+    ///
+    /// ```
+    /// use libtz::TimeSpec;
+    /// assert_eq!(TimeSpec::from_cocoa_absolute_time(721692800.25), Ok(TimeSpec{ sec: 1700000000, nsec: 250_000_000 }));
+    /// ```
+    pub fn from_cocoa_absolute_time(time: f64) -> Result<TimeSpec, String> {
+        if !(-COCOA_MAX..=COCOA_MAX).contains(&time) {
+            return Err(format!("Cocoa absolute time {} is out of range", time));
+        }
+        // `as` truncates towards zero, so negative times with a fraction need one less.
+        let whole = time as i64 - i64::from((time as i64 as f64) > time);
+        let nsec = ((time - whole as f64) * 1e9 + 0.5) as i64;
+        Ok(TimeSpec{ sec: whole + COCOA_OFFSET + nsec / 1_000_000_000, nsec: (nsec % 1_000_000_000) as u32 })
+    }
+
+    /// Convert to a Cocoa absolute time (see [`TimeSpec::from_cocoa_absolute_time`]).
+    pub fn to_cocoa_absolute_time(&self) -> f64 {
+        (self.sec as i128 - COCOA_OFFSET as i128) as f64 + self.nsec as f64 / 1e9
+    }
+}
+
+/// Convert a Cocoa absolute time (seconds since 2001-01-01 00:00:00 UTC, see
+/// [`TimeSpec::from_cocoa_absolute_time`]) to a system time, dropping any fraction of a second.
+///
+/// ```
+/// assert_eq!(libtz::from_cocoa_absolute_time(721692800.9), Ok(1700000000));
+/// assert_eq!(libtz::to_cocoa_absolute_time(1700000000), 721692800.0);
+/// ```
+pub fn from_cocoa_absolute_time(time: f64) -> Result<TimeT, String> {
+    TimeSpec::from_cocoa_absolute_time(time).map(|ts| ts.sec)
+}
+
+/// Convert a system time to a Cocoa absolute time (seconds since 2001-01-01 00:00:00 UTC).
+pub fn to_cocoa_absolute_time(time: TimeT) -> f64 {
+    TimeSpec{ sec: time, nsec: 0 }.to_cocoa_absolute_time()
+}
+
+/// Which date system a spreadsheet's serial dates use: the number of days since an epoch, with the time of day as
+/// the fraction (so 45244.5 is noon on 2023-11-14 in the 1900 system). Serial dates have no time zone; they're
+/// whatever the wall clock said.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ExcelDateSystem {
+    /// Excel's default, from Lotus 1-2-3: day 1 is 1900-01-01, and day 60 is 1900-02-29, which didn't happen
+    /// (Lotus thought 1900 was a leap year and Excel copied it), so every later day is one more than a count of
+    /// days would give. Serials below 1 are times of day on "1900-01-00", which is taken to be 1899-12-31.
+    #[default]
+    Date1900,
+    /// Excel for the Mac before 2011: day 0 is 1904-01-01.
+    Date1904,
+}
+
+// Days from 1970-01-01 to day 0 of each system: 1899-12-30, so that day 61 is 1900-03-01 once the phantom
+// 1900-02-29 is counted, and 1904-01-01.
+const EXCEL_1900_EPOCH: i64 = calendar::days_from_civil(1899, 11, 30);
+const EXCEL_1904_EPOCH: i64 = calendar::days_from_civil(1904, 0, 1);
+// Excel's last date is 9999-12-31, serial 2958465 in the 1900 system.
+const EXCEL_MAX_SERIAL: f64 = 2_958_466.0;
+
+impl Tm {
+    /// The wall clock time of a spreadsheet serial date in `system`, rounded to the second. The fields are
+    /// labeled UTC; [`timegm`](crate::timegm) gives the system time if the spreadsheet was in UTC, or use
+    /// [`Timezone::mktime`](crate::Timezone::mktime) for the zone it was in. Negative serials, serials after
+    /// 9999 and the 1900 system's nonexistent 1900-02-29 are errors.
+    ///
+    /// ```
+    /// use libtz::{ExcelDateSystem, Tm};
+    /// let tm = Tm::from_excel_serial(45244.5, ExcelDateSystem::Date1900).unwrap();
+    /// assert_eq!(tm.strftime("%F %T"), "2023-11-14 12:00:00");
+    /// assert_eq!(libtz::timegm(&tm), Ok(1699963200));
+    /// assert_eq!(tm.to_excel_serial(ExcelDateSystem::Date1904), Ok(43782.5));
+    /// ```
+    pub fn from_excel_serial(serial: f64, system: ExcelDateSystem) -> Result<Tm, String> {
+        if !(0.0..EXCEL_MAX_SERIAL).contains(&serial) {
+            return Err(format!("Serial date {} is out of range", serial));
+        }
+        // Rounded to the nearest second: serials are floating point, so times are rarely exact.
+        let secs = (serial * 86400.0 + 0.5) as i64;
+        let (day, secs) = (secs / 86400, secs % 86400);
+        let days = match system {
+            ExcelDateSystem::Date1904             => EXCEL_1904_EPOCH + day,
+            ExcelDateSystem::Date1900 if day > 60 => EXCEL_1900_EPOCH + day,
+            ExcelDateSystem::Date1900 if day < 60 => EXCEL_1900_EPOCH + day + 1,
+            ExcelDateSystem::Date1900             => return Err("Serial date 60 is 1900-02-29, which doesn't exist".into()),
+        };
+        let (year, month, mday) = calendar::civil_from_days(days);
+        Ok(Tm::from_ymd_hms(year as i32, month + 1, mday, (secs / 3600) as i32, (secs / 60 % 60) as i32, (secs % 60) as i32))
+    }
+
+    /// The spreadsheet serial date in `system` for this time's wall clock fields (the offset is ignored; see
+    /// [`Tm::from_excel_serial`]). Times before the system's first day or after 9999 are errors.
+    pub fn to_excel_serial(&self, system: ExcelDateSystem) -> Result<f64, String> {
+        let days = self.wall_seconds().div_euclid(86400);
+        let secs = self.wall_seconds().rem_euclid(86400);
+        let day = match system {
+            ExcelDateSystem::Date1904 => days - EXCEL_1904_EPOCH,
+            ExcelDateSystem::Date1900 if days >= EXCEL_1900_EPOCH + 61 => days - EXCEL_1900_EPOCH,
+            ExcelDateSystem::Date1900 => days - EXCEL_1900_EPOCH - 1,
+        };
+        let serial = day as f64 + secs as f64 / 86400.0;
+        if !(0.0..EXCEL_MAX_SERIAL).contains(&serial) {
+            return Err(format!("{}-{:02}-{:02} is out of range for {:?} serial dates", self.astronomical_year(), self.tm_mon + 1, self.tm_mday, system));
+        }
+        Ok(serial)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ntp() {
+        assert_eq!(TimeSpec::from_ntp_era(0, 0), Some(TimeSpec{ sec: -NTP_OFFSET, nsec: 0 }));
+        assert_eq!(TimeSpec::from_ntp_era(0, (NTP_OFFSET as u64) << 32), Some(TimeSpec{ sec: 0, nsec: 0 }));
+        // Era 1 starts 2036-02-07 06:28:16 UTC.
+        assert_eq!(TimeSpec::from_ntp_era(1, 0), Some(TimeSpec{ sec: 2085978496, nsec: 0 }));
+        assert_eq!(TimeSpec::from_ntp(0x0000_0001_0000_0000, 2085978496), Some(TimeSpec{ sec: 2085978497, nsec: 0 }));
+        assert_eq!(TimeSpec::from_ntp(0xffff_ffff_0000_0000, 2085978496), Some(TimeSpec{ sec: 2085978495, nsec: 0 }));
+        for ts in [TimeSpec{ sec: 1700000000, nsec: 123_456_789 }, TimeSpec{ sec: 2085978496, nsec: 1 },
+                   TimeSpec{ sec: -NTP_OFFSET - 1, nsec: 999_999_999 }] {
+            let (era, ntp) = ts.to_ntp().expect("to_ntp");
+            assert_eq!(TimeSpec::from_ntp_era(era, ntp), Some(ts));
+            assert_eq!(TimeSpec::from_ntp(ntp, ts.sec + 1_000_000_000), Some(ts));
+        }
+        assert_eq!(TimeSpec{ sec: -NTP_OFFSET - 1, nsec: 0 }.to_ntp(), Some((-1, 0xffff_ffff_0000_0000)));
+        // The ends of the ranges.
+        assert_eq!(TimeSpec::from_ntp_era(i32::MIN, 0), None);
+        assert!(TimeSpec::from_ntp_era(i32::MAX, u64::MAX).is_some());
+        assert_eq!(TimeSpec::from_ntp(0, TimeT::MAX), None);
+        assert_eq!(TimeSpec::from_ntp((NTP_OFFSET as u64 - 1) << 32, TimeT::MIN), None);
+        assert_eq!(TimeSpec::from_ntp((NTP_OFFSET as u64) << 32, TimeT::MIN).map(|ts| ts.sec), Some(TimeT::MIN));
+        assert_eq!(TimeSpec{ sec: TimeT::MAX, nsec: 0 }.to_ntp(), None);
+        assert!(TimeSpec{ sec: TimeT::MIN, nsec: 0 }.to_ntp().is_some());
+    }
+
+    #[test]
+    fn filetime() {
+        assert_eq!(TimeSpec::from_filetime(116_444_736_000_000_000), TimeSpec{ sec: 0, nsec: 0 });
+        assert_eq!(TimeSpec::from_filetime(0), TimeSpec{ sec: -FILETIME_OFFSET, nsec: 0 });
+        assert_eq!(TimeSpec::from_filetime(133_444_736_001_234_567), TimeSpec{ sec: 1700000000, nsec: 123_456_700 });
+        assert_eq!(TimeSpec{ sec: 1700000000, nsec: 123_456_789 }.to_filetime(), Some(133_444_736_001_234_567));
+        assert_eq!(TimeSpec::from_filetime(u64::MAX).to_filetime(), Some(u64::MAX));
+        assert_eq!(TimeSpec{ sec: -FILETIME_OFFSET - 1, nsec: 999_999_999 }.to_filetime(), None);
+        assert_eq!(TimeSpec{ sec: TimeT::MAX, nsec: 0 }.to_filetime(), None);
+    }
+
+    #[test]
+    fn cocoa() {
+        assert_eq!(from_cocoa_absolute_time(0.0), Ok(COCOA_OFFSET));
+        assert_eq!(crate::gmtime(COCOA_OFFSET).map(|tm| tm.strftime("%F %T")), Ok("2001-01-01 00:00:00".into()));
+        assert_eq!(TimeSpec::from_cocoa_absolute_time(-0.25), Ok(TimeSpec{ sec: COCOA_OFFSET - 1, nsec: 750_000_000 }));
+        assert_eq!(TimeSpec::from_cocoa_absolute_time(-2.0), Ok(TimeSpec{ sec: COCOA_OFFSET - 2, nsec: 0 }));
+        assert_eq!(from_cocoa_absolute_time(-0.25), Ok(COCOA_OFFSET - 1));
+        assert_eq!(TimeSpec::from_cocoa_absolute_time(1.9999999999), Ok(TimeSpec{ sec: COCOA_OFFSET + 2, nsec: 0 }));
+        for ts in [TimeSpec{ sec: 1700000000, nsec: 500_000_000 }, TimeSpec{ sec: 0, nsec: 0 }, TimeSpec{ sec: -1, nsec: 250_000_000 }] {
+            assert_eq!(TimeSpec::from_cocoa_absolute_time(ts.to_cocoa_absolute_time()), Ok(ts));
+        }
+        assert_eq!(to_cocoa_absolute_time(COCOA_OFFSET - 86400), -86400.0);
+        for bad in [f64::NAN, f64::INFINITY, -1e16] {
+            assert!(from_cocoa_absolute_time(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn excel() {
+        use ExcelDateSystem::*;
+        let date = |serial, system| Tm::from_excel_serial(serial, system).map(|tm| tm.strftime("%F %T"));
+        assert_eq!(date(1.0, Date1900), Ok("1900-01-01 00:00:00".into()));
+        assert_eq!(date(59.0, Date1900), Ok("1900-02-28 00:00:00".into()));
+        assert!(date(60.0, Date1900).is_err());
+        assert_eq!(date(61.0, Date1900), Ok("1900-03-01 00:00:00".into()));
+        assert_eq!(date(0.75, Date1900), Ok("1899-12-31 18:00:00".into())); // A time on its own
+        assert_eq!(date(25569.0, Date1900), Ok("1970-01-01 00:00:00".into()));
+        assert_eq!(date(2958465.99999, Date1900), Ok("9999-12-31 23:59:59".into()));
+        assert_eq!(date(0.0, Date1904), Ok("1904-01-01 00:00:00".into()));
+        assert_eq!(date(45244.0 + 1.0 / 3.0, Date1904), Ok("2027-11-15 08:00:00".into()));
+        assert_eq!(date(1.0 / 86400.0 - 1e-9, Date1904), Ok("1904-01-01 00:00:01".into()));
+        for bad in [-1.0, 2958466.0, f64::NAN, f64::INFINITY] {
+            assert!(Tm::from_excel_serial(bad, Date1900).is_err(), "{}", bad);
+        }
+
+        for system in [Date1900, Date1904] {
+            for serial in [1.0, 59.5, 61.0, 1462.25, 25569.0, 45244.5, 2958465.0] {
+                let tm = Tm::from_excel_serial(serial, system).expect("from_excel_serial");
+                assert_eq!(tm.to_excel_serial(system), Ok(serial), "{} {:?}", serial, system);
+            }
+        }
+        assert_eq!(Tm::from_ymd_hms(1904, 1, 1, 0, 0, 0).to_excel_serial(Date1900), Ok(1462.0));
+        assert_eq!(Tm::from_ymd_hms(1899, 12, 31, 12, 0, 0).to_excel_serial(Date1900), Ok(0.5));
+        assert!(Tm::from_ymd_hms(1899, 12, 30, 0, 0, 0).to_excel_serial(Date1900).is_err());
+        assert!(Tm::from_ymd_hms(1903, 12, 31, 0, 0, 0).to_excel_serial(Date1904).is_err());
+        assert!(Tm::from_ymd_hms(10000, 1, 1, 0, 0, 0).to_excel_serial(Date1900).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+// Quarters and fiscal years.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use alloc::{format, string::String};
+use crate::{Date, Month, Tm};
+
+/// A fiscal year that starts on the first of `start_month`, for bucketing by fiscal year and quarter. Fiscal
+/// years are named after the calendar year they end in, so with an October start (like the US government's)
+/// fiscal 2024 runs from 2023-10-01 to 2024-09-30. Quarters are three months each, starting with the first.
+///
+/// ```
+/// use libtz::{Date, FiscalCalendar, Month};
+/// let fiscal = FiscalCalendar{ start_month: Month::October };
+/// let date = Date::new(2023, 11, 14).unwrap();
+/// assert_eq!((fiscal.fiscal_year(date), fiscal.quarter(date)), (2024, 1));
+/// assert_eq!(fiscal.start_of_quarter(2024, 3).unwrap().to_string(), "2024-04-01");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FiscalCalendar {
+    /** The month the fiscal year starts in */      pub start_month : Month,
+}
+
+impl FiscalCalendar {
+    /// The calendar year: January to December, with quarters starting in January, April, July and October.
+    pub const CALENDAR: FiscalCalendar = FiscalCalendar{ start_month: Month::January };
+
+    /// The fiscal year `date` is in.
+    pub fn fiscal_year(&self, date: Date) -> i32 {
+        let start = self.start_month as i32;
+        // `start` counts from 0 and months from 1, so this is months from the start month on.
+        date.year() + i32::from(start != 0 && date.month() > start)
+    }
+
+    /// The fiscal quarter [1, 4] `date` is in.
+    pub fn quarter(&self, date: Date) -> u8 {
+        ((date.month() - 1 - self.start_month as i32).rem_euclid(12) / 3 + 1) as u8
+    }
+
+    /// The first day of `fiscal_year`.
+    pub fn start_of_year(&self, fiscal_year: i32) -> Result<Date, String> {
+        self.start_of_quarter(fiscal_year, 1)
+    }
+
+    /// The first day of `quarter` [1, 4] of `fiscal_year`.
+    pub fn start_of_quarter(&self, fiscal_year: i32, quarter: u8) -> Result<Date, String> {
+        if !(1..=4).contains(&quarter) {
+            return Err(format!("Quarter {} is out of range", quarter));
+        }
+        let start = self.start_month as i32;
+        let month = start + (quarter as i32 - 1) * 3;
+        let year = fiscal_year.checked_sub(i32::from(start != 0)).and_then(|year| year.checked_add(month / 12))
+                              .ok_or_else(|| format!("Fiscal year {} is out of range", fiscal_year))?;
+        Date::new(year, month % 12 + 1, 1)
+    }
+}
+
+impl Default for FiscalCalendar {
+    fn default() -> FiscalCalendar {
+        FiscalCalendar::CALENDAR
+    }
+}
+
+impl Tm {
+    /// The calendar quarter [1, 4] from `tm_mon` (January to March is 1). See [`FiscalCalendar`] for fiscal
+    /// quarters.
+    pub fn quarter(&self) -> u8 {
+        (self.tm_mon.rem_euclid(12) / 3 + 1) as u8
+    }
+}
+
+#[cfg(feature = "std")]
+impl crate::Timezone {
+    /// The start of the local calendar quarter that `time` is in (see [`Timezone::start_of_day`] for days
+    /// that don't start at midnight).
+    ///
+    /// [`Timezone::start_of_day`]: crate::Timezone::start_of_day
+    ///
+    /// ```
+    /// let tz = libtz::Timezone::new("America/New_York").unwrap();
+    /// let start = tz.start_of_quarter(1700000000).unwrap(); // 2023-11-14 17:13:20 EST
+    /// assert_eq!(tz.localtime(start).unwrap().strftime("%F %T %Z"), "2023-10-01 00:00:00 EDT");
+    /// ```
+    pub fn start_of_quarter(&self, time: crate::TimeT) -> Result<crate::TimeT, String> {
+        self.start_of_fiscal_quarter(time, &FiscalCalendar::CALENDAR)
+    }
+
+    /// The start of the local `fiscal` quarter that `time` is in.
+    pub fn start_of_fiscal_quarter(&self, time: crate::TimeT, fiscal: &FiscalCalendar) -> Result<crate::TimeT, String> {
+        let date = Date::try_from(&self.localtime(time)?)?;
+        self.start_of_day(fiscal.start_of_quarter(fiscal.fiscal_year(date), fiscal.quarter(date))?)
+    }
+
+    /// The start of the local `fiscal` year that `time` is in.
+    pub fn start_of_fiscal_year(&self, time: crate::TimeT, fiscal: &FiscalCalendar) -> Result<crate::TimeT, String> {
+        let date = Date::try_from(&self.localtime(time)?)?;
+        self.start_of_day(fiscal.start_of_year(fiscal.fiscal_year(date))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fiscal() {
+        let date = |y, m, d| Date::new(y, m, d).expect("date");
+        let quarters: alloc::vec::Vec<u8> = (1..=12).map(|m| Tm::from_ymd_hms(2023, m, 1, 0, 0, 0).quarter()).collect();
+        assert_eq!(quarters, [1, 1, 1, 2, 2, 2, 3, 3, 3, 4, 4, 4]);
+
+        let calendar = FiscalCalendar::default();
+        assert_eq!((calendar.fiscal_year(date(2023, 12, 31)), calendar.quarter(date(2023, 12, 31))), (2023, 4));
+        assert_eq!(calendar.start_of_quarter(2023, 4), Ok(date(2023, 10, 1)));
+
+        let april = FiscalCalendar{ start_month: Month::April }; // Like the UK and Japan
+        assert_eq!((april.fiscal_year(date(2024, 3, 31)), april.quarter(date(2024, 3, 31))), (2024, 4));
+        assert_eq!((april.fiscal_year(date(2024, 4, 1)), april.quarter(date(2024, 4, 1))), (2025, 1));
+        assert_eq!(april.start_of_year(2025), Ok(date(2024, 4, 1)));
+        assert_eq!(april.start_of_quarter(2025, 4), Ok(date(2025, 1, 1)));
+        for m in 1..=12 {
+            let d = date(2023, m, 15);
+            let start = april.start_of_quarter(april.fiscal_year(d), april.quarter(d)).expect("start_of_quarter");
+            assert!(start <= d && d.days_since_epoch() - start.days_since_epoch() < 92, "{}", d);
+        }
+        assert!(april.start_of_quarter(2024, 0).is_err() && april.start_of_quarter(2024, 5).is_err());
+        assert!(april.start_of_year(i32::MIN).is_err());
+
+        #[cfg(feature = "std")]
+        {
+            let tz = crate::Timezone::new("Europe/London").expect("timezone alloc");
+            let local = |t: Result<crate::TimeT, String>| tz.localtime(t.expect("start")).expect("localtime").strftime("%F %T %Z");
+            let time = 1700000000; // 2023-11-14 22:13:20 GMT
+            assert_eq!(local(tz.start_of_quarter(time)), "2023-10-01 00:00:00 BST");
+            assert_eq!(local(tz.start_of_fiscal_quarter(time, &april)), "2023-10-01 00:00:00 BST");
+            assert_eq!(local(tz.start_of_fiscal_year(time, &april)), "2023-04-01 00:00:00 BST");
+            assert_eq!(local(tz.start_of_fiscal_year(time, &FiscalCalendar{ start_month: Month::February })), "2023-02-01 00:00:00 GMT");
+        }
+    }
+}
@@ -0,0 +1,244 @@
+// Formatting times as text.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use core::fmt::Write;
+use alloc::{format, string::String};
+use crate::{Tm, calendar};
+
+/// How [`format_offset`] writes a UTC offset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OffsetFormat {
+    /// `+0530`, `-0800` (ISO 8601 basic format and strftime's `%z`).
+    Basic,
+    /// `+05:30`, `-08:00` (ISO 8601 extended format and strftime's `%:z`).
+    Extended,
+    /// `+05:30`, `-08`: like `Extended` but without the minutes when they are zero (the way tz abbreviations
+    /// like `-03` are written).
+    Short,
+    /// `Z` for UTC, otherwise like `Extended` (as in RFC 3339 timestamps).
+    Zulu,
+    /// `+0530`, `-08`: like `Basic` but without the minutes when they are zero (the way tzdata writes numeric
+    /// abbreviations, zic's `%z`).
+    Abbreviation,
+}
+
+/// Write a UTC offset (like [`Tm::tm_gmtoff`]), in seconds east of UTC, as text.
+///
+/// Offsets that aren't whole minutes (local mean time in old zone data) get seconds too: `+0019:32` would be
+/// ambiguous so those are `+001932` ([`OffsetFormat::Basic`]) or `+00:19:32` (the others).
+///
+/// ```
+/// use libtz::{format_offset, OffsetFormat};
+/// assert_eq!(format_offset(19800, OffsetFormat::Basic), "+0530");
+/// assert_eq!(format_offset(19800, OffsetFormat::Extended), "+05:30");
+/// assert_eq!(format_offset(-28800, OffsetFormat::Short), "-08");
+/// assert_eq!(format_offset(0, OffsetFormat::Zulu), "Z");
+/// assert_eq!(format_offset(-28800, OffsetFormat::Abbreviation), "-08");
+/// ```
+pub fn format_offset(gmtoff: i64, format: OffsetFormat) -> String {
+    if format == OffsetFormat::Zulu && gmtoff == 0 {
+        return "Z".into();
+    }
+    let (sign, off) = if gmtoff < 0 { ('-', gmtoff.unsigned_abs()) } else { ('+', gmtoff as u64) };
+    let (hours, minutes, seconds) = (off / 3600, off / 60 % 60, off % 60);
+    let sep = if matches!(format, OffsetFormat::Basic | OffsetFormat::Abbreviation) { "" } else { ":" };
+    match (format, minutes, seconds) {
+        (_, _, 1..)                     => format!("{}{:02}{}{:02}{}{:02}", sign, hours, sep, minutes, sep, seconds),
+        (OffsetFormat::Short | OffsetFormat::Abbreviation, 0, 0) => format!("{}{:02}", sign, hours),
+        _                               => format!("{}{:02}{}{:02}", sign, hours, sep, minutes),
+    }
+}
+
+/// Before or after noon, on a 12 hour clock. From [`Tm::hour12`].
+///
+/// It parses `AM`, `PM`, `a.m.` and `p.m.`, in any case, and displays as `AM` or `PM`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Meridiem {
+    /// Midnight up to noon.
+    Am,
+    /// Noon up to midnight.
+    Pm,
+}
+
+impl Meridiem {
+    /// The hour on a 24 hour clock for `hour` (1 to 12) on a 12 hour clock, or `None` if it's out of range.
+    ///
+    /// ```
+    /// use libtz::Meridiem;
+    /// assert_eq!((Meridiem::Am.hour24(12), Meridiem::Pm.hour24(12), Meridiem::Pm.hour24(9)), (Some(0), Some(12), Some(21)));
+    /// ```
+    pub const fn hour24(self, hour: u8) -> Option<u8> {
+        if hour < 1 || hour > 12 {
+            return None;
+        }
+        Some(hour % 12 + match self { Meridiem::Am => 0, Meridiem::Pm => 12 })
+    }
+}
+
+impl core::fmt::Display for Meridiem {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(C_NAMES.am_pm[*self as usize])
+    }
+}
+
+impl core::str::FromStr for Meridiem {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Meridiem, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "am" | "a.m." => Ok(Meridiem::Am),
+            "pm" | "p.m." => Ok(Meridiem::Pm),
+            _             => Err(format!("Invalid AM/PM {:?}", s)),
+        }
+    }
+}
+
+// The words strftime() uses.
+pub(crate) struct Names {
+    pub(crate) weekdays      : [&'static str; 7],
+    pub(crate) weekday_abbrs : [&'static str; 7],
+    pub(crate) months        : [&'static str; 12],
+    pub(crate) month_abbrs   : [&'static str; 12],
+    pub(crate) am_pm         : [&'static str; 2],
+}
+
+// The C (POSIX) locale.
+pub(crate) const C_NAMES: Names = Names{ weekdays: calendar::WEEKDAY_NAMES, weekday_abbrs: calendar::WEEKDAY_ABBRS,
+                                        months: calendar::MONTH_NAMES, month_abbrs: calendar::MONTH_ABBRS,
+                                        am_pm: ["AM", "PM"] };
+
+impl Tm {
+    /// The hour on a 12 hour clock (1 to 12) and whether it's before or after noon.
+    ///
+    /// ```
+    /// use libtz::Meridiem;
+    /// let tm = libtz::gmtime(1700000000).unwrap(); // 22:13:20
+    /// assert_eq!(tm.hour12(), (10, Meridiem::Pm));
+    /// ```
+    pub fn hour12(&self) -> (u8, Meridiem) {
+        // tm_hour is a public field and might not be normalized, so wrap it around the clock.
+        let hour = (self.tm_hour as i64).rem_euclid(24);
+        let meridiem = if hour >= 12 { Meridiem::Pm } else { Meridiem::Am };
+        ((hour + 11).rem_euclid(12) as u8 + 1, meridiem)
+    }
+
+    /// Format the time like C's `strftime()`.
+    ///
+    /// Supported conversions: `%a` `%A` `%b` `%B` `%C` `%d` `%D` `%e` `%F` `%h` `%H` `%I` `%j` `%k` `%l`
+    /// `%m` `%M` `%n` `%p` `%r` `%R` `%s` `%S` `%t` `%T` `%u` `%w` `%y` `%Y` `%z` `%:z` `%Z` and `%%`, with the
+    /// meanings from POSIX (in the C locale). `%z` and `%:z` are [`format_offset`] with
+    /// [`OffsetFormat::Basic`] and [`OffsetFormat::Extended`]. Anything else is copied through unchanged.
+    ///
+    /// ```
+    /// let tm = libtz::Timezone::new("IST-5:30").unwrap().localtime(1700000000).unwrap();
+    /// assert_eq!(tm.strftime("%a %F %T %z (%Z)"), "Wed 2023-11-15 03:43:20 +0530 (IST)");
+    /// assert_eq!(tm.strftime("%Y-%m-%dT%H:%M:%S%:z"), "2023-11-15T03:43:20+05:30");
+    /// ```
+    pub fn strftime(&self, format: &str) -> String {
+        self.strftime_names(format, &C_NAMES)
+    }
+
+    pub(crate) fn strftime_names(&self, format: &str, names: &Names) -> String {
+        let mut out = String::new();
+        let mut chars = format.chars();
+        let (year, (hour12, meridiem)) = (self.tm_year as i64 + 1900, self.hour12());
+        // Unnormalized fields can be anything, so these are widened before adding.
+        let (month, yday) = (self.tm_mon as i64 + 1, self.tm_yday as i64 + 1);
+        let name = |names: &[&'static str], i: i32| names.get(i as usize).copied().unwrap_or("?");
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            let _ = match chars.next() {
+                Some('a') => write!(out, "{}", name(&names.weekday_abbrs, self.tm_wday)),
+                Some('A') => write!(out, "{}", name(&names.weekdays, self.tm_wday)),
+                Some('b') | Some('h') => write!(out, "{}", name(&names.month_abbrs, self.tm_mon)),
+                Some('B') => write!(out, "{}", name(&names.months, self.tm_mon)),
+                Some('C') => write!(out, "{:02}", year.div_euclid(100)),
+                Some('d') => write!(out, "{:02}", self.tm_mday),
+                Some('D') => write!(out, "{:02}/{:02}/{:02}", month, self.tm_mday, year.rem_euclid(100)),
+                Some('e') => write!(out, "{:2}", self.tm_mday),
+                Some('F') => write!(out, "{:04}-{:02}-{:02}", year, month, self.tm_mday),
+                Some('H') => write!(out, "{:02}", self.tm_hour),
+                Some('I') => write!(out, "{:02}", hour12),
+                Some('j') => write!(out, "{:03}", yday),
+                Some('k') => write!(out, "{:2}", self.tm_hour),
+                Some('l') => write!(out, "{:2}", hour12),
+                Some('m') => write!(out, "{:02}", month),
+                Some('M') => write!(out, "{:02}", self.tm_min),
+                Some('n') => out.write_char('\n'),
+                Some('p') => write!(out, "{}", names.am_pm[meridiem as usize]),
+                Some('r') => write!(out, "{:02}:{:02}:{:02} {}", hour12, self.tm_min, self.tm_sec, names.am_pm[meridiem as usize]),
+                Some('R') => write!(out, "{:02}:{:02}", self.tm_hour, self.tm_min),
+                Some('s') => write!(out, "{}", self.instant()),
+                Some('S') => write!(out, "{:02}", self.tm_sec),
+                Some('t') => out.write_char('\t'),
+                Some('T') => write!(out, "{:02}:{:02}:{:02}", self.tm_hour, self.tm_min, self.tm_sec),
+                Some('u') => write!(out, "{}", if self.tm_wday == 0 { 7 } else { self.tm_wday }),
+                Some('w') => write!(out, "{}", self.tm_wday),
+                Some('y') => write!(out, "{:02}", year.rem_euclid(100)),
+                Some('Y') => write!(out, "{}", year),
+                Some('z') => write!(out, "{}", format_offset(self.tm_gmtoff, OffsetFormat::Basic)),
+                Some(':') if chars.as_str().starts_with('z') => {
+                    chars.next();
+                    write!(out, "{}", format_offset(self.tm_gmtoff, OffsetFormat::Extended))
+                },
+                Some('Z') => write!(out, "{}", self.tm_zone),
+                Some('%') => out.write_char('%'),
+                Some(other) => write!(out, "%{}", other),
+                None        => out.write_char('%'),
+            };
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets() {
+        for (gmtoff, basic, extended, short, zulu, abbr) in [(0,      "+0000",   "+00:00",    "+00",       "Z",         "+00"),
+                                                             (19800,  "+0530",   "+05:30",    "+05:30",    "+05:30",    "+0530"),
+                                                             (-28800, "-0800",   "-08:00",    "-08",       "-08:00",    "-08"),
+                                                             (-1172,  "-001932", "-00:19:32", "-00:19:32", "-00:19:32", "-001932"),
+                                                             (50400,  "+1400",   "+14:00",    "+14",       "+14:00",    "+14")] {
+            assert_eq!([OffsetFormat::Basic, OffsetFormat::Extended, OffsetFormat::Short, OffsetFormat::Zulu, OffsetFormat::Abbreviation].map(|f| format_offset(gmtoff, f)),
+                       [basic, extended, short, zulu, abbr]);
+        }
+    }
+
+    #[test]
+    fn strftime() {
+        let tm = crate::gmtime(951825600).expect("gmtime"); // 2000-02-29 12:00:00 UTC, a Tuesday
+        assert_eq!(tm.strftime("%A %B %e %Y, %I:%M %p"), "Tuesday February 29 2000, 12:00 PM");
+        assert_eq!(tm.strftime("%D %R %j %u %w %C %y %s %Z %z %:z %%"), "02/29/00 12:00 060 2 2 20 00 951825600 UTC +0000 +00:00 %");
+        assert_eq!(tm.strftime("%Q %"), "%Q %");
+        let tm = Tm{ tm_hour: 0, tm_gmtoff: -16200, tm_zone: "-0430".into(), ..tm };
+        assert_eq!(tm.strftime("%k|%l|%p|%s|%:z"), " 0|12|AM|951798600|-04:30");
+        assert_eq!(Tm{ tm_hour: 13, tm_min: 5, tm_sec: 9, ..tm.clone() }.strftime("%r"), "01:05:09 PM");
+        let wild = Tm{ tm_mon: i32::MAX, tm_yday: i32::MAX, tm_gmtoff: i64::MIN, ..tm };
+        assert_eq!(wild.strftime("%m|%j|%F|%D"), "2147483648|2147483648|2000-2147483648-29|2147483648/29/00");
+        assert_eq!(wild.strftime("%s"), wild.instant().to_string()); // Past the range of an i64
+    }
+
+    #[test]
+    fn hour12() {
+        let tm = crate::gmtime(0).expect("gmtime");
+        let hours: alloc::vec::Vec<(u8, Meridiem)> = [0, 1, 11, 12, 13, 23].iter().map(|&h| Tm{ tm_hour: h, ..tm.clone() }.hour12()).collect();
+        assert_eq!(hours, [(12, Meridiem::Am), (1, Meridiem::Am), (11, Meridiem::Am), (12, Meridiem::Pm), (1, Meridiem::Pm), (11, Meridiem::Pm)]);
+        assert_eq!(Tm{ tm_hour: -1, ..tm.clone() }.hour12(), (11, Meridiem::Pm));
+        assert_eq!(Tm{ tm_hour: i32::MAX, ..tm.clone() }.hour12(), (7, Meridiem::Am));
+        assert_eq!(Tm{ tm_hour: i32::MAX, ..tm.clone() }.strftime("%I %p"), "07 AM");
+        for (hour, meridiem) in hours {
+            assert!(meridiem.hour24(hour).is_some());
+        }
+        assert_eq!((Meridiem::Am.hour24(0), Meridiem::Pm.hour24(13)), (None, None));
+        assert_eq!(("a.m.".parse(), "PM".parse(), "pm".parse::<Meridiem>()), (Ok(Meridiem::Am), Ok(Meridiem::Pm), Ok(Meridiem::Pm)));
+        assert!("noon".parse::<Meridiem>().is_err());
+        assert_eq!(format!("{}", Meridiem::Pm), "PM");
+    }
+}
@@ -0,0 +1,81 @@
+// Support for structured fuzzing with the `arbitrary` crate.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use crate::{Timestamp, Tm};
+
+// A handful of abbreviations, so `tm_zone` is something a zone could plausibly produce.
+const ABBRS: &[&str] = &["UTC", "GMT", "EST", "EDT", "CET", "CEST", "IST", "JST", "LMT", "+0530", "-03", ""];
+
+/// Any field values at all (not just valid ones), since [`Timezone::mktime`][crate::Timezone::mktime] and
+/// [`timegm`][crate::timegm] have to cope with whatever they're given.
+impl<'a> Arbitrary<'a> for Tm {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Tm> {
+        Ok(Tm{
+            tm_sec    : u.arbitrary()?,
+            tm_min    : u.arbitrary()?,
+            tm_hour   : u.arbitrary()?,
+            tm_mday   : u.arbitrary()?,
+            tm_mon    : u.arbitrary()?,
+            tm_year   : u.arbitrary()?,
+            tm_wday   : u.arbitrary()?,
+            tm_yday   : u.arbitrary()?,
+            tm_isdst  : u.arbitrary()?,
+            tm_gmtoff : u.arbitrary()?,
+            tm_zone   : (*u.choose(ABBRS)?).into(),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Timestamp {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Timestamp> {
+        Ok(Timestamp(u.arbitrary()?))
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        <crate::TimeT as Arbitrary>::size_hint(depth)
+    }
+}
+
+/// The name of one of the installed zones (see [`available_zones`][crate::available_zones]), for fuzz targets
+/// that need a zone:
+///
+/// ```
+/// use arbitrary::{Arbitrary, Unstructured};
+/// let libtz::ZoneName(name) = libtz::ZoneName::arbitrary(&mut Unstructured::new(&[1, 2, 3, 4])).expect("arbitrary");
+/// let tz = libtz::Timezone::new(&name).expect("timezone alloc");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ZoneName(pub String);
+
+impl<'a> Arbitrary<'a> for ZoneName {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<ZoneName> {
+        static ZONES: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
+        let zones = ZONES.get_or_init(|| crate::available_zones().ok().filter(|zones| !zones.is_empty())
+                                                               .unwrap_or_else(|| vec!["UTC".to_string()]));
+        Ok(ZoneName(u.choose(zones)?.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arbitrary() {
+        let data: Vec<u8> = (0..=255).cycle().take(4096).collect();
+        let mut u = Unstructured::new(&data);
+        while !u.is_empty() {
+            let tm = Tm::arbitrary(&mut u).expect("tm");
+            let _ = crate::timegm(&tm);
+            let Timestamp(time) = Timestamp::arbitrary(&mut u).expect("timestamp");
+            if let Ok(tm) = crate::gmtime(time) {
+                assert_eq!(crate::timegm(&tm), Ok(time));
+            }
+            let ZoneName(name) = ZoneName::arbitrary(&mut u).expect("zone name");
+            assert!(crate::Timezone::new(&name).is_ok(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// Conversions to and from hifitime's `Epoch`.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use hifitime::{Duration, Epoch};
+use crate::{TimeT, Timezone};
+
+// 1972-01-01 00:00:00 UTC, when UTC started having whole leap seconds. Leap second aware zones count the
+// same seconds as POSIX time up to here, and TAI was 10 seconds ahead of UTC.
+const UTC_1972: TimeT = 63072000;
+
+fn leap_epoch() -> Epoch {
+    Epoch::from_gregorian_tai_hms(1972, 1, 1, 0, 0, 10)
+}
+
+fn seconds(duration: Duration) -> Result<TimeT, String> {
+    duration.total_nanoseconds().div_euclid(1_000_000_000).try_into().map_err(|_| "Time out of range".to_string())
+}
+
+impl Timezone {
+    /// Convert system time to a [`hifitime::Epoch`].
+    ///
+    /// For a leap second aware zone (see [`Timezone::is_leap_aware`]) `time` counts leap seconds, and the epoch
+    /// is worked out from that with libtz's leap second table alone, so comparing against hifitime's own
+    /// conversion of the POSIX time cross-checks the two tables:
+    ///
+    /// ```
+    /// # use libtz::Timezone;
+    /// let right = Timezone::new_right("UTC").expect("timezone alloc");
+    /// let posix = 1483228800; // 2017-01-01 00:00:00 UTC, just after the last leap second
+    /// let epoch = right.to_hifitime(right.posix2time(posix)).expect("to_hifitime");
+    /// assert_eq!(epoch, hifitime::Epoch::from_unix_seconds(posix as f64));
+    /// assert_eq!(right.from_hifitime(epoch), Ok(right.posix2time(posix)));
+    /// ```
+    ///
+    /// Other zones' system times are POSIX times, which are converted with hifitime's leap second table.
+    ///
+    /// Before 1972 leap second aware zones treat UTC as a constant 10 seconds behind TAI, where hifitime models
+    /// the fractional offsets UTC really had, so the two disagree by up to a few seconds there.
+    pub fn to_hifitime(&self, time: TimeT) -> Result<Epoch, String> {
+        if self.is_leap_aware() {
+            let since = (time as i128 - UTC_1972 as i128) * 1_000_000_000;
+            Ok(leap_epoch() + Duration::from_total_nanoseconds(since))
+        } else {
+            Ok(Epoch::from_unix_duration(Duration::from_total_nanoseconds(time as i128 * 1_000_000_000)))
+        }
+    }
+
+    /// Convert a [`hifitime::Epoch`] to system time (the inverse of [`Timezone::to_hifitime`]). Sub-second parts
+    /// are floored.
+    pub fn from_hifitime(&self, epoch: Epoch) -> Result<TimeT, String> {
+        if self.is_leap_aware() {
+            seconds(epoch - leap_epoch())?.checked_add(UTC_1972).ok_or_else(|| "Time out of range".to_string())
+        } else {
+            seconds(epoch.to_unix_duration())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hifitime() {
+        let right = Timezone::new_right("UTC").expect("timezone alloc");
+        let posix = Timezone::new("UTC").expect("timezone alloc");
+        // Every leap second in libtz's table lands where hifitime's table says it does.
+        let leaps = right.zone_info().expect("zone info").leap_seconds.clone();
+        assert!(!leaps.is_empty());
+        for time in (UTC_1972..1700000000).step_by(5_000_000).chain(leaps.iter().flat_map(|l| [l.time - 1, l.time + 1])) {
+            let t = right.time2posix(time);
+            assert_eq!(right.to_hifitime(time), Ok(Epoch::from_unix_seconds(t as f64)), "{}", time);
+            assert_eq!(right.from_hifitime(right.to_hifitime(time).expect("to_hifitime")), Ok(time));
+            assert_eq!(posix.to_hifitime(t), Ok(Epoch::from_unix_seconds(t as f64)));
+            assert_eq!(posix.from_hifitime(Epoch::from_unix_seconds(t as f64 + 0.5)), Ok(t));
+        }
+        // The leap second itself (23:59:60) has no POSIX time, but it's the second before midnight.
+        for leap in leaps {
+            let midnight = Epoch::from_unix_seconds(right.time2posix(leap.time + 1) as f64);
+            assert_eq!(right.to_hifitime(leap.time).map(|e| e + Duration::from_seconds(1.0)), Ok(midnight));
+        }
+        assert_eq!(posix.from_hifitime(Epoch::from_unix_seconds(-0.5)), Ok(-1));
+        assert_eq!(right.to_hifitime(0), Ok(Epoch::from_gregorian_tai_hms(1970, 1, 1, 0, 0, 10)));
+    }
+}
@@ -0,0 +1,110 @@
+// Describing times relative to now, for people.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use crate::{TimeT, Timezone, Tm, calendar};
+
+fn plural(n: u64, unit: &str) -> String {
+    format!("{} {}{}", n, unit, if n == 1 { "" } else { "s" })
+}
+
+fn relative(n: u64, unit: &str, past: bool) -> String {
+    if past { format!("{} ago", plural(n, unit)) } else { format!("in {}", plural(n, unit)) }
+}
+
+fn day_number(tm: &Tm) -> i64 {
+    calendar::days_from_civil(tm.tm_year as i64 + 1900, tm.tm_mon, tm.tm_mday as i64)
+}
+
+impl Timezone {
+    /// Describe `then` relative to `now` the way a person would: "just now", "5 minutes ago", "in 3 hours",
+    /// "yesterday at 14:05", "tomorrow at 09:00", "in 2 days", "3 weeks ago", "in 4 months", "2 years ago".
+    ///
+    /// Anything an hour or more away is described by the calendar in this zone rather than by counting 24 hour
+    /// periods, so 23:00 last night is "yesterday at 23:00" at 01:00 even though it was only 2 hours ago, and
+    /// days across a DST change are still days. Months and years are calendar months and years. If `then`
+    /// can't be converted to local time the description is from the elapsed seconds alone.
+    ///
+    /// ```
+    /// use libtz::Timezone;
+    /// let tz = Timezone::new("America/New_York").unwrap();
+    /// let now = 1700000000; // Tuesday 2023-11-14 17:13:20 EST
+    /// assert_eq!(tz.humanize(now - 3 * 3600, now), "3 hours ago");
+    /// assert_eq!(tz.humanize(now - 86400 - 3 * 3600 - 8 * 60, now), "yesterday at 14:05");
+    /// assert_eq!(tz.humanize(now + 2 * 86400, now), "in 2 days");
+    /// ```
+    pub fn humanize(&self, then: TimeT, now: TimeT) -> String {
+        let past = then < now;
+        let elapsed = then.abs_diff(now);
+        if elapsed < 45 {
+            return "just now".to_string();
+        }
+        let minutes = elapsed / 60 + u64::from(elapsed % 60 >= 30); // Rounded, without overflowing
+        if minutes < 60 {
+            return relative(minutes, "minute", past);
+        }
+        let (Ok(then_tm), Ok(now_tm)) = (self.localtime(then), self.localtime(now)) else {
+            return match elapsed {
+                ..86400 => relative(elapsed / 3600, "hour", past),
+                _       => relative(elapsed / 86400, "day", past),
+            };
+        };
+        let days = day_number(&then_tm) - day_number(&now_tm);
+        match days {
+            0  => relative((elapsed / 3600).max(1), "hour", past), // At least 59.5 minutes
+            -1 => format!("yesterday at {:02}:{:02}", then_tm.tm_hour, then_tm.tm_min),
+            1  => format!("tomorrow at {:02}:{:02}", then_tm.tm_hour, then_tm.tm_min),
+            -6..=6   => relative(days.unsigned_abs(), "day", past),
+            -31..=31 => relative(days.unsigned_abs() / 7, "week", past),
+            _ => {
+                let (earlier, later) = if past { (&then_tm, &now_tm) } else { (&now_tm, &then_tm) };
+                let mut months = (later.tm_year as i64 - earlier.tm_year as i64) * 12 + (later.tm_mon - earlier.tm_mon) as i64;
+                if later.tm_mday < earlier.tm_mday {
+                    months -= 1; // The last month isn't over yet
+                }
+                match months {
+                    ..12 => relative(months.max(1) as u64, "month", past),
+                    _    => relative(months as u64 / 12, "year", past),
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn humanize() {
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        let now = 1700000000; // Tuesday 2023-11-14 17:13:20 EST
+        let at = |y, m, d, h, min| tz.mktime(&Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(y, m, d, h, min, 0) }).expect("mktime");
+        assert_eq!(tz.humanize(now, now), "just now");
+        assert_eq!(tz.humanize(now + 30, now), "just now");
+        assert_eq!(tz.humanize(now - 60, now), "1 minute ago");
+        assert_eq!(tz.humanize(now + 20 * 60, now), "in 20 minutes");
+        assert_eq!(tz.humanize(now - 3600, now), "1 hour ago");
+        assert_eq!(tz.humanize(now - 3570, now), "1 hour ago"); // 59.5 minutes
+        assert_eq!(tz.humanize(now - 3569, now), "59 minutes ago");
+        assert_eq!(tz.humanize(TimeT::MIN, 0), "106751991167300 days ago"); // Too far back for localtime()
+        assert_eq!(tz.humanize(TimeT::MIN, TimeT::MAX), "213503982334601 days ago");
+        assert_eq!(tz.humanize(at(2023, 11, 14, 23, 59), now), "in 6 hours");
+        assert_eq!(tz.humanize(at(2023, 11, 15, 0, 5), now), "tomorrow at 00:05"); // Less than 7 hours away
+        assert_eq!(tz.humanize(at(2023, 11, 13, 9, 0), now), "yesterday at 09:00");
+        assert_eq!(tz.humanize(at(2023, 11, 12, 23, 0), now), "2 days ago");
+        assert_eq!(tz.humanize(at(2023, 11, 20, 8, 0), now), "in 6 days");
+        assert_eq!(tz.humanize(at(2023, 11, 21, 8, 0), now), "in 1 week");
+        assert_eq!(tz.humanize(at(2023, 10, 20, 8, 0), now), "3 weeks ago");
+        assert_eq!(tz.humanize(at(2023, 12, 20, 8, 0), now), "in 1 month");
+        assert_eq!(tz.humanize(at(2023, 5, 15, 8, 0), now), "5 months ago");
+        assert_eq!(tz.humanize(at(2021, 11, 14, 8, 0), now), "2 years ago");
+        assert_eq!(tz.humanize(at(2022, 11, 15, 8, 0), now), "11 months ago");
+
+        // Calendar days, not 24 hour periods: the day the clocks fall back is 25 hours long.
+        let sunday = at(2023, 11, 5, 0, 30);
+        assert_eq!(tz.humanize(sunday + 24 * 3600, sunday), "in 24 hours"); // 23:30 the same day
+        assert_eq!(tz.humanize(sunday + 25 * 3600, sunday), "tomorrow at 00:30");
+    }
+}
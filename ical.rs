@@ -0,0 +1,498 @@
+// iCalendar (RFC 5545) VTIMEZONE components.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::ops::Range;
+use std::sync::Arc;
+use crate::TimeT;
+use crate::{Tm, Timezone, ZoneInfo, Transition, LocalTimeType, OffsetFormat, format_offset, calendar};
+use crate::posix::{PosixTz, PosixDst, PosixRuleDate, PosixRuleDay};
+
+pub(crate) const WEEKDAYS: [&str; 7] = ["SU", "MO", "TU", "WE", "TH", "FR", "SA"];
+
+// One STANDARD or DAYLIGHT sub-component: every start with the same offsets and name is folded into one
+// component with RDATEs, unless it recurs by rule.
+struct Observance {
+    dst: bool,
+    from: i64,
+    to: i64,
+    name: Arc<str>,
+    starts: Vec<i64>, // Wall times, in `from`'s offset
+    rrule: Option<String>,
+}
+
+// Local date-time form: 19970714T133000
+fn fmt_local(wall: i64) -> String {
+    let (year, mon, mday) = calendar::civil_from_days(wall.div_euclid(86400));
+    let secs = wall.rem_euclid(86400);
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}", year, mon + 1, mday, secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+// TEXT values escape backslashes, commas and semicolons.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(',', "\\,").replace(';', "\\;")
+}
+
+// Content lines are folded to 75 octets (without splitting a UTF-8 sequence) and end with CRLF.
+fn push_line(out: &mut String, line: &str) {
+    let mut rest = line;
+    let mut limit = 75;
+    while rest.len() > limit {
+        let mut split = limit;
+        while !rest.is_char_boundary(split) { split -= 1 }
+        out.push_str(&rest[..split]);
+        out.push_str("\r\n ");
+        rest = &rest[split..];
+        limit = 74; // Account for the leading space
+    }
+    out.push_str(rest);
+    out.push_str("\r\n");
+}
+
+// The RRULE for a footer rule date, if it's expressible as one (Julian days and times outside the day are not).
+fn rrule(date: &PosixRuleDate) -> Option<String> {
+    let PosixRuleDay::MonthWeekDay{ month, week, weekday } = date.day else { return None };
+    if !(0..86400).contains(&date.time) {
+        return None;
+    }
+    let week = if week == 5 { -1 } else { week as i32 };
+    Some(format!("FREQ=YEARLY;BYMONTH={};BYDAY={}{}", month, week, WEEKDAYS[weekday as usize]))
+}
+
+// Does the change at wall time `wall` (in the old offset) land on `date`?
+fn on_rule_date(date: &PosixRuleDate, wall: i64) -> bool {
+    let PosixRuleDay::MonthWeekDay{ month, week, weekday } = date.day else { return false };
+    let days = wall.div_euclid(86400);
+    let (year, mon, mday) = calendar::civil_from_days(days);
+    wall.rem_euclid(86400) == date.time as i64
+        && mon + 1 == month as i32
+        && calendar::weekday_from_days(days) == weekday as i32
+        && if week == 5 { mday + 7 > calendar::month_days(year, mon) } else { (mday - 1) / 7 + 1 == week as i32 }
+}
+
+impl Timezone {
+    /// Describe the zone's rules over `range` as an iCalendar
+    /// ([RFC 5545](https://www.rfc-editor.org/rfc/rfc5545#section-3.6.5)) `VTIMEZONE` component.
+    ///
+    /// The `TZID` is the zone's name (see [`Provenance`][crate::Provenance]). The first sub-component is the
+    /// observance in effect at `range.start`, followed by one per distinct offset change in the range (repeats
+    /// are listed as `RDATE`s). Changes at the end of the range that follow the zone's POSIX rule (its TZif
+    /// footer) are described with `RRULE`s instead, with an `UNTIL` if the range ends before the rule takes
+    /// over for good. Lines end in CRLF and are folded as the RFC requires.
+    pub fn to_vtimezone(&self, range: Range<TimeT>) -> Result<String, String> {
+        if range.is_empty() {
+            return Err("Empty range".to_string());
+        }
+        let zi = self.zone_info()?;
+        let footer: Option<PosixTz> = zi.footer.as_deref().and_then(|f| f.parse().ok());
+        let rules = footer.as_ref().and_then(|f| Some((f, f.dst.as_ref()?)))
+                          .and_then(|(f, dst)| Some((f, dst, dst.rule?)))
+                          .filter(|(_, _, (start, end))| rrule(start).is_some() && rrule(end).is_some());
+        let transitions = self.transitions_between(range.start, range.end)?;
+
+        // The transitions at the end of the range that follow the rule.
+        let follows = |(at, before, after): &(TimeT, Tm, Tm)| {
+            let Some((std, dst, (start, end))) = rules else { return false };
+            let wall = at + before.tm_gmtoff;
+            if after.tm_isdst > 0 {
+                on_rule_date(&start, wall) && after.tm_gmtoff == dst.offset as i64 && *after.tm_zone == *dst.abbr
+            } else {
+                on_rule_date(&end, wall) && after.tm_gmtoff == std.std_offset as i64 && *after.tm_zone == *std.std_abbr
+            }
+        };
+        let ruled = transitions.len() - transitions.iter().rev().take_while(|t| follows(t)).count();
+        let open_ended = range.end > zi.transitions.last().map_or(TimeT::MIN, |t| t.time);
+
+        let mut observances: Vec<Observance> = vec![];
+        let first = self.localtime(range.start)?;
+        observances.push(Observance{ dst: first.tm_isdst > 0, from: first.tm_gmtoff, to: first.tm_gmtoff, name: first.tm_zone.clone(),
+                                     starts: vec![range.start + first.tm_gmtoff], rrule: None });
+        for (i, (at, before, after)) in transitions.iter().enumerate() {
+            let wall = at + before.tm_gmtoff;
+            let rrule = match rules {
+                Some((_, _, (start, end))) if i >= ruled => {
+                    let rrule = rrule(if after.tm_isdst > 0 { &start } else { &end }).unwrap();
+                    if observances.iter().any(|o| o.rrule.as_ref().is_some_and(|r| r.starts_with(&rrule))) {
+                        continue; // Already covered
+                    }
+                    let last = transitions.iter().rev().find(|(_, _, a)| (a.tm_isdst > 0) == (after.tm_isdst > 0)).unwrap();
+                    Some(if open_ended { rrule } else { format!("{};UNTIL={}Z", rrule, fmt_local(last.0)) })
+                },
+                _ => None,
+            };
+            match observances.iter_mut().find(|o| rrule.is_none() && o.rrule.is_none() && o.dst == (after.tm_isdst > 0)
+                                                   && o.from == before.tm_gmtoff && o.to == after.tm_gmtoff && o.name == after.tm_zone) {
+                Some(o) => o.starts.push(wall),
+                None    => observances.push(Observance{ dst: after.tm_isdst > 0, from: before.tm_gmtoff, to: after.tm_gmtoff,
+                                                        name: after.tm_zone.clone(), starts: vec![wall], rrule }),
+            }
+        }
+
+        let mut out = String::new();
+        push_line(&mut out, "BEGIN:VTIMEZONE");
+        push_line(&mut out, &format!("TZID:{}", escape(&self.provenance().name())));
+        for o in observances.iter() {
+            let kind = if o.dst { "DAYLIGHT" } else { "STANDARD" };
+            push_line(&mut out, &format!("BEGIN:{}", kind));
+            push_line(&mut out, &format!("DTSTART:{}", fmt_local(o.starts[0])));
+            if o.starts.len() > 1 {
+                push_line(&mut out, &format!("RDATE:{}", o.starts[1..].iter().map(|s| fmt_local(*s)).collect::<Vec<_>>().join(",")));
+            }
+            if let Some(ref rrule) = o.rrule {
+                push_line(&mut out, &format!("RRULE:{}", rrule));
+            }
+            push_line(&mut out, &format!("TZOFFSETFROM:{}", format_offset(o.from, OffsetFormat::Basic)));
+            push_line(&mut out, &format!("TZOFFSETTO:{}", format_offset(o.to, OffsetFormat::Basic)));
+            push_line(&mut out, &format!("TZNAME:{}", escape(&o.name)));
+            push_line(&mut out, &format!("END:{}", kind));
+        }
+        push_line(&mut out, "END:VTIMEZONE");
+        Ok(out)
+    }
+}
+
+// Open ended RRULEs that can't be turned into a POSIX footer are expanded up to this year, like zic does.
+const HORIZON_YEAR: i64 = 2037;
+
+// A yearly RRULE on the nth weekday of a month. That's all VTIMEZONEs use in practice.
+struct Rule {
+    month: i32,   // [0, 11]
+    week: i32,    // [1, 5] from the start or [-5, -1] from the end
+    weekday: i32, // Sunday = 0
+    until: Option<(i64, bool)>, // (time, is UTC)
+    count: Option<u32>,
+}
+
+// A sub-component parsed from a VTIMEZONE.
+struct Imported {
+    dst: bool,
+    from: i64,
+    to: i64,
+    name: Option<String>,
+    dtstart: i64, // Wall time in `from`'s offset
+    rdates: Vec<i64>,
+    rrule: Option<Rule>,
+}
+
+// Date-time form, either local (19970714T133000) or UTC (19970714T173000Z).
+pub(crate) fn parse_date_time(s: &str) -> Result<(i64, bool), String> {
+    let (s, utc) = match s.strip_suffix('Z') { Some(s) => (s, true), None => (s, false) };
+    let b = s.as_bytes();
+    let num = |range: Range<usize>| s.get(range).filter(|n| n.bytes().all(|c| c.is_ascii_digit())).and_then(|n| n.parse::<i64>().ok());
+    let (Some(year), Some(mon), Some(mday), Some(hour), Some(min), Some(sec)) = (num(0..4), num(4..6), num(6..8), num(9..11), num(11..13), num(13..15))
+        else { return Err(format!("Invalid date-time {:?}", s)) };
+    if b.len() != 15 || b[8] != b'T' || !(1..=12).contains(&mon) || mday < 1 || mday > calendar::month_days(year, mon as i32 - 1) as i64
+       || hour > 23 || min > 59 || sec > 60 {
+        return Err(format!("Invalid date-time {:?}", s));
+    }
+    Ok((calendar::days_from_civil(year, mon as i32 - 1, mday) * 86400 + hour * 3600 + min * 60 + sec, utc))
+}
+
+// UTC offset form: -0500, +054500
+fn parse_offset(s: &str) -> Result<i64, String> {
+    let err = || format!("Invalid UTC offset {:?}", s);
+    let sign = match s.as_bytes().first() { Some(b'+') => 1, Some(b'-') => -1, _ => return Err(err()) };
+    let digits = &s[1..];
+    if !(digits.len() == 4 || digits.len() == 6) || !digits.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(err());
+    }
+    let n = |range: Range<usize>| digits[range].parse::<i64>().unwrap();
+    let (hours, minutes, seconds) = (n(0..2), n(2..4), if digits.len() == 6 { n(4..6) } else { 0 });
+    if minutes > 59 || seconds > 59 {
+        return Err(err());
+    }
+    Ok(sign * (hours * 3600 + minutes * 60 + seconds))
+}
+
+fn parse_rrule(s: &str) -> Result<Rule, String> {
+    let err = |why: &str| Err(format!("Unsupported RRULE {:?}: {}", s, why));
+    let (mut freq, mut month, mut byday, mut until, mut count) = (None, None, None, None, None);
+    for part in s.split(';') {
+        let Some((key, value)) = part.split_once('=') else { return err("expected NAME=VALUE") };
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ"    => freq = Some(value.to_ascii_uppercase()),
+            "BYMONTH" => month = value.parse::<i32>().ok().filter(|m| (1..=12).contains(m)),
+            "BYDAY"   => byday = Some(value.to_ascii_uppercase()),
+            "UNTIL"   => until = Some(parse_date_time(value)?),
+            "COUNT"   => count = Some(value.parse::<u32>().map_err(|_| format!("Invalid RRULE COUNT {:?}", value))?),
+            "INTERVAL" if value == "1" => {},
+            "WKST"    => {},
+            _         => return err(&format!("{} isn't supported", key)),
+        }
+    }
+    if freq.as_deref() != Some("YEARLY") {
+        return err("only FREQ=YEARLY is supported");
+    }
+    let Some(month) = month else { return err("needs a valid BYMONTH") };
+    let Some(byday) = byday else { return err("needs BYDAY") };
+    let Some(weekday) = WEEKDAYS.iter().position(|d| byday.ends_with(d)) else { return err("bad BYDAY weekday") };
+    let week = match byday[..byday.len() - 2].trim_start_matches('+').parse::<i32>() {
+        Ok(week) if week != 0 && (-5..=5).contains(&week) => week,
+        _ => return err("BYDAY needs an ordinal between -5 and 5"),
+    };
+    Ok(Rule{ month: month - 1, week, weekday: weekday as i32, until, count })
+}
+
+impl Rule {
+    // The day (since the epoch) the rule lands on in `year`, if it does.
+    fn day(&self, year: i64) -> Option<i64> {
+        let first = calendar::days_from_civil(year, self.month, 1);
+        let len = calendar::month_days(year, self.month) as i64;
+        let day = if self.week > 0 {
+            first + (self.weekday - calendar::weekday_from_days(first)).rem_euclid(7) as i64 + 7 * (self.week as i64 - 1)
+        } else {
+            let last = first + len - 1;
+            last - (calendar::weekday_from_days(last) - self.weekday).rem_euclid(7) as i64 - 7 * (-self.week as i64 - 1)
+        };
+        (first..first + len).contains(&day).then_some(day)
+    }
+
+    // The equivalent POSIX rule date, at `time` seconds into the day.
+    fn posix(&self, time: i64) -> Option<PosixRuleDate> {
+        let week = match self.week { -1 => 5, 1..=4 => self.week, _ => return None };
+        Some(PosixRuleDate{ day: PosixRuleDay::MonthWeekDay{ month: self.month as u8 + 1, week: week as u8, weekday: self.weekday as u8 }, time: time as i32 })
+    }
+}
+
+impl Imported {
+    // The wall times this observance starts at, up to HORIZON_YEAR for open ended rules.
+    fn starts(&self) -> Vec<i64> {
+        let mut starts = vec![self.dtstart];
+        starts.extend(self.rdates.iter());
+        if let Some(ref rule) = self.rrule {
+            let time = self.dtstart.rem_euclid(86400);
+            let mut count = 1;
+            let first_year = calendar::civil_from_days(self.dtstart.div_euclid(86400)).0;
+            for year in first_year..=HORIZON_YEAR.max(first_year) {
+                let Some(day) = rule.day(year) else { continue };
+                let wall = day * 86400 + time;
+                if wall <= self.dtstart {
+                    continue;
+                }
+                match rule.until {
+                    Some((until, true)) if wall - self.from > until => break,
+                    Some((until, false)) if wall > until            => break,
+                    _ => {},
+                }
+                if rule.count.is_some_and(|c| count >= c) {
+                    break;
+                }
+                starts.push(wall);
+                count += 1;
+            }
+        }
+        starts
+    }
+
+    fn open_ended(&self) -> bool {
+        self.rrule.as_ref().is_some_and(|r| r.until.is_none() && r.count.is_none())
+    }
+
+    fn abbr(&self) -> String {
+        self.name.clone().unwrap_or_else(|| format_offset(self.to, OffsetFormat::Basic))
+    }
+}
+
+// The POSIX TZ rule for an open ended pair of observances, if there is one and POSIX can express it.
+fn footer(observances: &[Imported]) -> Option<PosixTz> {
+    let open: Vec<&Imported> = observances.iter().filter(|o| o.open_ended()).collect();
+    let [a, b] = open[..] else { return None };
+    let (std, dst) = if a.dst { (b, a) } else { (a, b) };
+    if std.dst || !dst.dst || std.from != dst.to || dst.from != std.to {
+        return None;
+    }
+    let rule_date = |o: &Imported| o.rrule.as_ref()?.posix(o.dtstart.rem_euclid(86400));
+    let footer = PosixTz{ std_abbr: std.abbr(), std_offset: std.to as i32,
+                          dst: Some(PosixDst{ abbr: dst.abbr(), offset: dst.to as i32, rule: Some((rule_date(dst)?, rule_date(std)?)) }) };
+    // Not every abbreviation can be written in a POSIX rule
+    (footer.to_string().parse::<PosixTz>().ok()? == footer).then_some(footer)
+}
+
+impl Timezone {
+    /// Build a [`Timezone`] from an iCalendar ([RFC 5545](https://www.rfc-editor.org/rfc/rfc5545#section-3.6.5))
+    /// `VTIMEZONE` component, for ICS files with custom zones. `text` can be a whole calendar; the first
+    /// `VTIMEZONE` in it is used. The zone's [`Provenance`][crate::Provenance] is
+    /// [`Synthesized`][crate::Provenance::Synthesized] with the `TZID` as the name.
+    ///
+    /// `RRULE`s must be yearly on the nth weekday of a month (which is what calendar programs write). If the
+    /// zone ends with a standard and daylight rule that go on forever they become the zone's POSIX rule, so
+    /// the zone works for any year. Other open ended rules are expanded up to 2037 and the last offset is used
+    /// after that. Times before the first observance use the first observance's `TZOFFSETFROM`.
+    pub fn from_vtimezone(text: &str) -> Result<Timezone, String> {
+        let unfolded = text.replace("\r\n ", "").replace("\r\n\t", "").replace("\n ", "").replace("\n\t", "");
+        let mut lines = unfolded.lines().map(|l| l.trim_end_matches('\r')).filter(|l| !l.is_empty()).map(|line| -> Result<(String, &str), String> {
+            let (name, value) = line.split_once(':').ok_or_else(|| format!("Invalid content line {:?}", line))?;
+            let name = name.split(';').next().unwrap().to_ascii_uppercase();
+            Ok((name, value))
+        });
+        let begin = |name: &str, value: &str| name == "BEGIN" && value.eq_ignore_ascii_case("VTIMEZONE");
+        lines.find(|l| l.as_ref().map_or(true, |(name, value)| begin(name, value))).ok_or("No VTIMEZONE found")??;
+
+        let (mut tzid, mut observances, mut current): (Option<String>, Vec<Imported>, Option<Imported>) = (None, vec![], None);
+        let (mut from, mut to, mut dtstart) = (None, None, None);
+        loop {
+            let Some(line) = lines.next() else { return Err("Unterminated VTIMEZONE".to_string()) };
+            let (name, value) = line?;
+            let value = value.trim();
+            match (name.as_str(), &mut current) {
+                ("END", None) if value.eq_ignore_ascii_case("VTIMEZONE") => break,
+                ("TZID", None) => tzid = Some(value.to_string()),
+                ("BEGIN", None) if value.eq_ignore_ascii_case("STANDARD") || value.eq_ignore_ascii_case("DAYLIGHT") => {
+                    current = Some(Imported{ dst: value.eq_ignore_ascii_case("DAYLIGHT"), from: 0, to: 0, name: None, dtstart: 0, rdates: vec![], rrule: None });
+                    (from, to, dtstart) = (None, None, None);
+                },
+                ("END", Some(o)) if value.eq_ignore_ascii_case(if o.dst { "DAYLIGHT" } else { "STANDARD" }) => {
+                    let (Some(f), Some(t), Some(d)) = (from, to, dtstart) else {
+                        return Err(format!("{} needs DTSTART, TZOFFSETFROM and TZOFFSETTO", value));
+                    };
+                    let mut o = current.take().unwrap();
+                    (o.from, o.to, o.dtstart) = (f, t, d);
+                    observances.push(o);
+                },
+                ("BEGIN" | "END", _) => return Err(format!("Unexpected {}:{}", name, value)),
+                ("DTSTART", Some(_))      => match parse_date_time(value)? {
+                    (time, false) => dtstart = Some(time),
+                    (_, true)     => return Err(format!("DTSTART must be a local time, not {:?}", value)),
+                },
+                ("TZOFFSETFROM", Some(_)) => from = Some(parse_offset(value)?),
+                ("TZOFFSETTO", Some(_))   => to = Some(parse_offset(value)?),
+                ("TZNAME", Some(o))       => o.name = Some(value.replace("\\,", ",").replace("\\;", ";").replace("\\\\", "\\")),
+                ("RRULE", Some(o))        => o.rrule = Some(parse_rrule(value)?),
+                ("RDATE", Some(o))        => for rdate in value.split(',') {
+                    match parse_date_time(rdate)? {
+                        (time, false) => o.rdates.push(time),
+                        (_, true)     => return Err(format!("RDATE must be a local time, not {:?}", rdate)),
+                    }
+                },
+                _ => {}, // Other properties (TZURL, LAST-MODIFIED, COMMENT, X-...) don't affect conversions
+            }
+        }
+        let tzid = tzid.ok_or("VTIMEZONE has no TZID")?;
+        if observances.is_empty() {
+            return Err("VTIMEZONE has no STANDARD or DAYLIGHT components".to_string());
+        }
+
+        let mut types: Vec<LocalTimeType> = vec![];
+        let mut type_index = |utoff: i64, isdst: bool, abbr: String| {
+            let t = LocalTimeType{ utoff: utoff as i32, isdst, abbr, is_std: false, is_ut: false };
+            types.iter().position(|u| *u == t).unwrap_or_else(|| { types.push(t); types.len() - 1 })
+        };
+        // Type 0 is used before the first transition.
+        let earliest = observances.iter().min_by_key(|o| o.dtstart - o.from).unwrap();
+        match observances.iter().find(|o| o.to == earliest.from) {
+            Some(o) => type_index(o.to, o.dst, o.abbr()),
+            None    => type_index(earliest.from, false, format_offset(earliest.from, OffsetFormat::Basic)),
+        };
+        let mut transitions = vec![];
+        for o in observances.iter() {
+            let local_time_type = type_index(o.to, o.dst, o.abbr());
+            transitions.extend(o.starts().into_iter().map(|wall| Transition{ time: wall - o.from, local_time_type }));
+        }
+        transitions.sort_by_key(|t| t.time);
+        if let Some(w) = transitions.windows(2).find(|w| w[0].time == w[1].time && w[0] != w[1]) {
+            return Err(format!("Conflicting observances start at {}", w[0].time));
+        }
+        transitions.dedup();
+
+        let footer = footer(&observances).or_else(|| {
+            let last = &types[transitions.last().map_or(0, |t| t.local_time_type)];
+            let rule = PosixTz{ std_abbr: last.abbr.clone(), std_offset: last.utoff, dst: None };
+            (!last.isdst && rule.to_string().parse::<PosixTz>().ok()? == rule).then_some(rule)
+        });
+        Timezone::from_zone_info(&tzid, ZoneInfo{ version: 2, transitions, types, leap_seconds: vec![], footer: footer.map(|f| f.to_string()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn export() {
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        let ics = tz.to_vtimezone(1104537600..1893456000).expect("to_vtimezone"); // 2005..2030
+        assert_eq!(ics, ["BEGIN:VTIMEZONE",
+                         "TZID:America/New_York",
+                         "BEGIN:STANDARD",
+                         "DTSTART:20041231T190000",
+                         "TZOFFSETFROM:-0500",
+                         "TZOFFSETTO:-0500",
+                         "TZNAME:EST",
+                         "END:STANDARD",
+                         "BEGIN:DAYLIGHT",
+                         "DTSTART:20050403T020000",
+                         "RDATE:20060402T020000",
+                         "TZOFFSETFROM:-0500",
+                         "TZOFFSETTO:-0400",
+                         "TZNAME:EDT",
+                         "END:DAYLIGHT",
+                         "BEGIN:STANDARD",
+                         "DTSTART:20051030T020000",
+                         "RDATE:20061029T020000",
+                         "TZOFFSETFROM:-0400",
+                         "TZOFFSETTO:-0500",
+                         "TZNAME:EST",
+                         "END:STANDARD",
+                         "BEGIN:DAYLIGHT",
+                         "DTSTART:20070311T020000",
+                         "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU;UNTIL=20290311T070000Z",
+                         "TZOFFSETFROM:-0500",
+                         "TZOFFSETTO:-0400",
+                         "TZNAME:EDT",
+                         "END:DAYLIGHT",
+                         "BEGIN:STANDARD",
+                         "DTSTART:20071104T020000",
+                         "RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU;UNTIL=20291104T060000Z",
+                         "TZOFFSETFROM:-0400",
+                         "TZOFFSETTO:-0500",
+                         "TZNAME:EST",
+                         "END:STANDARD",
+                         "END:VTIMEZONE", ""].join("\r\n"));
+
+        // Past the end of the zone's explicit transitions the rule goes on forever.
+        let ics = tz.to_vtimezone(2114380800..2145916800).expect("to_vtimezone"); // 2037..2038
+        assert!(ics.contains("RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU\r\n"), "{}", ics);
+
+        let ics = Timezone::new("Asia/Kathmandu").expect("timezone alloc").to_vtimezone(0..1).expect("to_vtimezone");
+        assert!(ics.contains("TZOFFSETTO:+0530\r\nTZNAME:+0530\r\n"), "{}", ics);
+        assert!(tz.to_vtimezone(5..5).is_err());
+    }
+
+    #[test]
+    fn import() {
+        let ics = ["BEGIN:VCALENDAR", "VERSION:2.0",
+                   "BEGIN:VTIMEZONE", "TZID:Custom/Eastern", "X-LIC-LOCATION:Custom/Eastern",
+                   "BEGIN:STANDARD", "DTSTART:19671029T020000", "RRULE:FREQ=YEARLY;BYDAY=-1SU;BYMONTH=10",
+                   "TZOFFSETFROM:-0400", "TZOFFSETTO:-0500", "TZNAME:EST", "END:STANDARD",
+                   "BEGIN:DAYLIGHT", "DTSTART:19870405T020000", "RRULE:FREQ=YEARLY;BYDAY=1SU;BY",
+                   " MONTH=4", "TZOFFSETFROM:-0500", "TZOFFSETTO:-0400", "TZNAME:EDT", "END:DAYLIGHT",
+                   "END:VTIMEZONE", "END:VCALENDAR", ""].join("\r\n");
+        let tz = Timezone::from_vtimezone(&ics).expect("from_vtimezone");
+        assert_eq!(tz.provenance(), &crate::Provenance::Synthesized("Custom/Eastern".to_string()));
+        assert_eq!(tz.zone_info().expect("zone_info").footer.as_deref(), Some("EST5EDT,M4.1.0,M10.5.0"));
+        let posix = Timezone::new("EST5EDT,M4.1.0,M10.5.0").expect("timezone alloc");
+        for time in (567993600..2840140800).step_by(86400 * 7 + 3607) { // 1988..2060
+            assert_eq!(tz.localtime(time), posix.localtime(time), "time {}", time);
+        }
+
+        // Round trip through to_vtimezone()
+        let ny = Timezone::new("America/New_York").expect("timezone alloc");
+        let tz = Timezone::from_vtimezone(&ny.to_vtimezone(-1633280400..1893456000).expect("to_vtimezone")).expect("from_vtimezone"); // 1918..2030
+        for time in (-1633280400..1893456000).step_by(86400 * 5 + 3607) {
+            assert_eq!(tz.localtime(time), ny.localtime(time), "time {}", time);
+        }
+
+        for bad in ["", "BEGIN:VTIMEZONE\r\nTZID:x\r\nEND:VTIMEZONE\r\n", "BEGIN:VTIMEZONE\r\nTZID:x\r\n",
+                    &ics.replace("TZID:Custom/Eastern\r\n", ""), &ics.replace("FREQ=YEARLY", "FREQ=MONTHLY"),
+                    &ics.replace("DTSTART:19870405T020000", "DTSTART:19870405T020000Z"), &ics.replace("-0500", "-5")] {
+            assert!(Timezone::from_vtimezone(bad).is_err(), "{:?} should fail", bad);
+        }
+    }
+
+    #[test]
+    fn folding() {
+        let mut out = String::new();
+        push_line(&mut out, &"x".repeat(160));
+        assert_eq!(out, format!("{}\r\n {}\r\n {}\r\n", "x".repeat(75), "x".repeat(74), "x".repeat(11)));
+    }
+}
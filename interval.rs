@@ -0,0 +1,115 @@
+// Half-open ranges of system time.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use core::ops::Range;
+use core::time::Duration;
+use crate::TimeT;
+#[cfg(feature = "std")]
+use crate::{Date, Timezone};
+
+/// The system times from `start` up to, but not including, `end`. An interval whose `end` isn't after its
+/// `start` is empty: it contains no times and overlaps nothing.
+///
+/// ```
+/// use libtz::Interval;
+/// let booking = Interval{ start: 1700000000, end: 1700007200 };
+/// let meeting = Interval{ start: 1700003600, end: 1700010800 };
+/// assert!(booking.overlaps(&meeting));
+/// assert_eq!(booking.intersection(&meeting), Some(Interval{ start: 1700003600, end: 1700007200 }));
+/// assert!(!booking.contains(1700007200));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Interval {
+    /// The first time in the interval.
+    pub start: TimeT,
+    /// The first time after the interval.
+    pub end: TimeT,
+}
+
+impl Interval {
+    /// Whether the interval contains no times.
+    pub fn is_empty(&self) -> bool {
+        self.end <= self.start
+    }
+
+    /// Whether `time` is in the interval.
+    pub fn contains(&self, time: TimeT) -> bool {
+        self.start <= time && time < self.end
+    }
+
+    /// Whether any time is in both intervals. Intervals that only touch (one ends when the other starts) don't
+    /// overlap.
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// The times in both intervals, or `None` if there aren't any.
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        let interval = Interval{ start: self.start.max(other.start), end: self.end.min(other.end) };
+        (!interval.is_empty()).then_some(interval)
+    }
+
+    /// How long the interval is (zero if it's empty).
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(if self.is_empty() { 0 } else { self.end.abs_diff(self.start) })
+    }
+
+    /// The local dates in `tz` that the interval has any time in, in order. A stay from 15:00 one day to 11:00
+    /// two days later spans 3 days; one that ends at midnight doesn't span the day starting then.
+    #[cfg(feature = "std")]
+    pub fn local_days_spanned(&self, tz: &Timezone) -> Result<Vec<Date>, String> {
+        if self.is_empty() {
+            return Ok(vec![]);
+        }
+        let first = Date::try_from(&tz.localtime(self.start)?)?.days_since_epoch();
+        let last = Date::try_from(&tz.localtime(self.end - 1)?)?.days_since_epoch();
+        (first..=last).map(Date::from_days_since_epoch).collect()
+    }
+}
+
+impl From<Range<TimeT>> for Interval {
+    fn from(range: Range<TimeT>) -> Interval {
+        Interval{ start: range.start, end: range.end }
+    }
+}
+
+impl From<Interval> for Range<TimeT> {
+    fn from(interval: Interval) -> Range<TimeT> {
+        interval.start..interval.end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval() {
+        let a = Interval::from(100..200);
+        let b = Interval{ start: 150, end: 300 };
+        assert!(a.contains(100) && a.contains(199) && !a.contains(200) && !a.contains(99));
+        assert_eq!(a.intersection(&b), Some(Interval{ start: 150, end: 200 }));
+        assert_eq!(b.intersection(&a), a.intersection(&b));
+        assert!(!a.overlaps(&Interval{ start: 200, end: 300 })); // Only touching
+        assert_eq!(a.duration(), Duration::from_secs(100));
+        let empty = Interval{ start: 200, end: 100 };
+        assert!(empty.is_empty() && !empty.contains(150) && !empty.overlaps(&a));
+        assert_eq!(empty.duration(), Duration::ZERO);
+        assert_eq!(Range::from(a), 100..200);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn local_days_spanned() {
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        let stay = Interval{ start: 1699909200, end: 1700064000 }; // 2023-11-13 16:00 to 2023-11-15 11:00 EST
+        let days = stay.local_days_spanned(&tz).expect("local_days_spanned");
+        assert_eq!(days.iter().map(|d| d.to_string()).collect::<Vec<_>>(), ["2023-11-13", "2023-11-14", "2023-11-15"]);
+        let to_midnight = Interval{ start: 1699909200, end: 1699938000 }; // Ends at 2023-11-14 00:00 EST
+        assert_eq!(to_midnight.local_days_spanned(&tz).map(|d| d.len()), Ok(1));
+        assert_eq!(to_midnight.local_days_spanned(&Timezone::new("Europe/London").expect("timezone alloc")).map(|d| d.len()), Ok(2));
+        assert_eq!(Interval{ start: 5, end: 5 }.local_days_spanned(&tz), Ok(vec![]));
+    }
+}
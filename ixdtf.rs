@@ -0,0 +1,202 @@
+// RFC 9557 Internet Extended Date/Time Format (IXDTF): RFC 3339 timestamps with a zone annotation.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use crate::{Timezone, Provenance, TimeSpec, Tm, OffsetFormat, format_offset, calendar};
+use crate::posix::PosixTz;
+
+/// Errors from [`parse_ixdtf`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IxdtfError {
+    /// The string isn't an RFC 9557 timestamp (or uses a critical `[!...]` tag this doesn't understand).
+    Syntax(String),
+    /// The zone annotation names a zone that doesn't exist.
+    UnknownZone(String),
+    /// The offset in the timestamp isn't the zone's offset at that instant, so the two disagree about the
+    /// local time (maybe the zone's rules changed after the timestamp was written).
+    OffsetMismatch { offset: i64, zone_offset: i64 },
+}
+
+impl std::fmt::Display for IxdtfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IxdtfError::Syntax(why)                          => write!(f, "{}", why),
+            IxdtfError::UnknownZone(name)                    => write!(f, "Unknown zone {:?}", name),
+            IxdtfError::OffsetMismatch{ offset, zone_offset } => write!(f, "Offset {} doesn't match the zone's offset {}",
+                                                                       format_offset(*offset, OffsetFormat::Extended),
+                                                                       format_offset(*zone_offset, OffsetFormat::Extended)),
+        }
+    }
+}
+
+impl std::error::Error for IxdtfError {}
+
+impl From<IxdtfError> for String {
+    fn from(err: IxdtfError) -> String {
+        err.to_string()
+    }
+}
+
+/// A timestamp parsed by [`parse_ixdtf`].
+pub struct Ixdtf {
+    /// The instant, from the date, time and offset.
+    pub time: TimeSpec,
+    /// The offset from UTC written in the timestamp. `None` for `Z` (or `-00:00`), which say the instant is
+    /// known but not the local time.
+    pub offset: Option<i64>,
+    /// The zone from the annotation (`[Europe/Paris]` or `[+01:00]`), if there was one.
+    pub zone: Option<Timezone>,
+}
+
+// ±HH:MM, in seconds.
+fn parse_offset(s: &str) -> Option<i64> {
+    let b = s.as_bytes();
+    let sign = match b.first() { Some(b'+') => 1, Some(b'-') => -1, _ => return None };
+    if b.len() != 6 || b[3] != b':' || !b[1..3].iter().chain(&b[4..6]).all(u8::is_ascii_digit) {
+        return None;
+    }
+    let (hours, minutes) = (s[1..3].parse::<i64>().ok()?, s[4..6].parse::<i64>().ok()?);
+    (hours < 24 && minutes < 60).then_some(sign * (hours * 3600 + minutes * 60))
+}
+
+// A relative path in the zoneinfo directory, not some other file.
+fn is_zone_name(name: &str) -> bool {
+    !name.starts_with(':') && name.split('/').all(|part| !part.is_empty() && part != "." && part != "..")
+}
+
+// A zone with a fixed offset, for `[+01:00]` annotations.
+fn fixed_zone(offset: i64) -> Result<Timezone, String> {
+    let std_offset = i32::try_from(offset).map_err(|_| "offset out of range".to_string())?;
+    Timezone::new(&PosixTz{ std_abbr: format_offset(offset, OffsetFormat::Basic), std_offset, dst: None }.to_string())
+}
+
+/// Parse an RFC 9557 timestamp like `2024-03-10T02:30:00-08:00[America/Los_Angeles]`.
+///
+/// This is an RFC 3339 timestamp (which must have an offset, or `Z`) optionally followed by the zone it was
+/// written in, in brackets, and other bracketed tags (like `[u-ca=gregory]`) which are ignored unless they are
+/// marked critical with `!`. A zone annotation may be marked critical too (`[!America/Los_Angeles]`), but
+/// either way an offset that doesn't match the zone is reported as [`IxdtfError::OffsetMismatch`]
+/// (applications that want to trust one or the other can retry without it).
+///
+/// ```
+/// let ts = libtz::parse_ixdtf("2023-11-14T23:13:20+01:00[Europe/Paris]").expect("parse");
+/// assert_eq!(ts.time.sec, 1700000000);
+/// assert_eq!(ts.zone.unwrap().localtime(ts.time.sec).unwrap().tm_zone.as_ref(), "CET");
+/// ```
+pub fn parse_ixdtf(s: &str) -> Result<Ixdtf, IxdtfError> {
+    let syntax = |why: &str| IxdtfError::Syntax(format!("Invalid RFC 9557 timestamp {:?}: {}", s, why));
+    let (stamp, mut suffix) = s.split_at(s.find('[').unwrap_or(s.len()));
+    let b = stamp.as_bytes();
+    let num = |range: std::ops::Range<usize>| stamp.get(range).filter(|n| n.bytes().all(|c| c.is_ascii_digit())).and_then(|n| n.parse::<i32>().ok());
+    let (Some(year), Some(month), Some(day), Some(hour), Some(min), Some(sec)) = (num(0..4), num(5..7), num(8..10), num(11..13), num(14..16), num(17..19))
+        else { return Err(syntax("bad date or time")) };
+    if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || !matches!(b[10], b'T' | b't' | b' ') || b[13] != b':' || b[16] != b':'
+       || !(1..=12).contains(&month) || day < 1 || day > calendar::month_days(year as i64, month - 1) || hour > 23 || min > 59 || sec > 60 {
+        return Err(syntax("bad date or time"));
+    }
+    let mut rest = &stamp[19..];
+    let mut nsec = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            return Err(syntax("bad fraction"));
+        }
+        nsec = fraction[..digits.min(9)].parse::<u32>().unwrap() * 10u32.pow(9 - digits.min(9) as u32);
+        rest = &fraction[digits..];
+    }
+    let offset = match rest {
+        "Z" | "z" | "-00:00" => None,
+        _                    => Some(parse_offset(rest).ok_or_else(|| syntax("bad offset"))?),
+    };
+    // The year has 4 digits, so this easily fits in an i64.
+    let wall = Tm::from_ymd_hms(year, month, day, hour, min, sec.min(59)).instant() as i64 + (sec == 60) as i64;
+    let time = TimeSpec{ sec: wall - offset.unwrap_or(0), nsec };
+
+    let mut zone = None;
+    while let Some(tag) = suffix.strip_prefix('[') {
+        let end = tag.find(']').ok_or_else(|| syntax("unterminated annotation"))?;
+        let (critical, tag) = match tag[..end].strip_prefix('!') { Some(tag) => (true, tag), None => (false, &tag[..end]) };
+        suffix = &suffix[end + 2..];
+        if tag.contains('=') {
+            if critical {
+                return Err(syntax(&format!("unsupported critical tag [!{}]", tag)));
+            }
+            continue;
+        }
+        if zone.is_some() {
+            return Err(syntax("more than one zone annotation"));
+        }
+        let tz = match parse_offset(tag) {
+            Some(fixed) => fixed_zone(fixed).map_err(|e| syntax(&e))?,
+            None if !is_zone_name(tag) => return Err(IxdtfError::UnknownZone(tag.to_string())),
+            None        => Timezone::new_strict(tag).ok().filter(|tz| !matches!(tz.provenance(), Provenance::PosixRule(_)))
+                                                     .ok_or_else(|| IxdtfError::UnknownZone(tag.to_string()))?,
+        };
+        if let Some(offset) = offset {
+            let zone_offset = tz.localtime(time.sec).map_err(|e| syntax(&e))?.tm_gmtoff;
+            if zone_offset != offset {
+                return Err(IxdtfError::OffsetMismatch{ offset, zone_offset });
+            }
+        }
+        zone = Some(tz);
+    }
+    if !suffix.is_empty() {
+        return Err(syntax("junk after the timestamp"));
+    }
+    Ok(Ixdtf{ time, offset, zone })
+}
+
+impl Timezone {
+    /// Write `time` as an RFC 9557 timestamp in this zone, like `2024-03-10T03:30:00-07:00[America/Los_Angeles]`.
+    /// Nanoseconds are written (without trailing zeros) only if there are some.
+    ///
+    /// Zones that aren't from the tz database (POSIX rules, zone files outside the zoneinfo directory) have no
+    /// name other programs would understand, so they are annotated with the offset instead (`[-05:00]`).
+    pub fn to_ixdtf(&self, time: TimeSpec) -> Result<String, String> {
+        let tm = self.localtime(time.sec)?;
+        let mut out = format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", tm.tm_year as i64 + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec);
+        if time.nsec != 0 {
+            out += format!(".{:09}", time.nsec).trim_end_matches('0');
+        }
+        out += &format_offset(tm.tm_gmtoff, OffsetFormat::Extended);
+        let name = self.provenance().name();
+        let named = !matches!(self.provenance(), Provenance::PosixRule(_)) && !name.starts_with('/') && !name.is_empty();
+        out += &format!("[{}]", if named { name } else { format_offset(tm.tm_gmtoff, OffsetFormat::Extended) });
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ixdtf() {
+        let la = Timezone::new("America/Los_Angeles").expect("timezone alloc");
+        let time = TimeSpec{ sec: 1710066600, nsec: 0 }; // 2024-03-10 10:30 UTC
+        assert_eq!(la.to_ixdtf(time), Ok("2024-03-10T03:30:00-07:00[America/Los_Angeles]".to_string()));
+        assert_eq!(la.to_ixdtf(TimeSpec{ nsec: 120_000_000, ..time }), Ok("2024-03-10T03:30:00.12-07:00[America/Los_Angeles]".to_string()));
+        let rule = Timezone::new("EST5EDT,M3.2.0,M11.1.0").expect("posix");
+        assert_eq!(rule.to_ixdtf(time), Ok("2024-03-10T06:30:00-04:00[-04:00]".to_string()));
+
+        let ts = parse_ixdtf("2024-03-10T03:30:00.12-07:00[America/Los_Angeles][u-ca=gregory]").expect("parse");
+        assert_eq!((ts.time, ts.offset), (TimeSpec{ nsec: 120_000_000, ..time }, Some(-7 * 3600)));
+        assert_eq!(ts.zone.expect("zone").provenance().name(), "America/Los_Angeles");
+        // 02:30 doesn't exist that day in Los Angeles; -08:00 makes it 10:30 UTC, when the zone says -07:00.
+        assert_eq!(parse_ixdtf("2024-03-10T02:30:00-08:00[America/Los_Angeles]").err(),
+                   Some(IxdtfError::OffsetMismatch{ offset: -8 * 3600, zone_offset: -7 * 3600 }));
+        let ts = parse_ixdtf("2024-03-10T10:30:00Z[!America/Los_Angeles]").expect("parse");
+        assert_eq!((ts.time, ts.offset), (time, None));
+        let ts = parse_ixdtf("2024-03-10T06:30:00-04:00[-04:00]").expect("parse");
+        assert_eq!(ts.zone.expect("zone").localtime(0).expect("localtime").tm_gmtoff, -4 * 3600);
+        assert_eq!(parse_ixdtf("2024-03-10T10:30:00+00:00").expect("parse").time, time);
+
+        assert_eq!(parse_ixdtf("2024-03-10T10:30:00Z[America/Los_Angle]").err(), Some(IxdtfError::UnknownZone("America/Los_Angle".to_string())));
+        assert_eq!(parse_ixdtf("2024-03-10T10:30:00Z[EST5EDT,M3.2.0,M11.1.0]").err(), Some(IxdtfError::UnknownZone("EST5EDT,M3.2.0,M11.1.0".to_string())));
+        for bad in ["2024-03-10T10:30:00", "2024-02-30T10:30:00Z", "2024-03-10T10:30:00Z[!u-ca=gregory]", "2024-03-10T10:30:00Z[UTC",
+                    "2024-03-10T10:30:00Z[UTC][UTC]", "2024-03-10T10:30:00Z junk", "2024-03-10T10:30:00.Z"] {
+            assert!(matches!(parse_ixdtf(bad), Err(IxdtfError::Syntax(_))), "{:?} should fail", bad);
+        }
+    }
+}
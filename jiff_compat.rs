@@ -0,0 +1,126 @@
+// Conversions to and from jiff's types.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::sync::Arc;
+use crate::{OffsetFormat, TimeSpec, Timestamp, Timezone, Tm, format_offset, offtime, timeoff};
+use crate::posix::PosixTz;
+
+/// Fails for times outside jiff's range (years -9999 to 9999).
+impl TryFrom<Timestamp> for jiff::Timestamp {
+    type Error = String;
+    fn try_from(time: Timestamp) -> Result<jiff::Timestamp, String> {
+        jiff::Timestamp::from_second(time.0).map_err(|e| e.to_string())
+    }
+}
+
+/// Sub-second parts are floored, like [`TimeSpec::to_unix_millis`].
+impl From<jiff::Timestamp> for Timestamp {
+    fn from(time: jiff::Timestamp) -> Timestamp {
+        Timestamp(TimeSpec::from(time).sec)
+    }
+}
+
+/// Fails for times outside jiff's range (years -9999 to 9999).
+impl TryFrom<TimeSpec> for jiff::Timestamp {
+    type Error = String;
+    fn try_from(time: TimeSpec) -> Result<jiff::Timestamp, String> {
+        jiff::Timestamp::new(time.sec, time.nsec as i32).map_err(|e| e.to_string())
+    }
+}
+
+impl From<jiff::Timestamp> for TimeSpec {
+    fn from(time: jiff::Timestamp) -> TimeSpec {
+        // jiff's nanoseconds have the same sign as its seconds, so this carries into `sec` before the epoch.
+        TimeSpec::new(time.as_second(), time.subsec_nanosecond() as i64).expect("jiff's range fits in a TimeT")
+    }
+}
+
+/// A [`Tm`] only knows its offset, not its zone, so the [`jiff::Zoned`] is in a fixed offset zone of
+/// `tm_gmtoff`. Out of range fields are normalized, like [`timeoff`] does.
+impl TryFrom<&Tm> for jiff::Zoned {
+    type Error = String;
+    fn try_from(tm: &Tm) -> Result<jiff::Zoned, String> {
+        let offset = i32::try_from(tm.tm_gmtoff).map_err(|_| "Offset out of range".to_string())?;
+        let offset = jiff::tz::Offset::from_seconds(offset).map_err(|e| e.to_string())?;
+        let time = jiff::Timestamp::try_from(Timestamp(timeoff(tm, tm.tm_gmtoff)?))?;
+        Ok(jiff::Zoned::new(time, jiff::tz::TimeZone::fixed(offset)))
+    }
+}
+
+/// The [`Tm`] for the zoned time, with the offset, DST flag and abbreviation from its jiff zone. Sub-second
+/// parts are dropped.
+impl From<&jiff::Zoned> for Tm {
+    fn from(zoned: &jiff::Zoned) -> Tm {
+        let info = zoned.time_zone().to_offset_info(zoned.timestamp());
+        let mut tm = offtime(Timestamp::from(zoned.timestamp()).0, info.offset().seconds() as i64).expect("jiff's range fits in a Tm");
+        tm.tm_isdst = info.dst().is_dst() as i32;
+        tm.tm_zone = Arc::from(info.abbreviation());
+        tm
+    }
+}
+
+impl Timezone {
+    /// The same zone as a [`jiff::tz::TimeZone`], built from this zone's compiled data (see
+    /// [`Timezone::to_tzif`]) rather than from jiff's own time zone database. That makes it handy for checking
+    /// jiff's conversions against libtz's:
+    ///
+    /// ```
+    /// # use libtz::Timezone;
+    /// let tz = Timezone::new("America/Los_Angeles").expect("timezone alloc");
+    /// let zoned = jiff::Timestamp::from_second(1700000000).expect("in range").to_zoned(tz.to_jiff().expect("to_jiff"));
+    /// assert_eq!(libtz::Tm::from(&zoned), tz.localtime(1700000000).expect("localtime"));
+    /// ```
+    pub fn to_jiff(&self) -> Result<jiff::tz::TimeZone, String> {
+        jiff::tz::TimeZone::tzif(&self.provenance().name(), &self.to_tzif()?).map_err(|e| e.to_string())
+    }
+
+    /// The libtz zone for a [`jiff::tz::TimeZone`]: the installed zone with the same IANA name, or a POSIX rule
+    /// for a fixed offset. Other jiff zones (like ones made from POSIX rules) aren't supported.
+    pub fn from_jiff(tz: &jiff::tz::TimeZone) -> Result<Timezone, String> {
+        if let Some(name) = tz.iana_name() {
+            return Timezone::new(name);
+        }
+        let offset = tz.to_fixed_offset().map_err(|_| "Only jiff zones with an IANA name or a fixed offset are supported".to_string())?.seconds();
+        let rule = PosixTz{ std_abbr: format_offset(offset as i64, OffsetFormat::Abbreviation), std_offset: offset, dst: None };
+        Timezone::new(&rule.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TimeT;
+
+    #[test]
+    fn jiff() {
+        for time in [0, -1, 1700000000, -2717640000, 253402207200] {
+            let jt = jiff::Timestamp::try_from(Timestamp(time)).expect("in range");
+            assert_eq!(jt.as_second(), time);
+            assert_eq!(Timestamp::from(jt), Timestamp(time));
+        }
+        assert!(jiff::Timestamp::try_from(Timestamp(TimeT::MAX)).is_err());
+        let half = jiff::Timestamp::new(-1, -500_000_000).expect("in range");
+        assert_eq!(TimeSpec::from(half), TimeSpec{ sec: -2, nsec: 500_000_000 });
+        assert_eq!(Timestamp::from(half), Timestamp(-2));
+        assert_eq!(jiff::Timestamp::try_from(TimeSpec{ sec: -2, nsec: 500_000_000 }), Ok(half));
+
+        // Differential check of jiff against libtz with the same zone data.
+        let tz = Timezone::new("America/Los_Angeles").expect("timezone alloc");
+        let jtz = tz.to_jiff().expect("to_jiff");
+        for time in (-2717668800..2000000000).step_by(7_777_777).chain(tz.zone_info().expect("zone info").transitions.iter().flat_map(|t| [t.time - 1, t.time])) {
+            let zoned = jiff::Timestamp::from_second(time).expect("in range").to_zoned(jtz.clone());
+            let tm = tz.localtime(time).expect("localtime");
+            assert_eq!(Tm::from(&zoned), tm, "{}", time);
+            let fixed = jiff::Zoned::try_from(&tm).expect("to zoned");
+            assert_eq!((fixed.timestamp().as_second(), fixed.datetime()), (time, zoned.datetime()));
+        }
+
+        assert_eq!(Timezone::from_jiff(&jtz).expect("from_jiff").localtime(1700000000).expect("localtime"), tz.localtime(1700000000).expect("localtime"));
+        let ist = Timezone::from_jiff(&jiff::tz::TimeZone::fixed(jiff::tz::Offset::from_seconds(19800).expect("offset"))).expect("from_jiff");
+        let tm = ist.localtime(1700000000).expect("localtime");
+        assert_eq!((tm.tm_hour, tm.tm_min, tm.tm_gmtoff, &*tm.tm_zone), (3, 43, 19800, "+0530"));
+        assert_eq!(Timezone::from_jiff(&jiff::tz::TimeZone::UTC).expect("from_jiff").localtime(0).expect("localtime").tm_gmtoff, 0);
+    }
+}
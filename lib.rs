@@ -3,20 +3,253 @@
 // Copyright © 2023 David Caldwell <david@porkrind.org>
 // License: MIT (see LICENSE.md file)
 
-#![doc = include_str!("README.md")]
+#![cfg_attr(feature = "std", doc = include_str!("README.md"))]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+extern crate alloc;
+
+// Emit a `tracing` event (at `$level`, a `tracing::Level` name) when the `tracing` feature is on, and nothing
+// otherwise. The arguments aren't evaluated when it's off.
+#[cfg(feature = "tracing")]
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => { tracing::event!(tracing::Level::$level, $($arg)+) };
+}
+#[cfg(all(any(feature = "std", test), not(feature = "tracing")))]
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {};
+}
+
+#[cfg(feature = "std")]
 mod timezone;
-pub use timezone::Timezone;
+#[cfg(feature = "std")]
+pub use timezone::{Timezone, Provenance, set_zone_dirs, zone_dirs, etc_gmt_name, DstYear, ZoneStats, DstConflict, MktimeError, Resolve, Policy, OffsetInfo, Clamped, ZoneError, TmSource, DetailedTm, Elapsed};
+#[cfg(feature = "std")]
+mod builder;
+#[cfg(feature = "std")]
+pub use builder::{TimezoneBuilder, Fallback};
+#[cfg(feature = "std")]
+mod resolver;
+#[cfg(feature = "std")]
+pub use resolver::ZoneResolver;
+#[cfg(not(feature = "std"))]
+mod embedded;
+#[cfg(not(feature = "std"))]
+pub use embedded::Timezone;
+#[cfg(feature = "std")]
+mod precomputed;
+#[cfg(feature = "std")]
+pub use precomputed::{PrecomputedZone, OffsetTable, OffsetSegment};
+#[cfg(feature = "std")]
+mod provider;
+#[cfg(feature = "std")]
+pub use provider::{TimeZoneProvider, FixedOffset};
+#[cfg(feature = "std")]
+mod compact;
+#[cfg(feature = "std")]
+pub use compact::{CompactTm, COMPACT_ZONE_MAX};
+#[cfg(feature = "std")]
+mod ctm;
+#[cfg(feature = "std")]
+pub use ctm::CTm;
+#[cfg(feature = "std")]
+pub mod conformance;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(feature = "test-util")]
+mod mock;
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+#[cfg(feature = "arbitrary")]
+pub use fuzz::ZoneName;
+#[cfg(feature = "sqlx")]
+mod postgres;
+#[cfg(feature = "diesel")]
+mod diesel_pg;
+#[cfg(feature = "jiff")]
+mod jiff_compat;
+#[cfg(feature = "hifitime")]
+mod hifitime_compat;
+mod duration;
+pub use duration::CivilDuration;
+mod civil;
+pub use civil::{Date, TimeOfDay, CivilDateTime, Weekday, Month};
+mod fiscal;
+pub use fiscal::FiscalCalendar;
+mod interval;
+pub use interval::Interval;
+pub mod tm2;
+#[cfg(feature = "std")]
+mod periods;
+#[cfg(feature = "std")]
+pub use periods::{Periods, GridDay};
+#[cfg(feature = "std")]
+mod truncate;
+#[cfg(feature = "std")]
+pub use truncate::CalendarUnit;
+mod timespec;
+pub use timespec::{TimeSpec, gmtime_ns, timegm_ns};
+mod epochs;
+pub use epochs::{ExcelDateSystem, from_cocoa_absolute_time, to_cocoa_absolute_time};
+mod timestamp;
+pub use timestamp::Timestamp;
+mod format;
+pub use format::{OffsetFormat, format_offset, Meridiem};
+mod offset;
+pub use offset::UtcOffset;
+#[cfg(feature = "locales")]
+mod locale;
+#[cfg(feature = "locales")]
+pub use locale::Locale;
+#[cfg(feature = "locales")]
+mod zone_names;
+#[cfg(feature = "locales")]
+pub use zone_names::NameStyle;
+#[cfg(feature = "std")]
+mod dump;
+#[cfg(feature = "std")]
+pub use dump::{ZoneDump, DumpEntry};
+#[cfg(feature = "std")]
+mod zonetab;
+#[cfg(feature = "std")]
+pub use zonetab::{ZoneTabEntry, zone_tab};
+#[cfg(feature = "std")]
+mod default_zone;
+#[cfg(feature = "std")]
+pub use default_zone::{set_default_timezone, default_timezone, invalidate_default_timezone, with_default_timezone, time2posix, posix2time};
+#[cfg(feature = "std")]
+mod reload;
+#[cfg(feature = "std")]
+pub use reload::WatchedTimezone;
+#[cfg(feature = "std")]
+mod clock;
+#[cfg(feature = "std")]
+pub use clock::{Clock, SystemClock, FixedClock, SteppingClock};
+#[cfg(feature = "std")]
+mod humanize;
+#[cfg(feature = "std")]
+mod build_info;
+#[cfg(feature = "std")]
+pub use build_info::{build_info, BuildInfo, Backend};
+#[cfg(feature = "std")]
+mod zoned;
+#[cfg(feature = "std")]
+pub use zoned::ZonedDateTime;
+#[cfg(feature = "std")]
+mod multizone;
+#[cfg(feature = "std")]
+pub use multizone::{UpcomingTransition, upcoming_transitions, upcoming_transitions_from, overlapping_hours, TransitionChange, diff_zones};
+#[cfg(feature = "std")]
+mod verify;
+#[cfg(feature = "std")]
+pub use verify::{verify_zone, Violation};
+#[cfg(feature = "std")]
+mod registry;
+#[cfg(feature = "std")]
+pub use registry::{ZoneRegistry, Eviction};
+#[cfg(feature = "tzdata-status")]
+mod tzdata_status;
+#[cfg(feature = "tzdata-status")]
+pub use tzdata_status::{TzdataStatus, TZDATA_RELEASES, tzdata_status, tzdata_status_against, installed_tzdata_version};
+#[cfg(feature = "tzdata-install")]
+mod tzdata_install;
+#[cfg(feature = "tzdata-install")]
+pub use tzdata_install::{download_tzdata, install_tzdata, download_and_install_tzdata, TZDATA_SIGNING_KEY};
+#[cfg(feature = "async")]
+mod async_registry;
+#[cfg(feature = "async")]
+pub use async_registry::{AsyncZoneRegistry, AsyncZoneResolver, ZoneFuture};
+#[cfg(feature = "std")]
+mod lookup;
+#[cfg(feature = "std")]
+pub use lookup::{available_zones, zones_using_abbrev, identify_zone_file, system_zone_names, ZoneStatus, zone_status};
+#[cfg(all(feature = "mmap", unix))]
+mod mmap;
+#[cfg(all(feature = "mmap", unix))]
+pub use mmap::MappedZone;
+#[cfg(feature = "std")]
+mod zic;
+#[cfg(feature = "std")]
+pub use zic::ZoneSource;
+mod tzif;
+#[cfg(feature = "std")]
+mod ical;
+#[cfg(feature = "std")]
+mod ixdtf;
+#[cfg(feature = "std")]
+pub use ixdtf::{Ixdtf, IxdtfError, parse_ixdtf};
+#[cfg(feature = "rrule")]
+mod rrule;
+#[cfg(feature = "rrule")]
+pub use rrule::{RRule, Frequency, Occurrences};
+#[cfg(feature = "relative")]
+mod relative;
+#[cfg(feature = "relative")]
+pub use relative::LocalResult;
+pub use tzif::{ZoneInfo, TzifView, Transition, LocalTimeType, LeapSecond, TzifReport, TzifError, validate_tzif};
+mod static_zone;
+pub use static_zone::{StaticZone, StaticLocalTimeType, StaticPosixTz, StaticPosixDst, static_zone_ident};
+#[cfg(feature = "std")]
+pub use static_zone::generate_static_zones;
+mod posix;
+pub use posix::{PosixRuleDate, PosixRuleDay};
+mod calendar;
+#[cfg(feature = "std")]
+mod android;
+pub use calendar::{Era, astronomical_to_era, era_to_astronomical, is_leap_year, days_in_year, days_in_month};
+use calendar::{MONTH_ABBRS, month_days};
+
+#[cfg(not(any(feature = "c-backend", feature = "rust-backend")))]
+compile_error!("libtz needs a backend: enable either the `c-backend` (default) or `rust-backend` feature");
+#[cfg(feature = "rust-backend")]
+mod sys;
+#[cfg(not(feature = "rust-backend"))]
+use libtz_sys as sys;
+// Check the pure Rust backend against libtz.
+#[cfg(all(test, not(feature = "rust-backend")))]
+#[path = "sys.rs"]
+mod native_sys;
 
-pub use libtz_sys::TimeT;
-use std::mem::MaybeUninit;
-use std::ffi::{CStr};
+pub use sys::{TimeT, TimezoneT};
+
+/// A system time in 64 bits, whatever the platform's `time_t` is. [`TimeT`] is always this: libtz-sys builds
+/// libtz with a 64 bit `time_t` (and the Rust backend matches it), so times after 2038 work even where the
+/// system's `time_t` is 32 bits. The crate won't build if that ever stops being true.
+pub type Time64 = i64;
+
+const _: fn(TimeT) -> Time64 = core::convert::identity; // TimeT is exactly Time64
+
+/// Convert `time` for an API that takes a 32 bit `time_t`. Times it can't hold (before 1901-12-13 20:45:52 UTC
+/// or after 2038-01-19 03:14:07 UTC) are errors rather than silently wrapping.
+///
+/// ```
+/// assert_eq!(libtz::to_time32(1700000000), Ok(1700000000));
+/// assert!(libtz::to_time32(2147483648).is_err()); // 2038-01-19 03:14:08 UTC
+/// ```
+pub fn to_time32(time: TimeT) -> Result<i32, String> {
+    i32::try_from(time).map_err(|_| format!("Time {} doesn't fit in a 32 bit time_t", time))
+}
+use core::mem::MaybeUninit;
+use core::ffi::CStr;
+use alloc::{format, vec, string::{String, ToString}, vec::Vec, sync::Arc};
 
 /// A broken down time representation, logically equivalent to `struct tm` in
 /// unix (though not binary compatible).
 ///
+/// The abbreviation is shared (zones intern their abbreviations) so cloning a `Tm` never allocates.
+///
+/// Dates use the proleptic Gregorian calendar: the Gregorian rules are applied to every year, even those before
+/// the calendar was adopted in 1582 (when Julian dates were in use). Years are numbered astronomically, so
+/// `tm_year` of -1900 is year 0 (1 BCE), -1901 is year -1 (2 BCE), and so on. See [`Tm::era_year`] for
+/// converting to BCE/CE. The earliest representable year is limited by `tm_year` being an `i32`.
+///
+/// `Tm`s are ordered chronologically: by the instant they represent (their fields as UTC, minus `tm_gmtoff`),
+/// with the remaining fields compared in declaration order to break ties. Out of range fields are normalized
+/// for the comparison, like [`timegm`] would.
+///
 /// Reference: <https://pubs.opengroup.org/onlinepubs/7908799/xsh/time.h.html>
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Tm {
     /** Seconds          [0, 60] */                 pub tm_sec    : i32,
     /** Minutes          [0, 59] */                 pub tm_min    : i32,
@@ -29,15 +262,227 @@ pub struct Tm {
     /** Daylight savings flag */                    pub tm_isdst  : i32,
 
     /** Seconds East of UTC */                      pub tm_gmtoff : i64,
-    /** Timezone abbreviation */                    pub tm_zone   : String,
+    /** Timezone abbreviation */                    pub tm_zone   : Arc<str>,
+}
+
+impl Tm {
+    /// Check that each field is in its normal range and that the day exists in that month and year.
+    ///
+    /// [`Timezone::mktime()`] quietly normalizes out of range values (Feb 30 becomes Mar 1 or 2) and reports
+    /// unrepresentable ones with a generic error. This reports every problem it finds, one message per problem
+    /// (like `"tm_mon=13 out of range [0, 11]"` or `"Feb 30 invalid for 2023"`). `tm_isdst`, `tm_gmtoff` and
+    /// `tm_zone` are not checked.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = vec![];
+        for (name, value, min, max) in [("tm_sec",  self.tm_sec,  0, 60),
+                                         ("tm_min",  self.tm_min,  0, 59),
+                                         ("tm_hour", self.tm_hour, 0, 23),
+                                         ("tm_mday", self.tm_mday, 1, 31),
+                                         ("tm_mon",  self.tm_mon,  0, 11),
+                                         ("tm_wday", self.tm_wday, 0, 6),
+                                         ("tm_yday", self.tm_yday, 0, 365)] {
+            if !(min..=max).contains(&value) {
+                problems.push(format!("{}={} out of range [{}, {}]", name, value, min, max));
+            }
+        }
+        if (0..12).contains(&self.tm_mon) && (1..=31).contains(&self.tm_mday) {
+            let year = self.tm_year as i64 + 1900;
+            if self.tm_mday > month_days(year, self.tm_mon) {
+                problems.push(format!("{} {} invalid for {}", MONTH_ABBRS[self.tm_mon as usize], self.tm_mday, year));
+            }
+        }
+        if problems.is_empty() { Ok(()) } else { Err(problems) }
+    }
+
+    /// Return a copy with every field carried into its normal range (so `tm_mday = 35` in January becomes
+    /// February 4th, `tm_sec = -1` becomes 59 seconds into the previous minute, etc.) and with `tm_wday` and
+    /// `tm_yday` filled in to match.
+    ///
+    /// This is pure calendar arithmetic in the proleptic Gregorian calendar: unlike
+    /// [`Timezone::mktime()`] it doesn't consult any timezone, so it knows nothing about DST gaps and leaves
+    /// `tm_isdst`, `tm_gmtoff` and `tm_zone` alone. It fails only if the year overflows.
+    pub fn normalized(&self) -> Result<Tm, String> {
+        let wall = self.wall_seconds();
+        let days = wall.div_euclid(86400);
+        let (year, month, mday) = calendar::civil_from_days(days);
+        let secs = wall.rem_euclid(86400) as i32;
+        Ok(Tm{
+            tm_sec    : secs % 60,
+            tm_min    : secs / 60 % 60,
+            tm_hour   : secs / 3600,
+            tm_mday   : mday,
+            tm_mon    : month,
+            tm_year   : i32::try_from(year - 1900).map_err(|_| "Year out of range".to_string())?,
+            tm_wday   : calendar::weekday_from_days(days),
+            tm_yday   : (days - calendar::days_from_civil(year, 0, 1)) as i32,
+            tm_isdst  : self.tm_isdst,
+            tm_gmtoff : self.tm_gmtoff,
+            tm_zone   : self.tm_zone.clone(),
+        })
+    }
+
+    // The wall clock time as seconds since 1970-01-01 00:00:00 *local* time (ie, as if the offset were 0), with
+    // all fields carried. This can't overflow since the fields are all i32s.
+    pub(crate) fn wall_seconds(&self) -> i64 {
+        let secs = self.tm_hour as i64 * 3600 + self.tm_min as i64 * 60 + self.tm_sec as i64;
+        let months = self.tm_year as i64 * 12 + self.tm_mon as i64;
+        let (year, month) = (months.div_euclid(12) + 1900, months.rem_euclid(12) as i32);
+        calendar::days_from_civil(year, month, self.tm_mday as i64) * 86400 + secs
+    }
 }
 
-impl TryFrom<&libtz_sys::Tm> for Tm {
+/// One field that differs between two [`Tm`]s. See [`Tm::diff_fields`].
+///
+/// Displays as `tm_hour: 1 != 2`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldDiff {
+    /** The field name (`"tm_hour"`, etc.) */       pub field : &'static str,
+    /** The value in `self` */                      pub left  : String,
+    /** The value in `other` */                     pub right : String,
+}
+
+impl core::fmt::Display for FieldDiff {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}: {} != {}", self.field, self.left, self.right)
+    }
+}
+
+impl Tm {
+    /// List the fields that differ between `self` and `other`, in declaration order. This is empty when the two
+    /// are equal.
+    ///
+    /// This is meant for test failures and debug output, where dumping two full `Tm`s makes it hard to spot
+    /// the one field that disagrees:
+    ///
+    /// ```
+    /// # let (a, b) = (libtz::gmtime(0).unwrap(), libtz::gmtime(3600).unwrap());
+    /// let diffs: Vec<String> = a.diff_fields(&b).iter().map(|d| d.to_string()).collect();
+    /// assert_eq!(diffs, ["tm_hour: 0 != 1"]);
+    /// ```
+    pub fn diff_fields(&self, other: &Tm) -> Vec<FieldDiff> {
+        let mut diffs = vec![];
+        let mut diff = |field, left: &dyn core::fmt::Debug, right: &dyn core::fmt::Debug| {
+            let (left, right) = (format!("{:?}", left), format!("{:?}", right));
+            if left != right {
+                diffs.push(FieldDiff{ field, left, right });
+            }
+        };
+        diff("tm_sec",    &self.tm_sec,    &other.tm_sec);
+        diff("tm_min",    &self.tm_min,    &other.tm_min);
+        diff("tm_hour",   &self.tm_hour,   &other.tm_hour);
+        diff("tm_mday",   &self.tm_mday,   &other.tm_mday);
+        diff("tm_mon",    &self.tm_mon,    &other.tm_mon);
+        diff("tm_year",   &self.tm_year,   &other.tm_year);
+        diff("tm_wday",   &self.tm_wday,   &other.tm_wday);
+        diff("tm_yday",   &self.tm_yday,   &other.tm_yday);
+        diff("tm_isdst",  &self.tm_isdst,  &other.tm_isdst);
+        diff("tm_gmtoff", &self.tm_gmtoff, &other.tm_gmtoff);
+        diff("tm_zone",   &self.tm_zone,   &other.tm_zone);
+        diffs
+    }
+}
+
+impl Tm {
+    /// 1970-01-01 00:00:00 UTC, the time [`gmtime`] gives for `0`.
+    pub fn unix_epoch() -> Tm {
+        Tm::from_ymd_hms(1970, 1, 1, 0, 0, 0)
+    }
+
+    /// A UTC time from a calendar date (`month` and `day` start at 1, like people write them) and time of
+    /// day. `tm_wday` and `tm_yday` are filled in. The values aren't checked; see [`Tm::validate`]. A `year` or
+    /// `month` too close to the ends of `i32` to store in `tm_year` or `tm_mon` saturates there.
+    ///
+    /// ```
+    /// let tm = libtz::Tm::from_ymd_hms(2023, 11, 14, 22, 13, 20);
+    /// assert_eq!(libtz::timegm(&tm), Ok(1700000000));
+    /// assert_eq!(tm, libtz::gmtime(1700000000).unwrap());
+    /// ```
+    pub fn from_ymd_hms(year: i32, month: i32, day: i32, hour: i32, min: i32, sec: i32) -> Tm {
+        let months = year as i64 * 12 + month as i64 - 1; // Can't overflow in 64 bits
+        let (y, m) = (months.div_euclid(12), months.rem_euclid(12) as i32);
+        let days = calendar::days_from_civil(y, m, day as i64);
+        Tm{
+            tm_sec    : sec,
+            tm_min    : min,
+            tm_hour   : hour,
+            tm_mday   : day,
+            tm_mon    : month.saturating_sub(1),
+            tm_year   : year.saturating_sub(1900),
+            tm_wday   : calendar::weekday_from_days(days),
+            tm_yday   : (days - calendar::days_from_civil(y, 0, 1)) as i32,
+            tm_isdst  : 0,
+            tm_gmtoff : 0,
+            tm_zone   : "UTC".into(),
+        }
+    }
+}
+
+impl Tm {
+    /// The year as an astronomical year number (`tm_year + 1900`), where year 0 is 1 BCE.
+    pub fn astronomical_year(&self) -> i64 {
+        self.tm_year as i64 + 1900
+    }
+
+    /// The year as an [`Era`] and a year counted from 1 within it.
+    ///
+    /// ```
+    /// // The Ides of March, 44 BCE (proleptic Gregorian)
+    /// let tm = libtz::Tm::from_ymd_hms(-43, 3, 15, 12, 0, 0);
+    /// let (era, year) = tm.era_year();
+    /// assert_eq!(format!("{} {}", year, era), "44 BCE");
+    /// assert_eq!(libtz::gmtime(libtz::timegm(&tm).unwrap()).unwrap(), tm);
+    /// ```
+    pub fn era_year(&self) -> (Era, i64) {
+        astronomical_to_era(self.astronomical_year())
+    }
+}
+
+/// The same as [`Tm::unix_epoch`].
+impl Default for Tm {
+    fn default() -> Tm {
+        Tm::unix_epoch()
+    }
+}
+
+impl Tm {
+    // Seconds since the epoch, computed straight from the fields (so unlike timegm() this can't fail). It's an
+    // i128 since unnormalized fields and offsets can take it past the range of an i64.
+    pub(crate) fn instant(&self) -> i128 {
+        let year = self.tm_year as i64 + 1900 + self.tm_mon.div_euclid(12) as i64;
+        let days = calendar::days_from_civil(year, self.tm_mon.rem_euclid(12), self.tm_mday as i64);
+        days as i128 * 86400 + self.tm_hour as i128 * 3600 + self.tm_min as i128 * 60 + self.tm_sec as i128 - self.tm_gmtoff as i128
+    }
+}
+
+impl Ord for Tm {
+    fn cmp(&self, other: &Tm) -> core::cmp::Ordering {
+        self.instant().cmp(&other.instant())
+            .then_with(|| (self.tm_sec, self.tm_min, self.tm_hour, self.tm_mday, self.tm_mon, self.tm_year)
+                         .cmp(&(other.tm_sec, other.tm_min, other.tm_hour, other.tm_mday, other.tm_mon, other.tm_year)))
+            .then_with(|| (self.tm_wday, self.tm_yday, self.tm_isdst, self.tm_gmtoff, &self.tm_zone)
+                         .cmp(&(other.tm_wday, other.tm_yday, other.tm_isdst, other.tm_gmtoff, &other.tm_zone)))
+    }
+}
+
+impl PartialOrd for Tm {
+    fn partial_cmp(&self, other: &Tm) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl TryFrom<&sys::Tm> for Tm {
     type Error = String;
-    fn try_from(tztm: &libtz_sys::Tm) -> Result<Self, Self::Error> {
+    fn try_from(tztm: &sys::Tm) -> Result<Self, Self::Error> {
         let zone: &str = unsafe { CStr::from_ptr(tztm.tm_zone).to_str().map_err(|_| "Invalid utf8")? };
+        Ok(Tm::with_zone(tztm, zone.into()))
+    }
+}
 
-        Ok(Tm{
+impl Tm {
+    // Convert from the libtz struct with an already decoded abbreviation.
+    #[allow(clippy::useless_conversion)] // c_long is only 32 bits on some targets
+    pub(crate) fn with_zone(tztm: &sys::Tm, zone: Arc<str>) -> Tm {
+        Tm{
             tm_sec    : tztm.tm_sec,
             tm_min    : tztm.tm_min,
             tm_hour   : tztm.tm_hour,
@@ -47,26 +492,26 @@ impl TryFrom<&libtz_sys::Tm> for Tm {
             tm_wday   : tztm.tm_wday,
             tm_yday   : tztm.tm_yday,
             tm_isdst  : tztm.tm_isdst,
-            tm_gmtoff : tztm.tm_gmtoff,
-            tm_zone   : zone.to_string(),
-        })
+            tm_gmtoff : tztm.tm_gmtoff.into(),
+            tm_zone   : zone,
+        }
     }
 }
 
-impl Into<libtz_sys::Tm> for &Tm {
-    fn into(self) -> libtz_sys::Tm {
-            libtz_sys::Tm{
-                tm_sec    : self.tm_sec,
-                tm_min    : self.tm_min,
-                tm_hour   : self.tm_hour,
-                tm_mday   : self.tm_mday,
-                tm_mon    : self.tm_mon,
-                tm_year   : self.tm_year,
-                tm_wday   : self.tm_wday,
-                tm_yday   : self.tm_yday,
-                tm_isdst  : self.tm_isdst,
-                tm_gmtoff : self.tm_gmtoff,
-                tm_zone   : std::ptr::null_mut(),
+impl From<&Tm> for sys::Tm {
+    fn from(tm: &Tm) -> sys::Tm {
+            sys::Tm{
+                tm_sec    : tm.tm_sec,
+                tm_min    : tm.tm_min,
+                tm_hour   : tm.tm_hour,
+                tm_mday   : tm.tm_mday,
+                tm_mon    : tm.tm_mon,
+                tm_year   : tm.tm_year,
+                tm_wday   : tm.tm_wday,
+                tm_yday   : tm.tm_yday,
+                tm_isdst  : tm.tm_isdst,
+                tm_gmtoff : tm.tm_gmtoff as core::ffi::c_long,
+                tm_zone   : core::ptr::null_mut(),
         }
     }
 }
@@ -76,9 +521,17 @@ impl Into<libtz_sys::Tm> for &Tm {
 /// This function is like [`Timezone::mktime()`][timezone::Timezone::mktime] except that it treats the `tm` as
 /// UTC (ignoring the `tm_idst` and `tm_zone` members).
 pub fn timegm(tm: &Tm) -> Result<TimeT, String> {
-    match unsafe { libtz_sys::timegm(&tm.into()) } {
-        -1    => Err(format!("Invalid date specified")),
-        time  => Ok(time),
+    checked_mktime(tm, |tztm| unsafe { sys::timegm(tztm) })
+}
+
+// The mktime() family returns -1 on error, but -1 is also a perfectly valid time (1969-12-31 23:59:59 UTC).
+// They always fill in `tm_wday` on success, though, so start it out invalid and see if it changed.
+pub(crate) fn checked_mktime(tm: impl Into<sys::Tm>, mktime: impl FnOnce(*mut sys::Tm) -> TimeT) -> Result<TimeT, String> {
+    let mut tztm: sys::Tm = tm.into();
+    tztm.tm_wday = -1;
+    match mktime(&mut tztm) {
+        -1 if tztm.tm_wday < 0 => Err("Invalid date specified".to_string()),
+        time                   => Ok(time),
     }
 }
 
@@ -87,18 +540,137 @@ pub fn timegm(tm: &Tm) -> Result<TimeT, String> {
 /// The `gmtime` function converts to Coordinated Universal Time, returning a pointer to a [`Tm`]
 /// structure.
 pub fn gmtime(time: TimeT) -> Result<Tm, String> {
-    let mut tztm = MaybeUninit::<libtz_sys::Tm>::uninit();
-    let ret = unsafe { libtz_sys::gmtime_r(&time, tztm.as_mut_ptr()) };
-    if ret == std::ptr::null_mut() {
+    let mut tztm = MaybeUninit::<sys::Tm>::uninit();
+    let ret = unsafe { sys::gmtime_r(&time, tztm.as_mut_ptr()) };
+    if ret.is_null() {
+        #[cfg(feature = "std")]
         return Err(format!("errno={}", std::io::Error::last_os_error()));
+        #[cfg(not(feature = "std"))]
+        return Err("Time out of range".to_string());
     }
     let tztm = unsafe { tztm.assume_init() };
     Tm::try_from(&tztm)
 }
 
+/// Convert a wide system time to UTC [`Tm`] with plain Rust arithmetic instead of calling libtz, so intermediate
+/// results in long range calculations don't have to fit in a [`TimeT`]. The result is the same as [`gmtime`]'s
+/// wherever both work. This fails if the year doesn't fit in `tm_year`.
+///
+/// ```
+/// let tm = libtz::gmtime_wide(253402300800).expect("gmtime_wide"); // The first second of year 10000
+/// assert_eq!((tm.tm_year + 1900, tm.tm_mon, tm.tm_mday), (10000, 0, 1));
+/// assert_eq!(libtz::timegm_wide(&tm), 253402300800);
+/// ```
+pub fn gmtime_wide(time: i128) -> Result<Tm, String> {
+    let out_of_range = || "Time out of range".to_string();
+    // Well past the years an i32 tm_year can hold, but small enough that the calendar math can't overflow.
+    let days = i64::try_from(time.div_euclid(86400)).ok().filter(|days| days.unsigned_abs() < 1 << 50).ok_or_else(out_of_range)?;
+    let secs = time.rem_euclid(86400) as i32;
+    let (year, month, day) = calendar::civil_from_days(days);
+    let tm_year = i32::try_from(year - 1900).map_err(|_| out_of_range())?;
+    Ok(Tm{
+        tm_sec    : secs % 60,
+        tm_min    : secs / 60 % 60,
+        tm_hour   : secs / 3600,
+        tm_mday   : day,
+        tm_mon    : month,
+        tm_year,
+        tm_wday   : calendar::weekday_from_days(days),
+        tm_yday   : (days - calendar::days_from_civil(year, 0, 1)) as i32,
+        tm_isdst  : 0,
+        tm_gmtoff : 0,
+        tm_zone   : "UTC".into(),
+    })
+}
+
+/// Convert UTC [`Tm`] to a wide system time with plain Rust arithmetic (the inverse of [`gmtime_wide`]). Like
+/// [`timegm`], out of range fields are normalized and `tm_isdst`, `tm_gmtoff` and `tm_zone` are ignored, but
+/// every `Tm` has an answer.
+pub fn timegm_wide(tm: &Tm) -> i128 {
+    let year = tm.tm_year as i64 + 1900 + tm.tm_mon.div_euclid(12) as i64;
+    let days = calendar::days_from_civil(year, tm.tm_mon.rem_euclid(12), tm.tm_mday as i64);
+    days as i128 * 86400 + tm.tm_hour as i128 * 3600 + tm.tm_min as i128 * 60 + tm.tm_sec as i128
+}
+
+/// Convert system time to UTC in a `const fn`, for deriving constants at compile time. A [`Tm`] can't be made
+/// in a `const` context (its `tm_zone` is reference counted), so this gives a [`CivilDateTime`] instead. Leap
+/// seconds aren't counted, as with [`gmtime`] in zones without them. `None` if the year doesn't fit in an `i32`.
+///
+/// ```
+/// use libtz::{gmtime_const, timegm_const, CivilDateTime, Date, TimeOfDay};
+/// const GPS_EPOCH: libtz::TimeT = timegm_const(&CivilDateTime::new(Date::from_ymd(1980, 1, 6).unwrap(), TimeOfDay::MIDNIGHT));
+/// const Y2K38: CivilDateTime = gmtime_const(i32::MAX as libtz::TimeT).unwrap();
+/// assert_eq!(GPS_EPOCH, 315964800);
+/// assert_eq!(Y2K38.to_string(), "2038-01-19T03:14:07");
+/// ```
+pub const fn gmtime_const(time: TimeT) -> Option<CivilDateTime> {
+    let (year, month, day) = calendar::civil_from_days(time.div_euclid(86400));
+    let secs = time.rem_euclid(86400) as i32;
+    if year < i32::MIN as i64 || year > i32::MAX as i64 {
+        return None;
+    }
+    let Some(date) = Date::from_ymd(year as i32, month + 1, day) else { return None };
+    let Some(time) = TimeOfDay::from_hms(secs / 3600, secs / 60 % 60, secs % 60) else { return None };
+    Some(CivilDateTime::new(date, time))
+}
+
+/// Convert UTC to system time in a `const fn` (the inverse of [`gmtime_const`]). A leap second (`:60`) is the
+/// same as the first second of the next minute, like [`timegm`] normalizes it.
+pub const fn timegm_const(dt: &CivilDateTime) -> TimeT {
+    let days = calendar::days_from_civil(dt.date.year() as i64, dt.date.month() - 1, dt.date.day() as i64);
+    days * 86400 + dt.time.seconds_since_midnight() as TimeT
+}
+
+/// Convert system time to a [`Tm`] at a fixed offset of `gmtoff` seconds east of UTC, like libtz's
+/// `offtime()`. This doesn't need a [`Timezone`].
+///
+/// libtz leaves the abbreviation blank for non-zero offsets. This uses the numeric style tzdata does for zones
+/// without a conventional abbreviation (like `+0530` or `-08`), and `UTC` for an offset of 0.
+///
+/// ```
+/// let tm = libtz::offtime(1700000000, 19800).expect("offtime");
+/// assert_eq!((tm.tm_hour, tm.tm_min, tm.tm_gmtoff, &*tm.tm_zone), (3, 43, 19800, "+0530"));
+/// assert_eq!(libtz::timeoff(&tm, 19800), Ok(1700000000));
+/// ```
+pub fn offtime(time: TimeT, gmtoff: i64) -> Result<Tm, String> {
+    let mut tm = gmtime(time.checked_add(gmtoff).ok_or("Time out of range")?)?;
+    tm.tm_gmtoff = gmtoff;
+    if gmtoff != 0 {
+        tm.tm_zone = format_offset(gmtoff, OffsetFormat::Abbreviation).into();
+    }
+    Ok(tm)
+}
+
+/// Convert a [`Tm`] at a fixed offset of `gmtoff` seconds east of UTC to system time, like libtz's
+/// `timeoff()`. The `tm`'s own `tm_gmtoff`, `tm_isdst` and `tm_zone` are ignored.
+pub fn timeoff(tm: &Tm, gmtoff: i64) -> Result<TimeT, String> {
+    timegm(tm)?.checked_sub(gmtoff).ok_or_else(|| "Time out of range".to_string())
+}
+
+/// The number of seconds from `time0` to `time1` (`time1 - time0`), like C's `difftime`.
+///
+/// The subtraction is done in 128 bits so it can't overflow, but the result is rounded to the nearest `f64`
+/// for differences beyond 2^53 seconds. Use [`difftime_secs`] for an exact answer.
+///
+/// (libtz's `difftime()` has to cope with every conceivable `time_t` type; with `TimeT` always being an
+/// `i64` there's nothing for it to do that Rust can't, so this doesn't call into C.)
+pub fn difftime(time1: TimeT, time0: TimeT) -> f64 {
+    (time1 as i128 - time0 as i128) as f64
+}
+
+/// The number of seconds from `time0` to `time1` (`time1 - time0`), or `None` if that doesn't fit in an `i64`.
+pub fn difftime_secs(time1: TimeT, time0: TimeT) -> Option<i64> {
+    time1.checked_sub(time0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    // Held by tests that change or depend on the TZ environment variable, since tests run in parallel.
+    #[cfg(feature = "std")]
+    pub(crate) static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
     #[test]
     fn gmtime_test() {
         let time = 283996800;
@@ -113,10 +685,176 @@ mod tests {
                           tm_yday   :0,
                           tm_isdst  :0,
                           tm_gmtoff :0,
-                          tm_zone   :"UTC".to_string()});
+                          tm_zone   :"UTC".into()});
         assert_eq!(timegm(&tm).expect("timegm"), time);
     }
 
+    #[test]
+    fn timegm_minus_one() {
+        let tm = gmtime(-1).expect("gmtime");
+        assert_eq!((tm.tm_year, tm.tm_mon, tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec), (69, 11, 31, 23, 59, 59));
+        assert_eq!(timegm(&tm), Ok(-1));
+        assert!(timegm(&Tm{ tm_year: i32::MAX, tm_mon: i32::MAX, ..tm }).is_err());
+    }
+
+    #[test]
+    fn validate() {
+        let mut tm = gmtime(951782400).expect("gmtime"); // 2000-02-29
+        assert_eq!(tm.validate(), Ok(()));
+        tm.tm_mday = 30;
+        assert_eq!(tm.validate(), Err(vec!["Feb 30 invalid for 2000".to_string()]));
+        tm.tm_year = 101; tm.tm_mday = 29;
+        assert_eq!(tm.validate(), Err(vec!["Feb 29 invalid for 2001".to_string()]));
+        tm.tm_mon = 13; tm.tm_sec = -1;
+        assert_eq!(tm.validate(), Err(vec!["tm_sec=-1 out of range [0, 60]".to_string(),
+                                           "tm_mon=13 out of range [0, 11]".to_string()]));
+    }
+
+    #[test]
+    fn normalized() {
+        let mut tm = gmtime(946684800).expect("gmtime"); // 2000-01-01
+        tm.tm_mday = 35; tm.tm_hour = 25; tm.tm_min = -1;
+        let norm = tm.normalized().expect("normalized");
+        assert_eq!(norm, gmtime(946684800 + 34 * 86400 + 25 * 3600 - 60).expect("gmtime")); // 2000-02-05 00:59:00
+        assert_eq!(norm.validate(), Ok(()));
+
+        tm = gmtime(946684800).expect("gmtime");
+        tm.tm_mon = -1; tm.tm_sec = -1;
+        assert_eq!(tm.normalized().expect("normalized"), gmtime(946684800 - 31 * 86400 - 1).expect("gmtime")); // 1999-11-30 23:59:59
+
+        tm.tm_year = i32::MAX; tm.tm_mon = 12; tm.tm_sec = 0;
+        assert!(tm.normalized().is_err());
+    }
+
+    #[test]
+    fn ord() {
+        let utc = gmtime(1700000000).expect("gmtime");
+        let tokyo = Tm{ tm_hour: utc.tm_hour + 9, tm_gmtoff: 9 * 3600, tm_zone: "JST".into(), ..utc.clone() };
+        let later = gmtime(1700000001).expect("gmtime");
+        assert!(tokyo < later && utc < later);
+        assert_ne!(utc.cmp(&tokyo), core::cmp::Ordering::Equal, "same instant but not equal");
+        let mut sorted = vec![later.clone(), tokyo.clone(), gmtime(0).expect("gmtime")];
+        sorted.sort();
+        assert_eq!(sorted, [gmtime(0).expect("gmtime"), tokyo.clone(), later]);
+        assert!(Tm{ tm_mon: 12, ..utc.clone() } > Tm{ tm_mon: 0, tm_year: utc.tm_year + 1, tm_mday: 1, ..utc.clone() }); // Jan 14 > Jan 1
+
+        // Fields far past anything real still compare (and sort) without overflowing.
+        let extreme = [Tm{ tm_gmtoff: i64::MIN, ..utc.clone() }, Tm{ tm_gmtoff: i64::MAX, tm_year: i32::MIN, ..utc.clone() },
+                       Tm{ tm_gmtoff: i64::MAX, tm_year: i32::MAX, tm_mon: i32::MAX, tm_mday: i32::MAX, tm_hour: i32::MAX, ..utc.clone() }];
+        let mut sorted = extreme.to_vec();
+        sorted.sort();
+        assert_eq!(sorted, [extreme[1].clone(), extreme[2].clone(), extreme[0].clone()]);
+
+        let set: std::collections::HashSet<Tm> = [utc.clone(), utc.clone(), tokyo].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn constructors() {
+        assert_eq!(Tm::unix_epoch(), gmtime(0).expect("gmtime"));
+        assert_eq!(Tm::default(), Tm::unix_epoch());
+        assert_eq!(Tm::from_ymd_hms(2000, 2, 29, 12, 0, 0), gmtime(951825600).expect("gmtime"));
+        assert_eq!(Tm::from_ymd_hms(1969, 12, 31, 23, 59, 59), gmtime(-1).expect("gmtime"));
+        let tm = Tm::from_ymd_hms(2023, 12, 31, 0, 0, 0);
+        assert_eq!((tm.tm_wday, tm.tm_yday), (0, 364));
+        // Extreme arguments don't overflow.
+        for (year, month) in [(i32::MIN, i32::MIN), (i32::MAX, i32::MAX), (i32::MIN, 1), (0, i32::MIN)] {
+            let tm = Tm::from_ymd_hms(year, month, 1, 0, 0, 0);
+            assert!((0..7).contains(&tm.tm_wday) && (0..366).contains(&tm.tm_yday), "{} {}", year, month);
+            assert!((0..7).contains(&Tm::from_ymd_hms(year, month, i32::MAX, 0, 0, 0).tm_wday));
+        }
+        assert_eq!(Tm::from_ymd_hms(i32::MIN, i32::MIN, 1, 0, 0, 0).tm_mon, i32::MIN);
+    }
+
+    #[test]
+    fn proleptic() {
+        let tm = gmtime(-62135596800).expect("gmtime"); // 0001-01-01, a Monday
+        assert_eq!((tm.astronomical_year(), tm.tm_mon, tm.tm_mday, tm.tm_wday), (1, 0, 1, 1));
+        assert_eq!(tm.era_year(), (Era::Ce, 1));
+        let tm = gmtime(-62135596800 - 1).expect("gmtime");
+        assert_eq!((tm.tm_year, tm.tm_mon, tm.tm_mday, tm.tm_yday), (-1900, 11, 31, 365)); // Year 0 is a leap year
+        assert_eq!(tm.era_year(), (Era::Bce, 1));
+        for tm in [Tm::from_ymd_hms(0, 2, 29, 0, 0, 0), Tm::from_ymd_hms(-4713, 11, 24, 12, 0, 0), Tm::from_ymd_hms(-1_000_000, 1, 1, 0, 0, 0)] {
+            let time = timegm(&tm).expect("timegm");
+            assert_eq!(time as i128, tm.instant());
+            assert_eq!(gmtime(time).expect("gmtime"), tm);
+        }
+        assert_eq!(Tm::from_ymd_hms(-4713, 11, 24, 12, 0, 0).era_year(), (Era::Bce, 4714)); // Julian day 0
+    }
+
+    #[test]
+    fn const_conversions() {
+        for time in [0, -1, 1700000000, -62135596800, 253402300799, -(1 << 40), 1 << 45] {
+            let dt = gmtime_const(time).expect("gmtime_const");
+            assert_eq!(CivilDateTime::try_from(&gmtime(time).expect("gmtime")), Ok(dt), "{}", time);
+            assert_eq!(timegm_const(&dt), time);
+        }
+        assert_eq!(gmtime_const(TimeT::MAX), None);
+        assert_eq!(gmtime_const(TimeT::MIN), None);
+        let leap = CivilDateTime::new(Date::from_ymd(2016, 12, 31).expect("date"), TimeOfDay::from_hms(23, 59, 60).expect("time"));
+        assert_eq!(timegm_const(&leap), 1483228800);
+        assert_eq!(Date::from_ymd(2023, 2, 29), None);
+        assert_eq!(TimeOfDay::from_hms(24, 0, 0), None);
+    }
+
+    #[test]
+    fn wide() {
+        for time in [0, -1, 1700000000, -62135596800, 253402300799, -(1 << 40), 1 << 45, 67768036191676799] {
+            let tm = gmtime(time).expect("gmtime");
+            assert_eq!(gmtime_wide(time as i128), Ok(tm.clone()), "{}", time);
+            assert_eq!(timegm_wide(&tm), time as i128);
+        }
+        assert!(gmtime(67768036191676800).is_err());
+        assert!(gmtime_wide(67768036191676800).is_err());
+        assert!(gmtime_wide(i128::MAX).is_err());
+        assert_eq!(gmtime_wide(-67768040609740800).map(|tm| (tm.tm_year, tm.tm_mon, tm.tm_mday)), Ok((i32::MIN, 0, 1)));
+        let extreme = Tm{ tm_year: i32::MAX, tm_mon: i32::MAX, tm_mday: i32::MAX, tm_hour: i32::MAX, tm_min: i32::MAX, tm_sec: i32::MAX, ..Tm::unix_epoch() };
+        assert!(timegm(&extreme).is_err());
+        assert!(timegm_wide(&extreme) > 67768036191676799); // Past the last time gmtime can give
+        let normalized = Tm::from_ymd_hms(2023, 14, 0, 24, 60, -1);
+        assert_eq!(timegm_wide(&normalized), timegm(&normalized).expect("timegm") as i128);
+    }
+
+    #[test]
+    fn time32() {
+        let y2038 = gmtime(i32::MAX as TimeT).expect("gmtime");
+        assert_eq!((y2038.tm_year + 1900, y2038.tm_mon + 1, y2038.tm_mday), (2038, 1, 19));
+        assert!(gmtime(i32::MAX as TimeT + 1).is_ok()); // No wrapping to 1901
+        assert_eq!(to_time32(i32::MIN as TimeT), Ok(i32::MIN));
+        assert!(to_time32(i32::MIN as TimeT - 1).is_err());
+        assert!(to_time32(i32::MAX as TimeT + 1).is_err());
+    }
+
+    #[test]
+    fn offtime_test() {
+        let tm = offtime(1700000000, -28800).expect("offtime");
+        assert_eq!(tm, Tm{ tm_hour: 14, tm_gmtoff: -28800, tm_zone: "-08".into(), ..gmtime(1700000000 - 28800).expect("gmtime") });
+        assert_eq!(offtime(1700000000, 0), gmtime(1700000000));
+        assert_eq!(&*offtime(0, 1172).expect("offtime").tm_zone, "+001932");
+        assert_eq!(timeoff(&tm, -28800), Ok(1700000000));
+        assert_eq!(timeoff(&gmtime(0).expect("gmtime"), 3600), Ok(-3600));
+        assert!(offtime(TimeT::MAX, 1).is_err());
+    }
+
+    #[test]
+    fn difftime_test() {
+        assert_eq!(difftime(1700000000, 1600000000), 100000000.0);
+        assert_eq!(difftime(0, 1), -1.0);
+        assert_eq!(difftime(TimeT::MAX, TimeT::MIN), 2f64.powi(64));
+        assert_eq!(difftime_secs(1700000000, 1600000000), Some(100000000));
+        assert_eq!(difftime_secs(TimeT::MAX, -1), None);
+    }
+
+    #[test]
+    fn diff_fields() {
+        let tm = gmtime(0).expect("gmtime");
+        assert_eq!(tm.diff_fields(&tm), vec![]);
+        let mut other = tm.clone();
+        other.tm_mday = 2; other.tm_zone = "GMT".into();
+        assert_eq!(tm.diff_fields(&other), vec![FieldDiff{ field: "tm_mday", left: "1".to_string(), right: "2".to_string() },
+                                                 FieldDiff{ field: "tm_zone", left: "\"UTC\"".to_string(), right: "\"GMT\"".to_string() }]);
+    }
+
     #[test]
     fn test_readme_deps() {
         version_sync::assert_markdown_deps_updated!("README.md");
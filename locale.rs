@@ -0,0 +1,188 @@
+// Month and weekday names in languages other than English.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use alloc::{format, string::String};
+use crate::Tm;
+use crate::format::{Names, C_NAMES};
+
+/// A language for [`Tm::strftime_locale`]. The names are the standalone forms from the Unicode CLDR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Locale {
+    /// English (the same as the C locale [`Tm::strftime`] uses).
+    En,
+    /// German.
+    De,
+    /// French.
+    Fr,
+    /// Spanish.
+    Es,
+    /// Italian.
+    It,
+    /// Portuguese.
+    Pt,
+    /// Dutch.
+    Nl,
+    /// Swedish.
+    Sv,
+    /// Japanese.
+    Ja,
+}
+
+const DE: Names = Names{
+    weekdays:      ["Sonntag", "Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag"],
+    weekday_abbrs: ["So", "Mo", "Di", "Mi", "Do", "Fr", "Sa"],
+    months:        ["Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September", "Oktober", "November", "Dezember"],
+    month_abbrs:   ["Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez"],
+    am_pm:         ["AM", "PM"],
+};
+
+const FR: Names = Names{
+    weekdays:      ["dimanche", "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi"],
+    weekday_abbrs: ["dim.", "lun.", "mar.", "mer.", "jeu.", "ven.", "sam."],
+    months:        ["janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre", "novembre", "décembre"],
+    month_abbrs:   ["janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.", "nov.", "déc."],
+    am_pm:         ["AM", "PM"],
+};
+
+const ES: Names = Names{
+    weekdays:      ["domingo", "lunes", "martes", "miércoles", "jueves", "viernes", "sábado"],
+    weekday_abbrs: ["dom", "lun", "mar", "mié", "jue", "vie", "sáb"],
+    months:        ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre"],
+    month_abbrs:   ["ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov", "dic"],
+    am_pm:         ["a. m.", "p. m."],
+};
+
+const IT: Names = Names{
+    weekdays:      ["domenica", "lunedì", "martedì", "mercoledì", "giovedì", "venerdì", "sabato"],
+    weekday_abbrs: ["dom", "lun", "mar", "mer", "gio", "ven", "sab"],
+    months:        ["gennaio", "febbraio", "marzo", "aprile", "maggio", "giugno", "luglio", "agosto", "settembre", "ottobre", "novembre", "dicembre"],
+    month_abbrs:   ["gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic"],
+    am_pm:         ["AM", "PM"],
+};
+
+const PT: Names = Names{
+    weekdays:      ["domingo", "segunda-feira", "terça-feira", "quarta-feira", "quinta-feira", "sexta-feira", "sábado"],
+    weekday_abbrs: ["dom", "seg", "ter", "qua", "qui", "sex", "sáb"],
+    months:        ["janeiro", "fevereiro", "março", "abril", "maio", "junho", "julho", "agosto", "setembro", "outubro", "novembro", "dezembro"],
+    month_abbrs:   ["jan", "fev", "mar", "abr", "mai", "jun", "jul", "ago", "set", "out", "nov", "dez"],
+    am_pm:         ["AM", "PM"],
+};
+
+const NL: Names = Names{
+    weekdays:      ["zondag", "maandag", "dinsdag", "woensdag", "donderdag", "vrijdag", "zaterdag"],
+    weekday_abbrs: ["zo", "ma", "di", "wo", "do", "vr", "za"],
+    months:        ["januari", "februari", "maart", "april", "mei", "juni", "juli", "augustus", "september", "oktober", "november", "december"],
+    month_abbrs:   ["jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec"],
+    am_pm:         ["a.m.", "p.m."],
+};
+
+const SV: Names = Names{
+    weekdays:      ["söndag", "måndag", "tisdag", "onsdag", "torsdag", "fredag", "lördag"],
+    weekday_abbrs: ["sön", "mån", "tis", "ons", "tors", "fre", "lör"],
+    months:        ["januari", "februari", "mars", "april", "maj", "juni", "juli", "augusti", "september", "oktober", "november", "december"],
+    month_abbrs:   ["jan", "feb", "mars", "apr", "maj", "juni", "juli", "aug", "sep", "okt", "nov", "dec"],
+    am_pm:         ["fm", "em"],
+};
+
+const JA: Names = Names{
+    weekdays:      ["日曜日", "月曜日", "火曜日", "水曜日", "木曜日", "金曜日", "土曜日"],
+    weekday_abbrs: ["日", "月", "火", "水", "木", "金", "土"],
+    months:        ["1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月", "9月", "10月", "11月", "12月"],
+    month_abbrs:   ["1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月", "9月", "10月", "11月", "12月"],
+    am_pm:         ["午前", "午後"],
+};
+
+impl Locale {
+    fn names(self) -> &'static Names {
+        match self {
+            Locale::En => &C_NAMES,
+            Locale::De => &DE,
+            Locale::Fr => &FR,
+            Locale::Es => &ES,
+            Locale::It => &IT,
+            Locale::Pt => &PT,
+            Locale::Nl => &NL,
+            Locale::Sv => &SV,
+            Locale::Ja => &JA,
+        }
+    }
+
+    /// The name of the month (`month` is 0 based, like `tm_mon`), or `None` if `month` is out of range.
+    pub fn month_name(self, month: i32) -> Option<&'static str> {
+        self.names().months.get(usize::try_from(month).ok()?).copied()
+    }
+
+    /// The abbreviated name of the month (`month` is 0 based, like `tm_mon`).
+    pub fn month_abbr(self, month: i32) -> Option<&'static str> {
+        self.names().month_abbrs.get(usize::try_from(month).ok()?).copied()
+    }
+
+    /// The name of the day of the week (`wday` is 0 for Sunday, like `tm_wday`).
+    pub fn weekday_name(self, wday: i32) -> Option<&'static str> {
+        self.names().weekdays.get(usize::try_from(wday).ok()?).copied()
+    }
+
+    /// The abbreviated name of the day of the week (`wday` is 0 for Sunday, like `tm_wday`).
+    pub fn weekday_abbr(self, wday: i32) -> Option<&'static str> {
+        self.names().weekday_abbrs.get(usize::try_from(wday).ok()?).copied()
+    }
+}
+
+/// Parse a language tag. Only the language matters, so BCP 47 tags (`de-AT`) and POSIX locale names
+/// (`pt_BR.UTF-8`) work too. `C` and `POSIX` are English.
+impl core::str::FromStr for Locale {
+    type Err = String;
+    fn from_str(tag: &str) -> Result<Locale, String> {
+        let language = tag.split(['-', '_', '.', '@']).next().unwrap_or("");
+        Ok(match language.to_ascii_lowercase().as_str() {
+            "en" | "c" | "posix" => Locale::En,
+            "de"                 => Locale::De,
+            "fr"                 => Locale::Fr,
+            "es"                 => Locale::Es,
+            "it"                 => Locale::It,
+            "pt"                 => Locale::Pt,
+            "nl"                 => Locale::Nl,
+            "sv"                 => Locale::Sv,
+            "ja"                 => Locale::Ja,
+            _                    => return Err(format!("Unsupported locale {:?}", tag)),
+        })
+    }
+}
+
+impl Tm {
+    /// Format the time like [`Tm::strftime`], but with the month and weekday names (`%a` `%A` `%b` `%B` `%h`)
+    /// and AM/PM (`%p`) in the language of `locale`. Everything else is the same as in the C locale.
+    ///
+    /// ```
+    /// use libtz::{Tm, Locale};
+    /// let tm = Tm::from_ymd_hms(2024, 3, 1, 9, 30, 0);
+    /// assert_eq!(tm.strftime_locale("%A %e %B %Y", Locale::De), "Freitag  1 März 2024");
+    /// assert_eq!(tm.strftime_locale("%a %d %b", "fr_FR.UTF-8".parse().unwrap()), "ven. 01 mars");
+    /// ```
+    pub fn strftime_locale(&self, format: &str, locale: Locale) -> String {
+        self.strftime_names(format, locale.names())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locales() {
+        let tm = Tm::from_ymd_hms(2023, 11, 14, 22, 13, 20);
+        assert_eq!(tm.strftime_locale("%c %a %A %b %B %p", Locale::En), tm.strftime("%c %a %A %b %B %p"));
+        assert_eq!(tm.strftime_locale("%A %d %B %Y %H:%M", Locale::Es), "martes 14 noviembre 2023 22:13");
+        assert_eq!(tm.strftime_locale("%Y年%B%e日 (%a) %p", Locale::Ja), "2023年11月14日 (火) 午後");
+        assert_eq!(tm.strftime_locale("%a %b", Locale::Nl), "di nov");
+        assert_eq!(Locale::Sv.month_name(4), Some("maj"));
+        assert_eq!(Locale::Pt.weekday_name(6), Some("sábado"));
+        assert_eq!(Locale::It.month_abbr(12), None);
+        assert_eq!(Locale::De.weekday_abbr(-1), None);
+        assert_eq!("de-AT".parse(), Ok(Locale::De));
+        assert_eq!("POSIX".parse(), Ok(Locale::En));
+        assert!("tlh".parse::<Locale>().is_err());
+    }
+}
@@ -0,0 +1,237 @@
+// Forgiving zone name lookup.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::path::Path;
+use crate::{Timezone, Provenance, ZoneInfo};
+use crate::timezone::{TZDIR, is_tzif};
+
+/// List the zones installed in the system's zone directory (`/usr/share/zoneinfo`), sorted by name. The
+/// `posix/` and `right/` copies of the database and the `localtime` and `posixrules` files are left out.
+pub fn available_zones() -> Result<Vec<String>, String> {
+    let mut zones = vec![];
+    list(Path::new(TZDIR), "", &mut zones)?;
+    zones.sort();
+    Ok(zones)
+}
+
+fn list(dir: &Path, prefix: &str, zones: &mut Vec<String>) -> Result<(), String> {
+    for entry in std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))? {
+        let entry = entry.map_err(|e| format!("{}: {}", dir.display(), e))?;
+        let Some(name) = entry.file_name().to_str().map(|name| format!("{}{}", prefix, name)) else { continue };
+        let path = entry.path();
+        if path.is_dir() {
+            if name != "posix" && name != "right" {
+                list(&path, &format!("{}/", name), zones)?;
+            }
+        } else if name != "localtime" && name != "posixrules" && is_tzif(&path) {
+            zones.push(name);
+        }
+    }
+    Ok(())
+}
+
+/// List the installed zones (see [`available_zones`]) that have ever used the abbreviation `abbr`, sorted by
+/// name. Abbreviations are far from unique (`IST` is used in India, Ireland and Israel) so this is meant for
+/// presenting the plausible choices when all there is to go on is an abbreviation, like in old log files.
+///
+/// ```
+/// let zones = libtz::zones_using_abbrev("IST").expect("zones_using_abbrev");
+/// assert!(zones.iter().any(|z| z == "Asia/Kolkata") && zones.iter().any(|z| z == "Europe/Dublin"));
+/// ```
+pub fn zones_using_abbrev(abbr: &str) -> Result<Vec<String>, String> {
+    Ok(available_zones()?.into_iter().filter(|zone| {
+        ZoneInfo::load(&Path::new(TZDIR).join(zone)).is_ok_and(|zi| zi.abbreviations().contains(&abbr))
+    }).collect())
+}
+
+/// The installed zones (see [`available_zones`]) whose files are identical to the zone file at `path`, sorted
+/// by name. Many zones share their data (`Japan` is a link to `Asia/Tokyo`) so there may well be more than one.
+///
+/// This is for recovering a zone name when all there is is a copy of the file, like an `/etc/localtime` that
+/// was copied instead of symlinked. See [`system_zone_names`].
+pub fn identify_zone_file(path: impl AsRef<Path>) -> Result<Vec<String>, String> {
+    let path = path.as_ref();
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let same_len = |zone: &Path| std::fs::metadata(zone).is_ok_and(|meta| meta.len() == data.len() as u64);
+    Ok(available_zones()?.into_iter().filter(|zone| {
+        let zone = Path::new(TZDIR).join(zone);
+        same_len(&zone) && std::fs::read(&zone).is_ok_and(|zone_data| zone_data == data)
+    }).collect())
+}
+
+/// The likely names of the system's default zone (`/etc/localtime`). When it's a symlink into the zoneinfo
+/// directory the name comes from the link and there's just the one. Otherwise its contents are matched against
+/// the installed zones (see [`identify_zone_file`]), which may find several, or none if the system zone
+/// doesn't come from the installed database.
+pub fn system_zone_names() -> Result<Vec<String>, String> {
+    let default = Path::new(crate::timezone::TZDEFAULT);
+    if std::fs::symlink_metadata(default).map_err(|e| format!("{}: {}", default.display(), e))?.is_symlink() {
+        let name = Provenance::SystemDefault(default.to_path_buf()).name();
+        if !name.starts_with('/') {
+            return Ok(vec![name]);
+        }
+    }
+    identify_zone_file(default)
+}
+
+/// Where a zone name stands in the tz database, from [`zone_status`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ZoneStatus {
+    /// A zone in its own right (`America/New_York`).
+    Canonical,
+    /// A current name that shares another zone's data, like `Europe/Bratislava` (a link to `Europe/Prague`
+    /// since they've agreed since 1970). It's still the right name for its country, so there's nothing to migrate.
+    Link { target: String },
+    /// An old name kept for backward compatibility, like `Asia/Calcutta` or `US/Eastern`: stored names should be
+    /// migrated to `replacement`.
+    Deprecated { replacement: String },
+    /// Not in the database at all.
+    Unknown,
+}
+
+impl ZoneStatus {
+    /// The name that should be used instead, for [`ZoneStatus::Deprecated`] names.
+    pub fn replacement(&self) -> Option<&str> {
+        match self {
+            ZoneStatus::Deprecated{ replacement } => Some(replacement),
+            _                                     => None,
+        }
+    }
+}
+
+/// Whether `name` is a canonical zone, a link, or a deprecated name (and what to replace it with), according
+/// to the installed `tzdata.zi` and `zone.tab` (each from the first of the [zone directories](crate::zone_dirs)
+/// that has it), so applications can migrate the zone names they've stored before the old ones are dropped.
+/// Links whose names are in `zone.tab` (one per country) count as current, as do the `UTC` and `GMT` style
+/// aliases of `Etc/` zones; every other link is deprecated.
+///
+/// ```
+/// use libtz::{zone_status, ZoneStatus};
+/// assert_eq!(zone_status("America/New_York").unwrap(), ZoneStatus::Canonical);
+/// assert_eq!(zone_status("Asia/Calcutta").unwrap().replacement(), Some("Asia/Kolkata"));
+/// ```
+pub fn zone_status(name: &str) -> Result<ZoneStatus, String> {
+    let read = |file: &str| {
+        let path = crate::zone_dirs().into_iter().map(|dir| dir.join(file)).find(|path| path.is_file())
+                                     .ok_or_else(|| format!("No {} in the zone directories", file))?;
+        std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))
+    };
+    let text = read("tzdata.zi")?;
+    let mut target = None;
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some("Z" | "Zone"), Some(zone), _) if zone == name => return Ok(ZoneStatus::Canonical),
+            (Some("L" | "Link"), Some(to), Some(link)) if link == name => target = Some(to.to_string()),
+            _ => {},
+        }
+    }
+    let Some(target) = target else { return Ok(ZoneStatus::Unknown) };
+    let in_zone_tab = read("zone.tab")?.lines().filter(|line| !line.starts_with('#')).any(|line| line.split('\t').nth(2) == Some(name));
+    let etc_alias = target.starts_with("Etc/") && ["UTC", "GMT"].iter().any(|base| name.trim_start_matches("Etc/").starts_with(base));
+    Ok(if in_zone_tab || etc_alias { ZoneStatus::Link{ target } } else { ZoneStatus::Deprecated{ replacement: target } })
+}
+
+// The number of single character insertions, deletions and substitutions it takes to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let next = (row[j + 1] + 1).min(row[j] + 1).min(diagonal + usize::from(ca != *cb));
+            diagonal = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+/// Up to 3 installed zones whose names are close to `name`, closest first.
+pub(crate) fn suggestions(name: &str, zones: &[String]) -> Vec<String> {
+    let name = name.to_lowercase();
+    let mut close: Vec<(usize, &String)> = zones.iter().map(|zone| (edit_distance(&name, &zone.to_lowercase()), zone))
+                                                .filter(|(distance, _)| *distance <= (name.len() / 3).max(2))
+                                                .collect();
+    close.sort();
+    close.into_iter().take(3).map(|(_, zone)| zone.clone()).collect()
+}
+
+impl Timezone {
+    /// Create a [`Timezone`] like [`Timezone::new`], but forgive mistakes in the case of zone names
+    /// (`america/new_york` loads `America/New_York`). If `name` isn't an installed zone or a POSIX TZ rule the
+    /// error suggests the installed zones with the closest names.
+    pub fn lookup(name: &str) -> Result<Timezone, String> {
+        let tz = Timezone::new(name);
+        if matches!(tz, Ok(ref tz) if !matches!(tz.provenance(), Provenance::PosixRule(_))) || name.is_empty() || name.starts_with('/') || name.starts_with(':') {
+            return tz;
+        }
+        let zones = available_zones()?;
+        if let Some(zone) = zones.iter().find(|zone| zone.eq_ignore_ascii_case(name)) {
+            return Timezone::new(zone);
+        }
+        tz.map_err(|_| match &suggestions(name, &zones)[..] {
+            []          => format!("Unknown zone {:?}", name),
+            suggestions => format!("Unknown zone {:?} (did you mean {}?)", name, suggestions.join(", ")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup() {
+        assert_eq!((edit_distance("kitten", "sitting"), edit_distance("", "abc"), edit_distance("abc", "abc")), (3, 3, 0));
+
+        let zones = available_zones().expect("available_zones");
+        assert!(zones.iter().any(|z| z == "America/New_York"));
+        assert!(!zones.iter().any(|z| z.starts_with("right/") || z == "posixrules" || z == "zone1970.tab"));
+
+        let tz = Timezone::lookup("america/new_york").expect("lookup");
+        assert_eq!(tz.provenance().name(), "America/New_York");
+        assert_eq!(Timezone::lookup("EST5EDT,M3.2.0,M11.1.0").expect("lookup").provenance(),
+                   &Provenance::PosixRule("EST5EDT,M3.2.0,M11.1.0".to_string()));
+        let err = Timezone::lookup("America/New_Yrok").expect_err("lookup should fail");
+        assert!(err.contains("did you mean America/New_York"), "{}", err);
+        assert_eq!(Timezone::lookup("Xyzzy/Plugh").err(), Some("Unknown zone \"Xyzzy/Plugh\"".to_string()));
+    }
+
+    #[test]
+    fn identify_zone_file() {
+        let zones = super::identify_zone_file(Path::new(TZDIR).join("Asia/Tokyo")).expect("identify_zone_file");
+        for zone in ["Asia/Tokyo", "Japan"] {
+            assert!(zones.iter().any(|z| z == zone), "{} missing from {:?}", zone, zones);
+        }
+        assert!(!zones.iter().any(|z| z == "Asia/Seoul"));
+        assert!(super::identify_zone_file("/nonexistent/zone").is_err());
+        assert!(!system_zone_names().expect("system_zone_names").is_empty());
+    }
+
+    #[test]
+    fn zones_using_abbrev() {
+        let zones = super::zones_using_abbrev("IST").expect("zones_using_abbrev");
+        for zone in ["Asia/Kolkata", "Asia/Jerusalem", "Europe/Dublin"] {
+            assert!(zones.iter().any(|z| z == zone), "{} missing from {:?}", zone, zones);
+        }
+        assert!(!zones.iter().any(|z| z == "America/New_York"));
+        assert!(super::zones_using_abbrev("NZST").expect("zones_using_abbrev").iter().any(|z| z == "Pacific/Auckland"));
+        assert_eq!(super::zones_using_abbrev("XYZZY"), Ok(vec![]));
+    }
+
+    #[test]
+    fn zone_status() {
+        assert_eq!(super::zone_status("America/New_York"), Ok(ZoneStatus::Canonical));
+        assert_eq!(super::zone_status("Europe/Bratislava"), Ok(ZoneStatus::Link{ target: "Europe/Prague".to_string() }));
+        assert_eq!(super::zone_status("UTC"), Ok(ZoneStatus::Link{ target: "Etc/UTC".to_string() }));
+        for (old, new) in [("Asia/Calcutta", "Asia/Kolkata"), ("US/Eastern", "America/New_York"), ("Europe/Kiev", "Europe/Kyiv")] {
+            assert_eq!(super::zone_status(old).as_ref().map(ZoneStatus::replacement), Ok(Some(new)), "{}", old);
+        }
+        assert_eq!(super::zone_status("Not/A_Zone"), Ok(ZoneStatus::Unknown));
+        assert_eq!(ZoneStatus::Canonical.replacement(), None);
+    }
+}
@@ -0,0 +1,182 @@
+// Zones read straight out of memory mapped TZif files.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use crate::{Tm, TimeT, Timezone, TzifView, ZoneInfo, zone_dirs};
+use crate::timezone::is_tzif;
+
+/// A zone whose TZif file is memory mapped (read only) and used in place, for servers that load hundreds of
+/// zones: instead of every [`Timezone`] reading its file and building its own tables on the heap, the kernel
+/// shares one copy of each file's pages between every thread and every process that maps it.
+///
+/// Opening one checks the file once (see [`TzifView`]); after that, conversions within the transition table are
+/// a binary search of the mapped bytes with no system calls, no locks and no allocation apart from the
+/// [`Tm`]'s abbreviation. Times after the last transition use the file's POSIX rule, which is loaded into a
+/// [`Timezone`] the first time it's needed. It's `Send` and `Sync`, so share it with an [`Arc`](std::sync::Arc).
+///
+/// Zones with leap seconds (the `right/` zones) aren't supported: [`MappedZone::open`] rejects them, so use a
+/// [`Timezone`] for those.
+///
+/// Replacing the file while it's mapped is fine (tzdata updates write a new file and rename it over the old one),
+/// but changing it in place isn't: see [`MappedZone::open`].
+///
+/// ```
+/// use libtz::MappedZone;
+/// // Safety: nothing writes to the system's zone files in place.
+/// let la = unsafe { MappedZone::open("America/Los_Angeles") }.unwrap();
+/// assert_eq!(la.offset_at(1700000000).unwrap(), (-28800, false, "PST"));
+/// assert_eq!(la.localtime(1700000000).unwrap().strftime("%F %T %Z"), "2023-11-14 14:13:20 PST");
+/// ```
+pub struct MappedZone {
+    path: PathBuf,
+    // Borrows from `map`, which lives as long as this and never moves. It's only handed out with `&self`'s lifetime.
+    view: TzifView<'static>,
+    map: Mapping,
+    rule: OnceLock<Result<Timezone, String>>,
+}
+
+struct Mapping {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+// The mapping is read only and never changes after it's made.
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr, self.len) };
+    }
+}
+
+impl Mapping {
+    fn new(path: &Path) -> Result<Mapping, String> {
+        use std::os::fd::AsRawFd;
+        let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let len = file.metadata().map_err(|e| format!("{}: {}", path.display(), e))?.len();
+        let len = usize::try_from(len).ok().filter(|len| *len > 0).ok_or_else(|| format!("{}: not a TZif file", path.display()))?;
+        let ptr = unsafe { libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_SHARED, file.as_raw_fd(), 0) };
+        if ptr == libc::MAP_FAILED {
+            return Err(format!("{}: {}", path.display(), std::io::Error::last_os_error()));
+        }
+        Ok(Mapping{ ptr, len })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+impl MappedZone {
+    /// Map the zone `name`: a zone name looked up in the [zone directories](crate::zone_dirs), or an absolute
+    /// path to a TZif file.
+    ///
+    /// # Safety
+    ///
+    /// The file is checked once, here, and then read in place for as long as the [`MappedZone`] lives, so
+    /// nothing may write to or truncate it in the meantime (in this process or any other). Changed contents can
+    /// break the checks [`TzifView`] relies on, which is undefined behavior, and reading pages that were
+    /// truncated away kills the process with `SIGBUS`. Renaming a new file over the old one is fine: the mapping
+    /// keeps the old file.
+    pub unsafe fn open(name: &str) -> Result<MappedZone, String> {
+        let path = if name.starts_with('/') { PathBuf::from(name) } else {
+            zone_dirs().into_iter().map(|dir| dir.join(name)).find(|path| is_tzif(path))
+                       .ok_or_else(|| format!("Unknown zone {:?}", name))?
+        };
+        let map = Mapping::new(&path)?;
+        // Only lives as long as `map` really, but the caller promised not to change the file underneath it.
+        let bytes: &'static [u8] = unsafe { std::slice::from_raw_parts(map.ptr as *const u8, map.len) };
+        let view = TzifView::parse(bytes).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if view.leap_count() > 0 {
+            return Err(format!("{}: zones with leap seconds aren't supported", path.display()));
+        }
+        trace_event!(DEBUG, path = %path.display(), bytes = map.len, "mapped zone file");
+        Ok(MappedZone{ path, view, map, rule: OnceLock::new() })
+    }
+
+    /// The file that's mapped.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The file's contents.
+    pub fn bytes(&self) -> &[u8] {
+        self.map.bytes()
+    }
+
+    /// The file's tables.
+    pub fn view(&self) -> &TzifView<'_> {
+        &self.view
+    }
+
+    fn rule(&self, footer: &str) -> Result<&Timezone, String> {
+        // Parsed as a rule (not handed to Timezone::new) so a zone file that happens to have the rule's name can't
+        // be picked up instead.
+        self.rule.get_or_init(|| Timezone::from_zone_info(footer, ZoneInfo::from_posix(&footer.parse()?)))
+                 .as_ref().map_err(|e| format!("{}: bad footer {:?}: {}", self.path.display(), footer, e))
+    }
+
+    /// The UTC offset (in seconds east), DST flag and abbreviation in effect at `time`.
+    pub fn offset_at(&self, time: TimeT) -> Result<(i64, bool, &str), String> {
+        match (self.view.type_at(time), self.view.footer()) {
+            (Some(i), _) => {
+                let (utoff, isdst, abbr) = self.view.local_time_type(i).expect("type_at gives valid types");
+                Ok((utoff as i64, isdst, abbr))
+            },
+            (None, Some(footer)) => {
+                let rule = self.rule(footer)?;
+                let tm = rule.localtime(time)?;
+                // The rule's abbreviations are in the file too, so hand out one that borrows from the mapping.
+                let abbr = (0..self.view.type_count()).filter_map(|i| self.view.local_time_type(i)).map(|(_, _, abbr)| abbr)
+                                                      .find(|abbr| **abbr == *tm.tm_zone).unwrap_or("");
+                Ok((tm.tm_gmtoff, tm.tm_isdst > 0, abbr))
+            },
+            (None, None) => unreachable!("type_at only gives None when there's a footer"),
+        }
+    }
+
+    /// Convert system time to local time.
+    pub fn localtime(&self, time: TimeT) -> Result<Tm, String> {
+        let (gmtoff, isdst, abbr) = self.offset_at(time)?;
+        let tm = crate::gmtime(time.checked_add(gmtoff).ok_or("Time out of range")?)?;
+        Ok(Tm{ tm_isdst: isdst as i32, tm_gmtoff: gmtoff, tm_zone: abbr.into(), ..tm })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn mapped_zone() {
+        let la = unsafe { MappedZone::open("America/Los_Angeles") }.expect("open");
+        let tz = Timezone::new("America/Los_Angeles").expect("timezone alloc");
+        assert_eq!(la.path(), Path::new("/usr/share/zoneinfo/America/Los_Angeles"));
+        assert_eq!(la.view().footer(), Some("PST8PDT,M3.2.0,M11.1.0"));
+        // Before the first transition (LMT), in the table and past its end (from the footer).
+        for time in [-3000000000, -2717640000, 0, 1700000000, 1720000000, 4102444800, 4118000000] {
+            let show = |tm: Tm| (tm.strftime("%F %T %Z %z"), tm.tm_isdst);
+            assert_eq!(la.localtime(time).map(show), tz.localtime(time).map(show), "{}", time);
+        }
+        assert_eq!(la.offset_at(4118000000), Ok((-25200, true, "PDT"))); // 2100-06-30
+        assert_eq!(la.view().transition(0), Some(crate::Transition{ time: -2717640000, local_time_type: 5 }));
+
+        // Shared between threads.
+        let la = Arc::new(la);
+        let threads: Vec<_> = (0..4).map(|i| { let la = la.clone(); std::thread::spawn(move || la.offset_at(1700000000 + i).map(|o| o.0)) }).collect();
+        assert!(threads.into_iter().all(|t| t.join().expect("join") == Ok(-28800)));
+
+        unsafe {
+            let utc = MappedZone::open("/usr/share/zoneinfo/UTC").expect("open");
+            assert_eq!(utc.offset_at(0), Ok((0, false, "UTC")));
+            assert!(MappedZone::open("Nowhere/Special").is_err());
+            assert!(MappedZone::open("right/UTC").is_err());
+            assert!(MappedZone::open("/usr/share/zoneinfo/zone.tab").is_err());
+        }
+    }
+}
@@ -0,0 +1,110 @@
+// Timezones made up on the spot, for tests.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use crate::{Timezone, TimeT, ZoneInfo, Transition, LocalTimeType, LeapSecond};
+
+/// A zone whose offsets and transitions are spelled out in code, so tests of DST edge cases don't depend on
+/// which version of the tz database the machine has (or whether it has one at all).
+///
+/// [`MockTimezone::build`] makes an ordinary [`Timezone`], so the code under test can't tell the difference.
+///
+/// ```
+/// use libtz::test_util::MockTimezone;
+/// // A zone that springs forward an hour at 02:00 on 2030-03-10, and never changes back.
+/// let tz = MockTimezone::new("Test/Forward", -5 * 3600, "TST")
+///     .transition(1899356400, -4 * 3600, true, "TDT")
+///     .build().unwrap();
+/// assert_eq!(tz.localtime(1899356399).unwrap().tm_hour, 1);
+/// assert_eq!(tz.localtime(1899356400).unwrap().tm_hour, 3);
+/// assert_eq!(tz.localtime(4000000000).unwrap().tm_zone.as_ref(), "TDT");
+/// ```
+#[derive(Clone, Debug)]
+pub struct MockTimezone {
+    name: String,
+    types: Vec<LocalTimeType>,
+    transitions: Vec<Transition>,
+    leap_seconds: Vec<LeapSecond>,
+    rule: Option<String>,
+}
+
+impl MockTimezone {
+    /// Start a zone named `name` that is `utoff` seconds east of UTC, in standard time, for all time.
+    pub fn new(name: &str, utoff: i32, abbr: &str) -> MockTimezone {
+        MockTimezone{ name: name.to_string(), types: vec![local_time_type(utoff, false, abbr)], transitions: vec![], leap_seconds: vec![],
+                      rule: None }
+    }
+
+    /// Switch to `utoff` seconds east of UTC at `time`. Transitions can be added in any order.
+    pub fn transition(mut self, time: TimeT, utoff: i32, isdst: bool, abbr: &str) -> MockTimezone {
+        let ltt = local_time_type(utoff, isdst, abbr);
+        let local_time_type = self.types.iter().position(|t| *t == ltt).unwrap_or_else(|| { self.types.push(ltt); self.types.len() - 1 });
+        self.transitions.push(Transition{ time, local_time_type });
+        self
+    }
+
+    /// Follow the POSIX TZ `rule` (like `"EST5EDT,M3.2.0,M11.1.0"`) after the last transition, instead of
+    /// staying on the last offset forever.
+    pub fn rule(mut self, rule: &str) -> MockTimezone {
+        self.rule = Some(rule.to_string());
+        self
+    }
+
+    /// Insert a leap second: from `time` on there are `correction` leap seconds in total.
+    pub fn leap_second(mut self, time: TimeT, correction: i32) -> MockTimezone {
+        self.leap_seconds.push(LeapSecond{ time, correction });
+        self
+    }
+
+    /// Make the [`Timezone`]. Fails if two transitions (or leap seconds) are at the same time, or the rule
+    /// doesn't parse.
+    pub fn build(&self) -> Result<Timezone, String> {
+        let mut transitions = self.transitions.clone();
+        transitions.sort_by_key(|t| t.time);
+        let mut leap_seconds = self.leap_seconds.clone();
+        leap_seconds.sort_by_key(|l| l.time);
+        if transitions.windows(2).any(|w| w[0].time == w[1].time) || leap_seconds.windows(2).any(|w| w[0].time == w[1].time) {
+            return Err(format!("{}: two transitions or leap seconds at the same time", self.name));
+        }
+        if let Some(rule) = &self.rule {
+            rule.parse::<crate::posix::PosixTz>().map_err(|e| format!("Bad rule {:?}: {}", rule, e))?;
+        }
+        Timezone::from_zone_info(&self.name, ZoneInfo{ version: 4, transitions, types: self.types.clone(), leap_seconds,
+                                                       footer: self.rule.clone() })
+    }
+}
+
+fn local_time_type(utoff: i32, isdst: bool, abbr: &str) -> LocalTimeType {
+    LocalTimeType{ utoff, isdst, abbr: abbr.to_string(), is_std: false, is_ut: false }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Tm, Provenance};
+
+    #[test]
+    fn mock_timezone() {
+        // Spring forward on 2030-03-10 and fall back on 2030-11-03, then follow the US rules.
+        let tz = MockTimezone::new("Test/Zone", -5 * 3600, "TST")
+            .transition(1919916000, -5 * 3600, false, "TST") // Out of order on purpose
+            .transition(1899356400, -4 * 3600, true, "TDT")
+            .rule("EST5EDT,M3.2.0,M11.1.0")
+            .build().expect("build");
+        assert_eq!(tz.provenance(), &Provenance::Synthesized("Test/Zone".to_string()));
+        assert_eq!(tz.localtime(1919916000 - 1).map(|tm| (tm.tm_hour, tm.tm_isdst)), Ok((1, 1)));
+        assert_eq!(tz.localtime(1919916000).map(|tm| (tm.tm_hour, tm.tm_isdst)), Ok((1, 0)));
+        let ambiguous = Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(2030, 11, 3, 1, 30, 0) };
+        assert_eq!(tz.offsets_for_local(&ambiguous).map(|o| o.len()), Ok(2));
+        // Past the last transition the rule takes over.
+        assert_eq!(tz.localtime(1938038400).map(|tm| tm.tm_zone), Ok("EDT".into()));
+
+        assert!(MockTimezone::new("Test/Dup", 0, "Z").transition(0, 3600, true, "A").transition(0, 0, false, "Z").build().is_err());
+        assert!(MockTimezone::new("Test/Bad", 0, "Z").rule("not a rule").build().is_err());
+        assert!(MockTimezone::new("Test/Bad", 0, "Z").rule("America/New_York").build().is_err()); // A zone, not a rule
+
+        let tz = MockTimezone::new("Test/Leap", 0, "UTC").leap_second(78796800, 1).build().expect("build");
+        assert_eq!(tz.localtime(78796800).map(|tm| tm.tm_sec), Ok(60));
+    }
+}
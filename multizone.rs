@@ -0,0 +1,247 @@
+// Queries across several zones at once.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
+use crate::{Tm, TimeT, Timezone, Clock, SystemClock, TimeOfDay, Interval};
+
+/// A change of offset, DST flag or abbreviation in one of the zones given to [`upcoming_transitions`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpcomingTransition {
+    /// Which zone changes: its index in the slice of zones.
+    pub zone: usize,
+    /// When it changes.
+    pub time: TimeT,
+    /// The local time just before the change.
+    pub before: Tm,
+    /// The local time at the change.
+    pub after: Tm,
+}
+
+impl UpcomingTransition {
+    /// How far the clocks move (negative when they go back).
+    pub fn shift(&self) -> i64 {
+        self.after.tm_gmtoff - self.before.tm_gmtoff
+    }
+}
+
+/// Every transition in any of `zones` from now until `within` from now, in order (transitions at the same time
+/// are in the order of their zones), so an ops dashboard can warn that 3 deployment regions change their clocks
+/// this weekend.
+///
+/// Like [`Timezone::time_until_next_transition`], transitions that don't change anything are left out.
+pub fn upcoming_transitions(zones: &[&Timezone], within: Duration) -> Result<Vec<UpcomingTransition>, String> {
+    upcoming_transitions_from(&SystemClock, zones, within)
+}
+
+/// Like [`upcoming_transitions`], but reading now from `clock`.
+///
+/// ```
+/// use libtz::{Timezone, FixedClock, upcoming_transitions_from};
+/// let zones = ["Europe/London", "Asia/Tokyo", "America/New_York"].map(|z| Timezone::new(z).unwrap());
+/// let now = FixedClock(1698364800); // 2023-10-27 00:00 UTC
+/// let week = std::time::Duration::from_secs(7 * 86400);
+/// let changes = upcoming_transitions_from(&now, &zones.each_ref(), week).unwrap();
+/// assert_eq!(changes.iter().map(|c| (c.zone, c.shift())).collect::<Vec<_>>(), [(0, -3600)]); // Tokyo has no DST and New York is a week later
+/// ```
+pub fn upcoming_transitions_from(clock: &impl Clock, zones: &[&Timezone], within: Duration) -> Result<Vec<UpcomingTransition>, String> {
+    let now = clock.now();
+    let end = now.saturating_add(TimeT::try_from(within.as_secs()).unwrap_or(TimeT::MAX));
+    let mut upcoming = vec![];
+    for (zone, tz) in zones.iter().enumerate() {
+        for (time, before, after) in tz.transitions_between(now, end)? {
+            upcoming.push(UpcomingTransition{ zone, time, before, after });
+        }
+    }
+    upcoming.sort_by_key(|t| (t.time, t.zone));
+    Ok(upcoming)
+}
+
+// The times in `range` that are between `from` and `to` local time in `tz`, in order.
+fn local_windows(tz: &Timezone, from: TimeOfDay, to: TimeOfDay, range: &Range<TimeT>) -> Result<Vec<Interval>, String> {
+    let (from, mut to) = (from.seconds_since_midnight() as i64, to.seconds_since_midnight() as i64);
+    if to <= from {
+        to += 86400; // Overnight
+    }
+    let range = Interval::from(range.clone());
+    let mut windows = vec![];
+    // Start a day early for overnight windows that began the day before.
+    for (_, _, date) in tz.days(range.start.saturating_sub(86400)..range.end)? {
+        let midnight = date.days_since_epoch() * 86400;
+        let window = Interval{ start: tz.time_for_wall_forward(midnight + from)?, end: tz.time_for_wall_forward(midnight + to)? };
+        windows.extend(window.intersection(&range));
+    }
+    Ok(windows)
+}
+
+/// The times in `range` when it's within working hours in every one of `zones`, for finding a time for a
+/// meeting: each zone comes with the local times its window starts and ends (`09:00` to `17:00`, say). A
+/// window that ends at or before its start runs overnight, into the next day.
+///
+/// Window edges that fall in a DST gap move forward past it (like alarms do), and the hours are applied to every
+/// day, weekends included.
+///
+/// ```
+/// use libtz::{Timezone, TimeOfDay, overlapping_hours};
+/// let (ny, london) = (Timezone::new("America/New_York").unwrap(), Timezone::new("Europe/London").unwrap());
+/// let nine_to_five = (TimeOfDay::new(9, 0, 0).unwrap(), TimeOfDay::new(17, 0, 0).unwrap());
+/// let day = 1700006400..1700092800; // 2023-11-15 00:00 to 2023-11-16 00:00 UTC
+/// let overlap = overlapping_hours(&[(&ny, nine_to_five.0, nine_to_five.1), (&london, nine_to_five.0, nine_to_five.1)], day).unwrap();
+/// let local = |t| ny.localtime(t).unwrap().strftime("%H:%M");
+/// assert_eq!(overlap.iter().map(|i| (local(i.start), local(i.end))).collect::<Vec<_>>(), [("09:00".to_string(), "12:00".to_string())]);
+/// ```
+pub fn overlapping_hours(zones: &[(&Timezone, TimeOfDay, TimeOfDay)], range: Range<TimeT>) -> Result<Vec<Interval>, String> {
+    let mut overlap: Vec<Interval> = Some(Interval::from(range.clone())).filter(|i| !i.is_empty()).into_iter().collect();
+    for (tz, from, to) in zones {
+        let windows = local_windows(tz, *from, *to, &range)?;
+        let (mut i, mut j, mut common) = (0, 0, vec![]);
+        while i < overlap.len() && j < windows.len() {
+            common.extend(overlap[i].intersection(&windows[j]));
+            if overlap[i].end < windows[j].end { i += 1 } else { j += 1 }
+        }
+        overlap = common;
+    }
+    Ok(overlap)
+}
+
+/// How a transition differs between two versions of a zone, from [`diff_zones`]. Each `Tm` is the local time at
+/// the transition (so it has the new offset, DST flag and abbreviation).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransitionChange {
+    /// Only the new version has a transition at `time`.
+    Added { time: TimeT, after: Tm },
+    /// Only the old version has a transition at `time`.
+    Removed { time: TimeT, after: Tm },
+    /// Both versions have a transition at `time`, but they change to different things.
+    Changed { time: TimeT, old: Tm, new: Tm },
+}
+
+impl TransitionChange {
+    /// When the transition is.
+    pub fn time(&self) -> TimeT {
+        match self {
+            TransitionChange::Added{ time, .. } | TransitionChange::Removed{ time, .. } | TransitionChange::Changed{ time, .. } => *time,
+        }
+    }
+}
+
+impl fmt::Display for TransitionChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = |tm: &Tm| format!("{} {}{}", crate::format_offset(tm.tm_gmtoff, crate::OffsetFormat::Extended), tm.tm_zone, if tm.tm_isdst > 0 { " DST" } else { "" });
+        let utc = crate::gmtime(self.time()).map_err(|_| fmt::Error)?.strftime("%F %T UTC");
+        match self {
+            TransitionChange::Added{ after, .. }   => write!(f, "{}: added, to {}", utc, state(after)),
+            TransitionChange::Removed{ after, .. } => write!(f, "{}: removed, was to {}", utc, state(after)),
+            TransitionChange::Changed{ old, new, .. } => write!(f, "{}: changed, to {} instead of {}", utc, state(new), state(old)),
+        }
+    }
+}
+
+/// What's different about the transitions of two versions of a zone in `range` (say the same zone loaded from
+/// the current tzdata and from an update, with [`Timezone::new_in`]), in time order, so operators can see exactly
+/// what an update changes before rolling it out. A transition that moves shows up as one removed and one added.
+///
+/// Like [`upcoming_transitions`], transitions that don't change the offset, DST flag or abbreviation are left
+/// out.
+///
+/// ```
+/// use libtz::{Timezone, diff_zones, TransitionChange};
+/// // The US moved its DST dates in 2007. Compare the real zone with one that kept the old rules.
+/// let (new, old) = (Timezone::new("America/New_York").unwrap(), Timezone::new("EST5EDT,M4.1.0,M10.5.0").unwrap());
+/// let changes = diff_zones(&old, &new, 1167609600..1199145600).unwrap(); // 2007
+/// assert_eq!(changes.iter().map(|c| c.to_string()).collect::<Vec<_>>(), [
+///     "2007-03-11 07:00:00 UTC: added, to -04:00 EDT DST",
+///     "2007-04-01 07:00:00 UTC: removed, was to -04:00 EDT DST",
+///     "2007-10-28 06:00:00 UTC: removed, was to -05:00 EST",
+///     "2007-11-04 06:00:00 UTC: added, to -05:00 EST",
+/// ]);
+/// ```
+pub fn diff_zones(old: &Timezone, new: &Timezone, range: Range<TimeT>) -> Result<Vec<TransitionChange>, String> {
+    let (mut old, mut new) = (old.transitions_between(range.start, range.end)?.into_iter().peekable(), new.transitions_between(range.start, range.end)?.into_iter().peekable());
+    let same = |a: &Tm, b: &Tm| (a.tm_gmtoff, a.tm_isdst > 0, &a.tm_zone) == (b.tm_gmtoff, b.tm_isdst > 0, &b.tm_zone);
+    let mut changes = vec![];
+    loop {
+        let change = match (old.peek(), new.peek()) {
+            (None, None) => break,
+            (Some((o, _, _)), Some((n, _, _))) if o == n => {
+                let ((time, _, old), (_, _, new)) = (old.next().expect("peeked"), new.next().expect("peeked"));
+                if same(&old, &new) { continue }
+                TransitionChange::Changed{ time, old, new }
+            },
+            (Some((o, _, _)), Some((n, _, _))) if n < o => { let (time, _, after) = new.next().expect("peeked"); TransitionChange::Added{ time, after } },
+            (Some(_), _) => { let (time, _, after) = old.next().expect("peeked"); TransitionChange::Removed{ time, after } },
+            (None, Some(_)) => { let (time, _, after) = new.next().expect("peeked"); TransitionChange::Added{ time, after } },
+        };
+        changes.push(change);
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FixedClock;
+
+    #[test]
+    fn upcoming() {
+        let zones = ["America/New_York", "Europe/Paris", "Australia/Sydney", "Asia/Kolkata"].map(|z| Timezone::new(z).expect("timezone alloc"));
+        let now = FixedClock(1711584000); // 2024-03-28 00:00 UTC
+        let changes = upcoming_transitions_from(&now, &zones.each_ref(), Duration::from_secs(14 * 86400)).expect("upcoming_transitions");
+        // Paris springs forward 2024-03-31 01:00 UTC, Sydney falls back 2024-04-06 16:00 UTC.
+        assert_eq!(changes.iter().map(|c| (c.zone, c.time, c.shift())).collect::<Vec<_>>(), [(1, 1711846800, 3600), (2, 1712419200, -3600)]);
+        assert_eq!((&*changes[1].before.tm_zone, &*changes[1].after.tm_zone), ("AEDT", "AEST"));
+        assert_eq!(upcoming_transitions_from(&now, &zones.each_ref(), Duration::ZERO), Ok(vec![]));
+        assert!(upcoming_transitions(&[&zones[0]], Duration::from_secs(366 * 86400)).expect("upcoming_transitions").len() >= 2);
+    }
+
+    #[test]
+    fn diff() {
+        let dir = std::env::temp_dir().join("libtz-diff-zones");
+        std::fs::create_dir_all(dir.join("Test")).expect("mkdir");
+        // An "update" where Paris becomes Moscow (no DST, +03:00 from 2011).
+        std::fs::copy("/usr/share/zoneinfo/Europe/Moscow", dir.join("Test/Zone")).expect("copy");
+        let (old, new) = (Timezone::new("Europe/Paris").expect("timezone alloc"), Timezone::new_in(&dir, "Test/Zone").expect("timezone alloc"));
+        let changes = diff_zones(&old, &new, 1293840000..1325376000).expect("diff_zones"); // 2011
+        assert_eq!(changes.iter().map(|c| c.time()).collect::<Vec<_>>(), [1301180400, 1301187600, 1319936400]);
+        assert!(matches!(&changes[0], TransitionChange::Added{ after, .. } if &*after.tm_zone == "MSK" && after.tm_gmtoff == 4 * 3600)); // Permanent "summer" time
+        assert!(matches!(&changes[1], TransitionChange::Removed{ after, .. } if &*after.tm_zone == "CEST")); // 2011-03-27 01:00 UTC
+        assert_eq!(changes[2].to_string(), "2011-10-30 01:00:00 UTC: removed, was to +01:00 CET");
+        assert_eq!(diff_zones(&old, &Timezone::new("Europe/Paris").expect("timezone alloc"), 0..2000000000), Ok(vec![]));
+
+        let changed = diff_zones(&Timezone::new("EST5EDT,M3.2.0,M11.1.0").expect("timezone alloc"), &Timezone::new("EST5XDT,M3.2.0,M11.1.0").expect("timezone alloc"),
+                                 1704067200..1735689600).expect("diff_zones"); // 2024
+        assert_eq!(changed.iter().map(|c| c.to_string()).collect::<Vec<_>>(), ["2024-03-10 07:00:00 UTC: changed, to -04:00 XDT DST instead of -04:00 EDT DST"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn overlapping() {
+        let hm = |h, m| TimeOfDay::new(h, m, 0).expect("TimeOfDay");
+        let (la, berlin, tokyo) = (Timezone::new("America/Los_Angeles").expect("timezone alloc"), Timezone::new("Europe/Berlin").expect("timezone alloc"),
+                                   Timezone::new("Asia/Tokyo").expect("timezone alloc"));
+        let utc = |t: TimeT| crate::gmtime(t).expect("gmtime").strftime("%F %H:%M");
+        let show = |overlap: Vec<Interval>| overlap.iter().map(|i| format!("{} to {}", utc(i.start), utc(i.end))).collect::<Vec<_>>();
+        let week = 1710460800..1711065600; // 2024-03-15 to 22 UTC, while the US is on DST and Europe isn't
+
+        // Berlin 08:00-18:00 is 07:00-17:00 UTC, LA 08:00-18:00 is 15:00-01:00 UTC.
+        let overlap = overlapping_hours(&[(&la, hm(8, 0), hm(18, 0)), (&berlin, hm(8, 0), hm(18, 0))], week.clone()).expect("overlapping_hours");
+        assert_eq!(overlap.len(), 7);
+        assert_eq!(show(overlap)[0], "2024-03-15 15:00 to 2024-03-15 17:00");
+
+        // Tokyo's night shift (22:00-06:00, 13:00-21:00 UTC) against Berlin's day.
+        let overlap = overlapping_hours(&[(&tokyo, hm(22, 0), hm(6, 0)), (&berlin, hm(8, 0), hm(18, 0))], week.clone()).expect("overlapping_hours");
+        assert_eq!(show(overlap)[..2], ["2024-03-15 13:00 to 2024-03-15 17:00", "2024-03-16 13:00 to 2024-03-16 17:00"]);
+
+        // Nobody's awake at the same time.
+        assert_eq!(overlapping_hours(&[(&la, hm(9, 0), hm(17, 0)), (&tokyo, hm(9, 0), hm(12, 0))], week.clone()), Ok(vec![]));
+        // No zones: the whole range.
+        assert_eq!(overlapping_hours(&[], week.clone()), Ok(vec![Interval::from(week)]));
+
+        // Berlin springs forward on 2024-03-31, so its 08:00 is an hour earlier in UTC.
+        let overlap = overlapping_hours(&[(&berlin, hm(8, 0), hm(9, 0))], 1711756800..1711929600).expect("overlapping_hours"); // 2024-03-30 to 04-01
+        assert_eq!(show(overlap), ["2024-03-30 07:00 to 2024-03-30 08:00", "2024-03-31 06:00 to 2024-03-31 07:00"]);
+    }
+}
@@ -0,0 +1,225 @@
+// UTC offsets.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use core::fmt;
+use core::ops::Neg;
+use alloc::{format, string::String};
+use crate::{OffsetFormat, format_offset};
+
+/// A UTC offset: seconds east of UTC, less than 26 hours either way (the range RFC 8536 recommends for TZif
+/// files; the largest offsets in use are +14:00 and -12:00). tzcode itself accepts bigger ones (POSIX TZ strings
+/// go up to 167:59:60), so the [`Timezone`](crate::Timezone) methods that report a zone's offset give plain
+/// seconds.
+///
+/// It parses the forms offsets are written in (`Z`, `+05:30`, `-0800`, `+05`, and with seconds `+00:19:32`
+/// or `+001932`, and the military letters `A` to `Z`, see [`UtcOffset::from_military`]) and formats with any
+/// [`OffsetFormat`]; `Display` gives the extended form.
+///
+/// ```
+/// use libtz::{UtcOffset, OffsetFormat};
+/// let india: UtcOffset = "+05:30".parse().unwrap();
+/// assert_eq!(india.seconds(), 19800);
+/// assert_eq!(india.format(OffsetFormat::Basic), "+0530");
+/// assert_eq!(india.checked_sub("-0800".parse().unwrap()).map(|d| d.to_string()), Some("+13:30".to_string()));
+/// assert_eq!("Z".parse::<UtcOffset>(), Ok(UtcOffset::UTC));
+/// assert!(UtcOffset::new(26 * 3600).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UtcOffset(i32);
+
+impl UtcOffset {
+    /// UTC itself.
+    pub const UTC: UtcOffset = UtcOffset(0);
+
+    /// The largest offset, in seconds, either way.
+    pub const MAX_SECONDS: i32 = 26 * 3600 - 1;
+
+    /// An offset of `seconds` east of UTC. It's an error if it's 26 hours or more.
+    pub fn new(seconds: i64) -> Result<UtcOffset, String> {
+        UtcOffset::from_seconds(seconds).ok_or_else(|| format!("Offset {} is out of range", seconds))
+    }
+
+    /// Like [`UtcOffset::new`], but `None` for offsets out of range, so it can be used in constants.
+    pub const fn from_seconds(seconds: i64) -> Option<UtcOffset> {
+        if seconds.unsigned_abs() > UtcOffset::MAX_SECONDS as u64 {
+            return None;
+        }
+        Some(UtcOffset(seconds as i32))
+    }
+
+    /// An offset of `hours`, `minutes` and `seconds`, which all have the same sign (`(-3, -30, 0)` is -03:30).
+    pub const fn from_hms(hours: i32, minutes: i32, seconds: i32) -> Option<UtcOffset> {
+        UtcOffset::from_seconds(hours as i64 * 3600 + minutes as i64 * 60 + seconds as i64)
+    }
+
+    /// Seconds east of UTC.
+    pub const fn seconds(self) -> i32 {
+        self.0
+    }
+
+    /// The whole hours, minutes and seconds of the offset, all with the offset's sign.
+    pub const fn as_hms(self) -> (i32, i32, i32) {
+        (self.0 / 3600, self.0 / 60 % 60, self.0 % 60)
+    }
+
+    /// Whether this is UTC.
+    pub const fn is_utc(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The offset written as text in `format`.
+    pub fn format(self, format: OffsetFormat) -> String {
+        format_offset(self.0 as i64, format)
+    }
+
+    /// The offset of a military (nautical) time zone letter: `A` to `M` are +01:00 to +12:00 (skipping `J`),
+    /// `N` to `Y` are -01:00 to -12:00 and `Z` is UTC. Lower case letters work too. `J` ("Juliett") means the
+    /// observer's local time, which isn't an offset, so it's `None` along with anything else that isn't a letter.
+    ///
+    /// ```
+    /// use libtz::UtcOffset;
+    /// assert_eq!(UtcOffset::from_military('R').map(|o| o.to_string()), Some("-05:00".to_string()));
+    /// assert_eq!(UtcOffset::from_military('K').map(|o| o.to_string()), Some("+10:00".to_string()));
+    /// ```
+    pub const fn from_military(letter: char) -> Option<UtcOffset> {
+        let hours = match letter.to_ascii_uppercase() {
+            c @ 'A'..='I' => c as i32 - 'A' as i32 + 1,
+            c @ 'K'..='M' => c as i32 - 'K' as i32 + 10,
+            c @ 'N'..='Y' => -(c as i32 - 'N' as i32 + 1),
+            'Z'           => 0,
+            _             => return None,
+        };
+        UtcOffset::from_hms(hours, 0, 0)
+    }
+
+    /// The military time zone letter for this offset (see [`UtcOffset::from_military`]), or `None` if it isn't a
+    /// whole number of hours from -12 to +12.
+    pub const fn military_letter(self) -> Option<char> {
+        if self.0 % 3600 != 0 {
+            return None;
+        }
+        let letter = match self.0 / 3600 {
+            0             => b'Z',
+            h @ 1..=9     => b'A' + h as u8 - 1,
+            h @ 10..=12   => b'K' + h as u8 - 10,
+            h @ -12..=-1  => b'N' + (-h) as u8 - 1,
+            _             => return None,
+        };
+        Some(letter as char)
+    }
+
+    /// The sum of two offsets, or `None` if it's out of range.
+    pub const fn checked_add(self, other: UtcOffset) -> Option<UtcOffset> {
+        UtcOffset::from_seconds(self.0 as i64 + other.0 as i64)
+    }
+
+    /// The difference between two offsets, or `None` if it's out of range.
+    pub const fn checked_sub(self, other: UtcOffset) -> Option<UtcOffset> {
+        UtcOffset::from_seconds(self.0 as i64 - other.0 as i64)
+    }
+}
+
+impl Neg for UtcOffset {
+    type Output = UtcOffset;
+    fn neg(self) -> UtcOffset {
+        UtcOffset(-self.0)
+    }
+}
+
+impl From<UtcOffset> for i64 {
+    fn from(offset: UtcOffset) -> i64 {
+        offset.0 as i64
+    }
+}
+
+impl TryFrom<i64> for UtcOffset {
+    type Error = String;
+    fn try_from(seconds: i64) -> Result<UtcOffset, String> {
+        UtcOffset::new(seconds)
+    }
+}
+
+impl fmt::Display for UtcOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format(OffsetFormat::Extended))
+    }
+}
+
+impl core::str::FromStr for UtcOffset {
+    type Err = String;
+    fn from_str(s: &str) -> Result<UtcOffset, String> {
+        let bad = || format!("Invalid UTC offset {:?}", s);
+        if s == "z" {
+            return Ok(UtcOffset::UTC);
+        }
+        if let [letter @ b'A'..=b'Z'] = s.as_bytes() {
+            return UtcOffset::from_military(*letter as char).ok_or_else(bad);
+        }
+        let sign = match s.as_bytes().first() { Some(b'+') => 1, Some(b'-') => -1, _ => return Err(bad()) };
+        let body = &s[1..];
+        // Either every field is separated by a colon or none are.
+        let digits: String = body.split(':').collect();
+        let colons = body.len() - digits.len();
+        if !digits.bytes().all(|b| b.is_ascii_digit()) || !matches!(digits.len(), 2 | 4 | 6) || (colons != 0 && colons != digits.len() / 2 - 1)
+           || (colons != 0 && body.split(':').any(|field| field.len() != 2)) {
+            return Err(bad());
+        }
+        let field = |i: usize| digits.get(i..i + 2).map_or(0, |f| f.parse::<i64>().unwrap_or(0));
+        let (hours, minutes, seconds) = (field(0), field(2), field(4));
+        if minutes >= 60 || seconds >= 60 {
+            return Err(bad());
+        }
+        UtcOffset::new(sign * (hours * 3600 + minutes * 60 + seconds)).map_err(|_| bad())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn utc_offset() {
+        let parse = |s: &str| s.parse::<UtcOffset>().map(UtcOffset::seconds);
+        assert_eq!(parse("+05:30"), Ok(19800));
+        assert_eq!(parse("-0800"), Ok(-28800));
+        assert_eq!(parse("-03"), Ok(-10800));
+        assert_eq!(parse("z"), Ok(0));
+        assert_eq!(parse("+00:19:32"), Ok(1172));
+        assert_eq!(parse("+001932"), Ok(1172));
+        assert_eq!(parse("+25:59:59"), Ok(UtcOffset::MAX_SECONDS));
+        for bad in ["", "05:30", "+5:30", "+05:3", "+0530:00", "+05:30:0", "+05:60", "+26", "+05:30Z", "UTC", "+", "+０５"] {
+            assert!(parse(bad).is_err(), "{:?}", bad);
+        }
+
+        let lmt = UtcOffset::from_hms(0, 19, 32).expect("from_hms");
+        assert_eq!((lmt.to_string(), lmt.format(OffsetFormat::Basic)), ("+00:19:32".to_string(), "+001932".to_string()));
+        assert_eq!((-lmt).as_hms(), (0, -19, -32));
+        assert_eq!(UtcOffset::UTC.format(OffsetFormat::Zulu), "Z");
+        assert!(UtcOffset::UTC.is_utc() && UtcOffset::default().is_utc());
+        assert_eq!(UtcOffset::from_seconds(-26 * 3600), None);
+        assert_eq!(UtcOffset::try_from(-12 * 3600).map(i64::from), Ok(-12 * 3600));
+        let max = UtcOffset::new(UtcOffset::MAX_SECONDS as i64).expect("new");
+        assert_eq!(max.checked_add(UtcOffset::from_seconds(1).expect("from_seconds")), None);
+        assert_eq!(max.checked_sub(max), Some(UtcOffset::UTC));
+        assert!(UtcOffset::new(-3600) < UtcOffset::new(0));
+    }
+
+    #[test]
+    fn military() {
+        let letters: String = (-12..=12).filter_map(|h| UtcOffset::from_hms(h, 0, 0)?.military_letter()).collect();
+        assert_eq!(letters, "YXWVUTSRQPONZABCDEFGHIKLM");
+        for letter in letters.chars() {
+            let offset = UtcOffset::from_military(letter).expect("from_military");
+            assert_eq!(offset.military_letter(), Some(letter));
+            assert_eq!(letter.to_string().parse(), Ok(offset));
+        }
+        assert_eq!(UtcOffset::from_military('q').map(UtcOffset::seconds), Some(-4 * 3600));
+        assert_eq!((UtcOffset::from_military('J'), UtcOffset::from_military('5')), (None, None));
+        assert!("J".parse::<UtcOffset>().is_err() && "r".parse::<UtcOffset>().is_err());
+        assert_eq!(UtcOffset::from_hms(13, 0, 0).and_then(UtcOffset::military_letter), None);
+        assert_eq!(UtcOffset::from_hms(5, 30, 0).and_then(UtcOffset::military_letter), None);
+    }
+}
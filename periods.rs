@@ -0,0 +1,275 @@
+// Local calendar days, weeks and months.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::ops::Range;
+use crate::{Date, TimeT, Timezone, Weekday};
+use crate::timezone::LocalCandidates;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Unit {
+    Day,
+    Week,
+    Month,
+}
+
+/// Local calendar periods, from [`Timezone::days`], [`Timezone::weeks`] or [`Timezone::months`]. Each item is
+/// `(start, end, date)`: the system time the period starts, the system time the next one starts (so `end` isn't
+/// in the period), and the local date of its first day.
+pub struct Periods<'a> {
+    tz: &'a Timezone,
+    unit: Unit,
+    date: Option<Date>,
+    start: TimeT,
+    end: TimeT,
+}
+
+/// A day in a [`Timezone::month_grid`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridDay {
+    /** The local date */                           pub date     : Date,
+    /** Whether it's in the month, not padding */  pub in_month : bool,
+    /** The system time the day starts */           pub start    : TimeT,
+    /** The system time the next day starts */      pub end      : TimeT,
+}
+
+fn next_date(date: Date, unit: Unit) -> Option<Date> {
+    match unit {
+        Unit::Day   => Date::from_days_since_epoch(date.days_since_epoch() + 1).ok(),
+        Unit::Week  => Date::from_days_since_epoch(date.days_since_epoch() + 7).ok(),
+        Unit::Month => match date.month() {
+            12    => Date::new(date.year().checked_add(1)?, 1, 1).ok(),
+            month => Date::new(date.year(), month + 1, 1).ok(),
+        },
+    }
+}
+
+impl Iterator for Periods<'_> {
+    type Item = (TimeT, TimeT, Date);
+    fn next(&mut self) -> Option<(TimeT, TimeT, Date)> {
+        let date = self.date?;
+        if self.start >= self.end {
+            return None;
+        }
+        let next = next_date(date, self.unit);
+        let end = next.and_then(|next| self.tz.start_of_day(next).ok());
+        self.date = next.filter(|_| end.is_some());
+        let start = self.start;
+        self.start = end.unwrap_or(TimeT::MAX);
+        Some((start, end?, date))
+    }
+}
+
+impl Timezone {
+    /// The first instant of `date` in this zone: local midnight, or if the clocks jumped past midnight, the
+    /// moment they did.
+    pub fn start_of_day(&self, date: Date) -> Result<TimeT, String> {
+        let wall = date.days_since_epoch() * 86400;
+        let LocalCandidates{ times, gap } = self.local_candidates(wall)?;
+        if let Some(time) = times.first() {
+            return Ok(*time);
+        }
+        let (before, after) = gap.ok_or("Invalid date specified")?;
+        self.transitions_between(wall - after, wall - before + 1)?.first().map(|(time, _, _)| *time).ok_or_else(|| "Invalid date specified".to_string())
+    }
+
+    /// How long `date` is in this zone, from its [start](Timezone::start_of_day) to the start of the next day:
+    /// usually 24 hours, but 23 or 25 when the clocks change (and odder lengths for some historic changes).
+    ///
+    /// ```
+    /// use libtz::{Timezone, Date};
+    /// let tz = Timezone::new("Europe/London").expect("timezone alloc");
+    /// let hours = |y, m, d| tz.day_length(Date::new(y, m, d).unwrap()).unwrap().as_secs() / 3600;
+    /// assert_eq!((hours(2024, 3, 31), hours(2024, 6, 1), hours(2024, 10, 27)), (23, 24, 25));
+    /// ```
+    pub fn day_length(&self, date: Date) -> Result<std::time::Duration, String> {
+        let next = Date::from_days_since_epoch(date.days_since_epoch() + 1)?;
+        Ok(std::time::Duration::from_secs((self.start_of_day(next)? - self.start_of_day(date)?) as u64))
+    }
+
+    /// The start of the next local day after `from`'s that's a `weekday` (so a week later if `from` is already
+    /// on one).
+    ///
+    /// ```
+    /// use libtz::{Timezone, Weekday};
+    /// let tz = Timezone::new("America/New_York").expect("timezone alloc");
+    /// let monday = tz.next_weekday(1700000000, Weekday::Monday).expect("next_weekday"); // from Tue 2023-11-14
+    /// assert_eq!(tz.localtime(monday).map(|tm| (tm.tm_mday, tm.tm_hour)), Ok((20, 0)));
+    /// ```
+    pub fn next_weekday(&self, from: TimeT, weekday: Weekday) -> Result<TimeT, String> {
+        let date = self.local_date(from)?;
+        let days = 1 + (weekday as i32 - date.weekday() as i32 - 1).rem_euclid(7) as i64;
+        self.start_of_day(Date::from_days_since_epoch(date.days_since_epoch() + days)?)
+    }
+
+    /// The start of the last local day before `from`'s that was a `weekday` (so a week earlier if `from` is on
+    /// one).
+    pub fn previous_weekday(&self, from: TimeT, weekday: Weekday) -> Result<TimeT, String> {
+        let date = self.local_date(from)?;
+        let days = 1 + (date.weekday() as i32 - weekday as i32 - 1).rem_euclid(7) as i64;
+        self.start_of_day(Date::from_days_since_epoch(date.days_since_epoch() - days)?)
+    }
+
+    /// The start of the `n`th `weekday` of `month` [1, 12] in `year` in this zone (see
+    /// [`Date::nth_weekday_of_month`]).
+    pub fn nth_weekday_of_month(&self, year: i32, month: i32, n: i32, weekday: Weekday) -> Result<TimeT, String> {
+        self.start_of_day(Date::nth_weekday_of_month(year, month, n, weekday)?)
+    }
+
+    fn local_date(&self, time: TimeT) -> Result<Date, String> {
+        let tm = self.localtime(time)?;
+        Date::new(tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday)
+    }
+
+    fn periods(&self, range: Range<TimeT>, unit: Unit, first: impl Fn(Date) -> Result<Date, String>) -> Result<Periods<'_>, String> {
+        let date = first(self.local_date(range.start)?)?;
+        Ok(Periods{ tz: self, unit, date: Some(date).filter(|_| !range.is_empty()), start: self.start_of_day(date)?, end: range.end })
+    }
+
+    /// The weeks of `month` [1, 12] in `year` as a calendar widget shows them: 4 to 6 rows of 7 days, each row
+    /// starting on `week_start`, with the days before and after the month that fill out the first and last weeks
+    /// marked as not `in_month`. Each day's start and end are local day boundaries (see
+    /// [`Timezone::start_of_day`]), so days with clock changes are 23 or 25 hours long.
+    ///
+    /// ```
+    /// use libtz::{Timezone, Weekday};
+    /// let tz = Timezone::new("America/New_York").expect("timezone alloc");
+    /// let grid = tz.month_grid(2024, 3, Weekday::Sunday).expect("month_grid");
+    /// assert_eq!(grid.len(), 6);
+    /// assert_eq!((grid[0][0].date.to_string(), grid[0][0].in_month), ("2024-02-25".to_string(), false));
+    /// assert_eq!(grid[2][0].end - grid[2][0].start, 23 * 3600); // March 10th, when DST started
+    /// ```
+    pub fn month_grid(&self, year: i32, month: i32, week_start: Weekday) -> Result<Vec<[GridDay; 7]>, String> {
+        let first = Date::new(year, month, 1)?;
+        let lead = (first.weekday() as i32 - week_start as i32).rem_euclid(7);
+        let weeks = (lead + crate::calendar::month_days(year as i64, month - 1) + 6) / 7;
+        let mut date = Date::from_days_since_epoch(first.days_since_epoch() - lead as i64)?;
+        let mut start = self.start_of_day(date)?;
+        let mut grid = Vec::with_capacity(weeks as usize);
+        for _ in 0..weeks {
+            let mut week = [GridDay{ date, in_month: false, start, end: start }; 7];
+            for day in week.iter_mut() {
+                let next = Date::from_days_since_epoch(date.days_since_epoch() + 1)?;
+                let end = self.start_of_day(next)?;
+                *day = GridDay{ date, in_month: date.year() == year && date.month() == month, start, end };
+                (date, start) = (next, end);
+            }
+            grid.push(week);
+        }
+        Ok(grid)
+    }
+
+    /// The local days that overlap `range`, for bucketing times by day. The first and last days are whole days,
+    /// so they can start before `range` or end after it. Days are 23 or 25 hours long when the clocks change.
+    ///
+    /// ```
+    /// use libtz::Timezone;
+    /// let tz = Timezone::new("Europe/London").expect("timezone alloc");
+    /// let days: Vec<_> = tz.days(1679702400..1679792400).expect("days").collect(); // 2023-03-25 00:00 to 26 01:00 UTC
+    /// assert_eq!(days.iter().map(|(start, end, date)| (date.to_string(), end - start)).collect::<Vec<_>>(),
+    ///            [("2023-03-25".to_string(), 24 * 3600), ("2023-03-26".to_string(), 23 * 3600)]);
+    /// ```
+    pub fn days(&self, range: Range<TimeT>) -> Result<Periods<'_>, String> {
+        self.periods(range, Unit::Day, Ok)
+    }
+
+    /// The local weeks that overlap `range`, starting on `first_day`. Like [`Timezone::days`], the first and last
+    /// weeks are whole weeks.
+    pub fn weeks(&self, range: Range<TimeT>, first_day: Weekday) -> Result<Periods<'_>, String> {
+        self.periods(range, Unit::Week, |date| Date::from_days_since_epoch(date.days_since_epoch() - (date.weekday() as i32 - first_day as i32).rem_euclid(7) as i64))
+    }
+
+    /// The local calendar months that overlap `range`. Like [`Timezone::days`], the first and last months are
+    /// whole months.
+    pub fn months(&self, range: Range<TimeT>) -> Result<Periods<'_>, String> {
+        self.periods(range, Unit::Month, |date| Date::new(date.year(), date.month(), 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tm;
+
+    #[test]
+    fn periods() {
+        let ny = Timezone::new("America/New_York").expect("timezone alloc");
+        let local = |y, m, d| ny.mktime(&Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(y, m, d, 0, 0, 0) }).expect("mktime");
+        let date = |y, m, d| Date::new(y, m, d).expect("date");
+
+        let days: Vec<_> = ny.days(local(2023, 11, 4) + 3600..local(2023, 11, 6)).expect("days").collect();
+        assert_eq!(days, [(local(2023, 11, 4), local(2023, 11, 5), date(2023, 11, 4)),
+                          (local(2023, 11, 5), local(2023, 11, 6), date(2023, 11, 5))]);
+        assert_eq!(days[1].1 - days[1].0, 25 * 3600);
+        assert_eq!(ny.days(5..5).expect("days").count(), 0);
+
+        // 2023-11-14 was a Tuesday.
+        let weeks: Vec<_> = ny.weeks(local(2023, 11, 14)..local(2023, 11, 21), Weekday::Monday).expect("weeks").collect();
+        assert_eq!(weeks, [(local(2023, 11, 13), local(2023, 11, 20), date(2023, 11, 13)),
+                           (local(2023, 11, 20), local(2023, 11, 27), date(2023, 11, 20))]);
+        assert_eq!(ny.weeks(local(2023, 11, 14)..local(2023, 11, 15), Weekday::Sunday).expect("weeks").next().map(|w| w.2), Some(date(2023, 11, 12)));
+
+        let months: Vec<_> = ny.months(local(2023, 11, 14)..local(2024, 2, 1) + 1).expect("months").map(|m| m.2).collect();
+        assert_eq!(months, [date(2023, 11, 1), date(2023, 12, 1), date(2024, 1, 1), date(2024, 2, 1)]);
+
+        // Santiago skipped midnight when DST started, so 2023-09-03 started at 01:00.
+        let santiago = Timezone::new("America/Santiago").expect("timezone alloc");
+        let start = santiago.start_of_day(date(2023, 9, 3)).expect("start_of_day");
+        let tm = santiago.localtime(start).expect("localtime");
+        assert_eq!((tm.tm_mday, tm.tm_hour, tm.tm_min), (3, 1, 0));
+        assert_eq!(santiago.localtime(start - 1).expect("localtime").tm_mday, 2);
+    }
+
+    #[test]
+    fn weekdays() {
+        let ny = Timezone::new("America/New_York").expect("timezone alloc");
+        let local = |y, m, d| ny.mktime(&Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(y, m, d, 0, 0, 0) }).expect("mktime");
+        let tuesday = local(2023, 11, 14) + 12 * 3600;
+        assert_eq!(ny.next_weekday(tuesday, Weekday::Wednesday), Ok(local(2023, 11, 15)));
+        assert_eq!(ny.next_weekday(tuesday, Weekday::Tuesday), Ok(local(2023, 11, 21)));
+        assert_eq!(ny.next_weekday(tuesday, Weekday::Monday), Ok(local(2023, 11, 20)));
+        assert_eq!(ny.previous_weekday(tuesday, Weekday::Monday), Ok(local(2023, 11, 13)));
+        assert_eq!(ny.previous_weekday(tuesday, Weekday::Tuesday), Ok(local(2023, 11, 7)));
+        assert_eq!(ny.previous_weekday(tuesday, Weekday::Wednesday), Ok(local(2023, 11, 8)));
+        // The US DST rule: the second Sunday in March, at 2:00.
+        let dst_day = ny.nth_weekday_of_month(2024, 3, 2, Weekday::Sunday).expect("nth_weekday_of_month");
+        assert_eq!(ny.time_until_next_transition(dst_day).expect("transition").map(|d| d.as_secs()), Some(2 * 3600));
+        assert!(ny.nth_weekday_of_month(2024, 13, 1, Weekday::Sunday).is_err());
+    }
+
+    #[test]
+    fn day_length() {
+        let length = |zone, y, m, d| Timezone::new(zone).expect("timezone alloc").day_length(Date::new(y, m, d).expect("date")).map(|d| d.as_secs());
+        assert_eq!(length("America/New_York", 2023, 3, 12), Ok(23 * 3600));
+        assert_eq!(length("America/New_York", 2023, 11, 5), Ok(25 * 3600));
+        assert_eq!(length("Australia/Lord_Howe", 2023, 4, 2), Ok(24 * 3600 + 1800)); // Half hour DST
+        assert_eq!(length("Europe/Amsterdam", 1937, 7, 1), Ok(86400 - 28)); // +00:19:32 to +00:20
+        // Samoa skipped 2011-12-30 when it moved across the date line.
+        assert_eq!(length("Pacific/Apia", 2011, 12, 29), Ok(86400));
+        assert_eq!(length("Pacific/Apia", 2011, 12, 30), Ok(0));
+    }
+
+    #[test]
+    fn month_grid() {
+        let ny = Timezone::new("America/New_York").expect("timezone alloc");
+        let grid = ny.month_grid(2015, 2, Weekday::Sunday).expect("month_grid"); // 28 days starting on a Sunday
+        assert_eq!(grid.len(), 4);
+        assert!(grid.iter().flatten().all(|day| day.in_month && day.date.weekday() as i32 == (day.date.day() - 1) % 7));
+        let grid = ny.month_grid(2026, 3, Weekday::Monday).expect("month_grid");
+        assert_eq!(grid.len(), 6);
+        let days: Vec<&GridDay> = grid.iter().flatten().collect();
+        assert_eq!((days[0].date, days[6].date), (Date::new(2026, 2, 23).expect("date"), Date::new(2026, 3, 1).expect("date")));
+        assert_eq!(days.iter().filter(|day| day.in_month).count(), 31);
+        assert!(days.windows(2).all(|w| w[0].end == w[1].start && w[0].date < w[1].date));
+        assert!(grid.iter().all(|week| week[0].date.weekday() == Weekday::Monday));
+        assert_eq!(days.iter().find(|day| day.end - day.start != 86400).map(|day| day.date.to_string()), Some("2026-03-08".to_string()));
+        assert!(ny.month_grid(2026, 13, Weekday::Monday).is_err());
+
+        // Samoa's skipped day is there, but empty.
+        let apia = Timezone::new("Pacific/Apia").expect("timezone alloc");
+        let grid = apia.month_grid(2011, 12, Weekday::Sunday).expect("month_grid");
+        let skipped = grid.iter().flatten().find(|day| day.in_month && day.date.day() == 30).expect("December 30th");
+        assert_eq!(skipped.start, skipped.end);
+    }
+}
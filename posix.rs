@@ -0,0 +1,231 @@
+// Parser for POSIX TZ rule strings (like "PST8PDT,M3.2.0,M11.1.0").
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use core::fmt;
+use alloc::{format, string::String};
+use crate::calendar;
+
+/// A parsed POSIX TZ rule string. Offsets are stored as seconds *east* of UT (like `tm_gmtoff`), which is the
+/// opposite sign of how they are written in the string.
+///
+/// Reference: <https://pubs.opengroup.org/onlinepubs/9699919799/basedefs/V1_chap08.html> and the extensions in
+/// [RFC 8536 section 3.3.1](https://www.rfc-editor.org/rfc/rfc8536#section-3.3.1).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PosixTz {
+    pub std_abbr: String,
+    pub std_offset: i32,
+    pub dst: Option<PosixDst>,
+}
+
+/// The daylight saving part of a [`PosixTz`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PosixDst {
+    pub abbr: String,
+    pub offset: i32,
+    /// When DST starts and ends. `None` if the string didn't specify, in which case libtz uses the US rules.
+    pub rule: Option<(PosixRuleDate, PosixRuleDate)>,
+}
+
+/// When a POSIX TZ rule starts or ends daylight saving time: a day and the local time of day it happens at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PosixRuleDate {
+    /** Day of the year */                                                  pub day  : PosixRuleDay,
+    /** Local time of day in seconds (possibly negative or past 24 hours) */ pub time : i32,
+}
+
+/// The day of the year part of a [`PosixRuleDate`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PosixRuleDay {
+    /// `Jn`: Day of the year [1, 365], ignoring Feb 29.
+    Julian1(u16),
+    /// `n`: Day of the year [0, 365], counting Feb 29.
+    Julian0(u16),
+    /// `Mm.w.d`: Weekday `d` (Sunday = 0) of week `w` (5 = last) of month `m` [1, 12].
+    MonthWeekDay { month: u8, week: u8, weekday: u8 },
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> { self.s.get(self.pos).copied() }
+    fn eat(&mut self, c: u8) -> bool { if self.peek() == Some(c) { self.pos += 1; true } else { false } }
+    fn err<T>(&self, what: &str) -> Result<T, String> { Err(format!("{} at position {}", what, self.pos)) }
+
+    fn abbr(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        let (abbr, quoted) = if self.eat(b'<') {
+            while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == b'+' || c == b'-') { self.pos += 1 }
+            let abbr = &self.s[start+1..self.pos];
+            if !self.eat(b'>') { return self.err("unterminated <abbreviation>") }
+            (abbr, true)
+        } else {
+            while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) { self.pos += 1 }
+            (&self.s[start..self.pos], false)
+        };
+        if abbr.len() < 3 {
+            return self.err(if quoted { "abbreviation too short" } else { "expected abbreviation" });
+        }
+        Ok(String::from_utf8(abbr.to_vec()).unwrap())
+    }
+
+    fn num(&mut self, max: i32) -> Result<i32, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) { self.pos += 1 }
+        match core::str::from_utf8(&self.s[start..self.pos]).unwrap().parse::<i32>() {
+            Ok(n) if n <= max => Ok(n),
+            Ok(_) => { self.pos = start; self.err("number out of range") },
+            Err(_) => self.err("expected number"),
+        }
+    }
+
+    // [+-]hh[:mm[:ss]], returned in seconds.
+    fn secs(&mut self) -> Result<i32, String> {
+        let sign = if self.eat(b'-') { -1 } else { self.eat(b'+'); 1 };
+        let mut secs = self.num(167)? * 3600;
+        if self.eat(b':') {
+            secs += self.num(59)? * 60;
+            if self.eat(b':') {
+                secs += self.num(59)?;
+            }
+        }
+        Ok(sign * secs)
+    }
+
+    fn rule_date(&mut self) -> Result<PosixRuleDate, String> {
+        let day = if self.eat(b'J') {
+            match self.num(365)? { 0 => return self.err("Julian day must be at least 1"),
+                                   n => PosixRuleDay::Julian1(n as u16) }
+        } else if self.eat(b'M') {
+            let month = self.num(12)?;
+            if month < 1 { return self.err("month must be at least 1") }
+            if !self.eat(b'.') { return self.err("expected '.'") }
+            let week = self.num(5)?;
+            if week < 1 { return self.err("week must be at least 1") }
+            if !self.eat(b'.') { return self.err("expected '.'") }
+            let weekday = self.num(6)?;
+            PosixRuleDay::MonthWeekDay{ month: month as u8, week: week as u8, weekday: weekday as u8 }
+        } else {
+            PosixRuleDay::Julian0(self.num(365)? as u16)
+        };
+        let time = if self.eat(b'/') { self.secs()? } else { 2 * 3600 };
+        Ok(PosixRuleDate{ day, time })
+    }
+}
+
+impl core::str::FromStr for PosixTz {
+    type Err = String;
+    fn from_str(s: &str) -> Result<PosixTz, String> {
+        let mut p = Parser{ s: s.as_bytes(), pos: 0 };
+        let std_abbr = p.abbr()?;
+        let std_offset = -p.secs()?;
+        let dst = if p.peek().is_some() {
+            let abbr = p.abbr()?;
+            let offset = if p.peek().is_some_and(|c| c != b',') { -p.secs()? } else { std_offset + 3600 };
+            let rule = if p.eat(b',') {
+                let start = p.rule_date()?;
+                if !p.eat(b',') { return p.err("expected ','") }
+                Some((start, p.rule_date()?))
+            } else {
+                None
+            };
+            Some(PosixDst{ abbr, offset, rule })
+        } else {
+            None
+        };
+        if p.peek().is_some() {
+            return p.err("unexpected trailing characters");
+        }
+        Ok(PosixTz{ std_abbr, std_offset, dst })
+    }
+}
+
+// Days since 1970-01-01 for a POSIX rule day.
+pub(crate) fn rule_day(year: i64, day: &PosixRuleDay) -> i64 {
+    let jan1 = calendar::days_from_civil(year, 0, 1);
+    match *day {
+        PosixRuleDay::Julian1(n) => jan1 + n as i64 - 1 + (calendar::is_leap_year(year) && n >= 60) as i64,
+        PosixRuleDay::Julian0(n) => jan1 + n as i64,
+        PosixRuleDay::MonthWeekDay{ month, week, weekday } => {
+            let month = month as i32 - 1;
+            let first = calendar::days_from_civil(year, month, 1);
+            let mut day = first + (weekday as i32 - calendar::weekday_from_days(first)).rem_euclid(7) as i64 + 7 * (week as i64 - 1);
+            while day >= first + calendar::month_days(year, month) as i64 {
+                day -= 7;
+            }
+            day
+        },
+    }
+}
+
+fn fmt_abbr(f: &mut fmt::Formatter<'_>, abbr: &str) -> fmt::Result {
+    if abbr.bytes().all(|c| c.is_ascii_alphabetic()) { write!(f, "{}", abbr) } else { write!(f, "<{}>", abbr) }
+}
+
+fn fmt_secs(f: &mut fmt::Formatter<'_>, secs: i32) -> fmt::Result {
+    let (sign, secs) = if secs < 0 { ("-", -secs) } else { ("", secs) };
+    write!(f, "{}{}", sign, secs / 3600)?;
+    match (secs / 60 % 60, secs % 60) {
+        (0, 0) => Ok(()),
+        (m, 0) => write!(f, ":{:02}", m),
+        (m, s) => write!(f, ":{:02}:{:02}", m, s),
+    }
+}
+
+impl fmt::Display for PosixRuleDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.day {
+            PosixRuleDay::Julian1(n) => write!(f, "J{}", n)?,
+            PosixRuleDay::Julian0(n) => write!(f, "{}", n)?,
+            PosixRuleDay::MonthWeekDay{ month, week, weekday } => write!(f, "M{}.{}.{}", month, week, weekday)?,
+        }
+        if self.time != 2 * 3600 {
+            write!(f, "/")?;
+            fmt_secs(f, self.time)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PosixTz {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_abbr(f, &self.std_abbr)?;
+        fmt_secs(f, -self.std_offset)?;
+        if let Some(ref dst) = self.dst {
+            fmt_abbr(f, &dst.abbr)?;
+            if dst.offset != self.std_offset + 3600 {
+                fmt_secs(f, -dst.offset)?;
+            }
+            if let Some((start, end)) = dst.rule {
+                write!(f, ",{},{}", start, end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse() {
+        for s in ["PST8PDT,M3.2.0,M11.1.0", "<+0330>-3:30", "IST-2IDT,M3.4.4/26,M10.5.0", "<-03>3<-02>,M3.5.0/-2,M10.5.0/-1",
+                  "EST5EDT", "AAA3BBB1,J60/1:30:15,300", "UTC0"] {
+            assert_eq!(s.parse::<PosixTz>().expect(s).to_string(), s, "round trip");
+        }
+        let tz: PosixTz = "NZST-12NZDT,M9.5.0,M4.1.0/3".parse().expect("parse");
+        assert_eq!(tz, PosixTz{ std_abbr: "NZST".to_string(),
+                                std_offset: 12 * 3600,
+                                dst: Some(PosixDst{ abbr: "NZDT".to_string(),
+                                                    offset: 13 * 3600,
+                                                    rule: Some((PosixRuleDate{ day: PosixRuleDay::MonthWeekDay{ month: 9, week: 5, weekday: 0 }, time: 7200 },
+                                                                PosixRuleDate{ day: PosixRuleDay::MonthWeekDay{ month: 4, week: 1, weekday: 0 }, time: 10800 })) }) });
+        for bad in ["", "PST", "PS8", "PST8PDT,M3.2.0", "PST8PDT,M13.2.0,M11.1.0", "<+1>-1", "UTC0 "] {
+            assert!(bad.parse::<PosixTz>().is_err(), "{:?} should fail", bad);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// Postgres TIMESTAMPTZ support for sqlx.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use ::sqlx::{Decode, Encode, Postgres, Type, TypeInfo};
+use ::sqlx::encode::IsNull;
+use ::sqlx::error::BoxDynError;
+use ::sqlx::postgres::{PgArgumentBuffer, PgTypeInfo, PgValueFormat, PgValueRef};
+use crate::{TimeSpec, Timestamp, calendar};
+
+// Postgres counts microseconds from 2000-01-01 00:00:00 UTC.
+const PG_EPOCH: i64 = 946684800;
+
+// The Unix microseconds of a TIMESTAMPTZ (binary or text).
+fn decode_micros(value: PgValueRef<'_>) -> Result<i64, BoxDynError> {
+    let micros = match value.format() {
+        PgValueFormat::Binary => <i64 as Decode<Postgres>>::decode(value)?,
+        PgValueFormat::Text   => return parse_text(value.as_str()?),
+    };
+    if micros == i64::MAX || micros == i64::MIN {
+        return Err("infinite timestamps aren't supported".into());
+    }
+    micros.checked_add(PG_EPOCH * 1_000_000).ok_or_else(|| "timestamp out of range".into())
+}
+
+fn encode_micros(unix_micros: Option<i64>, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+    let micros = unix_micros.and_then(|micros| micros.checked_sub(PG_EPOCH * 1_000_000)).ok_or("timestamp out of range for Postgres")?;
+    <i64 as Encode<Postgres>>::encode(micros, buf)
+}
+
+// Postgres's text output for TIMESTAMPTZ: `2023-11-14 22:13:20.5+00` (or with an offset like `+05:30` when the
+// session's TimeZone isn't UTC, and ` BC` at the end for years before 1).
+fn parse_text(s: &str) -> Result<i64, BoxDynError> {
+    let bad = || -> BoxDynError { format!("bad TIMESTAMPTZ {:?}", s).into() };
+    let (s, bc) = match s.strip_suffix(" BC") { Some(s) => (s, true), None => (s, false) };
+    let offset_at = s.rfind(['+', '-']).filter(|i| *i > 10).ok_or_else(bad)?;
+    let (datetime, offset) = s.split_at(offset_at);
+    let (date, time) = datetime.split_once(' ').ok_or_else(bad)?;
+    let num = |s: &str| s.parse::<i64>().map_err(|_| bad());
+
+    let mut date = date.splitn(3, '-');
+    let (year, month, day) = (num(date.next().ok_or_else(bad)?)?, num(date.next().ok_or_else(bad)?)?, num(date.next().ok_or_else(bad)?)?);
+    let year = if bc { 1 - year } else { year };
+    if !(1..=12).contains(&month) || day < 1 || day > calendar::month_days(year, month as i32 - 1) as i64 {
+        return Err(bad());
+    }
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let hms: Vec<i64> = time.split(':').map(num).collect::<Result<_, _>>()?;
+    let [h, m, sec] = hms[..] else { return Err(bad()) };
+    let micros = match fraction.len() {
+        0     => 0,
+        1..=6 => num(fraction)? * 10i64.pow(6 - fraction.len() as u32),
+        _     => return Err(bad()),
+    };
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let offset: Vec<i64> = offset[1..].split(':').map(num).collect::<Result<_, _>>()?;
+    let offset = sign * match offset[..] { [h] => h * 3600, [h, m] => h * 3600 + m * 60, [h, m, s] => h * 3600 + m * 60 + s, _ => return Err(bad()) };
+
+    let secs = calendar::days_from_civil(year, month as i32 - 1, day) * 86400 + h * 3600 + m * 60 + sec - offset;
+    Ok(secs * 1_000_000 + micros)
+}
+
+/// Stored as a Postgres `TIMESTAMPTZ`. Sub-second parts of stored values are dropped when they're read back.
+impl Type<Postgres> for Timestamp {
+    fn type_info() -> PgTypeInfo {
+        PgTypeInfo::with_name("timestamptz")
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        ty.name().eq_ignore_ascii_case("timestamptz") || ty.name().eq_ignore_ascii_case("timestamp")
+    }
+}
+
+impl Encode<'_, Postgres> for Timestamp {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        encode_micros(self.0.checked_mul(1_000_000), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Timestamp {
+    fn decode(value: PgValueRef<'r>) -> Result<Timestamp, BoxDynError> {
+        Ok(Timestamp(decode_micros(value)?.div_euclid(1_000_000)))
+    }
+}
+
+/// Stored as a Postgres `TIMESTAMPTZ`, which has microsecond precision (so nanoseconds are dropped).
+impl Type<Postgres> for TimeSpec {
+    fn type_info() -> PgTypeInfo {
+        <Timestamp as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <Timestamp as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, Postgres> for TimeSpec {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        encode_micros(self.to_unix_micros(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for TimeSpec {
+    fn decode(value: PgValueRef<'r>) -> Result<TimeSpec, BoxDynError> {
+        Ok(TimeSpec::from_unix_micros(decode_micros(value)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text() {
+        assert_eq!(parse_text("2000-01-01 00:00:00+00").expect("parse"), PG_EPOCH * 1_000_000);
+        assert_eq!(parse_text("2023-11-14 22:13:20.5+00").expect("parse"), 1700000000 * 1_000_000 + 500000);
+        assert_eq!(parse_text("2023-11-15 03:43:20.000001+05:30").expect("parse"), 1700000000 * 1_000_000 + 1);
+        assert_eq!(parse_text("2023-11-14 14:13:20-08").expect("parse"), 1700000000 * 1_000_000);
+        assert_eq!(parse_text("1969-12-31 23:59:59.999999+00").expect("parse"), -1);
+        assert_eq!(parse_text("0001-12-31 23:59:59+00 BC").expect("parse"), -62135596801 * 1_000_000);
+        assert_eq!(parse_text("1883-11-18 12:07:02-07:52:58").expect("parse"), -2717640000 * 1_000_000);
+        for bad in ["", "2023-11-14", "2023-02-29 00:00:00+00", "2023-11-14 22:13:20", "2023-11-14 22:13+00", "2023-11-14 22:13:20.1234567+00", "infinity"] {
+            assert!(parse_text(bad).is_err(), "{:?}", bad);
+        }
+        assert_eq!(<Timestamp as Type<Postgres>>::type_info().name(), "timestamptz");
+    }
+}
@@ -0,0 +1,257 @@
+// Conversions served entirely from precomputed tables.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::ops::Range;
+use std::sync::Arc;
+use crate::TimeT;
+use crate::{Tm, Timezone, Resolve, MktimeError, calendar};
+
+/// A snapshot of a [`Timezone`]'s rules over a fixed range of time, for code that can't afford to call into
+/// libtz. Create one with [`Timezone::precompute`].
+///
+/// All the work (including every call into libtz and every allocation) happens when it's created. After that
+/// conversions are plain arithmetic plus a binary search over the transitions in the range: no system calls,
+/// no locks and no allocation. The worst case is `O(log n)` comparisons where `n` is the number of
+/// transitions in the range (typically 2 per year, so about 8 comparisons for a century), plus a fixed amount
+/// of calendar arithmetic. It doesn't reference the `Timezone` it came from and is `Send` and `Sync`.
+///
+/// Times outside the range are errors (and the error path does allocate).
+#[derive(Clone, Debug)]
+pub struct PrecomputedZone {
+    range: Range<TimeT>,
+    segments: Vec<Segment>,
+    abbrs: Vec<Arc<str>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+    start: TimeT,
+    gmtoff: i64,
+    isdst: bool,
+    abbr: usize,
+}
+
+impl Timezone {
+    /// Do the warm-up for a [`PrecomputedZone`] covering `range`.
+    pub fn precompute(&self, range: Range<TimeT>) -> Result<PrecomputedZone, String> {
+        if range.is_empty() {
+            return Err("Empty range".to_string());
+        }
+        let mut zone = PrecomputedZone{ range: range.clone(), segments: vec![], abbrs: vec![] };
+        let first = self.localtime(range.start)?;
+        zone.push(range.start, &first);
+        for (at, _, after) in self.transitions_between(range.start, range.end)? {
+            zone.push(at, &after);
+        }
+        Ok(zone)
+    }
+}
+
+impl PrecomputedZone {
+    fn push(&mut self, start: TimeT, tm: &Tm) {
+        let abbr = match self.abbrs.iter().position(|a| *a == tm.tm_zone) {
+            Some(abbr) => abbr,
+            None       => { self.abbrs.push(tm.tm_zone.clone()); self.abbrs.len() - 1 },
+        };
+        self.segments.push(Segment{ start, gmtoff: tm.tm_gmtoff, isdst: tm.tm_isdst > 0, abbr });
+    }
+
+    /// The range of times this covers.
+    pub fn range(&self) -> Range<TimeT> {
+        self.range.clone()
+    }
+
+    fn segment(&self, time: TimeT) -> Result<&Segment, String> {
+        if !self.range.contains(&time) {
+            return Err(format!("{} is outside the precomputed range {:?}", time, self.range));
+        }
+        Ok(&self.segments[self.segments.partition_point(|s| s.start <= time) - 1])
+    }
+
+    /// The UTC offset (in seconds east), DST flag and abbreviation in effect at `time`.
+    pub fn offset_at(&self, time: TimeT) -> Result<(i64, bool, &str), String> {
+        let segment = self.segment(time)?;
+        Ok((segment.gmtoff, segment.isdst, &self.abbrs[segment.abbr]))
+    }
+
+    /// Convert system time to local time, overwriting `tm`.
+    pub fn localtime_into(&self, time: TimeT, tm: &mut Tm) -> Result<(), String> {
+        let segment = self.segment(time)?;
+        let wall = time + segment.gmtoff;
+        let days = wall.div_euclid(86400);
+        let secs = wall.rem_euclid(86400) as i32;
+        let (year, mon, mday) = calendar::civil_from_days(days);
+        tm.tm_sec    = secs % 60;
+        tm.tm_min    = secs / 60 % 60;
+        tm.tm_hour   = secs / 3600;
+        tm.tm_mday   = mday;
+        tm.tm_mon    = mon;
+        tm.tm_year   = (year - 1900) as i32;
+        tm.tm_wday   = calendar::weekday_from_days(days);
+        tm.tm_yday   = (days - calendar::days_from_civil(year, 0, 1)) as i32;
+        tm.tm_isdst  = segment.isdst as i32;
+        tm.tm_gmtoff = segment.gmtoff;
+        tm.tm_zone   = self.abbrs[segment.abbr].clone();
+        Ok(())
+    }
+
+    /// Convert system time to local time.
+    pub fn localtime(&self, time: TimeT) -> Result<Tm, String> {
+        let mut tm = Tm{ tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 0, tm_mon: 0, tm_year: 0, tm_wday: 0, tm_yday: 0,
+                         tm_isdst: 0, tm_gmtoff: 0, tm_zone: "".into() };
+        self.localtime_into(time, &mut tm)?;
+        Ok(tm)
+    }
+
+    /// Convert local time to system time, like [`Timezone::mktime_resolve`] (`tm_isdst`, `tm_wday` and
+    /// `tm_yday` are ignored and `resolve` decides what happens to repeated and skipped times).
+    pub fn mktime(&self, tm: &Tm, resolve: Resolve) -> Result<TimeT, MktimeError> {
+        let wall = tm.wall_seconds();
+        // tzcode's offsets are less than a week, so only segments near the wall time can match.
+        let first = self.segments.partition_point(|s| s.start <= wall.saturating_sub(8 * 86400)).saturating_sub(1);
+        let last = self.segments.partition_point(|s| s.start <= wall.saturating_add(8 * 86400));
+        let (mut times, mut count, mut gap) = ([0; 2], 0, None);
+        for i in first..last {
+            let (segment, next) = (&self.segments[i], self.segments.get(i + 1));
+            let end = next.map_or(self.range.end, |next| next.start);
+            let time = wall - segment.gmtoff;
+            if time >= segment.start && time < end && count < 2 && !times[..count].contains(&time) {
+                times[count] = time;
+                count += 1;
+            }
+            if let Some(next) = next {
+                if time >= next.start && wall - next.gmtoff < next.start {
+                    gap = Some((segment.gmtoff, next.gmtoff));
+                }
+            }
+        }
+        if count == 0 && gap.is_none() {
+            return Err(MktimeError::InvalidDate); // Outside the range
+        }
+        crate::timezone::resolve_candidates(wall, &times[..count], gap, resolve)
+    }
+}
+
+/// A stretch of time with one UTC offset, from an [`OffsetTable`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OffsetSegment {
+    /** The first time in the segment */           pub start  : TimeT,
+    /** The first time after it */                  pub end    : TimeT,
+    /** Seconds east of UTC */                      pub gmtoff : i64,
+    /** Daylight saving time flag */                pub isdst  : bool,
+    /** Abbreviation */                             pub abbr   : Arc<str>,
+}
+
+/// A [`Timezone`]'s offsets over a fixed range of time as a sorted list of segments, from
+/// [`Timezone::offset_table`]. Looking up a time is a binary search over the segments (typically 2 a year) with
+/// no calls into libtz, for code that converts a lot of timestamps and only needs their offsets. For whole
+/// [`Tm`]s and `mktime()` see [`PrecomputedZone`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OffsetTable {
+    segments: Vec<OffsetSegment>,
+}
+
+impl Timezone {
+    /// Precompute the offsets in effect over `range` (see [`OffsetTable`]).
+    ///
+    /// ```
+    /// let tz = libtz::Timezone::new("Europe/Paris").unwrap();
+    /// let table = tz.offset_table(1704067200..1735689600).unwrap(); // 2024
+    /// assert_eq!(table.segments().len(), 3);
+    /// let summer = table.lookup(1720000000).unwrap();
+    /// assert_eq!((summer.gmtoff, &*summer.abbr), (7200, "CEST"));
+    /// assert!(table.lookup(1800000000).is_none());
+    /// ```
+    pub fn offset_table(&self, range: Range<TimeT>) -> Result<OffsetTable, String> {
+        let zone = self.precompute(range)?;
+        let ends = zone.segments.iter().skip(1).map(|s| s.start).chain([zone.range.end]);
+        let segments = zone.segments.iter().zip(ends).map(|(s, end)| OffsetSegment{ start: s.start, end, gmtoff: s.gmtoff, isdst: s.isdst,
+                                                                                     abbr: zone.abbrs[s.abbr].clone() }).collect();
+        Ok(OffsetTable{ segments })
+    }
+}
+
+impl OffsetTable {
+    /// The segments, in time order. Each one ends where the next starts.
+    pub fn segments(&self) -> &[OffsetSegment] {
+        &self.segments
+    }
+
+    /// The range of times the table covers.
+    pub fn range(&self) -> Range<TimeT> {
+        self.segments[0].start..self.segments[self.segments.len() - 1].end
+    }
+
+    /// The segment `time` is in, or `None` if it's outside the table's range.
+    pub fn lookup(&self, time: TimeT) -> Option<&OffsetSegment> {
+        let segment = &self.segments[self.segments.partition_point(|s| s.start <= time).checked_sub(1)?];
+        (time < segment.end).then_some(segment)
+    }
+}
+
+impl crate::TimeZoneProvider for PrecomputedZone {
+    fn localtime(&self, time: TimeT) -> Result<Tm, String> {
+        PrecomputedZone::localtime(self, time)
+    }
+
+    fn mktime(&self, tm: &Tm, resolve: Resolve) -> Result<TimeT, MktimeError> {
+        PrecomputedZone::mktime(self, tm, resolve)
+    }
+
+    // Transitions past the end of the range aren't known, so they're reported as `None`.
+    fn next_transition(&self, time: TimeT) -> Result<Option<TimeT>, String> {
+        self.segment(time)?;
+        Ok(self.segments.get(self.segments.partition_point(|s| s.start <= time)).map(|s| s.start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn matches_libtz() {
+        let tz = Timezone::new("America/Los_Angeles").expect("timezone alloc");
+        let zone = tz.precompute(1577836800..1893456000).expect("precompute"); // 2020..2030
+        let mut tm = tz.localtime(0).expect("localtime");
+        for time in (1577836800..1893456000).step_by(3599 * 7) {
+            zone.localtime_into(time, &mut tm).expect("localtime_into");
+            assert_eq!(tm, tz.localtime(time).expect("localtime"), "time {}", time);
+            for resolve in [Resolve::Earlier, Resolve::Later, Resolve::Error] {
+                assert_eq!(zone.mktime(&tm, resolve), tz.mktime_resolve(&tm, resolve), "time {}", time);
+            }
+        }
+        assert_eq!(zone.offset_at(1593561600), Ok((-25200, true, "PDT")));
+        assert!(zone.localtime(0).is_err());
+        assert!(tz.precompute(10..10).is_err());
+    }
+
+    #[test]
+    fn offset_table() {
+        let tz = Timezone::new("America/Los_Angeles").expect("timezone alloc");
+        let table = tz.offset_table(1577836800..1893456000).expect("offset_table"); // 2020..2030
+        assert_eq!(table.range(), 1577836800..1893456000);
+        assert_eq!(table.segments().len(), 21);
+        assert!(table.segments().windows(2).all(|w| w[0].end == w[1].start && w[0].isdst != w[1].isdst));
+        for time in (1577836800..1893456000).step_by(3599 * 7) {
+            let segment = table.lookup(time).expect("lookup");
+            let tm = tz.localtime(time).expect("localtime");
+            assert_eq!((segment.gmtoff, segment.isdst, &segment.abbr), (tm.tm_gmtoff, tm.tm_isdst > 0, &tm.tm_zone), "time {}", time);
+        }
+        assert_eq!(table.lookup(1893456000 - 1).map(|s| &*s.abbr), Some("PST"));
+        assert_eq!((table.lookup(1577836800 - 1), table.lookup(1893456000)), (None, None));
+        assert!(tz.offset_table(10..10).is_err());
+    }
+
+    #[test]
+    fn gaps() {
+        let tz = Timezone::new("America/Los_Angeles").expect("timezone alloc");
+        let zone = tz.precompute(1704067200..1735689600).expect("precompute"); // 2024
+        let mut tm = tz.localtime(1710061200).expect("localtime"); // 2024-03-10 01:00 PST
+        tm.tm_min = 90;
+        for resolve in [Resolve::Earlier, Resolve::Later, Resolve::Error] {
+            assert_eq!(zone.mktime(&tm, resolve), tz.mktime_resolve(&tm, resolve));
+        }
+    }
+}
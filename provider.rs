@@ -0,0 +1,132 @@
+// A common interface to the different kinds of zone.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use crate::{Tm, TimeT, Timezone, Resolve, MktimeError, OffsetInfo, UtcOffset};
+
+/// The conversions every kind of zone can do, so code that only needs to convert times can be written once
+/// and handed a [`Timezone`] (whichever backend it's using), a [`PrecomputedZone`](crate::PrecomputedZone), a
+/// [`FixedOffset`], or a zone made by `test_util::MockTimezone` in tests.
+///
+/// ```
+/// use libtz::{TimeZoneProvider, Timezone, FixedOffset};
+/// fn hour(zone: &impl TimeZoneProvider, time: libtz::TimeT) -> i32 {
+///     zone.localtime(time).unwrap().tm_hour
+/// }
+/// assert_eq!(hour(&Timezone::new("America/New_York").unwrap(), 1700000000), 17);
+/// assert_eq!(hour(&FixedOffset::new(3600).unwrap(), 1700000000), 23);
+/// ```
+pub trait TimeZoneProvider {
+    /// Convert system time to local time.
+    fn localtime(&self, time: TimeT) -> Result<Tm, String>;
+
+    /// Convert local time to system time. `tm_isdst`, `tm_wday` and `tm_yday` are ignored and `resolve`
+    /// decides what happens to repeated and skipped times, like [`Timezone::mktime_resolve`].
+    fn mktime(&self, tm: &Tm, resolve: Resolve) -> Result<TimeT, MktimeError>;
+
+    /// The offset, DST flag and abbreviation in effect at `time`.
+    fn offset_at(&self, time: TimeT) -> Result<OffsetInfo, String> {
+        let tm = self.localtime(time)?;
+        Ok(OffsetInfo{ time, gmtoff: tm.tm_gmtoff, isdst: tm.tm_isdst > 0, abbr: tm.tm_zone.to_string() })
+    }
+
+    /// The first time after `time` that the offset, DST flag or abbreviation changes, or `None` if it never
+    /// does (or the zone can't tell).
+    fn next_transition(&self, time: TimeT) -> Result<Option<TimeT>, String>;
+}
+
+impl TimeZoneProvider for Timezone {
+    fn localtime(&self, time: TimeT) -> Result<Tm, String> {
+        Timezone::localtime(self, time)
+    }
+
+    fn mktime(&self, tm: &Tm, resolve: Resolve) -> Result<TimeT, MktimeError> {
+        self.mktime_resolve(tm, resolve)
+    }
+
+    fn next_transition(&self, time: TimeT) -> Result<Option<TimeT>, String> {
+        Ok(self.time_until_next_transition(time)?.map(|d| time + d.as_secs() as TimeT))
+    }
+}
+
+/// A zone that's always the same number of seconds east of UTC, with a numeric abbreviation (see
+/// [`offtime`](crate::offtime)).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FixedOffset {
+    gmtoff: i64,
+}
+
+impl FixedOffset {
+    /// UTC.
+    pub const UTC: FixedOffset = FixedOffset{ gmtoff: 0 };
+
+    /// A zone `gmtoff` seconds east of UTC. Offsets of a day or more are errors.
+    pub fn new(gmtoff: i64) -> Result<FixedOffset, String> {
+        if gmtoff.unsigned_abs() >= 86400 {
+            return Err(format!("Offset {} is out of range", gmtoff));
+        }
+        Ok(FixedOffset{ gmtoff })
+    }
+
+    /// Seconds east of UTC.
+    pub fn gmtoff(&self) -> i64 {
+        self.gmtoff
+    }
+}
+
+impl TryFrom<UtcOffset> for FixedOffset {
+    type Error = String;
+    fn try_from(offset: UtcOffset) -> Result<FixedOffset, String> {
+        FixedOffset::new(offset.seconds() as i64)
+    }
+}
+
+impl TimeZoneProvider for FixedOffset {
+    fn localtime(&self, time: TimeT) -> Result<Tm, String> {
+        crate::offtime(time, self.gmtoff)
+    }
+
+    fn mktime(&self, tm: &Tm, _resolve: Resolve) -> Result<TimeT, MktimeError> {
+        crate::timeoff(tm, self.gmtoff).map_err(|_| MktimeError::InvalidDate)
+    }
+
+    fn next_transition(&self, _time: TimeT) -> Result<Option<TimeT>, String> {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generic over the trait on purpose, so every implementation goes through the same code.
+    fn round_trip(zone: &impl TimeZoneProvider, time: TimeT) -> (Tm, TimeT) {
+        let tm = zone.localtime(time).expect("localtime");
+        let back = zone.mktime(&tm, Resolve::Earlier).expect("mktime");
+        (tm, back)
+    }
+
+    #[test]
+    fn providers() {
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        let fall_back = 1699164000; // 2023-11-05 01:00 EST, the second 01:00
+        assert_eq!(round_trip(&tz, fall_back).1, fall_back - 3600);
+        assert_eq!(TimeZoneProvider::next_transition(&tz, 1700000000), Ok(Some(1710054000)));
+        assert_eq!(tz.offset_at(1700000000).map(|o| (o.gmtoff, o.isdst, o.abbr)), Ok((-18000, false, "EST".to_string())));
+
+        let zone = tz.precompute(1672531200..1735689600).expect("precompute"); // 2023..2025
+        assert_eq!(round_trip(&zone, fall_back).1, fall_back - 3600);
+        assert_eq!(TimeZoneProvider::next_transition(&zone, 1700000000), Ok(Some(1710054000)));
+        assert_eq!(TimeZoneProvider::next_transition(&zone, 1730613600), Ok(None)); // Past the last one in range
+
+        let fixed = FixedOffset::new(-5 * 3600).expect("FixedOffset");
+        let (tm, back) = round_trip(&fixed, fall_back);
+        assert_eq!((tm.tm_hour, &*tm.tm_zone, back), (1, "-05", fall_back));
+        assert_eq!(fixed.next_transition(fall_back), Ok(None));
+        assert_eq!(FixedOffset::UTC.offset_at(0).map(|o| o.abbr), Ok("UTC".to_string()));
+        assert!(FixedOffset::new(86400).is_err() && FixedOffset::new(i64::MIN).is_err());
+        assert_eq!(FixedOffset::try_from("+05:30".parse::<UtcOffset>().expect("parse")).map(|f| f.gmtoff()), Ok(19800));
+        assert!(FixedOffset::try_from("+25:00".parse::<UtcOffset>().expect("parse")).is_err());
+    }
+}
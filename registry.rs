@@ -0,0 +1,369 @@
+// A bounded cache of loaded zones.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::{Timezone, TimezoneBuilder, TimeT, Clock, SystemClock, ZoneResolver};
+
+/// Why a zone was dropped from a [`ZoneRegistry`], for [`ZoneRegistry::on_evict`] callbacks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Eviction {
+    /// The registry was full and this was the least recently used zone.
+    Capacity,
+    /// It was loaded longer ago than the registry's time to live.
+    Expired,
+    /// [`ZoneRegistry::remove`] or [`ZoneRegistry::clear`] was called, or [`ZoneRegistry::preload`] dropped
+    /// a zone it wasn't asked to keep.
+    Removed,
+}
+
+type EvictFn = Box<dyn Fn(&str, &Arc<Timezone>, Eviction) + Send + Sync>;
+
+struct Entry {
+    tz: Arc<Timezone>,
+    loaded: TimeT,
+    last_used: u64,
+    pinned: bool, // From preload(): never evicted and never expires
+}
+
+#[derive(Default)]
+struct Entries {
+    zones: HashMap<String, Entry>,
+    uses: u64, // Counts up on every get(), to order entries by how recently they were used
+    sealed: bool, // After preload(), only pinned zones are handed out and nothing more is loaded
+}
+
+/// A cache of loaded zones that holds at most a fixed number of them, for servers that need whatever zones
+/// their users happen to be in without letting the set grow forever.
+///
+/// When the registry is full, loading another zone drops the least recently used one. Zones can also be given
+/// a time to live, after which they're loaded again (picking up tzdata updates). Zones already handed out
+/// stay valid after they've been dropped from the registry; they're [`Arc`]s.
+///
+/// ```
+/// use libtz::ZoneRegistry;
+/// let registry = ZoneRegistry::new(100).ttl(std::time::Duration::from_secs(3600));
+/// let tz = registry.get("America/New_York").expect("get");
+/// assert!(std::sync::Arc::ptr_eq(&tz, &registry.get("America/New_York").expect("get")));
+/// ```
+pub struct ZoneRegistry {
+    entries: Mutex<Entries>,
+    capacity: usize,
+    ttl: Option<Duration>,
+    loader: TimezoneBuilder,
+    clock: Box<dyn Clock + Send + Sync>,
+    on_evict: Option<EvictFn>,
+}
+
+impl ZoneRegistry {
+    /// Make an empty registry that holds up to `capacity` zones (at least 1).
+    pub fn new(capacity: usize) -> ZoneRegistry {
+        ZoneRegistry{ entries: Mutex::new(Entries::default()), capacity: capacity.max(1), ttl: None, loader: TimezoneBuilder::default(),
+                      clock: Box::new(SystemClock), on_evict: None }
+    }
+
+    /// Reload zones that were loaded more than `ttl` ago. By default zones are kept until they're evicted.
+    pub fn ttl(mut self, ttl: Duration) -> ZoneRegistry {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Load zones with `loader` instead of [`Timezone::new`].
+    pub fn loader(mut self, loader: TimezoneBuilder) -> ZoneRegistry {
+        self.loader = loader;
+        self
+    }
+
+    /// Fetch zones' data with `resolver` (see [`TimezoneBuilder::resolver`]), keeping the loader's other options.
+    pub fn resolver(mut self, resolver: impl ZoneResolver + 'static) -> ZoneRegistry {
+        self.loader = std::mem::take(&mut self.loader).resolver(resolver);
+        self
+    }
+
+    /// Tell the time (for the time to live) with `clock` instead of the system clock.
+    pub fn clock(mut self, clock: impl Clock + Send + Sync + 'static) -> ZoneRegistry {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Call `f` with the name and zone of every zone dropped from the registry. It's called after the
+    /// registry's lock is released, so it can use the registry.
+    pub fn on_evict(mut self, f: impl Fn(&str, &Arc<Timezone>, Eviction) + Send + Sync + 'static) -> ZoneRegistry {
+        self.on_evict = Some(Box::new(f));
+        self
+    }
+
+    /// The zone `name`, loading it if it isn't in the registry (or has expired). Errors loading it aren't
+    /// cached.
+    ///
+    /// The registry's lock isn't held while a zone loads, so a slow load doesn't hold up lookups of other zones.
+    /// If several threads ask for the same new zone at once they may each load it; the first one loaded is kept
+    /// and the others get that one.
+    pub fn get(&self, name: &str) -> Result<Arc<Timezone>, String> {
+        let now = self.clock.now();
+        if let Some(found) = self.cached(name, now) {
+            return found;
+        }
+        let tz = Arc::new(self.loader.build(name)?);
+        self.add(name, tz, now)
+    }
+
+    // The zone `name` if there's no need to load it.
+    fn cached(&self, name: &str, now: TimeT) -> Option<Result<Arc<Timezone>, String>> {
+        let mut evicted = vec![];
+        let found = self.lookup(&mut self.entries.lock().unwrap(), name, now, &mut evicted);
+        self.notify(evicted);
+        found
+    }
+
+    // Add a zone that was loaded without the lock held, unless another caller got there first (then theirs is used).
+    fn add(&self, name: &str, tz: Arc<Timezone>, now: TimeT) -> Result<Arc<Timezone>, String> {
+        let mut evicted = vec![];
+        let result = {
+            let mut entries = self.entries.lock().unwrap();
+            match self.lookup(&mut entries, name, now, &mut evicted) {
+                Some(found) => found,
+                None        => Ok(self.insert(&mut entries, name, tz, now, &mut evicted)),
+            }
+        };
+        self.notify(evicted);
+        result
+    }
+
+    // The zone `name` if it's in the registry and hasn't expired (or an error if the registry is sealed and it
+    // wasn't preloaded), or `None` if it needs loading.
+    fn lookup(&self, entries: &mut Entries, name: &str, now: TimeT, evicted: &mut Vec<(String, Arc<Timezone>, Eviction)>) -> Option<Result<Arc<Timezone>, String>> {
+        entries.uses += 1;
+        let uses = entries.uses;
+        if entries.sealed {
+            return Some(entries.zones.get_mut(name).filter(|e| e.pinned).map(|entry| { entry.last_used = uses; entry.tz.clone() })
+                                                                          .ok_or_else(|| format!("Zone {:?} wasn't preloaded", name)));
+        }
+        if let Some(entry) = entries.zones.get_mut(name) {
+            if !self.expired(entry, now) {
+                entry.last_used = uses;
+                trace_event!(TRACE, zone = name, "registry hit");
+                return Some(Ok(entry.tz.clone()));
+            }
+            let entry = entries.zones.remove(name).expect("just found it");
+            evicted.push((name.to_string(), entry.tz, Eviction::Expired));
+        }
+        trace_event!(DEBUG, zone = name, "registry miss");
+        None
+    }
+
+    // Add a freshly loaded zone, making room for it if need be.
+    fn insert(&self, entries: &mut Entries, name: &str, tz: Arc<Timezone>, now: TimeT, evicted: &mut Vec<(String, Arc<Timezone>, Eviction)>) -> Arc<Timezone> {
+        while entries.zones.len() >= self.capacity {
+            let Some(lru) = entries.zones.iter().filter(|(_, e)| !e.pinned).min_by_key(|(_, e)| e.last_used).map(|(name, _)| name.clone()) else { break };
+            let entry = entries.zones.remove(&lru).expect("just found it");
+            evicted.push((lru, entry.tz, Eviction::Capacity));
+        }
+        let uses = entries.uses;
+        entries.zones.insert(name.to_string(), Entry{ tz: tz.clone(), loaded: now, last_used: uses, pinned: false });
+        tz
+    }
+
+    // The first half of get(), for loaders that run elsewhere: the zone if there's no need to load it.
+    #[cfg(feature = "async")]
+    pub(crate) fn get_cached(&self, name: &str) -> Option<Result<Arc<Timezone>, String>> {
+        self.cached(name, self.clock.now())
+    }
+
+    // The second half: add the zone that was loaded.
+    #[cfg(feature = "async")]
+    pub(crate) fn put(&self, name: &str, tz: Arc<Timezone>) -> Result<Arc<Timezone>, String> {
+        self.add(name, tz, self.clock.now())
+    }
+
+    fn expired(&self, entry: &Entry, now: TimeT) -> bool {
+        // A clock that's stepped backwards makes the age negative, which counts as just loaded.
+        !entry.pinned && self.ttl.is_some_and(|ttl| now.saturating_sub(entry.loaded).max(0) as u64 >= ttl.as_secs())
+    }
+
+    /// Load the zones `names` now and keep them for good, then never load anything again: from then on
+    /// [`get`](ZoneRegistry::get) only gives these zones (other names are errors) and no files are read, so a
+    /// process can drop its filesystem access (with seccomp or landlock, say) once it's started up. The zones'
+    /// data is loaded up front too, so methods that need it (like [`Timezone::zone_info`]) don't read files later.
+    ///
+    /// Preloaded zones don't count against the capacity, don't expire, and are only dropped by
+    /// [`remove`](ZoneRegistry::remove) or [`clear`](ZoneRegistry::clear). Other zones already in the registry
+    /// are dropped. If any of the zones can't be loaded, the error lists all of them and the registry is left as
+    /// it was. Calling it again adds more zones.
+    ///
+    /// ```
+    /// use libtz::ZoneRegistry;
+    /// let registry = ZoneRegistry::new(10);
+    /// registry.preload(&["America/New_York", "Europe/Paris"]).expect("preload");
+    /// // ... drop filesystem access ...
+    /// assert!(registry.get("Europe/Paris").is_ok());
+    /// assert!(registry.get("Asia/Tokyo").is_err());
+    /// ```
+    pub fn preload(&self, names: &[&str]) -> Result<(), String> {
+        let mut loaded = vec![];
+        let mut errors = vec![];
+        for name in names {
+            match self.loader.build(name).and_then(|tz| { tz.zone_info()?; Ok(tz) }) {
+                Ok(tz) => loaded.push((name.to_string(), Arc::new(tz))),
+                Err(e) => errors.push(format!("{}: {}", name, e)),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(format!("Couldn't preload {}", errors.join(", ")));
+        }
+        let now = self.clock.now();
+        let evicted: Vec<_> = {
+            let mut entries = self.entries.lock().unwrap();
+            entries.sealed = true;
+            let uses = entries.uses;
+            let unpinned: Vec<String> = entries.zones.iter().filter(|(_, e)| !e.pinned).map(|(name, _)| name.clone()).collect();
+            let evicted = unpinned.into_iter().filter_map(|name| entries.zones.remove(&name).map(|e| (name, e.tz, Eviction::Removed))).collect();
+            for (name, tz) in loaded {
+                entries.zones.insert(name, Entry{ tz, loaded: now, last_used: uses, pinned: true });
+            }
+            evicted
+        };
+        self.notify(evicted);
+        Ok(())
+    }
+
+    fn notify(&self, evicted: Vec<(String, Arc<Timezone>, Eviction)>) {
+        if let Some(on_evict) = &self.on_evict {
+            for (name, tz, why) in evicted {
+                on_evict(&name, &tz, why);
+            }
+        }
+    }
+
+    /// Drop the zone `name` from the registry, if it's there. Returns whether it was.
+    pub fn remove(&self, name: &str) -> bool {
+        let entry = self.entries.lock().unwrap().zones.remove(name);
+        let removed = entry.is_some();
+        self.notify(entry.into_iter().map(|e| (name.to_string(), e.tz, Eviction::Removed)).collect());
+        removed
+    }
+
+    /// Drop every zone from the registry.
+    pub fn clear(&self) {
+        let zones = std::mem::take(&mut self.entries.lock().unwrap().zones);
+        self.notify(zones.into_iter().map(|(name, e)| (name, e.tz, Eviction::Removed)).collect());
+    }
+
+    /// Drop the zones whose time to live has run out, without waiting for them to be asked for.
+    pub fn purge_expired(&self) {
+        let now = self.clock.now();
+        let expired: Vec<_> = {
+            let mut entries = self.entries.lock().unwrap();
+            let names: Vec<String> = entries.zones.iter().filter(|(_, e)| self.expired(e, now)).map(|(name, _)| name.clone()).collect();
+            names.into_iter().filter_map(|name| entries.zones.remove(&name).map(|e| (name, e.tz, Eviction::Expired))).collect()
+        };
+        self.notify(expired);
+    }
+
+    /// The number of zones in the registry.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().zones.len()
+    }
+
+    /// Whether the registry is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The most zones the registry will hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fallback, SteppingClock};
+
+    #[test]
+    fn registry() {
+        let evictions = Arc::new(Mutex::new(vec![]));
+        let log = evictions.clone();
+        let registry = ZoneRegistry::new(2).ttl(Duration::from_secs(100))
+                                          .clock(SteppingClock::new(0, 25)) // Every get() is 25 seconds after the last
+                                          .loader(Timezone::builder().fallback(Fallback::Error))
+                                          .on_evict(move |name, _, why| log.lock().unwrap().push((name.to_string(), why)));
+        let tokyo = registry.get("Asia/Tokyo").expect("get");
+        registry.get("Europe/Paris").expect("get");
+        assert!(Arc::ptr_eq(&tokyo, &registry.get("Asia/Tokyo").expect("get")));
+        registry.get("America/Chicago").expect("get"); // Paris is least recently used
+        assert_eq!(registry.len(), 2);
+        assert!(registry.get("EST5EDT,M3.2.0,M11.1.0").is_err()); // Not a zone file, with this loader
+        assert_eq!(registry.len(), 2);
+        assert_eq!(*evictions.lock().unwrap(), [("Europe/Paris".to_string(), Eviction::Capacity)]);
+
+        // Tokyo was loaded at 0 and it's now 125.
+        let reloaded = registry.get("Asia/Tokyo").expect("get");
+        assert!(!Arc::ptr_eq(&tokyo, &reloaded));
+        assert_eq!(evictions.lock().unwrap().last(), Some(&("Asia/Tokyo".to_string(), Eviction::Expired)));
+        assert_eq!(tokyo.localtime(0).map(|tm| tm.tm_hour), Ok(9)); // Still usable
+
+        assert!(registry.remove("Asia/Tokyo"));
+        assert!(!registry.remove("Asia/Tokyo"));
+        registry.clear();
+        assert!(registry.is_empty());
+        assert_eq!(evictions.lock().unwrap().iter().filter(|(_, why)| *why == Eviction::Removed).count(), 2);
+
+        // The loader's fallback is kept when a resolver is added.
+        let registry = registry.resolver(|name: &str| Ok((name == "Config/Office").then(|| std::fs::read("/usr/share/zoneinfo/Asia/Tokyo").expect("read"))));
+        assert_eq!(registry.get("Config/Office").map(|tz| tz.localtime(0).map(|tm| tm.tm_hour)), Ok(Ok(9)));
+        assert!(registry.get("EST5EDT,M3.2.0,M11.1.0").is_err());
+    }
+
+    #[test]
+    fn load_unlocked() {
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        let wait = Mutex::new(wait);
+        let registry = Arc::new(ZoneRegistry::new(10).resolver(move |name: &str| {
+            if name != "Slow/Zone" { return Ok(None) }
+            wait.lock().unwrap().recv().map_err(|e| e.to_string())?;
+            Ok(Some(std::fs::read("/usr/share/zoneinfo/Asia/Tokyo").map_err(|e| e.to_string())?))
+        }));
+        let slow = { let registry = registry.clone(); std::thread::spawn(move || registry.get("Slow/Zone").map(|tz| tz.localtime(0).map(|tm| tm.tm_hour))) };
+        std::thread::sleep(Duration::from_millis(50)); // Let it start loading
+        assert!(registry.get("Europe/Paris").is_ok()); // Not held up by it
+        release.send(()).expect("send");
+        assert_eq!(slow.join().expect("join"), Ok(Ok(9)));
+        assert_eq!(registry.len(), 2);
+
+        // The clock going backwards doesn't expire anything.
+        let registry = ZoneRegistry::new(2).ttl(Duration::from_secs(100)).clock(SteppingClock::new(1000, -500));
+        let tokyo = registry.get("Asia/Tokyo").expect("get");
+        assert!(Arc::ptr_eq(&tokyo, &registry.get("Asia/Tokyo").expect("get")));
+    }
+
+    #[test]
+    fn preload() {
+        let evictions = Arc::new(Mutex::new(vec![]));
+        let log = evictions.clone();
+        let registry = ZoneRegistry::new(1).ttl(Duration::from_secs(100))
+                                          .clock(SteppingClock::new(0, 1000)) // Everything expires between calls
+                                          .loader(Timezone::builder().fallback(Fallback::Error))
+                                          .on_evict(move |name, _, why| log.lock().unwrap().push((name.to_string(), why)));
+        registry.get("Asia/Tokyo").expect("get");
+        let err = registry.preload(&["Europe/Paris", "Not/AZone", "Europe/Berln"]).expect_err("preload");
+        assert!(err.contains("Not/AZone") && err.contains("Europe/Berln"), "{}", err);
+        assert!(registry.get("America/Chicago").is_ok()); // Still loading zones
+
+        registry.preload(&["Europe/Paris", "America/New_York"]).expect("preload");
+        assert_eq!(registry.len(), 2); // More than the capacity
+        assert!(evictions.lock().unwrap().contains(&("America/Chicago".to_string(), Eviction::Removed)));
+        let paris = registry.get("Europe/Paris").expect("get");
+        assert!(Arc::ptr_eq(&paris, &registry.get("Europe/Paris").expect("get"))); // Never expires
+        assert!(paris.zone_info().is_ok());
+        assert_eq!(registry.get("Asia/Tokyo").err(), Some("Zone \"Asia/Tokyo\" wasn't preloaded".to_string()));
+        assert_eq!(registry.len(), 2);
+
+        registry.preload(&["Asia/Tokyo"]).expect("preload");
+        assert!(registry.get("Asia/Tokyo").is_ok() && registry.get("America/New_York").is_ok());
+    }
+}
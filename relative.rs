@@ -0,0 +1,234 @@
+// Parsing relative times like "tomorrow 17:00" or "in 3 hours".
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use crate::{TimeT, Timezone, Weekday, Meridiem, calendar};
+use crate::timezone::LocalCandidates;
+
+/// A local time's system time, which can be ambiguous (when the clocks fall back) or not exist (when they
+/// spring forward). From [`Timezone::parse_relative`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocalResult<T> {
+    /// There's exactly one.
+    Single(T),
+    /// The local time happens twice: (earlier, later).
+    Ambiguous(T, T),
+    /// The local time was skipped.
+    None,
+}
+
+impl<T> LocalResult<T> {
+    /// The only result, or `None` if there isn't exactly one.
+    pub fn single(self) -> Option<T> {
+        match self { LocalResult::Single(t) => Some(t), _ => None }
+    }
+
+    /// The earliest result, if there is one.
+    pub fn earliest(self) -> Option<T> {
+        match self { LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => Some(t), LocalResult::None => None }
+    }
+
+    /// The latest result, if there is one.
+    pub fn latest(self) -> Option<T> {
+        match self { LocalResult::Single(t) | LocalResult::Ambiguous(_, t) => Some(t), LocalResult::None => None }
+    }
+}
+
+const WEEKDAYS: [(&str, Weekday); 14] = [
+    ("sunday", Weekday::Sunday), ("monday", Weekday::Monday), ("tuesday", Weekday::Tuesday),
+    ("wednesday", Weekday::Wednesday), ("thursday", Weekday::Thursday), ("friday", Weekday::Friday),
+    ("saturday", Weekday::Saturday), ("sun", Weekday::Sunday), ("mon", Weekday::Monday), ("tue", Weekday::Tuesday),
+    ("wed", Weekday::Wednesday), ("thu", Weekday::Thursday), ("fri", Weekday::Friday), ("sat", Weekday::Saturday),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Unit {
+    Seconds(i64),
+    Days(i64),
+    Months(i64),
+}
+
+fn parse_unit(s: &str) -> Option<Unit> {
+    Some(match s.strip_suffix('s').filter(|s| s.len() > 1).unwrap_or(s) {
+        "s" | "sec" | "second"         => Unit::Seconds(1),
+        "m" | "min" | "minute"         => Unit::Seconds(60),
+        "h" | "hr" | "hour"            => Unit::Seconds(3600),
+        "d" | "day"                    => Unit::Days(1),
+        "w" | "wk" | "week"            => Unit::Days(7),
+        "mo" | "month"                 => Unit::Months(1),
+        "y" | "yr" | "year"            => Unit::Months(12),
+        _                              => return None,
+    })
+}
+
+fn parse_amount(s: &str) -> Option<i64> {
+    match s {
+        "a" | "an" => Some(1),
+        _          => s.parse().ok(),
+    }
+}
+
+// A time of day ("17:00", "17:00:30", "9am", "9:30pm", "9:30p.m.", "noon", "midnight") as seconds since midnight.
+fn parse_time(s: &str) -> Option<i64> {
+    match s {
+        "noon"     => return Some(12 * 3600),
+        "midnight" => return Some(0),
+        _          => {},
+    }
+    let (s, meridiem) = ["am", "pm", "a.m.", "p.m."].iter().find_map(|suffix| Some((s.strip_suffix(suffix)?, suffix.parse::<Meridiem>().ok())))
+                                                    .unwrap_or((s, None));
+    // The hour can be 1 or 2 digits, minutes and seconds are always 2.
+    let fields: Vec<i64> = s.split(':').enumerate().map(|(i, f)| f.parse().ok().filter(|_| (if i == 0 { 1 } else { 2 }..=2).contains(&f.len())))
+                            .collect::<Option<_>>()?;
+    let (hour, min, sec) = match (&fields[..], meridiem) {
+        (&[h], Some(_))         => (h, 0, 0),
+        (&[h, m], _)            => (h, m, 0),
+        (&[h, m, s], _)         => (h, m, s),
+        _                       => return None,
+    };
+    let hour = match meridiem {
+        Some(meridiem) => meridiem.hour24(u8::try_from(hour).ok()?)? as i64,
+        None           => hour,
+    };
+    ((0..24).contains(&hour) && (0..60).contains(&min) && (0..=60).contains(&sec)).then_some(hour * 3600 + min * 60 + sec)
+}
+
+impl Timezone {
+    /// Parse a time relative to `now` the way people type them at a command line, in this zone:
+    ///
+    /// - `now`
+    /// - `in 3 hours`, `in a week`, `2 days ago`: units are seconds, minutes, hours, days, weeks, months and
+    ///   years (or `s`, `min`, `h`, `d`, `w`, `mo` and `y`). Seconds, minutes and hours are exact elapsed time.
+    ///   Days and longer move the calendar and keep the time of day, clamping to the end of shorter months.
+    /// - `today`, `tomorrow` and `yesterday` (keeping the time of day), `monday` (today or the next Monday),
+    ///   `next monday` (not today), `last monday` (before today), optionally followed by a time of day.
+    /// - A time of day on its own (today): `17:00`, `17:00:30`, `9am`, `9:30 pm`, `9:30 p.m.`, `noon` or `midnight`.
+    ///   Weekdays without a time mean midnight.
+    ///
+    /// Case doesn't matter, and an `at` before the time of day is allowed. Anything else is an error. Times
+    /// that land in a gap or overlap when the clocks change give [`LocalResult::None`] or
+    /// [`LocalResult::Ambiguous`].
+    ///
+    /// ```
+    /// use libtz::{LocalResult, Timezone};
+    /// let tz = Timezone::new("America/New_York").unwrap();
+    /// let now = 1700000000; // Tuesday 2023-11-14 17:13:20 EST
+    /// let at = |s| tz.parse_relative(s, now).unwrap().single().map(|t| tz.localtime(t).unwrap());
+    /// assert_eq!(at("tomorrow 9am").map(|tm| (tm.tm_mday, tm.tm_hour)), Some((15, 9)));
+    /// assert_eq!(at("next monday at 17:30").map(|tm| (tm.tm_mday, tm.tm_hour, tm.tm_min)), Some((20, 17, 30)));
+    /// assert_eq!(tz.parse_relative("in 3 hours", now), Ok(LocalResult::Single(now + 3 * 3600)));
+    /// ```
+    pub fn parse_relative(&self, input: &str, now: TimeT) -> Result<LocalResult<TimeT>, String> {
+        let bad = || format!("Can't understand the time {:?}", input);
+        let lower = input.to_lowercase();
+        let words: Vec<&str> = lower.split_whitespace().collect();
+        let tm = self.localtime(now)?;
+        let day = calendar::days_from_civil(tm.tm_year as i64 + 1900, tm.tm_mon, tm.tm_mday as i64);
+        let time_of_day = tm.tm_hour as i64 * 3600 + tm.tm_min as i64 * 60 + tm.tm_sec as i64;
+
+        let offset = match words[..] {
+            ["now"]                       => return Ok(LocalResult::Single(now)),
+            ["in", amount, unit]          => parse_amount(amount).zip(parse_unit(unit)),
+            [amount, unit, "ago"]         => parse_amount(amount).zip(parse_unit(unit)).and_then(|(n, unit)| Some((n.checked_neg()?, unit))),
+            _                             => None,
+        };
+        if let Some((n, unit)) = offset {
+            return match unit {
+                Unit::Seconds(secs) => n.checked_mul(secs).and_then(|secs| now.checked_add(secs)).map(LocalResult::Single).ok_or_else(bad),
+                Unit::Days(days)    => self.wall_result(n.checked_mul(days).and_then(|days| day.checked_add(days)).ok_or_else(bad)?, time_of_day),
+                Unit::Months(months) => {
+                    let total = n.checked_mul(months).and_then(|m| (tm.tm_year as i64 * 12 + tm.tm_mon as i64).checked_add(m)).ok_or_else(bad)?;
+                    let (year, month) = (total.div_euclid(12) + 1900, total.rem_euclid(12) as i32);
+                    i32::try_from(year).map_err(|_| bad())?;
+                    let mday = tm.tm_mday.min(calendar::month_days(year, month));
+                    self.wall_result(calendar::days_from_civil(year, month, mday as i64), time_of_day)
+                },
+            };
+        }
+
+        let weekday = |name: &str| WEEKDAYS.iter().find(|(n, _)| *n == name).map(|(_, w)| *w as i64);
+        let today = calendar::weekday_from_days(day) as i64;
+        let (date, rest) = match words[..] {
+            ["today", ref rest @ ..]               => (Some((day, true)), rest),
+            ["tomorrow", ref rest @ ..]            => (Some((day + 1, true)), rest),
+            ["yesterday", ref rest @ ..]           => (Some((day - 1, true)), rest),
+            ["next", w, ref rest @ ..] if weekday(w).is_some()
+                                                   => (weekday(w).map(|w| (day + 1 + (w - today - 1).rem_euclid(7), false)), rest),
+            ["last", w, ref rest @ ..] if weekday(w).is_some()
+                                                   => (weekday(w).map(|w| (day - 1 - (today - w - 1).rem_euclid(7), false)), rest),
+            [w, ref rest @ ..] if weekday(w).is_some()
+                                                   => (weekday(w).map(|w| (day + (w - today).rem_euclid(7), false)), rest),
+            ref rest                               => (None, rest),
+        };
+        let rest = match rest { ["at", ref rest @ ..] if !rest.is_empty() => rest, rest => rest };
+        let time = match rest {
+            []                                     => None,
+            [time]                                 => Some(parse_time(time).ok_or_else(bad)?),
+            [time, meridiem @ ("am" | "pm" | "a.m." | "p.m.")] => Some(parse_time(&format!("{}{}", time, meridiem)).ok_or_else(bad)?),
+            _                                      => return Err(bad()),
+        };
+        match (date, time) {
+            (Some((date, _)), Some(time))          => self.wall_result(date, time),
+            (Some((date, keep)), None)             => self.wall_result(date, if keep { time_of_day } else { 0 }),
+            (None, Some(time))                     => self.wall_result(day, time),
+            (None, None)                           => Err(bad()),
+        }
+    }
+
+    fn wall_result(&self, day: i64, time_of_day: i64) -> Result<LocalResult<TimeT>, String> {
+        let wall = day.checked_mul(86400).and_then(|wall| wall.checked_add(time_of_day)).ok_or("Time out of range")?;
+        let LocalCandidates{ times, .. } = self.local_candidates(wall)?;
+        Ok(match times[..] {
+            [time]           => LocalResult::Single(time),
+            [earlier, later] => LocalResult::Ambiguous(earlier, later),
+            _                => LocalResult::None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tm;
+
+    #[test]
+    fn parse_relative() {
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        let now = tz.mktime(&Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(2023, 11, 1, 1, 30, 0) }).expect("mktime"); // A Wednesday
+        let fields = |s: &str| tz.parse_relative(s, now).map(|r| r.earliest().map(|t| {
+            let tm = tz.localtime(t).expect("localtime");
+            (tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min)
+        }));
+        assert_eq!(fields("now"), Ok(Some((11, 1, 1, 30))));
+        assert_eq!(fields("Tomorrow"), Ok(Some((11, 2, 1, 30))));
+        assert_eq!(fields("yesterday noon"), Ok(Some((10, 31, 12, 0))));
+        assert_eq!(fields("today at 9:15 pm"), Ok(Some((11, 1, 21, 15))));
+        assert_eq!(fields("today at 9:15 p.m."), Ok(Some((11, 1, 21, 15))));
+        assert_eq!(fields("at 5pm"), Ok(Some((11, 1, 17, 0))));
+        assert_eq!(fields("12a.m."), Ok(Some((11, 1, 0, 0))));
+        assert_eq!(fields("12am"), Ok(Some((11, 1, 0, 0))));
+        assert_eq!(fields("wednesday"), Ok(Some((11, 1, 0, 0))));
+        assert_eq!(fields("next wed 8am"), Ok(Some((11, 8, 8, 0))));
+        assert_eq!(fields("last wed"), Ok(Some((10, 25, 0, 0))));
+        assert_eq!(fields("fri 23:59:59"), Ok(Some((11, 3, 23, 59))));
+        assert_eq!(fields("in 2 weeks"), Ok(Some((11, 15, 1, 30))));
+        assert_eq!(fields("a month ago"), Ok(Some((10, 1, 1, 30))));
+        assert_eq!(fields("in 1 yr"), Ok(Some((11, 1, 1, 30))));
+        assert_eq!(tz.parse_relative("in 90 min", now), Ok(LocalResult::Single(now + 5400)));
+        assert_eq!(tz.parse_relative("10 s ago", now), Ok(LocalResult::Single(now - 10)));
+
+        // 2023-11-05 01:30 happened twice, and 2024-03-10 02:30 not at all.
+        let sunday = tz.parse_relative("sunday 1:30", now).expect("parse");
+        assert!(matches!(sunday, LocalResult::Ambiguous(a, b) if b - a == 3600));
+        assert_eq!(tz.parse_relative("in 4 days", now), Ok(sunday));
+        let march = tz.mktime(&Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(2024, 3, 9, 12, 0, 0) }).expect("mktime");
+        assert_eq!(tz.parse_relative("tomorrow 2:30am", march), Ok(LocalResult::None));
+
+        for bad in ["", "later", "in hours", "in 3 fortnights", "25:00", "13pm", "0am", "tomorrow tomorrow", "at",
+                    "tomorrow at", "monday 9 o'clock", "in 99999999999999999 years", "in 106751991147624 days", "9:5",
+                    "-9223372036854775808 seconds ago"] {
+            assert!(tz.parse_relative(bad, now).is_err(), "{:?}", bad);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+// Picking up tzdata updates without restarting.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::ffi::CString;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::{Duration, SystemTime};
+use crate::{Timezone, Provenance};
+
+// Enough to notice a file being rewritten in place or replaced (package managers usually write a new file and
+// rename it over the old one, which changes the inode).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    inode: u64, // 0 where there are no inodes
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<FileStamp> {
+        let meta = std::fs::metadata(path).ok()?;
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(&meta);
+        #[cfg(not(unix))]
+        let inode = 0; // Elsewhere the modification time and length have to do
+        Some(FileStamp{ modified: meta.modified().ok(), inode, len: meta.len() })
+    }
+}
+
+impl Timezone {
+    /// Load the zone again from wherever it originally came from, replacing `self`. Zones loaded from files
+    /// pick up any changes made to the file since (for instance by an OS tzdata update).
+    ///
+    /// Zones built in memory ([`Provenance::Synthesized`]) have nothing to reload from and return an error.
+    pub fn reload(&mut self) -> Result<(), String> {
+        *self = self.reloaded()?;
+        Ok(())
+    }
+
+    fn reloaded(&self) -> Result<Timezone, String> {
+        let tzname = |name: &str| CString::new(name).map_err(|_| "name has internal null byte".to_string());
+        match self.provenance() {
+            Provenance::Utc                       => Timezone::new(""),
+            Provenance::SystemDefault(_)          => Timezone::alloc(None),
+            Provenance::File(path)                => Timezone::alloc(Some(&tzname(&format!(":{}", path.display()))?)),
+            Provenance::AndroidTzdata(path, zone) => Timezone::from_android(path.clone(), zone.clone()),
+            Provenance::PosixRule(rule)           => Timezone::new(rule),
+            Provenance::Synthesized(name)         => Err(format!("{}: zone was built in memory and can't be reloaded", name)),
+            Provenance::Static(zone)              => Timezone::from_static(zone),
+        }
+    }
+
+    // The file the zone was loaded from, if any.
+    fn source_file(&self) -> Option<&Path> {
+        match self.provenance() {
+            Provenance::SystemDefault(path) | Provenance::File(path) | Provenance::AndroidTzdata(path, _) => Some(path),
+            Provenance::Utc | Provenance::PosixRule(_) | Provenance::Synthesized(_) | Provenance::Static(_) => None,
+        }
+    }
+}
+
+/// A [`Timezone`] that gets reloaded when the file it came from changes.
+///
+/// [`WatchedTimezone::get`] hands out the current zone as an `Arc`. When a change is noticed (by
+/// [`WatchedTimezone::check`] or the thread started by [`WatchedTimezone::watch`]) the zone is loaded again and
+/// swapped in; callers that already have the old zone keep using it until they call `get` again.
+///
+/// ```
+/// use libtz::{Timezone, WatchedTimezone};
+/// let tz = WatchedTimezone::new(Timezone::new("Europe/Paris").expect("timezone alloc"));
+/// let _watcher = tz.watch(std::time::Duration::from_secs(60));
+/// let tm = tz.get().localtime(0).expect("localtime");
+/// ```
+pub struct WatchedTimezone {
+    current: RwLock<Arc<Timezone>>,
+    stamp: Mutex<Option<FileStamp>>,
+}
+
+impl WatchedTimezone {
+    /// Start watching `tz`'s file. Zones that didn't come from a file are never reloaded.
+    pub fn new(tz: Timezone) -> Arc<WatchedTimezone> {
+        let stamp = tz.source_file().and_then(FileStamp::of);
+        Arc::new(WatchedTimezone{ current: RwLock::new(Arc::new(tz)), stamp: Mutex::new(stamp) })
+    }
+
+    /// The current zone.
+    pub fn get(&self) -> Arc<Timezone> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Reload the zone if its file has changed since it was loaded. Returns whether it was reloaded.
+    pub fn check(&self) -> Result<bool, String> {
+        let mut stamp = self.stamp.lock().unwrap();
+        let current = self.get();
+        let Some(path) = current.source_file() else { return Ok(false) };
+        let now = FileStamp::of(path);
+        if now.is_none() || now == *stamp {
+            return Ok(false); // A missing file is probably in the middle of being replaced. Keep the old zone.
+        }
+        let fresh = current.reloaded()?;
+        *self.current.write().unwrap() = Arc::new(fresh);
+        *stamp = now;
+        Ok(true)
+    }
+
+    /// Start a thread that calls [`WatchedTimezone::check`] every `interval`. The thread exits once the
+    /// [`WatchedTimezone`] is dropped. Errors from reloading are ignored (the old zone stays in use) and retried
+    /// at the next interval.
+    pub fn watch(self: &Arc<Self>, interval: Duration) -> std::thread::JoinHandle<()> {
+        let watched: Weak<WatchedTimezone> = Arc::downgrade(self);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            let Some(watched) = watched.upgrade() else { return };
+            let _ = watched.check();
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reload() {
+        let path = std::env::temp_dir().join(format!("libtz-reload-test-{}", std::process::id()));
+        std::fs::copy("/usr/share/zoneinfo/Asia/Tokyo", &path).expect("copy");
+        let mut tz = Timezone::new(&format!(":{}", path.display())).expect("timezone alloc");
+        let watched = WatchedTimezone::new(Timezone::new(&format!(":{}", path.display())).expect("timezone alloc"));
+        let first = watched.get();
+        assert_eq!(watched.check(), Ok(false));
+
+        let tmp = path.with_extension("new");
+        std::fs::copy("/usr/share/zoneinfo/Europe/Paris", &tmp).expect("copy");
+        std::fs::rename(&tmp, &path).expect("rename");
+        let reloaded = tz.reload();
+        let checked = watched.check();
+        std::fs::remove_file(&path).expect("remove");
+
+        reloaded.expect("reload");
+        assert_eq!(tz.localtime(0).expect("localtime").tm_gmtoff, 3600);
+        assert_eq!(checked, Ok(true));
+        assert_eq!(watched.get().localtime(0).expect("localtime").tm_gmtoff, 3600);
+        assert_eq!(first.localtime(0).expect("localtime").tm_gmtoff, 9 * 3600);
+        assert_eq!(watched.check(), Ok(false), "missing file keeps the old zone");
+
+        let mut rule = Timezone::new("EST5EDT,M3.2.0,M11.1.0").expect("timezone alloc");
+        rule.reload().expect("reload");
+        assert_eq!(rule.provenance(), &Provenance::PosixRule("EST5EDT,M3.2.0,M11.1.0".to_string()));
+        let mut synthesized = Timezone::from_tzif("Tokyo", &std::fs::read("/usr/share/zoneinfo/Asia/Tokyo").expect("read")).expect("from_tzif");
+        assert!(synthesized.reload().is_err());
+    }
+}
@@ -0,0 +1,45 @@
+// Pluggable sources of zone data.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Where a [`TimezoneBuilder`](crate::TimezoneBuilder) (and so a [`ZoneRegistry`](crate::ZoneRegistry)) gets zone
+/// data, for fetching zones from a database, an object store or a config service instead of the local filesystem.
+///
+/// Closures `Fn(&str) -> Result<Option<Vec<u8>>, String>` are resolvers, and so are maps from names to TZif data.
+///
+/// ```
+/// use libtz::{Timezone, ZoneResolver};
+/// let store = |name: &str| -> Result<Option<Vec<u8>>, String> {
+///     // Fetch it from somewhere. This just reads a file under a different name.
+///     Ok((name == "Company/HQ").then(|| std::fs::read("/usr/share/zoneinfo/America/Chicago").unwrap()))
+/// };
+/// let tz = Timezone::builder().resolver(store).build("Company/HQ").unwrap();
+/// assert_eq!(tz.localtime(1700000000).unwrap().strftime("%H:%M %Z"), "16:13 CST");
+/// ```
+pub trait ZoneResolver: Send + Sync {
+    /// The contents of the TZif file for the zone `name`, or `None` if it doesn't know the zone (so the name is
+    /// looked up the usual way). Errors fail the load.
+    fn resolve(&self, name: &str) -> Result<Option<Vec<u8>>, String>;
+}
+
+impl<F> ZoneResolver for F where F: Fn(&str) -> Result<Option<Vec<u8>>, String> + Send + Sync {
+    fn resolve(&self, name: &str) -> Result<Option<Vec<u8>>, String> {
+        self(name)
+    }
+}
+
+impl ZoneResolver for HashMap<String, Vec<u8>> {
+    fn resolve(&self, name: &str) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.get(name).cloned())
+    }
+}
+
+impl fmt::Debug for dyn ZoneResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ZoneResolver")
+    }
+}
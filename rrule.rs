@@ -0,0 +1,338 @@
+// iCalendar (RFC 5545) recurrence rule expansion.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::collections::VecDeque;
+use crate::{TimeT, Timezone, calendar};
+use crate::ical::{WEEKDAYS, parse_date_time};
+
+// Expansion gives up (ending the iteration) once it gets past this year, so rules that never match still end.
+const LAST_YEAR: i64 = 9999;
+
+/// How often an [`RRule`] repeats (its `FREQ`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frequency {
+    Yearly,
+    Monthly,
+    Weekly,
+    Daily,
+}
+
+/// A parsed RFC 5545 recurrence rule, like `FREQ=MONTHLY;BYDAY=-1FR;COUNT=6` (the last Friday of the month,
+/// 6 times).
+///
+/// The supported parts are `FREQ` (`YEARLY`, `MONTHLY`, `WEEKLY` or `DAILY`), `INTERVAL`, `COUNT`, `UNTIL`,
+/// `BYMONTH`, `BYMONTHDAY`, `BYDAY`, `BYHOUR`, `BYMINUTE`, `BYSECOND`, `BYSETPOS` and `WKST`. Anything else
+/// is an error rather than being silently ignored.
+///
+/// ```
+/// use libtz::{Timezone, Tm, RRule};
+/// let tz = Timezone::new("Europe/Berlin").unwrap();
+/// let rule: RRule = "FREQ=WEEKLY;BYDAY=MO;COUNT=3".parse().unwrap();
+/// let start = tz.mktime(&Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(2023, 10, 16, 9, 0, 0) }).unwrap();
+/// let hours: Vec<_> = rule.expand(&tz, start).map(|t| tz.localtime(t).unwrap().tm_hour).collect();
+/// assert_eq!(hours, [9, 9, 9]); // 09:00 every week, even across the switch back to standard time
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RRule {
+    freq: Frequency,
+    interval: i64,
+    count: Option<u32>,
+    until: Option<(i64, bool)>, // (time, is UTC), where a local time is in wall seconds
+    by_month: Vec<i32>,         // [0, 11]
+    by_month_day: Vec<i32>,     // [1, 31] or [-31, -1]
+    by_day: Vec<(i32, i32)>,    // (ordinal (0 for every one), weekday)
+    by_hour: Vec<i32>,
+    by_minute: Vec<i32>,
+    by_second: Vec<i32>,
+    by_set_pos: Vec<i32>,
+    week_start: i32,
+}
+
+fn parse_list(key: &str, value: &str, min: i32, max: i32, nonzero: bool) -> Result<Vec<i32>, String> {
+    value.split(',').map(|n| match n.trim_start_matches('+').parse::<i32>() {
+        Ok(n) if (min..=max).contains(&n) && !(nonzero && n == 0) => Ok(n),
+        _ => Err(format!("Invalid RRULE {} {:?}", key, value)),
+    }).collect()
+}
+
+fn parse_weekday(s: &str) -> Result<i32, String> {
+    WEEKDAYS.iter().position(|d| *d == s).map(|d| d as i32).ok_or_else(|| format!("Invalid RRULE weekday {:?}", s))
+}
+
+impl std::str::FromStr for RRule {
+    type Err = String;
+    fn from_str(s: &str) -> Result<RRule, String> {
+        let s = s.strip_prefix("RRULE:").unwrap_or(s);
+        let mut rule = RRule{ freq: Frequency::Yearly, interval: 1, count: None, until: None, by_month: vec![],
+                              by_month_day: vec![], by_day: vec![], by_hour: vec![], by_minute: vec![],
+                              by_second: vec![], by_set_pos: vec![], week_start: 1 };
+        let mut freq = None;
+        for part in s.split(';') {
+            let Some((key, value)) = part.split_once('=') else { return Err(format!("Invalid RRULE part {:?}", part)) };
+            let (key, value) = (key.to_ascii_uppercase(), value.to_ascii_uppercase());
+            match key.as_str() {
+                "FREQ"       => freq = Some(match value.as_str() {
+                    "YEARLY"  => Frequency::Yearly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "WEEKLY"  => Frequency::Weekly,
+                    "DAILY"   => Frequency::Daily,
+                    _         => return Err(format!("Unsupported RRULE FREQ {:?}", value)),
+                }),
+                "INTERVAL"   => rule.interval = value.parse::<u32>().ok().filter(|i| *i > 0).ok_or_else(|| format!("Invalid RRULE INTERVAL {:?}", value))? as i64,
+                "COUNT"      => rule.count = Some(value.parse::<u32>().map_err(|_| format!("Invalid RRULE COUNT {:?}", value))?),
+                "UNTIL"      => rule.until = Some(match value.len() {
+                    8 => parse_date_time(&format!("{}T235959", value))?, // A DATE means until the end of that day
+                    _ => parse_date_time(&value)?,
+                }),
+                "BYMONTH"    => rule.by_month = parse_list(&key, &value, 1, 12, false)?.into_iter().map(|m| m - 1).collect(),
+                "BYMONTHDAY" => rule.by_month_day = parse_list(&key, &value, -31, 31, true)?,
+                "BYHOUR"     => rule.by_hour = parse_list(&key, &value, 0, 23, false)?,
+                "BYMINUTE"   => rule.by_minute = parse_list(&key, &value, 0, 59, false)?,
+                "BYSECOND"   => rule.by_second = parse_list(&key, &value, 0, 60, false)?,
+                "BYSETPOS"   => rule.by_set_pos = parse_list(&key, &value, -366, 366, true)?,
+                "WKST"       => rule.week_start = parse_weekday(&value)?,
+                "BYDAY"      => rule.by_day = value.split(',').map(|day| {
+                    if !day.is_ascii() {
+                        return Err(format!("Invalid RRULE BYDAY {:?}", day));
+                    }
+                    let (ordinal, weekday) = day.split_at(day.len().saturating_sub(2));
+                    let ordinal = match ordinal {
+                        "" => 0,
+                        _  => parse_list(&key, ordinal, -53, 53, true)?[0],
+                    };
+                    Ok((ordinal, parse_weekday(weekday)?))
+                }).collect::<Result<_, String>>()?,
+                _            => return Err(format!("Unsupported RRULE part {}", key)),
+            }
+        }
+        rule.freq = freq.ok_or("RRULE needs a FREQ")?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err("RRULE can't have both COUNT and UNTIL".to_string());
+        }
+        if rule.by_day.iter().any(|(ordinal, _)| *ordinal != 0) && !matches!(rule.freq, Frequency::Yearly | Frequency::Monthly) {
+            return Err("RRULE BYDAY ordinals only work with FREQ=YEARLY or FREQ=MONTHLY".to_string());
+        }
+        Ok(rule)
+    }
+}
+
+impl RRule {
+    /// The occurrences of the rule in `tz`, starting at `dtstart` (which is always the first one, as RFC 5545
+    /// says). Each occurrence has the same local time of day as `dtstart` (unless `BYHOUR`, `BYMINUTE` or
+    /// `BYSECOND` say otherwise), whatever the zone's offset is that day.
+    ///
+    /// A local time that occurs twice is the first one. A local time that is skipped by a transition is
+    /// interpreted with the offset from before the transition, which moves it later (so 02:30 becomes 03:30 when
+    /// the clocks spring forward from 02:00 to 03:00).
+    ///
+    /// Rules without a `COUNT` or `UNTIL` go on until the year 9999. The iteration also ends early if a time
+    /// can't be converted.
+    pub fn expand<'a>(&'a self, tz: &'a Timezone, dtstart: TimeT) -> Occurrences<'a> {
+        let start = tz.localtime(dtstart).map(|tm| tm.wall_seconds()).unwrap_or(i64::MAX);
+        Occurrences{ rule: self, tz, start, period: 0, pending: VecDeque::from([dtstart]), emitted: 0, done: start == i64::MAX }
+    }
+
+    // The days (since the epoch) in the month that the BYMONTHDAY and BYDAY parts pick, or `default_day`.
+    fn month_days(&self, year: i64, month: i32, default_day: i32) -> Vec<i64> {
+        let first = calendar::days_from_civil(year, month, 1);
+        let len = calendar::month_days(year, month);
+        if !self.by_month_day.is_empty() {
+            self.by_month_day.iter().map(|d| if *d > 0 { *d } else { len + 1 + d })
+                                    .filter(|d| (1..=len).contains(d))
+                                    .map(|d| first + d as i64 - 1)
+                                    .filter(|day| self.by_day.is_empty() || self.by_day.iter().any(|(_, wd)| calendar::weekday_from_days(*day) == *wd))
+                                    .collect()
+        } else if !self.by_day.is_empty() {
+            self.weekdays_in(first, len as i64)
+        } else {
+            (default_day <= len).then_some(first + default_day as i64 - 1).into_iter().collect()
+        }
+    }
+
+    // The days BYDAY picks from the `len` days starting at `first`, with ordinals counted within them.
+    fn weekdays_in(&self, first: i64, len: i64) -> Vec<i64> {
+        let mut days = vec![];
+        for (ordinal, weekday) in &self.by_day {
+            let matching: Vec<i64> = (first..first + len).filter(|day| calendar::weekday_from_days(*day) == *weekday).collect();
+            match *ordinal {
+                0            => days.extend(matching),
+                n if n > 0   => days.extend(matching.get(n as usize - 1)),
+                n            => days.extend(matching.len().checked_sub(n.unsigned_abs() as usize).map(|i| matching[i])),
+            }
+        }
+        days
+    }
+
+    // The days in the `period`th period after the one containing `start` (as days since the epoch), and the year
+    // the period starts in.
+    fn period_days(&self, start: i64, period: i64) -> (Vec<i64>, i64) {
+        let (y0, m0, d0) = calendar::civil_from_days(start);
+        let in_month = |day: &i64| self.by_month.is_empty() || self.by_month.contains(&calendar::civil_from_days(*day).1);
+        match self.freq {
+            Frequency::Yearly => {
+                let year = y0 + period * self.interval;
+                let days = if !self.by_month.is_empty() {
+                    self.by_month.iter().flat_map(|month| self.month_days(year, *month, d0)).collect()
+                } else if !self.by_month_day.is_empty() {
+                    (0..12).flat_map(|month| self.month_days(year, month, d0)).collect()
+                } else if !self.by_day.is_empty() {
+                    let first = calendar::days_from_civil(year, 0, 1);
+                    self.weekdays_in(first, calendar::days_from_civil(year + 1, 0, 1) - first)
+                } else {
+                    self.month_days(year, m0, d0)
+                };
+                (days, year)
+            },
+            Frequency::Monthly => {
+                let months = y0 * 12 + m0 as i64 + period * self.interval;
+                let (year, month) = (months.div_euclid(12), months.rem_euclid(12) as i32);
+                let days = if self.by_month.is_empty() || self.by_month.contains(&month) { self.month_days(year, month, d0) } else { vec![] };
+                (days, year)
+            },
+            Frequency::Weekly => {
+                let week = start - (calendar::weekday_from_days(start) - self.week_start).rem_euclid(7) as i64 + period * self.interval * 7;
+                let weekday = calendar::weekday_from_days(start);
+                let days = (week..week + 7).filter(|day| match self.by_day.is_empty() {
+                    true  => calendar::weekday_from_days(*day) == weekday,
+                    false => self.by_day.iter().any(|(_, wd)| calendar::weekday_from_days(*day) == *wd),
+                }).filter(in_month).collect();
+                (days, calendar::civil_from_days(week).0)
+            },
+            Frequency::Daily => {
+                let day = start + period * self.interval;
+                let (year, month, mday) = calendar::civil_from_days(day);
+                let len = calendar::month_days(year, month);
+                let keep = in_month(&day)
+                    && (self.by_month_day.is_empty() || self.by_month_day.iter().any(|d| *d == mday || len + 1 + d == mday))
+                    && (self.by_day.is_empty() || self.by_day.iter().any(|(_, wd)| calendar::weekday_from_days(day) == *wd));
+                (if keep { vec![day] } else { vec![] }, year)
+            },
+        }
+    }
+
+    // Every wall time in the `period`th period, in order.
+    fn period_walls(&self, start: i64, period: i64) -> (Vec<i64>, i64) {
+        let (mut days, year) = self.period_days(start.div_euclid(86400), period);
+        days.sort();
+        days.dedup();
+        let time = start.rem_euclid(86400) as i32;
+        let or = |list: &Vec<i32>, default: i32| if list.is_empty() { vec![default] } else { list.clone() };
+        let (hours, minutes, seconds) = (or(&self.by_hour, time / 3600), or(&self.by_minute, time / 60 % 60), or(&self.by_second, time % 60));
+        let mut walls = vec![];
+        for day in days {
+            for h in &hours {
+                for m in &minutes {
+                    walls.extend(seconds.iter().map(|s| day * 86400 + *h as i64 * 3600 + *m as i64 * 60 + *s as i64));
+                }
+            }
+        }
+        walls.sort();
+        walls.dedup();
+        if !self.by_set_pos.is_empty() {
+            let mut picked: Vec<i64> = self.by_set_pos.iter().filter_map(|pos| match *pos {
+                n if n > 0 => walls.get(n as usize - 1).copied(),
+                n          => walls.len().checked_sub(n.unsigned_abs() as usize).map(|i| walls[i]),
+            }).collect();
+            picked.sort();
+            picked.dedup();
+            walls = picked;
+        }
+        (walls, year)
+    }
+}
+
+/// The occurrences of an [`RRule`], from [`RRule::expand`].
+pub struct Occurrences<'a> {
+    rule: &'a RRule,
+    tz: &'a Timezone,
+    start: i64, // dtstart's wall time
+    period: i64,
+    pending: VecDeque<TimeT>,
+    emitted: u32,
+    done: bool,
+}
+
+impl Occurrences<'_> {
+    fn fill(&mut self) {
+        while self.pending.is_empty() && !self.done {
+            let (walls, year) = self.rule.period_walls(self.start, self.period);
+            self.period += 1;
+            if year > LAST_YEAR {
+                self.done = true;
+            }
+            for wall in walls.into_iter().filter(|wall| *wall > self.start) {
+                if matches!(self.rule.until, Some((until, false)) if wall > until) {
+                    self.done = true;
+                    break;
+                }
+                match self.tz.time_for_wall_forward(wall).ok() {
+                    Some(time) if matches!(self.rule.until, Some((until, true)) if time > until) => { self.done = true; break },
+                    Some(time) => self.pending.push_back(time),
+                    None       => { self.done = true; break },
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for Occurrences<'_> {
+    type Item = TimeT;
+    fn next(&mut self) -> Option<TimeT> {
+        if self.rule.count.is_some_and(|count| self.emitted >= count) {
+            return None;
+        }
+        self.fill();
+        let time = self.pending.pop_front()?;
+        self.emitted += 1;
+        Some(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tm;
+
+    fn local(tz: &Timezone, y: i32, mo: i32, d: i32, h: i32, mi: i32) -> TimeT {
+        tz.mktime(&Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(y, mo, d, h, mi, 0) }).expect("mktime")
+    }
+
+    fn dates(tz: &Timezone, rule: &str, start: TimeT, n: usize) -> Vec<(i32, i32, i32, i32, i32)> {
+        rule.parse::<RRule>().expect("parse").expand(tz, start).take(n)
+            .map(|t| tz.localtime(t).expect("localtime"))
+            .map(|tm| (tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min)).collect()
+    }
+
+    #[test]
+    fn expand() {
+        let ny = Timezone::new("America/New_York").expect("timezone alloc");
+        // RFC 5545 section 3.8.5.3 examples.
+        let start = local(&ny, 1997, 9, 2, 9, 0);
+        assert_eq!(dates(&ny, "FREQ=DAILY;COUNT=3", start, 100), [(1997, 9, 2, 9, 0), (1997, 9, 3, 9, 0), (1997, 9, 4, 9, 0)]);
+        assert_eq!(dates(&ny, "FREQ=WEEKLY;UNTIL=19971007T000000Z;WKST=SU;BYDAY=TU,TH", start, 100).len(), 10);
+        assert_eq!(dates(&ny, "FREQ=MONTHLY;COUNT=4;BYDAY=1FR", local(&ny, 1997, 9, 5, 9, 0), 100),
+                   [(1997, 9, 5, 9, 0), (1997, 10, 3, 9, 0), (1997, 11, 7, 9, 0), (1997, 12, 5, 9, 0)]);
+        assert_eq!(dates(&ny, "FREQ=MONTHLY;BYMONTHDAY=-2;COUNT=3", start, 100), [(1997, 9, 2, 9, 0), (1997, 9, 29, 9, 0), (1997, 10, 30, 9, 0)]);
+        assert_eq!(dates(&ny, "FREQ=YEARLY;BYDAY=20MO", local(&ny, 1997, 5, 19, 9, 0), 3), [(1997, 5, 19, 9, 0), (1998, 5, 18, 9, 0), (1999, 5, 17, 9, 0)]);
+        assert_eq!(dates(&ny, "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1", local(&ny, 1997, 9, 30, 9, 0), 3),
+                   [(1997, 9, 30, 9, 0), (1997, 10, 31, 9, 0), (1997, 11, 28, 9, 0)]);
+        assert_eq!(dates(&ny, "FREQ=DAILY;BYHOUR=9,10,11;BYMINUTE=0,30;COUNT=4", start, 100),
+                   [(1997, 9, 2, 9, 0), (1997, 9, 2, 9, 30), (1997, 9, 2, 10, 0), (1997, 9, 2, 10, 30)]);
+        assert_eq!(dates(&ny, "FREQ=YEARLY;INTERVAL=4;BYMONTH=11;BYDAY=TU;BYMONTHDAY=2,3,4,5,6,7,8", local(&ny, 1996, 11, 5, 9, 0), 3),
+                   [(1996, 11, 5, 9, 0), (2000, 11, 7, 9, 0), (2004, 11, 2, 9, 0)]);
+
+        // The local time stays put across DST changes, and a skipped time moves forward.
+        assert_eq!(dates(&ny, "FREQ=DAILY", local(&ny, 2023, 3, 11, 2, 30), 2), [(2023, 3, 11, 2, 30), (2023, 3, 12, 3, 30)]);
+        let leap = dates(&ny, "FREQ=YEARLY", local(&ny, 2024, 2, 29, 12, 0), 2);
+        assert_eq!(leap, [(2024, 2, 29, 12, 0), (2028, 2, 29, 12, 0)]);
+        assert_eq!(dates(&ny, "FREQ=MONTHLY;UNTIL=19971231", start, 100).len(), 4);
+        assert_eq!("FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=30".parse::<RRule>().expect("parse").expand(&ny, start).count(), 1);
+
+        for bad in ["COUNT=3", "FREQ=HOURLY", "FREQ=DAILY;BYWEEKNO=1", "FREQ=DAILY;COUNT=1;UNTIL=19970101T000000Z",
+                    "FREQ=WEEKLY;BYDAY=1MO", "FREQ=DAILY;INTERVAL=0", "FREQ=DAILY;BYMONTH=13", "FREQ=WEEKLY;BYDAY=ÄA"] {
+            assert!(bad.parse::<RRule>().is_err(), "{:?} should fail", bad);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+// serde adapters for `TimeT` fields.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+//! Modules for `#[serde(with = "...")]` on [`TimeT`](crate::TimeT) fields (the `serde` feature), so a struct
+//! can pick the wire format of each timestamp without wrapper types:
+//!
+//! ```
+//! use serde::{Serialize, Deserialize};
+//! #[derive(Serialize, Deserialize)]
+//! struct Event {
+//!     #[serde(with = "libtz::serde::rfc3339_utc")]
+//!     start: libtz::TimeT,
+//!     #[serde(with = "libtz::serde::ts_millis")]
+//!     updated: libtz::TimeT,
+//! }
+//! let json = serde_json::to_string(&Event{ start: 1700000000, updated: 1700000000 }).unwrap();
+//! assert_eq!(json, r#"{"start":"2023-11-14T22:13:20Z","updated":1700000000000}"#);
+//! ```
+
+/// Seconds since 1970-01-01 00:00:00 UTC, as an integer.
+pub mod ts_seconds {
+    use ::serde::{Deserialize, Deserializer, Serializer};
+    use crate::TimeT;
+
+    pub fn serialize<S: Serializer>(time: &TimeT, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(*time)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TimeT, D::Error> {
+        TimeT::deserialize(deserializer)
+    }
+}
+
+/// Milliseconds since 1970-01-01 00:00:00 UTC, as an integer (like JavaScript's `Date.now()`). Fractions of a
+/// second are dropped when deserializing (rounding down, so times before 1970 stay in the right second).
+pub mod ts_millis {
+    use ::serde::{Deserialize, Deserializer, Serializer, ser::Error};
+    use crate::TimeT;
+
+    pub fn serialize<S: Serializer>(time: &TimeT, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(time.checked_mul(1000).ok_or_else(|| S::Error::custom(format!("Time {} is out of range for milliseconds", time)))?)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TimeT, D::Error> {
+        Ok(i64::deserialize(deserializer)?.div_euclid(1000))
+    }
+}
+
+// The RFC 3339 timestamp for `tm`, which has to be in years 0 to 9999.
+fn rfc3339(tm: &crate::Tm) -> Result<String, String> {
+    if !(0..=9999).contains(&tm.astronomical_year()) {
+        return Err(format!("Year {} can't be written in RFC 3339", tm.astronomical_year()));
+    }
+    Ok(tm.strftime("%Y-%m-%dT%H:%M:%S%:z").replace("+00:00", "Z"))
+}
+
+// Any RFC 3339 timestamp, with any offset. Fractions of a second are dropped.
+fn parse_rfc3339<'de, D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<crate::TimeT, D::Error> {
+    use ::serde::{Deserialize, de::Error};
+    let s = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+    if s.contains('[') {
+        return Err(D::Error::custom(format!("Invalid RFC 3339 timestamp {:?}", s)));
+    }
+    crate::parse_ixdtf(&s).map(|ts| ts.time.sec).map_err(D::Error::custom)
+}
+
+/// An RFC 3339 string in UTC (`2023-11-14T22:13:20Z`). Deserializing takes any offset.
+pub mod rfc3339_utc {
+    use ::serde::{Deserializer, Serializer, ser::Error};
+    use crate::TimeT;
+
+    pub fn serialize<S: Serializer>(time: &TimeT, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&crate::gmtime(*time).and_then(|tm| super::rfc3339(&tm)).map_err(S::Error::custom)?)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TimeT, D::Error> {
+        super::parse_rfc3339(deserializer)
+    }
+}
+
+/// An RFC 3339 string with the local time and offset in the [`default_timezone`](crate::default_timezone)
+/// (`2023-11-14T17:13:20-05:00`), for output people read. Use
+/// [`with_default_timezone`](crate::with_default_timezone) to pick the zone for a particular serialization.
+/// Deserializing takes any offset.
+pub mod rfc3339_in_zone {
+    use ::serde::{Deserializer, Serializer, ser::Error};
+    use crate::TimeT;
+
+    pub fn serialize<S: Serializer>(time: &TimeT, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&crate::default_timezone().localtime(*time).and_then(|tm| super::rfc3339(&tm)).map_err(S::Error::custom)?)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TimeT, D::Error> {
+        super::parse_rfc3339(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::serde::{Serialize, Deserialize};
+    use crate::{TimeT, Timezone, with_default_timezone};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Times {
+        #[serde(with = "crate::serde::ts_seconds")]      seconds: TimeT,
+        #[serde(with = "crate::serde::ts_millis")]       millis: TimeT,
+        #[serde(with = "crate::serde::rfc3339_utc")]     utc: TimeT,
+        #[serde(with = "crate::serde::rfc3339_in_zone")] local: TimeT,
+    }
+
+    #[test]
+    fn serde() {
+        let times = |t| Times{ seconds: t, millis: t, utc: t, local: t };
+        let json = with_default_timezone(Timezone::new("America/New_York").expect("timezone alloc"), || serde_json::to_string(&times(1700000000)).expect("serialize"));
+        assert_eq!(json, r#"{"seconds":1700000000,"millis":1700000000000,"utc":"2023-11-14T22:13:20Z","local":"2023-11-14T17:13:20-05:00"}"#);
+        assert_eq!(serde_json::from_str::<Times>(&json).expect("deserialize"), times(1700000000));
+        let json = with_default_timezone(Timezone::new("UTC").expect("timezone alloc"), || serde_json::to_string(&times(-1)).expect("serialize"));
+        assert_eq!(json, r#"{"seconds":-1,"millis":-1000,"utc":"1969-12-31T23:59:59Z","local":"1969-12-31T23:59:59Z"}"#);
+
+        let parsed: Times = serde_json::from_str(r#"{"seconds":0,"millis":-1,"utc":"2023-11-14T23:13:20.75+01:00","local":"2023-11-14t22:13:20z"}"#).expect("deserialize");
+        assert_eq!(parsed, Times{ seconds: 0, millis: -1, utc: 1700000000, local: 1700000000 });
+        for bad in [r#""2023-11-14T22:13:20""#, r#""2023-11-14T22:13:20Z[UTC]""#, "1700000000"] {
+            assert!(serde_json::from_str::<Times>(&format!(r#"{{"seconds":0,"millis":0,"utc":{},"local":"2023-11-14T22:13:20Z"}}"#, bad)).is_err(), "{}", bad);
+        }
+        assert!(serde_json::to_string(&times(TimeT::MAX / 100)).is_err());
+        assert!(serde_json::to_string(&Times{ utc: 300000000000, ..times(0) }).is_err()); // Year 11476
+    }
+}
@@ -0,0 +1,246 @@
+// Zones compiled into the program as static tables.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use alloc::{format, string::{String, ToString}, vec::Vec};
+use core::fmt::Write;
+use crate::{Timezone, ZoneInfo, Transition, LocalTimeType, LeapSecond};
+use crate::posix::{PosixTz, PosixDst, PosixRuleDate, PosixRuleDay};
+
+/// A zone's compiled data as static tables, for firmware that includes exactly the zones it needs: the Rust
+/// source for one comes from [`ZoneInfo::to_rust`] (or `tz codegen <zone>...`), typically run by hand or from a
+/// build script. Unlike [`Timezone::from_tzif`] there's no TZif data to decode and check at run time, and the
+/// footer rule is parsed ahead of time too: with the Rust backend (and so without `std`) the tables are used where
+/// they are, without being copied. libtz itself can only load files, so with the C backend [`Timezone::from_static`]
+/// writes the zone out as TZif and loads that.
+///
+/// ```
+/// use libtz::{StaticZone, StaticLocalTimeType, StaticPosixTz, Transition, Timezone};
+/// // What `tz codegen` writes, for a zone that jumps across the date line.
+/// static JUMP: StaticZone = StaticZone {
+///     name: "Example/Jump",
+///     version: 2,
+///     transitions: &[Transition{ time: 1325239200, local_time_type: 1 }],
+///     types: &[StaticLocalTimeType{ utoff: -36000, isdst: false, abbr: "-10", is_std: false, is_ut: false },
+///              StaticLocalTimeType{ utoff: 50400, isdst: false, abbr: "+14", is_std: false, is_ut: false }],
+///     leap_seconds: &[],
+///     footer: Some(StaticPosixTz{ std_abbr: "+14", std_offset: 50400, dst: None }),
+/// };
+/// let tz = Timezone::from_static(&JUMP).unwrap();
+/// assert_eq!(tz.localtime(1325239199).unwrap().strftime("%F %T %Z"), "2011-12-29 23:59:59 -10"); // December 30th is skipped
+/// assert_eq!(tz.localtime(1325239200).unwrap().strftime("%F %T %Z"), "2011-12-31 00:00:00 +14");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StaticZone {
+    /** Zone name */                                pub name         : &'static str,
+    /** TZif format version (1 to 4) */             pub version      : u8,
+    /** Transitions, in time order */               pub transitions  : &'static [Transition],
+    /** Local time types */                         pub types        : &'static [StaticLocalTimeType],
+    /** Leap second corrections, in time order */   pub leap_seconds : &'static [LeapSecond],
+    /** POSIX TZ rule for times after the last transition */ pub footer : Option<StaticPosixTz>,
+}
+
+/// A [`LocalTimeType`] that can go in a `static`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StaticLocalTimeType {
+    /** Seconds east of UT */                       pub utoff  : i32,
+    /** Daylight saving time flag */                pub isdst  : bool,
+    /** Abbreviation */                             pub abbr   : &'static str,
+    /** Transition times were standard time */      pub is_std : bool,
+    /** Transition times were UT */                 pub is_ut  : bool,
+}
+
+/// A parsed POSIX TZ rule (like `CET-1CEST,M3.5.0,M10.5.0/3`) that can go in a `static`. Offsets are seconds
+/// *east* of UT, the opposite sign of how they are written in the rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StaticPosixTz {
+    /** Standard time abbreviation */               pub std_abbr   : &'static str,
+    /** Standard time's seconds east of UT */       pub std_offset : i32,
+    /** Daylight saving time, if the rule has it */ pub dst        : Option<StaticPosixDst>,
+}
+
+/// The daylight saving part of a [`StaticPosixTz`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StaticPosixDst {
+    /** Abbreviation */                             pub abbr   : &'static str,
+    /** Seconds east of UT */                       pub offset : i32,
+    /** When DST starts and ends (`None` uses the US rules, as libtz does) */ pub rule : Option<(PosixRuleDate, PosixRuleDate)>,
+}
+
+impl StaticZone {
+    /// The same data as a [`ZoneInfo`].
+    pub fn zone_info(&self) -> ZoneInfo {
+        ZoneInfo{ version: self.version,
+                  transitions: self.transitions.to_vec(),
+                  types: self.types.iter().map(|t| LocalTimeType{ utoff: t.utoff, isdst: t.isdst, abbr: t.abbr.to_string(), is_std: t.is_std, is_ut: t.is_ut })
+                                          .collect(),
+                  leap_seconds: self.leap_seconds.to_vec(),
+                  footer: self.footer.map(|f| f.posix_tz().to_string()) }
+    }
+
+    /// The earliest and latest transition times, or `None` if there are none (see [`ZoneInfo::data_range`]).
+    pub fn data_range(&self) -> Option<(crate::TimeT, crate::TimeT)> {
+        Some((self.transitions.first()?.time, self.transitions.last()?.time))
+    }
+
+    // See ZoneInfo::abbreviations_chronological().
+    pub(crate) fn abbreviations_chronological(&self) -> Vec<&'static str> {
+        crate::tzif::abbreviations_chronological(self.transitions, self.types.len(), |i| self.types[i].abbr)
+    }
+}
+
+impl StaticPosixTz {
+    pub(crate) fn posix_tz(&self) -> PosixTz {
+        PosixTz{ std_abbr: self.std_abbr.to_string(), std_offset: self.std_offset,
+                 dst: self.dst.map(|dst| PosixDst{ abbr: dst.abbr.to_string(), offset: dst.offset, rule: dst.rule }) }
+    }
+}
+
+impl Timezone {
+    /// Create a [`Timezone`] from a zone compiled into the program (see [`StaticZone`]).
+    pub fn from_static(zone: &'static StaticZone) -> Result<Timezone, String> {
+        Timezone::from_static_zone(zone)
+    }
+}
+
+// Rust source for a footer rule, as a StaticPosixTz.
+fn rust_posix_tz(tz: &PosixTz) -> String {
+    let date = |d: &PosixRuleDate| {
+        let day = match d.day {
+            PosixRuleDay::Julian1(n) => format!("Julian1({})", n),
+            PosixRuleDay::Julian0(n) => format!("Julian0({})", n),
+            PosixRuleDay::MonthWeekDay{ month, week, weekday } => format!("MonthWeekDay{{ month: {}, week: {}, weekday: {} }}", month, week, weekday),
+        };
+        format!("libtz::PosixRuleDate{{ day: libtz::PosixRuleDay::{}, time: {} }}", day, d.time)
+    };
+    let dst = match tz.dst {
+        None => "None".to_string(),
+        Some(ref dst) => {
+            let rule = dst.rule.map_or("None".to_string(), |(start, end)| format!("Some(({}, {}))", date(&start), date(&end)));
+            format!("Some(libtz::StaticPosixDst{{ abbr: {:?}, offset: {}, rule: {} }})", dst.abbr, dst.offset, rule)
+        },
+    };
+    format!("libtz::StaticPosixTz{{ std_abbr: {:?}, std_offset: {}, dst: {} }}", tz.std_abbr, tz.std_offset, dst)
+}
+
+impl ZoneInfo {
+    /// Rust source for a `static` [`StaticZone`] called `ident` holding this data, for the zone `name`.
+    ///
+    /// ```
+    /// let zi = libtz::Timezone::new("UTC0").unwrap().zone_info().unwrap().clone();
+    /// let source = zi.to_rust("Etc/UTC", "UTC").unwrap();
+    /// assert!(source.starts_with("pub static UTC: libtz::StaticZone = libtz::StaticZone {\n    name: \"Etc/UTC\","));
+    /// ```
+    pub fn to_rust(&self, name: &str, ident: &str) -> Result<String, String> {
+        let valid = ident.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') && ident.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!("{:?} isn't a Rust identifier", ident));
+        }
+        if let Some(bad) = self.transitions.iter().find(|t| t.local_time_type >= self.types.len()) {
+            return Err(format!("transition at {} uses nonexistent local time type {}", bad.time, bad.local_time_type));
+        }
+        let footer = self.footer.as_deref().map(str::parse::<PosixTz>).transpose().map_err(|e| format!("bad footer: {}", e))?;
+        // Debug formatting of strings gives valid Rust string literals.
+        let mut out = String::new();
+        let _ = writeln!(out, "pub static {}: libtz::StaticZone = libtz::StaticZone {{", ident);
+        let _ = writeln!(out, "    name: {:?},", name);
+        let _ = writeln!(out, "    version: {},", self.version);
+        let _ = writeln!(out, "    transitions: &[");
+        for t in self.transitions.iter() {
+            let _ = writeln!(out, "        libtz::Transition{{ time: {}, local_time_type: {} }},", t.time, t.local_time_type);
+        }
+        let _ = writeln!(out, "    ],\n    types: &[");
+        for t in self.types.iter() {
+            let _ = writeln!(out, "        libtz::StaticLocalTimeType{{ utoff: {}, isdst: {}, abbr: {:?}, is_std: {}, is_ut: {} }},", t.utoff, t.isdst, t.abbr, t.is_std, t.is_ut);
+        }
+        let _ = writeln!(out, "    ],\n    leap_seconds: &[");
+        for l in self.leap_seconds.iter() {
+            let _ = writeln!(out, "        libtz::LeapSecond{{ time: {}, correction: {} }},", l.time, l.correction);
+        }
+        let _ = writeln!(out, "    ],\n    footer: {},\n}};", footer.as_ref().map_or("None".to_string(), |f| format!("Some({})", rust_posix_tz(f))));
+        Ok(out)
+    }
+}
+
+/// The identifier [`generate_static_zones`] uses for a zone: its name in upper case with anything that can't be
+/// in an identifier replaced by `_` (`America/Port-au-Prince` is `AMERICA_PORT_AU_PRINCE`).
+pub fn static_zone_ident(name: &str) -> String {
+    let ident: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) { format!("_{}", ident) } else { ident }
+}
+
+/// Rust source defining a `static` [`StaticZone`] for each of the installed zones `names` (named by
+/// [`static_zone_ident`]), for writing to a file that firmware `include!()`s. A build script can call this
+/// (with `libtz` as a build dependency) to keep the tables in step with the build machine's tzdata.
+///
+/// ```
+/// let source = libtz::generate_static_zones(&["Europe/Berlin", "Asia/Kolkata"]).unwrap();
+/// assert!(source.contains("pub static EUROPE_BERLIN: libtz::StaticZone"));
+/// assert!(source.contains("footer: Some(libtz::StaticPosixTz{ std_abbr: \"IST\", std_offset: 19800, dst: None }),"));
+/// ```
+#[cfg(feature = "std")]
+pub fn generate_static_zones(names: &[&str]) -> Result<String, String> {
+    let mut out = format!("// Generated by libtz {} from tzdata. Don't edit.\n", env!("CARGO_PKG_VERSION"));
+    let mut idents: alloc::vec::Vec<String> = alloc::vec![];
+    for name in names {
+        let ident = static_zone_ident(name);
+        if idents.contains(&ident) {
+            return Err(format!("{} and another zone would both be {}", name, ident));
+        }
+        let tz = crate::Timezone::builder().fallback(crate::Fallback::Error).build(name)?;
+        out.push('\n');
+        out.push_str(&tz.zone_info()?.to_rust(name, &ident)?);
+        idents.push(ident);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{boxed::Box, vec};
+
+    #[test]
+    fn static_zone() {
+        let tz = crate::Timezone::from_tzif("Test/Zone", &std::fs::read("/usr/share/zoneinfo/Europe/Dublin").expect("read")).expect("from_tzif");
+        let zi = tz.zone_info().expect("zone_info").clone();
+        // What the generated code would give.
+        let types: Vec<StaticLocalTimeType> = zi.types.iter().map(|t| StaticLocalTimeType{ utoff: t.utoff, isdst: t.isdst, abbr: Box::leak(t.abbr.clone().into_boxed_str()),
+                                                                                            is_std: t.is_std, is_ut: t.is_ut }).collect();
+        let leak = |s: &str| -> &'static str { Box::leak(s.to_string().into_boxed_str()) };
+        let footer: PosixTz = zi.footer.as_deref().expect("footer").parse().expect("parse footer");
+        let footer = StaticPosixTz{ std_abbr: leak(&footer.std_abbr), std_offset: footer.std_offset,
+                                    dst: footer.dst.map(|dst| StaticPosixDst{ abbr: leak(&dst.abbr), offset: dst.offset, rule: dst.rule }) };
+        let zone: &'static StaticZone = Box::leak(Box::new(StaticZone{ name: "Europe/Dublin", version: zi.version, transitions: Box::leak(zi.transitions.clone().into_boxed_slice()),
+                                                                       types: Box::leak(types.into_boxed_slice()), leap_seconds: &[], footer: Some(footer) }));
+        assert_eq!(zone.zone_info(), zi);
+        assert_eq!(zone.data_range(), zi.data_range());
+        assert_eq!(zone.abbreviations_chronological(), zi.abbreviations_chronological());
+        let from_static = Timezone::from_static(zone).expect("from_static");
+        #[cfg(feature = "std")]
+        assert_eq!((from_static.provenance(), from_static.zone_info()), (&crate::Provenance::Static(zone), Ok(&zi)));
+        for time in [-2000000000, 0, 1700000000, 4000000000] {
+            let show = |tm: crate::Tm| (tm.strftime("%F %T %Z"), tm.tm_gmtoff, tm.tm_isdst);
+            assert_eq!(from_static.localtime(time).map(show), tz.localtime(time).map(show));
+        }
+
+        let source = zi.to_rust("Europe/Dublin", "DUBLIN").expect("to_rust");
+        assert!(source.contains(&format!("libtz::Transition{{ time: {}, local_time_type: {} }},\n", zi.transitions[0].time, zi.transitions[0].local_time_type)));
+        assert!(source.contains("libtz::StaticLocalTimeType{ utoff: 3600, isdst: false, abbr: \"IST\", is_std: false, is_ut: false },"));
+        assert!(source.ends_with("    footer: Some(libtz::StaticPosixTz{ std_abbr: \"IST\", std_offset: 3600, dst: Some(libtz::StaticPosixDst{ abbr: \"GMT\", offset: 0, \
+                                  rule: Some((libtz::PosixRuleDate{ day: libtz::PosixRuleDay::MonthWeekDay{ month: 10, week: 5, weekday: 0 }, time: 7200 }, \
+                                  libtz::PosixRuleDate{ day: libtz::PosixRuleDay::MonthWeekDay{ month: 3, week: 5, weekday: 0 }, time: 3600 })) }) }),\n};\n"));
+        assert!(zi.to_rust("Europe/Dublin", "2DUBLIN").is_err());
+        assert!(ZoneInfo{ footer: Some("1".to_string()), ..zi.clone() }.to_rust("X", "X").is_err());
+        assert!(ZoneInfo{ transitions: vec![Transition{ time: 0, local_time_type: 99 }], ..zi }.to_rust("X", "X").is_err());
+
+        assert_eq!(static_zone_ident("America/Port-au-Prince"), "AMERICA_PORT_AU_PRINCE");
+        assert_eq!(static_zone_ident("3Zone"), "_3ZONE");
+        #[cfg(feature = "std")]
+        {
+            assert!(generate_static_zones(&["Etc/GMT+5", "Etc/GMT-5"]).is_err()); // Both ETC_GMT_5
+            assert!(generate_static_zones(&["Not/A_Zone"]).is_err());
+        }
+    }
+}
@@ -0,0 +1,654 @@
+// A pure Rust stand-in for libtz-sys.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+//! A pure Rust implementation of the parts of [`libtz-sys`](https://docs.rs/libtz-sys) this crate uses, with
+//! the same types and function signatures. It's used instead of libtz when the `rust-backend` feature is
+//! enabled.
+//!
+//! Zone names are resolved the same way (see [`Provenance`][crate::Provenance]) and conversions give the same
+//! results as libtz, including leap seconds in the `right/` zones and libtz's handling of `tm_isdst` in
+//! `mktime_z()`. One difference: a POSIX TZ rule with daylight saving time but no rule for when it starts and
+//! ends (like `AAA3BBB`) uses the current US rules, where libtz would use the `posixrules` file.
+
+use core::ffi::{c_char, c_int, c_long, c_void};
+#[cfg(feature = "std")]
+use core::ffi::CStr;
+use alloc::{boxed::Box, ffi::CString, string::{String, ToString}, vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+use crate::{ZoneInfo, StaticZone, Transition, LeapSecond, calendar};
+use crate::posix::{PosixTz, PosixRuleDate, PosixRuleDay, rule_day};
+
+/// The system time type (seconds since the epoch).
+pub type TimeT = i64;
+
+/// Binary compatible with libtz's `struct tm`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Tm {
+    /** Seconds          [0, 60] */                 pub tm_sec   : c_int,
+    /** Minutes          [0, 59] */                 pub tm_min   : c_int,
+    /** Hour             [0, 23] */                 pub tm_hour  : c_int,
+    /** Day of the month [1, 31] */                 pub tm_mday  : c_int,
+    /** Month            [0, 11]  (January = 0) */  pub tm_mon   : c_int,
+    /** Year minus 1900 */                          pub tm_year  : c_int,
+    /** Day of the week  [0, 6]   (Sunday = 0) */   pub tm_wday  : c_int,
+    /** Day of the year  [0, 365] (Jan/01 = 0) */   pub tm_yday  : c_int,
+    /** Daylight savings flag */                    pub tm_isdst : c_int,
+    /** Seconds East of UTC */                      pub tm_gmtoff : c_long,
+    /** Timezone abbreviation */                    pub tm_zone: *const c_char,
+}
+
+/// An opaque pointer to a loaded zone.
+pub type TimezoneT = *const c_void;
+
+// The US rules, which libtz uses (via its posixrules file) for POSIX TZ strings without a rule.
+const DEFAULT_RULE: (PosixRuleDate, PosixRuleDate) = (PosixRuleDate{ day: PosixRuleDay::MonthWeekDay{ month: 3, week: 2, weekday: 0 }, time: 7200 },
+                                            PosixRuleDate{ day: PosixRuleDay::MonthWeekDay{ month: 11, week: 1, weekday: 0 }, time: 7200 });
+
+// An offset in effect: (UT offset, isdst, abbreviation index).
+type State = (i64, bool, usize);
+
+// A zone's tables: parsed at run time, or compiled into the program and used where they are.
+enum Tables {
+    Owned(ZoneInfo),
+    Static(&'static StaticZone),
+}
+
+impl Tables {
+    fn transitions(&self) -> &[Transition] {
+        match self { Tables::Owned(info) => &info.transitions, Tables::Static(zone) => zone.transitions }
+    }
+
+    fn leap_seconds(&self) -> &[LeapSecond] {
+        match self { Tables::Owned(info) => &info.leap_seconds, Tables::Static(zone) => zone.leap_seconds }
+    }
+
+    fn type_count(&self) -> usize {
+        match self { Tables::Owned(info) => info.types.len(), Tables::Static(zone) => zone.types.len() }
+    }
+
+    // Local time type `index`: (UT offset, isdst, abbreviation).
+    fn local_type(&self, index: usize) -> (i32, bool, &str) {
+        match self {
+            Tables::Owned(info)  => { let t = &info.types[index]; (t.utoff, t.isdst, &t.abbr) },
+            Tables::Static(zone) => { let t = &zone.types[index]; (t.utoff, t.isdst, t.abbr) },
+        }
+    }
+}
+
+struct Zone {
+    tables: Tables,
+    footer: Option<PosixTz>,
+    abbrs: Vec<CString>,
+    type_abbrs: Vec<usize>,
+    footer_abbrs: (usize, usize), // (std, dst)
+    types: Vec<(i64, bool, bool)>,  // libtz's local time types: (UT offset, isdst, unspecified)
+    seen: Vec<(i64, bool)>,         // libtz's transition types, most recent first
+}
+
+impl Zone {
+    #[cfg(feature = "std")]
+    fn load(name: Option<&CStr>) -> Result<Zone, String> {
+        use crate::Provenance;
+        Zone::new(match Provenance::resolve(name) {
+            Provenance::Utc => utc_info(),
+            Provenance::SystemDefault(path) | Provenance::File(path) => ZoneInfo::load(&path)?,
+            Provenance::PosixRule(rule) | Provenance::Synthesized(rule) => ZoneInfo::from_posix(&rule.parse()?),
+            Provenance::AndroidTzdata(path, zone) => ZoneInfo::parse(&crate::android::read(&path, &zone)?)?,
+            Provenance::Static(zone) => return Zone::new_static(zone),
+        })
+    }
+
+    fn new(info: ZoneInfo) -> Result<Zone, String> {
+        let footer: Option<PosixTz> = info.footer.as_deref().filter(|f| !f.is_empty()).map(str::parse).transpose()?;
+        Zone::with_tables(Tables::Owned(info), footer)
+    }
+
+    // The footer was parsed when the static tables were generated, so there's nothing to parse here.
+    fn new_static(zone: &'static StaticZone) -> Result<Zone, String> {
+        Zone::with_tables(Tables::Static(zone), zone.footer.map(|f| f.posix_tz()))
+    }
+
+    fn with_tables(tables: Tables, footer: Option<PosixTz>) -> Result<Zone, String> {
+        let mut abbrs: Vec<CString> = vec![];
+        let mut intern = |abbr: &str| -> Result<usize, String> {
+            let abbr = CString::new(abbr).map_err(|_| "abbreviation has internal null byte".to_string())?;
+            Ok(abbrs.iter().position(|a| *a == abbr).unwrap_or_else(|| { abbrs.push(abbr); abbrs.len() - 1 }))
+        };
+        let type_abbrs = (0..tables.type_count()).map(|i| intern(tables.local_type(i).2)).collect::<Result<Vec<_>, _>>()?;
+        let footer_abbrs = match footer {
+            Some(ref f) => (intern(&f.std_abbr)?, f.dst.as_ref().map_or(Ok(0), |dst| intern(&dst.abbr))?),
+            None        => (0, 0),
+        };
+        let mut zone = Zone{ tables, footer, abbrs, type_abbrs, footer_abbrs, types: vec![], seen: vec![] };
+        zone.types = zone.all_types();
+        zone.seen = zone.seen_types();
+        Ok(zone)
+    }
+
+    fn type_state(&self, index: usize) -> State {
+        let (utoff, isdst, _) = self.tables.local_type(index);
+        (utoff as i64, isdst, self.type_abbrs[index])
+    }
+
+    // The POSIX rule's transitions in years around `year`, in order: (time, isdst). Only for rules with DST.
+    fn footer_transitions(footer: &PosixTz, year: i64) -> [(TimeT, bool); 6] {
+        let Some(ref dst) = footer.dst else { return [(TimeT::MAX, false); 6] };
+        let (start, end) = dst.rule.unwrap_or(DEFAULT_RULE);
+        let mut transitions = [(0, false); 6];
+        for (i, year) in (year - 1..=year + 1).enumerate() {
+            // Saturating, since times near the ends of TimeT land in years whose seconds don't fit.
+            transitions[i * 2] = (rule_day(year, &start.day).saturating_mul(86400).saturating_add(start.time as i64 - footer.std_offset as i64), true);
+            transitions[i * 2 + 1] = (rule_day(year, &end.day).saturating_mul(86400).saturating_add(end.time as i64 - dst.offset as i64), false);
+        }
+        // On a tie the start wins, so DST all year (like "EST5EDT,0/0,J365/25") works.
+        transitions.sort_by_key(|(time, isdst)| (*time, *isdst));
+        transitions
+    }
+
+    fn footer_state(&self, footer: &PosixTz, time: TimeT) -> State {
+        let std = (footer.std_offset as i64, false, self.footer_abbrs.0);
+        let Some(ref dst) = footer.dst else { return std };
+        let year = calendar::civil_from_days(time.saturating_add(footer.std_offset as i64).div_euclid(86400)).0;
+        match Zone::footer_transitions(footer, year).into_iter().rev().find(|(t, _)| *t <= time) {
+            Some((_, true)) => (dst.offset as i64, true, self.footer_abbrs.1),
+            _               => std,
+        }
+    }
+
+    fn state(&self, time: TimeT) -> State {
+        let transitions = self.tables.transitions();
+        if let Some(ref footer) = self.footer {
+            if transitions.last().is_none_or(|last| time >= last.time) {
+                return self.footer_state(footer, time);
+            }
+        }
+        match transitions.partition_point(|t| t.time <= time) {
+            0 => self.type_state(0),
+            i => self.type_state(transitions[i - 1].local_time_type),
+        }
+    }
+
+    // The leap second correction at `time` and whether `time` is itself a leap second.
+    fn leap_correction(&self, time: TimeT) -> (i64, bool) {
+        let leaps = self.tables.leap_seconds();
+        match leaps.partition_point(|l| l.time <= time) {
+            0 => (0, false),
+            i => {
+                let previous = if i > 1 { leaps[i - 2].correction } else { 0 };
+                (leaps[i - 1].correction as i64, time == leaps[i - 1].time && previous < leaps[i - 1].correction)
+            },
+        }
+    }
+
+    fn localtime(&self, time: TimeT) -> Option<Tm> {
+        let (utoff, isdst, abbr) = self.state(time);
+        let (correction, hit) = self.leap_correction(time);
+        let mut tm = breakdown(time.checked_add(utoff)?.checked_sub(correction)?, utoff, self.abbrs[abbr].as_ptr())?;
+        tm.tm_sec += hit as c_int;
+        tm.tm_isdst = isdst as c_int;
+        Some(tm)
+    }
+
+    // The local time types in libtz's order: the file's, then the footer's (which libtz always appends).
+    fn all_types(&self) -> Vec<(i64, bool, bool)> { // (UT offset, isdst, unspecified)
+        let mut types: Vec<_> = (0..self.tables.type_count()).map(|i| self.tables.local_type(i)).map(|(utoff, isdst, abbr)| (utoff as i64, isdst, abbr == "-00")).collect();
+        if let Some(ref footer) = self.footer {
+            let std = (footer.std_offset as i64, false, footer.std_abbr == "-00");
+            match footer.dst {
+                None                                  => types.push(std),
+                Some(ref dst) if self.perpetual_dst() => types.push((dst.offset as i64, true, false)),
+                Some(ref dst)                         => types.extend([std, (dst.offset as i64, true, false)]),
+            }
+        }
+        types
+    }
+
+    // Whether the footer is DST all year, which libtz represents with just the DST type and no transitions.
+    fn perpetual_dst(&self) -> bool {
+        let Some(ref footer) = self.footer else { return false };
+        let Some(ref dst) = footer.dst else { return false };
+        let (start, end) = dst.rule.unwrap_or(DEFAULT_RULE);
+        let start = rule_day(2001, &start.day) * 86400 + start.time as i64 - footer.std_offset as i64;
+        let end = rule_day(2001, &end.day) * 86400 + end.time as i64 - dst.offset as i64;
+        end - start >= 365 * 86400
+    }
+
+    // The types of libtz's transitions (the file's, then the footer's), most recent first without duplicates.
+    fn seen_types(&self) -> Vec<(i64, bool)> {
+        let types = self.all_types();
+        let file_types = self.tables.type_count();
+        let mut seen: Vec<usize> = vec![];
+        if let Some(ref footer @ PosixTz{ dst: Some(ref dst), .. }) = self.footer {
+            if !self.perpetual_dst() {
+                let (start, end) = dst.rule.unwrap_or(DEFAULT_RULE);
+                let start = rule_day(2001, &start.day) * 86400 + start.time as i64 - footer.std_offset as i64;
+                let end = rule_day(2001, &end.day) * 86400 + end.time as i64 - dst.offset as i64;
+                // Southern hemisphere rules end DST before starting it, so the last transition of the year is to DST.
+                seen = if end < start { vec![file_types + 1, file_types] } else { vec![file_types, file_types + 1] };
+            }
+        }
+        for t in self.tables.transitions().iter().rev() {
+            if !seen.contains(&t.local_time_type) {
+                seen.push(t.local_time_type);
+            }
+        }
+        seen.into_iter().filter(|&i| !types[i].2).map(|i| (types[i].0, types[i].1)).collect()
+    }
+
+    // libtz's time1(): mktime() with its handling of a `tm_isdst` that doesn't match the time.
+    fn time1(&self, tm: &mut Tm) -> Option<TimeT> {
+        if tm.tm_isdst > 1 {
+            tm.tm_isdst = 1;
+        }
+        if let Some(time) = self.time2(tm) {
+            return Some(time);
+        }
+        if tm.tm_isdst < 0 {
+            return None;
+        }
+        // We're supposed to assume that somebody took a time of one type and did some math on it that yielded
+        // a bad wall time. Try to divine the type they started from and adjust to the type they need.
+        for same in self.seen.iter().filter(|t| t.1 == (tm.tm_isdst != 0)) {
+            for other in self.seen.iter().filter(|t| t.1 != (tm.tm_isdst != 0)) {
+                let mut adjusted = Tm{ tm_sec: tm.tm_sec.wrapping_add((other.0 - same.0) as c_int), tm_isdst: (tm.tm_isdst == 0) as c_int, ..*tm };
+                if let Some(time) = self.time2(&mut adjusted) {
+                    *tm = adjusted;
+                    return Some(time);
+                }
+            }
+        }
+        None
+    }
+
+    // libtz's time2(): first try without normalizing the seconds (in case `tm_sec` is a leap second).
+    fn time2(&self, tm: &mut Tm) -> Option<TimeT> {
+        let (time, found) = self.time2sub(tm, false).or_else(|| self.time2sub(tm, true))?;
+        *tm = found;
+        Some(time)
+    }
+
+    // libtz's time2sub(): normalize the fields and binary search for a time whose local time matches.
+    fn time2sub(&self, tm: &Tm, normalize_seconds: bool) -> Option<(TimeT, Tm)> {
+        let mut yourtm = *tm;
+        if normalize_seconds {
+            normalize_overflow(&mut yourtm.tm_min, &mut yourtm.tm_sec, 60)?;
+        }
+        normalize_overflow(&mut yourtm.tm_hour, &mut yourtm.tm_min, 60)?;
+        normalize_overflow(&mut yourtm.tm_mday, &mut yourtm.tm_hour, 24)?;
+        let months = yourtm.tm_year as i64 * 12 + yourtm.tm_mon as i64;
+        let days = calendar::days_from_civil(months.div_euclid(12) + 1900, months.rem_euclid(12) as i32, yourtm.tm_mday as i64);
+        let (year, mon, mday) = calendar::civil_from_days(days);
+        (yourtm.tm_year, yourtm.tm_mon, yourtm.tm_mday) = (c_int::try_from(year - 1900).ok()?, mon, mday);
+
+        let saved_seconds = if (0..60).contains(&yourtm.tm_sec) {
+            0
+        } else if yourtm.tm_year < 70 {
+            // Setting tm_sec to 0 might push the time below the minimum representable time, so use 59.
+            yourtm.tm_sec = yourtm.tm_sec.checked_add(1 - 60)?;
+            core::mem::replace(&mut yourtm.tm_sec, 59)
+        } else {
+            core::mem::replace(&mut yourtm.tm_sec, 0)
+        };
+
+        let (mut lo, mut hi) = (TimeT::MIN, TimeT::MAX);
+        let (mut t, mut mytm) = loop {
+            let t = (lo / 2 + hi / 2).clamp(lo, hi);
+            let (mut t, mytm) = (t, self.localtime(t));
+            let dir = match mytm {
+                // Assume t is too extreme to be represented in a Tm and make it less extreme on the next pass.
+                None         => if t > 0 { 1 } else { -1 },
+                Some(ref tm) => tmcomp(tm, &yourtm),
+            };
+            if dir == 0 {
+                break (t, mytm.unwrap());
+            }
+            if t == lo {
+                if t == TimeT::MAX { return None }
+                t += 1;
+                lo += 1;
+            } else if t == hi {
+                if t == TimeT::MIN { return None }
+                t -= 1;
+                hi -= 1;
+            }
+            if lo > hi {
+                return None;
+            }
+            if dir > 0 { hi = t } else { lo = t }
+        };
+
+        // If the match has a different UT offset than the one asked for, see if the one asked for works too.
+        if mytm.tm_gmtoff != yourtm.tm_gmtoff && (-86400..=86400).contains(&yourtm.tm_gmtoff) {
+            if let Some(altt) = t.checked_add((mytm.tm_gmtoff - yourtm.tm_gmtoff) as TimeT) {
+                if let Some(alttm) = self.localtime(altt) {
+                    if alttm.tm_isdst == mytm.tm_isdst && alttm.tm_gmtoff == yourtm.tm_gmtoff && tmcomp(&alttm, &yourtm) == 0 {
+                        (t, mytm) = (altt, alttm);
+                    }
+                }
+            }
+        }
+
+        if yourtm.tm_isdst >= 0 && mytm.tm_isdst != yourtm.tm_isdst {
+            // Right time, wrong type. Hunt for right time, right type.
+            let types = &self.types;
+            t = types.iter().rev().filter(|i| i.1 == (yourtm.tm_isdst != 0)).find_map(|i| {
+                types.iter().rev().filter(|j| j.1 != (yourtm.tm_isdst != 0) && !j.2).find_map(|j| {
+                    let newt = t.checked_add(j.0 - i.0)?;
+                    let newtm = self.localtime(newt)?;
+                    (tmcomp(&newtm, &yourtm) == 0 && newtm.tm_isdst == yourtm.tm_isdst).then_some(newt)
+                })
+            })?;
+        }
+
+        let t = t.checked_add(saved_seconds as TimeT)?;
+        Some((t, self.localtime(t)?))
+    }
+}
+
+fn normalize_overflow(tens: &mut c_int, units: &mut c_int, base: c_int) -> Option<()> {
+    let delta = units.div_euclid(base);
+    *units -= delta * base;
+    *tens = tens.checked_add(delta)?;
+    Some(())
+}
+
+fn tmcomp(a: &Tm, b: &Tm) -> i64 {
+    if a.tm_year != b.tm_year {
+        return if a.tm_year < b.tm_year { -1 } else { 1 };
+    }
+    [a.tm_mon - b.tm_mon, a.tm_mday - b.tm_mday, a.tm_hour - b.tm_hour, a.tm_min - b.tm_min, a.tm_sec - b.tm_sec]
+        .into_iter().find(|&d| d != 0).unwrap_or(0) as i64
+}
+
+// Break seconds since 1970-01-01 (local) into a Tm. Fails if the year doesn't fit.
+fn breakdown(secs: i64, gmtoff: i64, zone: *const c_char) -> Option<Tm> {
+    let days = secs.div_euclid(86400);
+    let rem = secs.rem_euclid(86400) as c_int;
+    let (year, mon, mday) = calendar::civil_from_days(days);
+    Some(Tm{ tm_sec: rem % 60, tm_min: rem / 60 % 60, tm_hour: rem / 3600, tm_mday: mday, tm_mon: mon,
+             tm_year: c_int::try_from(year - 1900).ok()?,
+             tm_wday: calendar::weekday_from_days(days),
+             tm_yday: (days - calendar::days_from_civil(year, 0, 1)) as c_int,
+             tm_isdst: 0, tm_gmtoff: gmtoff as c_long, tm_zone: zone })
+}
+
+/// Load a zone. Returns null if it can't be loaded.
+///
+/// # Safety
+/// `zone` must be null or a valid C string.
+#[cfg(feature = "std")]
+pub unsafe fn tzalloc(zone: *const c_char) -> TimezoneT {
+    let name = if zone.is_null() { None } else { Some(unsafe { CStr::from_ptr(zone) }) };
+    match Zone::load(name) {
+        Ok(zone) => Box::into_raw(Box::new(zone)) as TimezoneT,
+        Err(_)   => core::ptr::null(),
+    }
+}
+
+// Load a zone that's already been parsed (one built in memory, or without std).
+#[cfg_attr(not(feature = "rust-backend"), allow(dead_code))] // Only the tests use this module with the C backend
+pub(crate) fn tzalloc_zone_info(info: ZoneInfo) -> Result<TimezoneT, String> {
+    Ok(Box::into_raw(Box::new(Zone::new(info)?)) as TimezoneT)
+}
+
+// Load a zone compiled into the program, using its tables in place.
+#[cfg_attr(not(feature = "rust-backend"), allow(dead_code))] // Only the tests use this module with the C backend
+pub(crate) fn tzalloc_static(zone: &'static StaticZone) -> Result<TimezoneT, String> {
+    Ok(Box::into_raw(Box::new(Zone::new_static(zone)?)) as TimezoneT)
+}
+
+// Bytes used by the zone `tz`, including its heap allocations.
+#[cfg(feature = "std")]
+#[cfg_attr(not(feature = "rust-backend"), allow(dead_code))] // Only the tests use this module with the C backend
+pub(crate) fn tz_footprint(tz: TimezoneT) -> usize {
+    let zone = unsafe { &*(tz as *const Zone) };
+    core::mem::size_of::<Zone>()
+        + match zone.tables { Tables::Owned(ref info) => info.heap_size(), Tables::Static(_) => 0 }
+        + zone.footer.as_ref().map_or(0, |f| f.std_abbr.capacity() + f.dst.as_ref().map_or(0, |d| d.abbr.capacity()))
+        + zone.abbrs.capacity() * core::mem::size_of::<CString>() + zone.abbrs.iter().map(|a| a.as_bytes_with_nul().len()).sum::<usize>()
+        + zone.type_abbrs.capacity() * core::mem::size_of::<usize>()
+        + zone.types.capacity() * core::mem::size_of::<(i64, bool, bool)>()
+        + zone.seen.capacity() * core::mem::size_of::<(i64, bool)>()
+}
+
+/// Free a zone from [`tzalloc`].
+///
+/// # Safety
+/// `tz` must have come from [`tzalloc`] and not have been freed already.
+pub unsafe fn tzfree(tz: TimezoneT) {
+    if !tz.is_null() {
+        drop(unsafe { Box::from_raw(tz as *mut Zone) });
+    }
+}
+
+/// Convert system time to local time. Returns `tmp` or null on failure. `tm_zone` points into the zone and
+/// lives as long as it does.
+///
+/// # Safety
+/// `tz` must be a live zone from [`tzalloc`] and the pointers must be valid.
+pub unsafe fn localtime_rz(tz: TimezoneT, timep: *const TimeT, tmp: *mut Tm) -> *mut Tm {
+    let zone = unsafe { &*(tz as *const Zone) };
+    match zone.localtime(unsafe { *timep }) {
+        Some(tm) => { unsafe { *tmp = tm }; tmp },
+        None     => overflow(),
+    }
+}
+
+/// Convert local time to system time, normalizing `*tmp`. Returns -1 (and leaves `*tmp` alone) on failure.
+///
+/// # Safety
+/// `tz` must be a live zone from [`tzalloc`] and `tmp` must be valid for writes (it's `*const` to match libtz-sys).
+pub unsafe fn mktime_z(tz: TimezoneT, tmp: *const Tm) -> TimeT {
+    let zone = unsafe { &*(tz as *const Zone) };
+    zone.time1(unsafe { &mut *(tmp as *mut Tm) }).unwrap_or(-1)
+}
+
+// Like libtz, say why a conversion failed with EOVERFLOW in errno (which Timezone::localtime() and gmtime()
+// report), where there's a C library with an errno to set.
+fn overflow<T>() -> *mut T {
+    #[cfg(all(unix, any(target_os = "linux", target_os = "emscripten", target_os = "redox")))]
+    unsafe { *libc::__errno_location() = libc::EOVERFLOW };
+    #[cfg(all(unix, any(target_os = "android", target_os = "openbsd", target_os = "netbsd")))]
+    unsafe { *libc::__errno() = libc::EOVERFLOW };
+    #[cfg(all(unix, any(target_vendor = "apple", target_os = "freebsd", target_os = "dragonfly")))]
+    unsafe { *libc::__error() = libc::EOVERFLOW };
+    core::ptr::null_mut()
+}
+
+fn utc_info() -> ZoneInfo {
+    ZoneInfo::from_posix(&PosixTz{ std_abbr: "UTC".to_string(), std_offset: 0, dst: None })
+}
+
+#[cfg(feature = "std")]
+fn with_utc<T>(f: impl FnOnce(&Zone) -> T) -> T {
+    static UTC: OnceLock<Zone> = OnceLock::new();
+    f(UTC.get_or_init(|| Zone::new(utc_info()).expect("UTC always loads")))
+}
+
+// Without std there's nowhere to keep a shared zone, so make one each time.
+#[cfg(not(feature = "std"))]
+fn with_utc<T>(f: impl FnOnce(&Zone) -> T) -> T {
+    f(&Zone::new(utc_info()).expect("UTC always loads"))
+}
+
+/// Convert system time to UTC. Returns `tmp` or null on failure.
+///
+/// # Safety
+/// The pointers must be valid.
+pub unsafe fn gmtime_r(timep: *const TimeT, tmp: *mut Tm) -> *mut Tm {
+    match breakdown(unsafe { *timep }, 0, c"UTC".as_ptr()) {
+        Some(tm) => { unsafe { *tmp = tm }; tmp },
+        None     => overflow(),
+    }
+}
+
+/// Convert UTC to system time, normalizing `*tmp` (`tm_isdst` is ignored). Returns -1 (and leaves `*tmp` alone)
+/// on failure.
+///
+/// # Safety
+/// `tmp` must be valid for writes (it's `*const` to match libtz-sys).
+pub unsafe fn timegm(tmp: *const Tm) -> TimeT {
+    let mut tm = Tm{ tm_isdst: 0, ..unsafe { *tmp } };
+    match with_utc(|utc| utc.time1(&mut tm)) {
+        Some(time) => { unsafe { *(tmp as *mut Tm) = tm }; time },
+        None       => -1,
+    }
+}
+
+/// Convert from leap-second to POSIX `time_t`s.
+///
+/// # Safety
+/// `tz` must be a live zone from [`tzalloc`].
+pub unsafe fn time2posix_z(tz: TimezoneT, t: TimeT) -> TimeT {
+    let zone = unsafe { &*(tz as *const Zone) };
+    t.saturating_sub(zone.leap_correction(t).0)
+}
+
+/// Convert from POSIX to leap-second `time_t`s.
+///
+/// # Safety
+/// `tz` must be a live zone from [`tzalloc`].
+pub unsafe fn posix2time_z(tz: TimezoneT, t: TimeT) -> TimeT {
+    let zone = unsafe { &*(tz as *const Zone) };
+    let corr = |t: TimeT| zone.leap_correction(t).0;
+    // This is libtz's algorithm: start with a guess and walk to the earliest time that maps back to `t`. It
+    // saturates at the ends of TimeT, where there's nowhere further to walk.
+    let mut x = t.saturating_add(corr(t));
+    let mut y = x.saturating_sub(corr(x));
+    if y < t {
+        while y < t && x < TimeT::MAX { x += 1; y = x.saturating_sub(corr(x)) }
+        if y != t { x = x.saturating_sub(1) }
+    } else if y > t {
+        while y > t && x > TimeT::MIN { x -= 1; y = x.saturating_sub(corr(x)) }
+        if y != t { x = x.saturating_add(1) }
+    }
+    x
+}
+
+#[cfg(all(test, feature = "c-backend"))]
+mod tests {
+    use super::*;
+
+    fn same(a: &Tm, b: &libtz_sys::Tm) -> bool {
+        let zone = |p: *const c_char| unsafe { CStr::from_ptr(p) };
+        (a.tm_sec, a.tm_min, a.tm_hour, a.tm_mday, a.tm_mon, a.tm_year, a.tm_wday, a.tm_yday, a.tm_isdst, a.tm_gmtoff)
+            == (b.tm_sec, b.tm_min, b.tm_hour, b.tm_mday, b.tm_mon, b.tm_year, b.tm_wday, b.tm_yday, b.tm_isdst, b.tm_gmtoff)
+            && zone(a.tm_zone) == zone(b.tm_zone)
+    }
+
+    fn to_c(tm: &Tm) -> libtz_sys::Tm {
+        libtz_sys::Tm{ tm_sec: tm.tm_sec, tm_min: tm.tm_min, tm_hour: tm.tm_hour, tm_mday: tm.tm_mday, tm_mon: tm.tm_mon, tm_year: tm.tm_year,
+                       tm_wday: tm.tm_wday, tm_yday: tm.tm_yday, tm_isdst: tm.tm_isdst, tm_gmtoff: tm.tm_gmtoff, tm_zone: tm.tm_zone }
+    }
+
+    #[test]
+    fn matches_libtz() {
+        for name in ["America/New_York", "Europe/Dublin", "Australia/Lord_Howe", "America/Sao_Paulo", "Pacific/Apia", "Asia/Kathmandu",
+                     "Africa/Casablanca", "Antarctica/Troll", "America/Los_Angeles", "right/UTC", "right/America/New_York", "Europe/Moscow",
+                     "", "UTC", ":Europe/Paris", "EST5EDT,M3.2.0,M11.1.0", "<+0330>-3:30", "IST-2IDT,M3.4.4/26,M10.5.0", "EST5EDT,0/0,J365/25",
+                     "<-03>3<-02>,M3.5.0/-2,M10.5.0/-1", "NZST-12NZDT,M9.5.0,M4.1.0/3"] {
+            let cname = CString::new(name).unwrap();
+            let (ours, theirs) = unsafe { (tzalloc(cname.as_ptr()), libtz_sys::tzalloc(cname.as_ptr())) };
+            assert!(!ours.is_null() && !theirs.is_null(), "{:?}", name);
+            let zone = unsafe { &*(ours as *const Zone) };
+            // mktime() is slow (it's a binary search), so only check it near transitions and for some of the sweep.
+            let mut times: Vec<(TimeT, bool)> = (-5_000_000_000..5_000_000_000).step_by(86400 * 97 + 3607).enumerate().map(|(i, t)| (t, i % 16 == 0)).collect();
+            times.extend(zone.tables.transitions().iter().flat_map(|t| [(t.time - 1, true), (t.time, true)]));
+            times.extend(zone.tables.leap_seconds().iter().flat_map(|l| [(l.time - 1, true), (l.time, true), (l.time + 1, true)]));
+            times.extend((2020..2040).flat_map(|year| zone.footer.as_ref().filter(|f| f.dst.is_some()).map(|f| Zone::footer_transitions(f, year)).into_iter().flatten())
+                                     .flat_map(|(t, _)| [(t - 1, true), (t, true), (t + 1, true)]));
+            for (time, check_mktime) in times {
+                let (mut a, mut b) = (std::mem::MaybeUninit::<Tm>::uninit(), std::mem::MaybeUninit::<libtz_sys::Tm>::uninit());
+                let (ra, rb) = unsafe { (localtime_rz(ours, &time, a.as_mut_ptr()), libtz_sys::localtime_rz(theirs, &time, b.as_mut_ptr())) };
+                assert_eq!(ra.is_null(), rb.is_null(), "{:?} localtime({})", name, time);
+                if ra.is_null() {
+                    continue;
+                }
+                let (a, b) = unsafe { (a.assume_init(), b.assume_init()) };
+                assert!(same(&a, &b), "{:?} localtime({}): {:?} != {:?}", name, time, a, b);
+
+                assert_eq!(unsafe { time2posix_z(ours, time) }, unsafe { libtz_sys::time2posix_z(theirs, time) }, "{:?} time2posix({})", name, time);
+                assert_eq!(unsafe { posix2time_z(ours, time) }, unsafe { libtz_sys::posix2time_z(theirs, time) }, "{:?} posix2time({})", name, time);
+
+                if !check_mktime {
+                    continue;
+                }
+                // mktime() of the local time, and nearby times that might be skipped, repeated or mislabeled.
+                for (field, delta) in [(0, 0), (1, -30), (1, 30), (1, 90), (0, 90), (0, -3600), (2, 1), (2, -1)] {
+                    for isdst in [-1, 0, 1] {
+                        let mut tm = a;
+                        match field { 0 => tm.tm_sec += delta, 1 => tm.tm_min += delta, _ => tm.tm_hour += delta }
+                        tm.tm_isdst = isdst;
+                        tm.tm_wday = -1;
+                        let mut c = to_c(&tm);
+                        let (ta, tb) = unsafe { (mktime_z(ours, &raw mut tm), libtz_sys::mktime_z(theirs, &raw mut c)) };
+                        assert_eq!(ta, tb, "{:?} mktime({:?})", name, to_c(&a));
+                        assert!(tm.tm_wday == -1 && c.tm_wday == -1 || same(&tm, &c), "{:?} mktime({:?}): {:?} != {:?}", name, a, tm, c);
+                    }
+                }
+            }
+            unsafe { tzfree(ours); libtz_sys::tzfree(theirs) };
+        }
+
+        // Nothing overflows at the ends of TimeT.
+        let right = unsafe { tzalloc(c"right/UTC".as_ptr()) };
+        for time in [TimeT::MIN, TimeT::MIN + 1, TimeT::MAX - 1, TimeT::MAX] {
+            let (posix, leap) = unsafe { (time2posix_z(right, time), posix2time_z(right, time)) };
+            assert!(posix <= time && leap.abs_diff(time) < 100, "{} {} {}", time, posix, leap);
+        }
+        unsafe { tzfree(right) };
+
+        for bad in [":Not/A_Zone", "/nonexistent", "Not a zone"] {
+            let cname = CString::new(bad).unwrap();
+            assert!(unsafe { tzalloc(cname.as_ptr()) }.is_null(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn utc() {
+        for time in [0, -1, 951782400, i32::MAX as TimeT + 1, -62135596800, 67767976233532799, 67767976233532800] {
+            let (mut a, mut b) = (std::mem::MaybeUninit::<Tm>::uninit(), std::mem::MaybeUninit::<libtz_sys::Tm>::uninit());
+            let (ra, rb) = unsafe { (gmtime_r(&time, a.as_mut_ptr()), libtz_sys::gmtime_r(&time, b.as_mut_ptr())) };
+            assert_eq!(ra.is_null(), rb.is_null(), "gmtime({})", time);
+            if ra.is_null() {
+                continue;
+            }
+            let (mut a, mut b) = unsafe { (a.assume_init(), b.assume_init()) };
+            assert!(same(&a, &b), "gmtime({}): {:?} != {:?}", time, a, b);
+            a.tm_mon += 14;
+            b.tm_mon += 14;
+            assert_eq!(unsafe { timegm(&raw mut a) }, unsafe { libtz_sys::timegm(&raw mut b) }, "timegm({:?})", b);
+            assert!(same(&a, &b), "timegm: {:?} != {:?}", a, b);
+        }
+    }
+
+    // Times that don't fit in a struct tm fail with EOVERFLOW in errno, which Timezone::localtime() reports.
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn overflow_errno() {
+        let errno_after = |f: &dyn Fn() -> bool| {
+            unsafe { *libc::__errno_location() = 0 };
+            assert!(f(), "conversion should fail");
+            std::io::Error::last_os_error().raw_os_error()
+        };
+        let (a, b) = (std::mem::MaybeUninit::<Tm>::uninit(), std::mem::MaybeUninit::<libtz_sys::Tm>::uninit());
+        for name in ["America/New_York", "UTC"] {
+            let cname = CString::new(name).unwrap();
+            let (ours, theirs) = unsafe { (tzalloc(cname.as_ptr()), libtz_sys::tzalloc(cname.as_ptr())) };
+            // Not TimeT::MAX: libtz fails that one without setting errno.
+            let ours_errno = errno_after(&|| unsafe { localtime_rz(ours, &TimeT::MIN, a.as_ptr() as *mut Tm) }.is_null());
+            let theirs_errno = errno_after(&|| unsafe { libtz_sys::localtime_rz(theirs, &TimeT::MIN, b.as_ptr() as *mut libtz_sys::Tm) }.is_null());
+            assert_eq!((ours_errno, theirs_errno), (Some(libc::EOVERFLOW), Some(libc::EOVERFLOW)), "{:?}", name);
+            unsafe { tzfree(ours); libtz_sys::tzfree(theirs) };
+        }
+        for time in [TimeT::MIN, TimeT::MAX] {
+            let ours_errno = errno_after(&|| unsafe { gmtime_r(&time, a.as_ptr() as *mut Tm) }.is_null());
+            let theirs_errno = errno_after(&|| unsafe { libtz_sys::gmtime_r(&time, b.as_ptr() as *mut libtz_sys::Tm) }.is_null());
+            assert_eq!((ours_errno, theirs_errno), (Some(libc::EOVERFLOW), Some(libc::EOVERFLOW)), "gmtime({})", time);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+// Generators for property testing code that uses libtz.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+//! Generators for property testing with [proptest](https://docs.rs/proptest) and
+//! [quickcheck](https://docs.rs/quickcheck) (the `test-util` feature).
+//!
+//! [`Tm`] and [`Timestamp`] implement both crates' `Arbitrary` traits, and the functions here give proptest
+//! strategies for the times most likely to shake out bugs:
+//!
+//! ```
+//! use proptest::prelude::*;
+//! use libtz::test_util::interesting_times;
+//!
+//! proptest!(|(time in interesting_times())| {
+//!     if let Ok(tm) = libtz::gmtime(time) {
+//!         prop_assert_eq!(libtz::timegm(&tm), Ok(time));
+//!     }
+//! });
+//! ```
+//!
+//! [`MockTimezone`] builds zones with made up transitions for testing DST edge cases.
+
+pub use crate::mock::MockTimezone;
+use proptest::prelude::*;
+use crate::{Timezone, TimeT, Timestamp, Tm, gmtime};
+
+// Times whose UTC year fits in `tm_year` (an i32) with lots of room to spare.
+const TM_RANGE: core::ops::Range<TimeT> = -(1 << 55)..(1 << 55);
+
+// Times where off by one errors, 32 bit overflows and the like tend to turn up.
+const INTERESTING: &[TimeT] = &[
+    0, -1, 1, 86399, 86400, -86400,
+    i32::MAX as TimeT, i32::MAX as TimeT + 1, i32::MIN as TimeT, i32::MIN as TimeT - 1, u32::MAX as TimeT,
+    -62135596800,      // 0001-01-01 00:00:00 UTC
+    -62167219200,      // 0000-01-01 00:00:00 UTC
+    253402300799,      // 9999-12-31 23:59:59 UTC
+    951782400,         // 2000-02-29 00:00:00 UTC
+    1483228799,        // The second before the last leap second (2016-12-31 23:59:60)
+    TimeT::MIN, TimeT::MAX, TimeT::MIN + 1, TimeT::MAX - 1,
+];
+
+/// System times that are likely to find bugs: the epoch and the seconds around it, 32 bit rollovers, the
+/// limits of 4 digit years, leap days, the extremes of [`TimeT`], and ordinary times from the last couple of
+/// centuries. Note that [`gmtime`] fails for the extremes.
+pub fn interesting_times() -> impl Strategy<Value = TimeT> {
+    prop_oneof![
+        2 => proptest::sample::select(INTERESTING),
+        1 => -(1i64 << 40)..(1i64 << 40),
+        3 => -2208988800i64..4102444800, // 1900 to 2100
+    ]
+}
+
+/// System times within a minute of `tz`'s recorded transitions, where converting between local and system
+/// time gets tricky.
+pub fn times_near_transitions(tz: &Timezone) -> Result<BoxedStrategy<TimeT>, String> {
+    let transitions: Vec<TimeT> = tz.zone_info()?.transitions.iter().map(|t| t.time).collect();
+    if transitions.is_empty() {
+        return Ok(interesting_times().boxed());
+    }
+    Ok((proptest::sample::select(transitions), -60..=60 as TimeT).prop_map(|(time, delta)| time.saturating_add(delta)).boxed())
+}
+
+/// Valid UTC [`Tm`]s (every field in range and consistent with the others, as [`gmtime`] makes them).
+pub fn valid_tm() -> impl Strategy<Value = Tm> {
+    prop_oneof![interesting_times(), TM_RANGE].prop_filter_map("time out of range", |time| gmtime(time).ok())
+}
+
+/// Valid local [`Tm`]s in `tz`, as [`Timezone::localtime`] makes them.
+pub fn valid_local_tm(tz: Timezone) -> impl Strategy<Value = Tm> {
+    interesting_times().prop_filter_map("time out of range", move |time| tz.localtime(time).ok())
+}
+
+impl Arbitrary for Tm {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Tm>;
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        valid_tm().boxed()
+    }
+}
+
+impl Arbitrary for Timestamp {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Timestamp>;
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        interesting_times().prop_map(Timestamp).boxed()
+    }
+}
+
+fn quickcheck_time(g: &mut quickcheck::Gen) -> TimeT {
+    match <u8 as quickcheck::Arbitrary>::arbitrary(g) % 3 {
+        0 => *g.choose(INTERESTING).expect("not empty"),
+        1 => <i64 as quickcheck::Arbitrary>::arbitrary(g).rem_euclid(4102444800 + 2208988800) - 2208988800,
+        _ => <i64 as quickcheck::Arbitrary>::arbitrary(g),
+    }
+}
+
+impl quickcheck::Arbitrary for Tm {
+    fn arbitrary(g: &mut quickcheck::Gen) -> Tm {
+        let time = quickcheck_time(g);
+        gmtime(time).or_else(|_| gmtime(time.rem_euclid(TM_RANGE.end))).expect("in range")
+    }
+}
+
+impl quickcheck::Arbitrary for Timestamp {
+    fn arbitrary(g: &mut quickcheck::Gen) -> Timestamp {
+        Timestamp(quickcheck_time(g))
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Timestamp>> {
+        Box::new(quickcheck::Arbitrary::shrink(&self.0).map(Timestamp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timegm;
+
+    proptest! {
+        #[test]
+        fn gmtime_round_trips(tm in any::<Tm>()) {
+            prop_assert_eq!(tm.validate(), Ok(()));
+            prop_assert_eq!(gmtime(timegm(&tm).expect("timegm")), Ok(tm));
+        }
+
+        #[test]
+        fn localtime_round_trips(time in times_near_transitions(&Timezone::new("Europe/London").expect("timezone alloc")).expect("strategy")) {
+            let tz = Timezone::new("Europe/London").expect("timezone alloc");
+            let tm = tz.localtime(time).expect("localtime");
+            prop_assert_eq!(tz.mktime(&tm), Ok(time));
+        }
+    }
+
+    #[test]
+    fn quickcheck() {
+        fn timestamp(t: Timestamp) -> bool {
+            t.checked_add_seconds(1).map_or(t == Timestamp::MAX, |later| later.is_after(t))
+        }
+        fn tm(tm: Tm) -> bool {
+            tm.validate().is_ok()
+        }
+        quickcheck::quickcheck(timestamp as fn(Timestamp) -> bool);
+        quickcheck::quickcheck(tm as fn(Tm) -> bool);
+    }
+}
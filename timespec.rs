@@ -0,0 +1,150 @@
+// Times with nanoseconds.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use alloc::string::String;
+use crate::{Tm, TimeT, Timezone};
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/// A system time with nanoseconds, like C's `struct timespec`.
+///
+/// `nsec` is always in `[0, 999_999_999]` and counts forward from `sec`, so times before the epoch have a
+/// negative `sec` and a positive `nsec` (half a second before the epoch is `{ sec: -1, nsec: 500_000_000 }`).
+/// That keeps the [`Tm`] for a time the same as the one for its whole second.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "diesel", derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow),
+           diesel(sql_type = diesel::sql_types::Timestamptz, sql_type = diesel::sql_types::Timestamp))]
+pub struct TimeSpec {
+    /** Seconds since the epoch */                  pub sec  : TimeT,
+    /** Nanoseconds  [0, 999_999_999] */            pub nsec : u32,
+}
+
+impl TimeSpec {
+    /// Make a [`TimeSpec`], carrying whole seconds out of `nsec` (which may be negative).
+    pub fn new(sec: TimeT, nsec: i64) -> Option<TimeSpec> {
+        let sec = sec.checked_add(nsec.div_euclid(NANOS_PER_SEC as i64))?;
+        Some(TimeSpec{ sec, nsec: nsec.rem_euclid(NANOS_PER_SEC as i64) as u32 })
+    }
+
+    /// The time `millis` milliseconds after the epoch (before it, if negative). Every `i64` is in range.
+    pub fn from_unix_millis(millis: i64) -> TimeSpec {
+        TimeSpec{ sec: millis.div_euclid(1000), nsec: millis.rem_euclid(1000) as u32 * 1_000_000 }
+    }
+
+    /// The time `micros` microseconds after the epoch (before it, if negative). Every `i64` is in range.
+    pub fn from_unix_micros(micros: i64) -> TimeSpec {
+        TimeSpec{ sec: micros.div_euclid(1_000_000), nsec: micros.rem_euclid(1_000_000) as u32 * 1000 }
+    }
+
+    /// Milliseconds since the epoch, or `None` if that doesn't fit in an `i64`.
+    ///
+    /// Sub-millisecond parts are floored (dropped toward the past, not toward zero), so -0.5ms is -1ms. That
+    /// way every time in the same millisecond converts to the same number, before the epoch too.
+    pub fn to_unix_millis(&self) -> Option<i64> {
+        (self.sec as i128 * 1000 + (self.nsec / 1_000_000) as i128).try_into().ok()
+    }
+
+    /// Microseconds since the epoch, or `None` if that doesn't fit in an `i64`. Sub-microsecond parts are
+    /// floored, like [`TimeSpec::to_unix_millis`].
+    pub fn to_unix_micros(&self) -> Option<i64> {
+        (self.sec as i128 * 1_000_000 + (self.nsec / 1000) as i128).try_into().ok()
+    }
+}
+
+#[cfg(feature = "std")]
+impl TryFrom<TimeSpec> for std::time::SystemTime {
+    type Error = String;
+    fn try_from(ts: TimeSpec) -> Result<std::time::SystemTime, String> {
+        let whole = std::time::Duration::from_secs(ts.sec.unsigned_abs());
+        let epoch_sec = if ts.sec < 0 { std::time::UNIX_EPOCH.checked_sub(whole) } else { std::time::UNIX_EPOCH.checked_add(whole) };
+        epoch_sec.and_then(|t| t.checked_add(std::time::Duration::from_nanos(ts.nsec as u64))).ok_or_else(|| "Time out of range for SystemTime".to_string())
+    }
+}
+
+#[cfg(feature = "std")]
+impl TryFrom<std::time::SystemTime> for TimeSpec {
+    type Error = String;
+    fn try_from(time: std::time::SystemTime) -> Result<TimeSpec, String> {
+        let out_of_range = || "SystemTime out of range".to_string();
+        match time.duration_since(std::time::UNIX_EPOCH) {
+            Ok(d)  => Ok(TimeSpec{ sec: d.as_secs().try_into().map_err(|_| out_of_range())?, nsec: d.subsec_nanos() }),
+            Err(e) => {
+                let d = e.duration();
+                let sec: TimeT = d.as_secs().try_into().map_err(|_| out_of_range())?;
+                TimeSpec::new(-sec, -(d.subsec_nanos() as i64)).ok_or_else(out_of_range)
+            },
+        }
+    }
+}
+
+impl Timezone {
+    /// Convert a system time with nanoseconds to local time. The nanoseconds are returned alongside the [`Tm`]
+    /// (which only has whole seconds).
+    pub fn localtime_ns(&self, time: TimeSpec) -> Result<(Tm, u32), String> {
+        Ok((self.localtime(time.sec)?, time.nsec))
+    }
+
+    /// Convert local time plus `nsec` nanoseconds to a system time. `nsec` may be out of range, in which case
+    /// the whole seconds are carried into the result.
+    pub fn mktime_ns(&self, tm: &Tm, nsec: i64) -> Result<TimeSpec, String> {
+        TimeSpec::new(self.mktime(tm)?, nsec).ok_or_else(|| "Time out of range".into())
+    }
+}
+
+/// Convert a system time with nanoseconds to UTC. See [`gmtime`](crate::gmtime).
+pub fn gmtime_ns(time: TimeSpec) -> Result<(Tm, u32), String> {
+    Ok((crate::gmtime(time.sec)?, time.nsec))
+}
+
+/// Convert UTC plus `nsec` nanoseconds to a system time. See [`timegm`](crate::timegm).
+pub fn timegm_ns(tm: &Tm, nsec: i64) -> Result<TimeSpec, String> {
+    TimeSpec::new(crate::timegm(tm)?, nsec).ok_or_else(|| "Time out of range".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timespec() {
+        assert_eq!(TimeSpec::new(0, -500_000_000), Some(TimeSpec{ sec: -1, nsec: 500_000_000 }));
+        assert_eq!(TimeSpec::new(1, 2_500_000_000), Some(TimeSpec{ sec: 3, nsec: 500_000_000 }));
+        assert_eq!(TimeSpec::new(TimeT::MAX, 1_000_000_000), None);
+        assert!(TimeSpec{ sec: -1, nsec: 999_999_999 } < TimeSpec{ sec: 0, nsec: 0 });
+
+        assert_eq!(TimeSpec::from_unix_millis(-1), TimeSpec{ sec: -1, nsec: 999_000_000 });
+        assert_eq!(TimeSpec::from_unix_micros(1_700_000_000_123_456), TimeSpec{ sec: 1700000000, nsec: 123_456_000 });
+        assert_eq!(TimeSpec{ sec: -1, nsec: 999_500_000 }.to_unix_millis(), Some(-1));
+        assert_eq!(TimeSpec{ sec: 1, nsec: 999_999_999 }.to_unix_micros(), Some(1_999_999));
+        for millis in [i64::MIN, -1, 0, 1, 1_700_000_000_123, i64::MAX] {
+            assert_eq!(TimeSpec::from_unix_millis(millis).to_unix_millis(), Some(millis));
+            assert_eq!(TimeSpec::from_unix_micros(millis).to_unix_micros(), Some(millis));
+        }
+        assert_eq!(TimeSpec{ sec: TimeT::MAX / 1000 + 1, nsec: 0 }.to_unix_millis(), None);
+        assert_eq!(TimeSpec{ sec: TimeT::MIN / 1_000_000 - 1, nsec: 0 }.to_unix_micros(), None);
+
+        let (tm, nsec) = gmtime_ns(TimeSpec{ sec: -1, nsec: 250 }).expect("gmtime_ns");
+        assert_eq!((tm.tm_hour, tm.tm_min, tm.tm_sec, nsec), (23, 59, 59, 250));
+        assert_eq!(timegm_ns(&tm, 250), Ok(TimeSpec{ sec: -1, nsec: 250 }));
+
+        let tz = Timezone::new("IST-5:30").expect("timezone alloc"); // A POSIX rule so this works without std too
+        let time = TimeSpec{ sec: 1700000000, nsec: 123_456_789 };
+        let (tm, nsec) = tz.localtime_ns(time).expect("localtime_ns");
+        assert_eq!((tm.tm_hour, tm.tm_min, nsec), (3, 43, 123_456_789));
+        assert_eq!(tz.mktime_ns(&tm, nsec as i64), Ok(time));
+
+        #[cfg(feature = "std")]
+        {
+            for ts in [time, TimeSpec{ sec: -1, nsec: 1 }, TimeSpec{ sec: -86400, nsec: 0 }] {
+                assert_eq!(std::time::SystemTime::try_from(ts).and_then(TimeSpec::try_from), Ok(ts));
+            }
+            assert_eq!(std::time::SystemTime::try_from(TimeSpec{ sec: -1, nsec: 500_000_000 }),
+                       Ok(std::time::UNIX_EPOCH - std::time::Duration::from_millis(500)));
+            for sec in [TimeT::MIN, TimeT::MAX] { // Out of SystemTime's range on some platforms, but never a panic
+                let _ = std::time::SystemTime::try_from(TimeSpec{ sec, nsec: 999_999_999 });
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// Overflow checked arithmetic on system times.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use crate::TimeT;
+
+/// A system time ([`TimeT`]) with arithmetic that can't silently overflow.
+///
+/// Adding to a raw `TimeT` near its limits wraps in release builds (and panics in debug builds). These methods
+/// either say so (`checked_`) or stop at the limit (`saturating_`).
+///
+/// ```
+/// use libtz::Timestamp;
+/// let t = Timestamp(1700000000);
+/// assert_eq!(t.checked_add_seconds(86400), Some(Timestamp(1700086400)));
+/// assert_eq!(Timestamp(i64::MAX).checked_add_seconds(1), None);
+/// assert_eq!(Timestamp(1700086400).checked_sub(t), Some(86400));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "diesel", derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow),
+           diesel(sql_type = diesel::sql_types::Timestamptz, sql_type = diesel::sql_types::Timestamp))]
+pub struct Timestamp(pub TimeT);
+
+impl Timestamp {
+    /// The earliest time a [`TimeT`] can hold.
+    pub const MIN: Timestamp = Timestamp(TimeT::MIN);
+    /// The latest time a [`TimeT`] can hold.
+    pub const MAX: Timestamp = Timestamp(TimeT::MAX);
+
+    /// `seconds` later (earlier, if negative), or `None` if that's out of range.
+    pub fn checked_add_seconds(self, seconds: i64) -> Option<Timestamp> {
+        self.0.checked_add(seconds).map(Timestamp)
+    }
+
+    /// `seconds` earlier (later, if negative), or `None` if that's out of range.
+    pub fn checked_sub_seconds(self, seconds: i64) -> Option<Timestamp> {
+        self.0.checked_sub(seconds).map(Timestamp)
+    }
+
+    /// `seconds` later, stopping at [`Timestamp::MIN`] or [`Timestamp::MAX`].
+    pub fn saturating_add(self, seconds: i64) -> Timestamp {
+        Timestamp(self.0.saturating_add(seconds))
+    }
+
+    /// `seconds` earlier, stopping at [`Timestamp::MIN`] or [`Timestamp::MAX`].
+    pub fn saturating_sub(self, seconds: i64) -> Timestamp {
+        Timestamp(self.0.saturating_sub(seconds))
+    }
+
+    /// The seconds from `earlier` to `self` (negative if `earlier` is actually later), or `None` if that
+    /// doesn't fit in an `i64`.
+    pub fn checked_sub(self, earlier: Timestamp) -> Option<i64> {
+        self.0.checked_sub(earlier.0)
+    }
+
+    /// The seconds between the two times, whichever comes first. This always fits.
+    pub fn abs_diff(self, other: Timestamp) -> u64 {
+        self.0.abs_diff(other.0)
+    }
+
+    /// Whether `self` is strictly before `other`.
+    pub fn is_before(self, other: Timestamp) -> bool {
+        self < other
+    }
+
+    /// Whether `self` is strictly after `other`.
+    pub fn is_after(self, other: Timestamp) -> bool {
+        self > other
+    }
+
+    /// Whether `self` is within `seconds` of `other`, in either direction.
+    pub fn is_within(self, other: Timestamp, seconds: u64) -> bool {
+        self.abs_diff(other) <= seconds
+    }
+}
+
+impl From<TimeT> for Timestamp {
+    fn from(time: TimeT) -> Timestamp {
+        Timestamp(time)
+    }
+}
+
+impl From<Timestamp> for TimeT {
+    fn from(time: Timestamp) -> TimeT {
+        time.0
+    }
+}
+
+impl core::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp() {
+        assert_eq!(Timestamp::MAX.checked_add_seconds(1), None);
+        assert_eq!(Timestamp::MIN.checked_add_seconds(-1), None);
+        assert_eq!(Timestamp::MIN.checked_sub_seconds(1), None);
+        assert_eq!(Timestamp(0).checked_sub_seconds(i64::MIN), None);
+        assert_eq!(Timestamp::MAX.saturating_add(1), Timestamp::MAX);
+        assert_eq!(Timestamp(-5).saturating_sub(i64::MAX), Timestamp::MIN);
+        assert_eq!(Timestamp::MAX.checked_sub(Timestamp::MIN), None);
+        assert_eq!(Timestamp(10).checked_sub(Timestamp(20)), Some(-10));
+        assert_eq!(Timestamp::MAX.abs_diff(Timestamp::MIN), u64::MAX);
+        assert!(Timestamp(1).is_before(Timestamp(2)) && !Timestamp(2).is_before(Timestamp(2)));
+        assert!(Timestamp(3).is_after(Timestamp(2)));
+        assert!(Timestamp(100).is_within(Timestamp(40), 60) && !Timestamp(100).is_within(Timestamp(39), 60));
+        assert_eq!(TimeT::from(Timestamp::from(42)), 42);
+        assert_eq!(Timestamp(-1).to_string(), "-1");
+    }
+}
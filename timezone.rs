@@ -3,15 +3,73 @@
 // Copyright © 2023 David Caldwell <david@porkrind.org>
 // License: MIT (see LICENSE.md file)
 
-use libtz_sys::{TimezoneT, TimeT, tzalloc, tzfree, localtime_rz, mktime_z, posix2time_z, time2posix_z};
-use std::ffi::CString;
+use crate::sys::{TimezoneT, TimeT, tzalloc, tzfree, localtime_rz, mktime_z, posix2time_z, time2posix_z};
+use std::ffi::{CStr, CString};
 use std::mem::MaybeUninit;
+use std::path::PathBuf;
+use std::os::raw::c_char;
+use std::sync::{Arc, OnceLock, RwLock};
 use crate::Tm;
+use crate::tzif::ZoneInfo;
+
+/// The directory libtz looks in for zone files (set by `libtz-sys` when it compiles the C code).
+pub(crate) const TZDIR: &str = "/usr/share/zoneinfo";
+
+/// The file libtz loads when no zone is specified.
+pub(crate) const TZDEFAULT: &str = "/etc/localtime";
+
+static ZONE_DIRS: RwLock<Vec<PathBuf>> = RwLock::new(vec![]);
+
+/// Look up relative zone names in each of `dirs`, in order, before `/usr/share/zoneinfo`. This lets corrected
+/// or pre-release zone files be shipped in a directory of their own, layered over the system's database
+/// without touching it. The first directory with a zone file of the right name wins.
+///
+/// This affects every [`Timezone::new`] (and everything built on it) from then on, in all threads. Zones that
+/// are already loaded aren't changed. Relative directories are resolved against the current directory now.
+/// Pass an empty list to go back to just the system directory.
+///
+/// ```no_run
+/// libtz::set_zone_dirs(["/opt/myapp/zoneinfo"]);
+/// let tz = libtz::Timezone::new("America/New_York").unwrap(); // /opt/myapp/zoneinfo/America/New_York, if it's there
+/// ```
+pub fn set_zone_dirs(dirs: impl IntoIterator<Item = impl Into<PathBuf>>) {
+    let dirs = dirs.into_iter().map(|dir| { let dir = dir.into(); std::path::absolute(&dir).unwrap_or(dir) }).collect();
+    *ZONE_DIRS.write().unwrap() = dirs;
+}
+
+/// The directories relative zone names are looked up in, in the order they're searched: the ones given to
+/// [`set_zone_dirs`] and then the system's.
+pub fn zone_dirs() -> Vec<PathBuf> {
+    ZONE_DIRS.read().unwrap().iter().cloned().chain([PathBuf::from(TZDIR)]).collect()
+}
+
+/// The name of the `Etc/GMT` zone for `offset`, which has to be a whole number of hours from -12 to +14.
+///
+/// Those zones' names use the POSIX sign convention, which is backwards: `Etc/GMT-8` is 8 hours *ahead* of UTC.
+/// This takes the offset the usual way round.
+///
+/// ```
+/// use libtz::{etc_gmt_name, UtcOffset};
+/// assert_eq!(etc_gmt_name(UtcOffset::from_hms(8, 0, 0).unwrap()).unwrap(), "Etc/GMT-8");
+/// assert_eq!(etc_gmt_name(UtcOffset::UTC).unwrap(), "Etc/GMT");
+/// assert!(etc_gmt_name("+05:30".parse().unwrap()).is_err());
+/// ```
+pub fn etc_gmt_name(offset: crate::UtcOffset) -> Result<String, String> {
+    let (hours, minutes, seconds) = offset.as_hms();
+    if minutes != 0 || seconds != 0 || !(-12..=14).contains(&hours) {
+        return Err(format!("There's no Etc/GMT zone for {}", offset));
+    }
+    Ok(match hours {
+        0     => "Etc/GMT".to_string(),
+        hours => format!("Etc/GMT{:+}", -hours),
+    })
+}
 
 /// A `Timezone` holds the storage for the libtz C library. Create one with
-/// [`Timezone::new`] (to specify a specific timezone) or [`Timezone::default`]
-/// (to use the default system timezone, which it looks for in
-/// `/etc/localtime`).
+/// [`Timezone::new`] (to specify a specific timezone), [`Timezone::from_env`]
+/// (to use the `TZ` environment variable, or the system timezone if it's not
+/// set) or [`Timezone::system`] (to use the system timezone, which it looks for
+/// in `/etc/localtime`, whatever `TZ` says).
 ///
 /// # Example:
 ///
@@ -23,57 +81,910 @@ use crate::Tm;
 /// assert_eq!(tz.mktime(&tm).expect("mktime"), time);
 /// ```
 pub struct Timezone {
-    tz: TimezoneT,
+    pub(crate) tz: TimezoneT,
+    provenance: Provenance,
+    zone_info: OnceLock<Result<ZoneInfo, String>>,
+    abbrs: RwLock<Vec<(*const c_char, Arc<str>)>>,
+    segment: RwLock<Option<Segment>>,
+    supported_range: OnceLock<Result<(TimeT, TimeT), String>>,
+}
+
+// The stretch of time between two recorded transitions that the last localtime() landed in. Conversions of
+// nearby times (which is most of them, in logs and the like) can skip libtz's search for the transition.
+#[derive(Clone)]
+struct Segment {
+    start: TimeT, // Inclusive
+    end: TimeT,   // Exclusive
+    gmtoff: i64,
+    isdst: i32,
+    abbr: Arc<str>,
+}
+
+impl Segment {
+    // Inside the segment the offset is known, so local time is just UTC shifted by it.
+    fn localtime(&self, time: TimeT) -> Option<Tm> {
+        if time < self.start || time >= self.end {
+            return None;
+        }
+        let mut tztm = MaybeUninit::<crate::sys::Tm>::uninit();
+        let ret = unsafe { crate::sys::gmtime_r(&time.checked_add(self.gmtoff)?, tztm.as_mut_ptr()) };
+        if ret.is_null() {
+            return None;
+        }
+        let mut tm = Tm::with_zone(&unsafe { tztm.assume_init() }, self.abbr.clone());
+        tm.tm_gmtoff = self.gmtoff;
+        tm.tm_isdst = self.isdst;
+        Some(tm)
+    }
+}
+
+/// Describes how a [`Timezone`]'s rules were resolved. See [`Timezone::provenance`].
+///
+/// libtz interprets zone names (and the `TZ` environment variable) like this:
+///
+/// - Not set at all: load the system default zone file, `/etc/localtime`.
+/// - The empty string: UTC (with the abbreviation "UTC"). POSIX leaves this implementation defined and libtz
+///   chooses UTC without consulting any files.
+/// - A leading `:`: the rest of the value is a file name. The colon is stripped and the name is *only* looked
+///   up as a file--it never falls back to being parsed as a POSIX rule.
+/// - An absolute path (`/...`): load that file.
+/// - Anything else (including relative paths like `../foo`): look for a file of that name relative to the
+///   directories given to [`set_zone_dirs`] and then `/usr/share/zoneinfo` (*not* the current directory). If
+///   there's no such file, parse the name as a POSIX TZ rule string like `EST5EDT,M3.2.0,M11.1.0`.
+///
+/// Android has no zoneinfo directory, so there when a relative name doesn't match a file it's looked up in
+/// Android's combined `tzdata` file before being treated as a POSIX rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Provenance {
+    /// The zone was specified as the empty string, which means UTC.
+    Utc,
+    /// No zone was specified (`TZ` was unset) so the system default was loaded from this file.
+    SystemDefault(PathBuf),
+    /// The zone was loaded from this TZif file.
+    File(PathBuf),
+    /// The zone was loaded from this Android `tzdata` file, where it has this name.
+    AndroidTzdata(PathBuf, String),
+    /// The name didn't match a zone file so it was parsed as a POSIX TZ rule string.
+    PosixRule(String),
+    /// The zone was built in memory (for instance from an iCalendar `VTIMEZONE`) and has this name.
+    Synthesized(String),
+    /// The zone was compiled into the program (see [`Timezone::from_static`]).
+    Static(&'static crate::StaticZone),
+}
+
+impl Provenance {
+    // This mirrors the name resolution in libtz's tzloadbody() and zoneinit() (plus Android's tzdata file, which
+    // libtz doesn't know about). It doesn't check that POSIX rules are valid.
+    pub(crate) fn resolve(name: Option<&CStr>) -> Provenance {
+        let Some(name) = name else {
+            return Provenance::SystemDefault(PathBuf::from(TZDEFAULT));
+        };
+        let name = String::from_utf8_lossy(name.to_bytes()).into_owned();
+        if name.is_empty() {
+            return Provenance::Utc;
+        }
+        let file = name.strip_prefix(':').unwrap_or(&name);
+        let path = if file.starts_with('/') { PathBuf::from(file) } else {
+            let overrides = ZONE_DIRS.read().unwrap().iter().map(|dir| dir.join(file)).find(|path| is_tzif(path));
+            overrides.unwrap_or_else(|| PathBuf::from(TZDIR).join(file))
+        };
+        if is_tzif(&path) {
+            return Provenance::File(path);
+        }
+        // Only Android has tzdata files, and looking for one means reading it, so don't bother elsewhere.
+        if cfg!(target_os = "android") && !file.starts_with('/') {
+            if let Some(tzdata) = crate::android::find(file) {
+                return Provenance::AndroidTzdata(tzdata, file.to_string());
+            }
+        }
+        if name.starts_with(':') || file.starts_with('/') {
+            return Provenance::File(path);
+        }
+        Provenance::PosixRule(name)
+    }
+
+    // A name for the zone, for places that need one (like a VTIMEZONE's TZID): the zone name for files in the
+    // zoneinfo directory (following the `/etc/localtime` symlink if there is one), otherwise the path or rule.
+    pub(crate) fn name(&self) -> String {
+        match self {
+            Provenance::Utc                    => "UTC".to_string(),
+            Provenance::PosixRule(rule)        => rule.clone(),
+            Provenance::Synthesized(name)      => name.clone(),
+            Provenance::Static(zone)           => zone.name.to_string(),
+            Provenance::AndroidTzdata(_, zone) => zone.clone(),
+            Provenance::File(path)             => zone_dirs().iter().find_map(|dir| path.strip_prefix(dir).ok())
+                                                                     .unwrap_or(path).display().to_string(),
+            Provenance::SystemDefault(path)    => {
+                let target = std::fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+                let target = target.to_string_lossy();
+                match target.find("/zoneinfo/") {
+                    Some(i) => target[i + "/zoneinfo/".len()..].to_string(),
+                    None    => target.into_owned(),
+                }
+            },
+        }
+    }
+}
+
+/// When daylight saving time begins and ends during a year. See [`Timezone::dst_schedule`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DstYear {
+    /// The first time daylight saving time starts during the year.
+    pub start: TimeT,
+    /// The last time daylight saving time ends during the year. In the southern hemisphere this is earlier
+    /// than `start`.
+    pub end: TimeT,
+    /// The number of seconds the clocks move forward at `start` (usually 3600).
+    pub savings: i32,
+}
+
+/// An overview of a zone's compiled data. See [`Timezone::stats`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ZoneStats {
+    /// The number of recorded transitions (not counting any extrapolated from the zone's POSIX rule).
+    pub transition_count: usize,
+    /// The number of distinct local time types (offset, DST flag and abbreviation combinations).
+    pub type_count: usize,
+    /// The number of distinct abbreviations.
+    pub abbr_count: usize,
+    /// Whether the zone has a leap second table (the `right/` zones do).
+    pub has_leap_table: bool,
+    /// The first recorded transition, if any.
+    pub earliest: Option<TimeT>,
+    /// The last recorded transition, if any.
+    pub latest: Option<TimeT>,
+}
+
+/// Where a [`Timezone::localtime_detailed`] answer came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TmSource {
+    /// The zone's recorded transitions (the time is within [`Timezone::data_range`]).
+    Recorded,
+    /// The zone's POSIX rule (its TZif footer) extrapolated past the last recorded transition, or the last
+    /// local time type if it has no rule. Zones with no transitions at all (like UTC) always get this.
+    Rule,
+    /// The zone's initial local time type (usually LMT), assumed for times before the first transition.
+    Initial,
+}
+
+/// A local time along with how it was worked out. See [`Timezone::localtime_detailed`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DetailedTm {
+    /// The local time, exactly as [`Timezone::localtime`] would return it.
+    pub tm: Tm,
+    /// Whether `tm` came from recorded transitions or was extrapolated.
+    pub source: TmSource,
+}
+
+/// The time between two instants, from [`Timezone::elapsed`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Elapsed {
+    /// The number of SI seconds that actually passed, counting any leap seconds inserted (or removed) between the
+    /// two times.
+    pub si: i64,
+    /// The plain difference of the POSIX times, which pretends every day has 86400 seconds.
+    pub posix: i64,
+}
+
+/// A result from [`Timezone::localtime_clamped`] or [`Timezone::mktime_clamped`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Clamped<T> {
+    /// The converted value, for the nearest supported time if the requested one was out of range.
+    pub value: T,
+    /// Whether the requested time was out of range (so `value` is for the edge of the range instead).
+    pub clamped: bool,
+}
+
+/// One way to read a local time, from [`Timezone::offsets_for_local`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OffsetInfo {
+    /// The system time this reading gives.
+    pub time: TimeT,
+    /// Seconds east of UTC.
+    pub gmtoff: i64,
+    /// Whether daylight saving time is in effect.
+    pub isdst: bool,
+    /// The zone abbreviation (like `EDT`).
+    pub abbr: String,
+}
+
+/// What [`Timezone::mktime_dst`] should do when a [`Tm`]'s `tm_isdst` flag contradicts the zone's rules for
+/// that local time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DstConflict {
+    /// Trust the flag, like C's `mktime` does. Asking for 01:00 DST in the middle of winter gives the time one
+    /// hour before 01:00 standard time.
+    Honor,
+    /// Disregard the flag and let the zone's rules decide (as if `tm_isdst` were negative).
+    Ignore,
+    /// Return [`MktimeError::DstConflict`].
+    Error,
+}
+
+/// How [`Timezone::mktime_resolve`] should pick an instant for a local time that occurs twice (when the clocks
+/// fall back) or not at all (when they spring forward).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolve {
+    /// For a repeated time use the first occurrence. For a skipped time use the offset from *after* the
+    /// transition, which gives an instant before the transition (so 02:30 in a 02:00-03:00 gap becomes 01:30).
+    Earlier,
+    /// For a repeated time use the second occurrence. For a skipped time use the offset from *before* the
+    /// transition, which gives an instant after the transition (so 02:30 in a 02:00-03:00 gap becomes 03:30).
+    Later,
+    /// Return [`MktimeError::Ambiguous`] or [`MktimeError::Nonexistent`].
+    Error,
+}
+
+/// What [`Timezone::add_months`] and [`Timezone::add_years`] should do when the day of the month doesn't exist
+/// in the new month, like Jan 31 plus one month or Feb 29 plus one year.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// Use the last day of the new month (Jan 31 plus one month is Feb 28 or 29, and Feb 29 plus one year is
+    /// Feb 28).
+    Clamp,
+    /// Carry the extra days into the following month, like C's `mktime` does (Jan 31 plus one month is Mar 3,
+    /// or Mar 2 in a leap year, and Feb 29 plus one year is Mar 1).
+    Overflow,
+    /// Return an error.
+    Reject,
+}
+
+/// Errors from [`Timezone::mktime_dst`], [`Timezone::mktime_resolve`] and [`Timezone::convert_resolve`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MktimeError {
+    /// The time couldn't be converted (for instance it isn't representable).
+    InvalidDate,
+    /// `tm_isdst` was `requested` but the zone's rules say it should be `actual` for that local time.
+    DstConflict { requested: i32, actual: i32 },
+    /// The local time occurs twice, at `earlier` and `later`.
+    Ambiguous { earlier: TimeT, later: TimeT },
+    /// The local time was skipped by a transition.
+    Nonexistent,
+}
+
+impl std::fmt::Display for MktimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MktimeError::InvalidDate => write!(f, "Invalid date specified"),
+            MktimeError::DstConflict{ requested, actual } => write!(f, "tm_isdst={} conflicts with the zone's tm_isdst={}", requested, actual),
+            MktimeError::Ambiguous{ earlier, later }      => write!(f, "Local time is ambiguous (could be {} or {})", earlier, later),
+            MktimeError::Nonexistent                      => write!(f, "Local time does not exist"),
+        }
+    }
+}
+
+impl std::error::Error for MktimeError {}
+
+impl From<MktimeError> for String {
+    fn from(err: MktimeError) -> String {
+        err.to_string()
+    }
+}
+
+/// Errors from [`Timezone::new_strict`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ZoneError {
+    /// The name isn't a zone in the database or a valid POSIX TZ rule.
+    UnknownZone(String),
+    /// The name refers to a zone but it couldn't be loaded (for instance the file is corrupt).
+    Invalid(String),
+    /// The name is a path (like `/etc/localtime-copy` or `:/etc/localtime-copy`) but there's no zone file
+    /// there: it doesn't exist, can't be read, or isn't a TZif file.
+    BadFile { path: PathBuf, reason: String },
+}
+
+impl std::fmt::Display for ZoneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ZoneError::UnknownZone(name)       => write!(f, "Unknown zone {:?}", name),
+            ZoneError::Invalid(err)            => write!(f, "{}", err),
+            ZoneError::BadFile{ path, reason } => write!(f, "Bad zone file {}: {}", path.display(), reason),
+        }
+    }
+}
+
+impl std::error::Error for ZoneError {}
+
+impl From<ZoneError> for String {
+    fn from(err: ZoneError) -> String {
+        err.to_string()
+    }
+}
+
+// Check that there's a zone file at `path`, to say what's wrong with paths given as zone names instead of libtz's
+// unhelpful failure.
+fn check_zone_file(path: &std::path::Path) -> Result<(), ZoneError> {
+    let bad = |reason: &str| Err(ZoneError::BadFile{ path: path.to_path_buf(), reason: reason.to_string() });
+    match std::fs::metadata(path) {
+        Err(e)                  => bad(&e.to_string()),
+        Ok(m) if m.is_dir()     => bad("it's a directory"),
+        Ok(_) if !is_tzif(path) => bad("not a TZif file"),
+        Ok(_)                   => Ok(()),
+    }
+}
+
+// Big enough that the per-chunk overhead (and abbreviation decoding in localtime_many()) is negligible.
+#[cfg(feature = "rayon")]
+const PAR_CHUNK: usize = 4096;
+
+pub(crate) struct LocalCandidates {
+    // The matching instants, in order.
+    pub times: Vec<TimeT>,
+    // If there were none, the offsets from before and after the transition that skipped over the local time.
+    pub gap: Option<(i64, i64)>,
+}
+
+// Pick the result for `mktime_resolve()` given the instants matching the wall time (in order) and the offsets
+// around the gap if there weren't any.
+pub(crate) fn resolve_candidates(wall: i64, times: &[TimeT], gap: Option<(i64, i64)>, resolve: Resolve) -> Result<TimeT, MktimeError> {
+    match (times, gap, resolve) {
+        ([time], _, _)                           => Ok(*time),
+        ([earlier, later], _, Resolve::Error)    => Err(MktimeError::Ambiguous{ earlier: *earlier, later: *later }),
+        ([earlier, _], _, Resolve::Earlier)      => Ok(*earlier),
+        ([_, later], _, Resolve::Later)          => Ok(*later),
+        ([], Some((_, after)), Resolve::Earlier) => Ok(wall - after),
+        ([], Some((before, _)), Resolve::Later)  => Ok(wall - before),
+        _                                        => Err(MktimeError::Nonexistent),
+    }
+}
+
+pub(crate) fn is_tzif(path: &std::path::Path) -> bool {
+    use std::io::Read;
+    let mut magic = [0; 4];
+    std::fs::File::open(path).and_then(|mut f| f.read_exact(&mut magic)).is_ok() && &magic == b"TZif"
 }
 
 impl Timezone {
     /// Create a [`Timezone`] for the specified timezone name. The name can be
     /// something like `America/New_York`, `US/Pacific`, `UTC`, `PST`, etc. It
     /// can even specify a custom time conversion function. See
-    /// [`libtz_sys::tzalloc`] for more details.
+    /// [`libtz_sys::tzalloc`](https://docs.rs/libtz-sys/latest/libtz_sys/fn.tzalloc.html) for more details.
+    ///
+    /// See [`Provenance`] for exactly how the name is interpreted.
     pub fn new(name: &str) -> Result<Timezone, String> {
         let tzname = CString::new(name).map_err(|_| "name has internal null byte".to_string())?;
-        let tz = unsafe { tzalloc(tzname.as_ptr()) };
-        if tz == std::ptr::null_mut() {
-            return Err("tzalloc failed".to_string());
+        Timezone::alloc(Some(&tzname))
+    }
+
+    /// Create a [`Timezone`] like [`Timezone::new`], but only if `name` is a zone in the database (or a zone
+    /// file) or a valid POSIX TZ rule. Anything else is a [`ZoneError::UnknownZone`], so a typo in a zone name
+    /// can't quietly turn into some other zone. Absolute paths that aren't zone files are a
+    /// [`ZoneError::BadFile`].
+    pub fn new_strict(name: &str) -> Result<Timezone, ZoneError> {
+        let unknown = || ZoneError::UnknownZone(name.to_string());
+        let tzname = CString::new(name).map_err(|_| unknown())?;
+        let provenance = Provenance::resolve(Some(&tzname));
+        let file = name.strip_prefix(':').unwrap_or(name);
+        match (&provenance, Timezone::alloc(Some(&tzname))) {
+            (Provenance::PosixRule(rule), _) if rule.parse::<crate::posix::PosixTz>().is_err() => Err(unknown()),
+            (Provenance::File(path), tz) if file.starts_with('/')                             => check_zone_file(path).and(tz.map_err(ZoneError::Invalid)),
+            (Provenance::File(path), _) if !is_tzif(path)                                     => Err(unknown()),
+            (_, tz)                                                                          => tz.map_err(ZoneError::Invalid),
         }
-        Ok(Timezone{
-            tz: tz
-        })
     }
 
-    /// Create a [`Timezone`] based on the `TZ` environment variable. If `TZ` is
-    /// not set, use the tzfile stored in `/etc/localtime`. If that doesn't
-    /// exist it will return an error.
-    pub fn default() -> Result<Timezone, String> {
+    /// Create a [`Timezone`] like [`Timezone::new`] but look up relative names in `dir` instead of
+    /// `/usr/share/zoneinfo`, for instance to use a zone database bundled with an app or a test fixture.
+    ///
+    /// Names that aren't in `dir` are parsed as POSIX TZ rules (unless they start with `:`)--the system's zone
+    /// directory is never consulted. The empty string and absolute paths work just like they do with
+    /// [`Timezone::new`].
+    pub fn new_in(dir: impl AsRef<std::path::Path>, name: &str) -> Result<Timezone, String> {
+        let file = name.strip_prefix(':').unwrap_or(name);
+        if name.is_empty() || file.starts_with('/') {
+            return Timezone::new(name);
+        }
+        let path = std::path::absolute(dir.as_ref().join(file)).map_err(|e| format!("{}: {}", dir.as_ref().display(), e))?;
+        if name.starts_with(':') || is_tzif(&path) {
+            // libtz would look for a relative name in its own directory, so give it the full path.
+            let path = path.to_str().ok_or_else(|| format!("{}: path is not valid utf8", path.display()))?;
+            return Timezone::new(&format!(":{}", path));
+        }
+        let mut tz = Timezone::from_zone_info(name, ZoneInfo::from_posix(&name.parse()?))?;
+        tz.provenance = Provenance::PosixRule(name.to_string());
+        Ok(tz)
+    }
+
+    /// Create a [`Timezone`] for the `right/` variant of the zone `name`, whose times count leap seconds, if
+    /// there is one. Otherwise this is the same as [`Timezone::new`]. Use [`Timezone::is_leap_aware`] to find
+    /// out which one was loaded.
+    pub fn new_right(name: &str) -> Result<Timezone, String> {
+        Timezone::builder().leap_seconds(true).fallback(crate::Fallback::Error).build(name)
+            .or_else(|_| Timezone::builder().leap_seconds(false).build(name))
+    }
+
+    /// Create a [`Timezone`] the way C's `localtime()` picks one, in this order:
+    ///
+    /// 1. If the `TZ` environment variable is set, the zone it names. The empty string is UTC; see
+    ///    [`Provenance`] for how other values are interpreted and [`Timezone::provenance`] to find out which
+    ///    interpretation was used. If it's a path (`:/path` or `/path`) to something that isn't a zone file, the
+    ///    error says what's wrong with it. It's an error if `TZ` has a null byte in it.
+    /// 2. Otherwise the system zone, as with [`Timezone::system`].
+    ///
+    /// This loads the zone every time it's called. See [`default_timezone`](crate::default_timezone) for a
+    /// cached version.
+    pub fn from_env() -> Result<Timezone, String> {
         use std::os::unix::ffi::OsStringExt;
-        let zone_cstr;
-        let zone = match std::env::var_os("TZ") {
-            Some(zone) => { zone_cstr = CString::new(zone.into_vec()).map_err(|_| "name has internal null byte".to_string())?;
-                            zone_cstr.as_ptr() },
-            None       => std::ptr::null_mut(),
+        let Some(zone) = std::env::var_os("TZ") else { return Timezone::system() };
+        // Owned here so it outlives the zone being loaded from it.
+        let zone = CString::new(zone.into_vec()).map_err(|_| "TZ has an internal null byte".to_string())?;
+        Timezone::alloc(Some(&zone))
+    }
+
+    /// Create a [`Timezone`] for the system's zone, from `/etc/localtime`, ignoring the `TZ` environment
+    /// variable. It's an error if there's no `/etc/localtime` (as in many containers). Its provenance is
+    /// [`Provenance::SystemDefault`].
+    pub fn system() -> Result<Timezone, String> {
+        Timezone::alloc(None)
+    }
+
+    /// The same as [`Timezone::from_env`]. The name doesn't say whether `TZ` is respected, so use
+    /// [`Timezone::from_env`] or [`Timezone::system`] instead.
+    #[allow(clippy::should_implement_trait)]
+    #[deprecated(note = "use Timezone::from_env() to respect TZ, or Timezone::system() to ignore it")]
+    pub fn default() -> Result<Timezone, String> {
+        Timezone::from_env()
+    }
+
+    pub(crate) fn alloc(name: Option<&CStr>) -> Result<Timezone, String> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("tzalloc", name = ?name).entered();
+        let provenance = Provenance::resolve(name);
+        if let Provenance::AndroidTzdata(path, zone) = &provenance {
+            return Timezone::from_android(path.clone(), zone.clone());
+        }
+        if let (Some(name), Provenance::File(path)) = (name, &provenance) {
+            if matches!(name.to_bytes().first(), Some(b'/' | b':')) {
+                check_zone_file(path)?;
+            }
+        }
+        // libtz only knows about its own directory, so zones found in one of the override directories are given
+        // to it by full path.
+        let override_name = match &provenance {
+            Provenance::File(path) if !path.starts_with(TZDIR) =>
+                Some(CString::new(format!(":{}", path.display())).map_err(|_| "name has internal null byte".to_string())?),
+            _ => None,
         };
-        let tz = unsafe { tzalloc(zone) };
-        if tz == std::ptr::null_mut() {
+        let name = override_name.as_deref().or(name);
+        let tz = unsafe { tzalloc(name.map_or(std::ptr::null(), |name| name.as_ptr())) };
+        if tz.is_null() {
+            trace_event!(WARN, "tzalloc failed");
             return Err("tzalloc failed".to_string());
         }
+        trace_event!(DEBUG, provenance = ?provenance, "zone allocated");
+        Ok(Timezone{
+            tz,
+            provenance,
+            zone_info: OnceLock::new(),
+            abbrs: RwLock::new(vec![]),
+            segment: RwLock::new(None),
+            supported_range: OnceLock::new(),
+        })
+    }
+
+    // libtz can't read Android's tzdata file, so the zone's data is pulled out and loaded like a synthesized zone.
+    pub(crate) fn from_android(path: PathBuf, zone: String) -> Result<Timezone, String> {
+        let mut tz = Timezone::from_zone_info(&zone, ZoneInfo::parse(&crate::android::read(&path, &zone)?)?)?;
+        tz.provenance = Provenance::AndroidTzdata(path, zone);
+        Ok(tz)
+    }
+
+    /// The zone's compiled data: its transitions, local time types and leap second table.
+    ///
+    /// libtz doesn't give access to its parsed data so this is parsed separately the first time it's needed
+    /// (for zones loaded from files that means re-reading the file) and then kept for the life of the
+    /// [`Timezone`]. Zones given as POSIX TZ rules have no transitions, just the rule's local time types and
+    /// the rule itself as the footer.
+    pub fn zone_info(&self) -> Result<&ZoneInfo, String> {
+        self.zone_info.get_or_init(|| match self.provenance {
+            Provenance::Utc                                                  => Ok(ZoneInfo::from_posix(&"UTC0".parse()?)),
+            Provenance::SystemDefault(ref path) | Provenance::File(ref path) => ZoneInfo::load(path),
+            Provenance::PosixRule(ref rule)                                  => Ok(ZoneInfo::from_posix(&rule.parse()?)),
+            Provenance::Static(zone)                                         => Ok(zone.zone_info()),
+            Provenance::Synthesized(_) | Provenance::AndroidTzdata(..)       => Err("zone data missing".to_string()), // Set up front, if it is known
+        }).as_ref().map_err(Clone::clone)
+    }
+
+    /// The earliest and latest times covered by the zone's transition data, or `None` if it has none (see
+    /// [`ZoneInfo::data_range`]). Outside of it answers are extrapolated, so they may be less trustworthy the further
+    /// out they are.
+    pub fn data_range(&self) -> Result<Option<(TimeT, TimeT)>, String> {
+        if let Provenance::Static(zone) = self.provenance {
+            return Ok(zone.data_range());
+        }
+        Ok(self.zone_info()?.data_range())
+    }
+
+    /// The time of the zone's first recorded transition, or `None` if it has none (like UTC or a POSIX rule).
+    /// For most zones this is when the place switched from local mean time to a standard offset.
+    pub fn earliest_transition(&self) -> Result<Option<TimeT>, String> {
+        Ok(self.zone_info()?.transitions.first().map(|t| t.time))
+    }
+
+    /// The local time type in effect before the first transition: usually local mean time (`LMT`) with an offset
+    /// to the second, worked out from the place's longitude, rather than a round standard offset.
+    ///
+    /// ```
+    /// let tz = libtz::Timezone::new("America/Los_Angeles").expect("timezone alloc");
+    /// let lmt = tz.initial_local_time_type().expect("initial_local_time_type");
+    /// assert_eq!((lmt.abbr.as_str(), lmt.utoff), ("LMT", -(7 * 3600 + 52 * 60 + 58)));
+    /// assert!(tz.in_initial_era(tz.earliest_transition().unwrap().unwrap() - 1).unwrap());
+    /// ```
+    pub fn initial_local_time_type(&self) -> Result<crate::LocalTimeType, String> {
+        // RFC 8536: type 0 is the one used before the first transition.
+        self.zone_info()?.types.first().cloned().ok_or_else(|| "zone has no local time types".to_string())
+    }
+
+    /// Whether `time` is before the zone's first transition (so in the era of [`Timezone::initial_local_time_type`]).
+    /// Zones without transitions are always in it.
+    pub fn in_initial_era(&self, time: TimeT) -> Result<bool, String> {
+        Ok(self.earliest_transition()?.is_none_or(|first| time < first))
+    }
+
+    /// The earliest and latest system times [`Timezone::localtime`] can convert in this zone (the limits come
+    /// from `tm_year` being an `i32`, shifted by the zone's offsets). It's worked out the first time it's needed
+    /// and then kept for the life of the [`Timezone`].
+    pub fn supported_range(&self) -> Result<(TimeT, TimeT), String> {
+        self.supported_range.get_or_init(|| {
+            self.localtime(0)?;
+            // Conversions work for one unbroken range of times around the epoch, so bisect for its ends.
+            let edge = |mut good: TimeT, mut bad: TimeT| {
+                while good.abs_diff(bad) > 1 {
+                    let mid = good / 2 + bad / 2;
+                    if self.localtime(mid).is_ok() { good = mid } else { bad = mid }
+                }
+                good
+            };
+            Ok((edge(0, TimeT::MIN), edge(0, TimeT::MAX)))
+        }).clone()
+    }
+
+    /// [`Timezone::localtime`], except that times outside [`Timezone::supported_range`] are converted as the
+    /// nearest supported time instead of failing, for things like UI sliders and open ended ranges.
+    ///
+    /// ```
+    /// let tz = libtz::Timezone::new("Europe/Paris").unwrap();
+    /// let far = tz.localtime_clamped(i64::MAX).unwrap();
+    /// assert!(far.clamped);
+    /// assert_eq!(far.value.tm_year, i32::MAX);
+    /// assert!(!tz.localtime_clamped(1700000000).unwrap().clamped);
+    /// ```
+    pub fn localtime_clamped(&self, time: TimeT) -> Result<Clamped<Tm>, String> {
+        if let Ok(tm) = self.localtime(time) {
+            return Ok(Clamped{ value: tm, clamped: false });
+        }
+        let (min, max) = self.supported_range()?;
+        Ok(Clamped{ value: self.localtime(time.clamp(min, max))?, clamped: true })
+    }
+
+    /// [`Timezone::mktime`], except that local times outside [`Timezone::supported_range`] give the nearest
+    /// supported system time instead of failing.
+    pub fn mktime_clamped(&self, tm: &Tm) -> Result<Clamped<TimeT>, String> {
+        if let Ok(time) = self.mktime(tm) {
+            return Ok(Clamped{ value: time, clamped: false });
+        }
+        let (min, max) = self.supported_range()?;
+        Ok(Clamped{ value: if crate::timegm_wide(tm) < 0 { min } else { max }, clamped: true })
+    }
+
+    /// Every abbreviation the zone has used or will use, in the order they first come into use. This is handy
+    /// for deciding which abbreviations to accept when parsing times in the zone.
+    ///
+    /// ```
+    /// let tz = libtz::Timezone::new("EST5EDT,M3.2.0,M11.1.0").expect("timezone alloc");
+    /// assert_eq!(tz.abbreviations(), Ok(vec!["EST".to_string(), "EDT".to_string()]));
+    /// ```
+    pub fn abbreviations(&self) -> Result<Vec<String>, String> {
+        if let Provenance::Static(zone) = self.provenance {
+            return Ok(zone.abbreviations_chronological().into_iter().map(|abbr| abbr.to_string()).collect());
+        }
+        Ok(self.zone_info()?.abbreviations_chronological().into_iter().map(|abbr| abbr.to_string()).collect())
+    }
+
+    /// Create a [`Timezone`] named `name` from the contents of a TZif file (for instance one compiled in with
+    /// `include_bytes!()`). This works the same in `no_std` builds.
+    pub fn from_tzif(name: &str, data: &[u8]) -> Result<Timezone, String> {
+        Timezone::from_zone_info(name, ZoneInfo::parse(data)?)
+    }
+
+    // The rust backend can load the data directly.
+    #[cfg(feature = "rust-backend")]
+    pub(crate) fn from_zone_info(name: &str, zi: ZoneInfo) -> Result<Timezone, String> {
         Ok(Timezone{
-            tz: tz
+            tz: crate::sys::tzalloc_zone_info(zi.clone())?,
+            provenance: Provenance::Synthesized(name.to_string()),
+            zone_info: OnceLock::from(Ok(zi)),
+            abbrs: RwLock::new(vec![]),
+            segment: RwLock::new(None),
+            supported_range: OnceLock::new(),
         })
     }
 
+    // libtz can only load zones from files, so zones built in memory are written to a temporary file and loaded
+    // from there. tzalloc() reads the whole file, so it can be removed straight away.
+    #[cfg(not(feature = "rust-backend"))]
+    pub(crate) fn from_zone_info(name: &str, zi: ZoneInfo) -> Result<Timezone, String> {
+        use std::io::Write;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!("libtz-{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+        let data = zi.to_tzif()?;
+        std::fs::OpenOptions::new().write(true).create_new(true).open(&path).and_then(|mut f| f.write_all(&data))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let tzname = path.to_str().and_then(|p| CString::new(format!(":{}", p)).ok()).ok_or("bad temporary file name".to_string());
+        let tz = tzname.and_then(|tzname| Timezone::alloc(Some(&tzname)));
+        let _ = std::fs::remove_file(&path);
+        let mut tz = tz?;
+        tz.provenance = Provenance::Synthesized(name.to_string());
+        tz.zone_info = OnceLock::from(Ok(zi));
+        Ok(tz)
+    }
+
+    // The rust backend uses the tables where they are; the zone's ZoneInfo is only made if it's asked for.
+    #[cfg(feature = "rust-backend")]
+    pub(crate) fn from_static_zone(zone: &'static crate::StaticZone) -> Result<Timezone, String> {
+        Ok(Timezone{
+            tz: crate::sys::tzalloc_static(zone)?,
+            provenance: Provenance::Static(zone),
+            zone_info: OnceLock::new(),
+            abbrs: RwLock::new(vec![]),
+            segment: RwLock::new(None),
+            supported_range: OnceLock::new(),
+        })
+    }
+
+    #[cfg(not(feature = "rust-backend"))]
+    pub(crate) fn from_static_zone(zone: &'static crate::StaticZone) -> Result<Timezone, String> {
+        let mut tz = Timezone::from_zone_info(zone.name, zone.zone_info())?;
+        tz.provenance = Provenance::Static(zone);
+        Ok(tz)
+    }
+
+    /// Roughly how many bytes of memory the zone is using: the backend's copy of the zone's rules plus the
+    /// caches kept on the Rust side ([`Timezone::zone_info`], the abbreviations and the last transition
+    /// segment). It's an estimate--allocator overhead isn't counted--but close enough for budgeting.
+    ///
+    /// The C backend allocates the same fixed-size `struct state` (about 25KB) for every zone no matter how
+    /// small, so that usually dominates.
+    ///
+    /// ```
+    /// let tz = libtz::Timezone::new("America/New_York").expect("timezone alloc");
+    /// let before = tz.memory_footprint();
+    /// tz.zone_info().expect("zone_info");
+    /// assert!(tz.memory_footprint() > before);
+    /// ```
+    pub fn memory_footprint(&self) -> usize {
+        let zone_info = match self.zone_info.get() {
+            Some(Ok(zi)) => zi.heap_size(),
+            Some(Err(e)) => e.capacity(),
+            None         => 0,
+        };
+        let provenance = match &self.provenance {
+            Provenance::Utc                                                 => 0,
+            Provenance::SystemDefault(path) | Provenance::File(path)        => path.capacity(),
+            Provenance::PosixRule(name) | Provenance::Synthesized(name)     => name.capacity(),
+            Provenance::AndroidTzdata(path, zone)                           => path.capacity() + zone.capacity(),
+            Provenance::Static(_)                                           => 0,
+        };
+        let arc_str = |s: &Arc<str>| 2 * std::mem::size_of::<usize>() + s.len(); // The reference counts, then the string
+        let abbrs = self.abbrs.read().unwrap();
+        let abbrs = abbrs.capacity() * std::mem::size_of::<(*const c_char, Arc<str>)>() + abbrs.iter().map(|(_, a)| arc_str(a)).sum::<usize>();
+        let segment = self.segment.read().unwrap().as_ref().map_or(0, |s| arc_str(&s.abbr));
+        std::mem::size_of::<Timezone>() + self.backend_footprint() + zone_info + provenance + abbrs + segment
+    }
+
+    #[cfg(not(feature = "rust-backend"))]
+    fn backend_footprint(&self) -> usize {
+        // sizeof(struct state) in libtz's localtime.c on 64 bit systems: the counts, 2000 transition times and
+        // types, 256 local time types, the abbreviation characters and 50 leap seconds.
+        const STATE_SIZE: usize = 24 + 2000 * 8 + 2000 + 256 * 24 + 512 + 50 * 16 + 8;
+        STATE_SIZE
+    }
+
+    #[cfg(feature = "rust-backend")]
+    fn backend_footprint(&self) -> usize {
+        crate::sys::tz_footprint(self.tz)
+    }
+
+    /// The underlying libtz `timezone_t`, for passing to other C code. It still belongs to the [`Timezone`] and
+    /// is freed when the [`Timezone`] is dropped.
+    ///
+    /// With the `rust-backend` feature this is the pure Rust backend's handle, which only its own functions
+    /// understand.
+    pub fn as_raw(&self) -> TimezoneT {
+        self.tz
+    }
+
+    /// Take ownership of a `timezone_t` from libtz's `tzalloc()`. The [`Timezone`] will free it with `tzfree()`.
+    ///
+    /// Where the zone came from isn't known so its provenance is [`Provenance::Synthesized`] with an empty name
+    /// and [`Timezone::zone_info`] returns an error.
+    ///
+    /// # Safety
+    ///
+    /// `tz` must be a valid, non-null `timezone_t` from the same backend's `tzalloc()` that nothing else will
+    /// free or modify.
+    pub unsafe fn from_raw(tz: TimezoneT) -> Timezone {
+        Timezone{
+            tz,
+            provenance: Provenance::Synthesized(String::new()),
+            zone_info: OnceLock::new(),
+            abbrs: RwLock::new(vec![]),
+            segment: RwLock::new(None),
+            supported_range: OnceLock::new(),
+        }
+    }
+
+    /// Give up ownership of the underlying `timezone_t` and return it. It must be freed with `tzfree()` (or
+    /// turned back into a [`Timezone`] with [`Timezone::from_raw`]). Abbreviations returned in [`Tm`]s from
+    /// this zone stay valid since they are copies.
+    pub fn into_raw(self) -> TimezoneT {
+        let mut this = std::mem::ManuallyDrop::new(self);
+        // Drop everything except the handle.
+        unsafe {
+            std::ptr::drop_in_place(&mut this.provenance);
+            std::ptr::drop_in_place(&mut this.zone_info);
+            std::ptr::drop_in_place(&mut this.abbrs);
+            std::ptr::drop_in_place(&mut this.segment);
+            std::ptr::drop_in_place(&mut this.supported_range);
+        }
+        this.tz
+    }
+
+    /// Report how the zone name was interpreted when this [`Timezone`] was created.
+    pub fn provenance(&self) -> &Provenance {
+        &self.provenance
+    }
+
     /// Convert system time to a local time [`Tm`].
     ///
     /// The `localtime` function corrects for the time zone and any time zone adjustments (such as Daylight
     /// Saving Time in the United States).
+    ///
+    /// Once the zone's data has been parsed (by [`Timezone::zone_info`] or anything that uses it), the zone
+    /// remembers the recorded transitions on either side of the last time it converted, so converting a run of
+    /// nearby times (like the timestamps in a log) mostly skips searching for the right transition. Conversions
+    /// never parse the data themselves, so call `zone_info()` first to turn this on.
     pub fn localtime(&self, time: TimeT) -> Result<Tm, String> {
-        let mut tztm = MaybeUninit::<libtz_sys::Tm>::uninit();
+        if let Some(tm) = self.localtime_cached(time) {
+            trace_event!(TRACE, time, "segment cache hit");
+            return Ok(tm);
+        }
+        trace_event!(TRACE, time, "segment cache miss");
+        let tztm = self.localtime_raw(time)?;
+        let tm = Tm::with_zone(&tztm, self.intern(tztm.tm_zone)?);
+        self.cache_segment(time, &tm);
+        Ok(tm)
+    }
+
+    fn localtime_cached(&self, time: TimeT) -> Option<Tm> {
+        self.segment.read().unwrap().as_ref()?.localtime(time)
+    }
+
+    // If another thread is using the segment this one is left uncached rather than waiting for it.
+    fn cache_segment(&self, time: TimeT, tm: &Tm) {
+        if let Some(segment) = self.segment_for(time, tm) {
+            if let Ok(mut cached) = self.segment.try_write() {
+                *cached = Some(segment);
+            }
+        }
+    }
+
+    // The recorded transitions on either side of `time` (whose local time is `tm`), from zone_info() if it has
+    // already been loaded. Times before the first or after the last transition don't get one, nor do leap second
+    // zones (where local time isn't UTC shifted by the offset).
+    fn segment_for(&self, time: TimeT, tm: &Tm) -> Option<Segment> {
+        let Some(Ok(zi)) = self.zone_info.get() else { return None };
+        let i = zi.transitions.partition_point(|t| t.time <= time);
+        if !zi.leap_seconds.is_empty() || i == 0 || i == zi.transitions.len() {
+            return None;
+        }
+        let t = &zi.types[zi.transitions[i - 1].local_time_type];
+        if t.utoff as i64 != tm.tm_gmtoff || t.isdst != (tm.tm_isdst > 0) || t.abbr != *tm.tm_zone {
+            return None; // libtz disagrees with our reading of the data, so stay out of its way.
+        }
+        let (start, end) = (zi.transitions[i - 1].time, zi.transitions[i].time);
+        Some(Segment{ start, end, gmtoff: tm.tm_gmtoff, isdst: tm.tm_isdst, abbr: tm.tm_zone.clone() })
+    }
+
+    /// Convert system time to a local time [`Tm`] and say whether the answer came from the zone's recorded
+    /// transitions or from extrapolating its rule. Far future times use the rule as it stands today, which
+    /// governments may well change before then.
+    ///
+    /// ```
+    /// use libtz::{Timezone, TmSource};
+    /// let tz = Timezone::new("America/New_York").expect("timezone alloc");
+    /// assert_eq!(tz.localtime_detailed(1700000000).expect("localtime").source, TmSource::Recorded);
+    /// assert_eq!(tz.localtime_detailed(16725225600).expect("localtime").source, TmSource::Rule); // 2500
+    /// ```
+    pub fn localtime_detailed(&self, time: TimeT) -> Result<DetailedTm, String> {
+        let tm = self.localtime(time)?;
+        let zi = self.zone_info()?;
+        let source = match (zi.transitions.first(), zi.transitions.last()) {
+            (Some(first), _) if time < first.time => TmSource::Initial,
+            (_, Some(last)) if time <= last.time  => TmSource::Recorded,
+            _                                     => TmSource::Rule,
+        };
+        Ok(DetailedTm{ tm, source })
+    }
+
+    /// Convert system time to local time, overwriting `tm`.
+    ///
+    /// This is [`localtime`][Timezone::localtime] for tight loops: the caller's `Tm` is reused and its
+    /// abbreviation is only replaced when it changes, so converting a run of times doesn't touch the allocator.
+    pub fn localtime_into(&self, time: TimeT, tm: &mut Tm) -> Result<(), String> {
+        let tztm = self.localtime_raw(time)?;
+        if tm.tm_zone.as_bytes() != unsafe { CStr::from_ptr(tztm.tm_zone) }.to_bytes() {
+            tm.tm_zone = self.intern(tztm.tm_zone)?;
+        }
+        tm.tm_sec    = tztm.tm_sec;
+        tm.tm_min    = tztm.tm_min;
+        tm.tm_hour   = tztm.tm_hour;
+        tm.tm_mday   = tztm.tm_mday;
+        tm.tm_mon    = tztm.tm_mon;
+        tm.tm_year   = tztm.tm_year;
+        tm.tm_wday   = tztm.tm_wday;
+        tm.tm_yday   = tztm.tm_yday;
+        tm.tm_isdst  = tztm.tm_isdst;
+        tm.tm_gmtoff = tztm.tm_gmtoff;
+        Ok(())
+    }
+
+    // libtz's abbreviation pointers point into the zone's storage, which lives as long as we do and never
+    // changes, so they make good keys for sharing one copy of each abbreviation. There are only ever a handful
+    // per zone so a linear search beats hashing.
+    fn intern(&self, abbr: *const c_char) -> Result<Arc<str>, String> {
+        if let Some((_, zone)) = self.abbrs.read().unwrap().iter().find(|(ptr, _)| *ptr == abbr) {
+            return Ok(zone.clone());
+        }
+        let zone: Arc<str> = unsafe { CStr::from_ptr(abbr) }.to_str().map_err(|_| "Invalid utf8")?.into();
+        self.abbrs.write().unwrap().push((abbr, zone.clone()));
+        Ok(zone)
+    }
+
+    pub(crate) fn localtime_raw(&self, time: TimeT) -> Result<crate::sys::Tm, String> {
+        let mut tztm = MaybeUninit::<crate::sys::Tm>::uninit();
         let ret = unsafe { localtime_rz(self.tz, &time, tztm.as_mut_ptr()) };
-        if ret == std::ptr::null_mut() {
+        if ret.is_null() {
             return Err(format!("errno={}", std::io::Error::last_os_error()));
         }
-        let tztm = unsafe { tztm.assume_init() };
-        Tm::try_from(&tztm)
+        Ok(unsafe { tztm.assume_init() })
+    }
+
+    /// Convert a batch of system times to local time [`Tm`]s.
+    ///
+    /// This is equivalent to calling [`localtime`][Timezone::localtime] on each time, but the abbreviations are
+    /// looked up without taking the zone's abbreviation lock for each time and the result is allocated up front.
+    /// It stops at the first time that fails to convert.
+    ///
+    /// Once the zone's data has been parsed (see [`localtime`][Timezone::localtime]), the stretch between the
+    /// recorded transitions around each time is kept for the next one, and times that land in it are converted by
+    /// shifting UTC without searching for the transition again. Sorted input (like
+    /// the timestamps in a log) only searches once per transition, so it goes much faster than input in random
+    /// order (which still works, but searches most of the time).
+    pub fn localtime_many(&self, times: &[TimeT]) -> Result<Vec<Tm>, String> {
+        let mut abbrs: Vec<(*const c_char, Arc<str>)> = vec![];
+        let mut tms = Vec::with_capacity(times.len());
+        let mut segment: Option<Segment> = None;
+        for time in times {
+            if let Some(tm) = segment.as_ref().and_then(|s| s.localtime(*time)) {
+                tms.push(tm);
+                continue;
+            }
+            let tztm = self.localtime_raw(*time)?;
+            let zone = match abbrs.iter().find(|(ptr, _)| *ptr == tztm.tm_zone) {
+                Some((_, zone)) => zone.clone(),
+                None => {
+                    let zone = self.intern(tztm.tm_zone)?;
+                    abbrs.push((tztm.tm_zone, zone.clone()));
+                    zone
+                },
+            };
+            let tm = Tm::with_zone(&tztm, zone);
+            segment = self.segment_for(*time, &tm).or(segment);
+            tms.push(tm);
+        }
+        Ok(tms)
     }
 
     /// Convert local time [`Tm`] to system time.
@@ -94,33 +1005,728 @@ impl Timezone {
     /// determined.  The `mktime` function returns the specified calendar time; If the calendar time cannot be
     /// represented, it returns an error.
     pub fn mktime(&self, tm: &Tm) -> Result<TimeT, String> {
-        match unsafe { mktime_z(self.tz, &tm.into()) } {
-            -1    => Err(format!("Invalid date specified")),
-            time  => Ok(time),
+        crate::checked_mktime(tm, |tztm| unsafe { mktime_z(self.tz, tztm) })
+    }
+
+    /// Convert a batch of local time [`Tm`]s to system times.
+    ///
+    /// This is equivalent to calling [`mktime`][Timezone::mktime] on each `Tm`. It stops at the first one that
+    /// fails to convert.
+    pub fn mktime_many(&self, tms: &[Tm]) -> Result<Vec<TimeT>, String> {
+        tms.iter().map(|tm| self.mktime(tm)).collect()
+    }
+
+    /// Like [`localtime_many`][Timezone::localtime_many] but splits the work across rayon's thread pool.
+    #[cfg(feature = "rayon")]
+    pub fn par_localtime_many(&self, times: &[TimeT]) -> Result<Vec<Tm>, String> {
+        use rayon::prelude::*;
+        let chunks = times.par_chunks(PAR_CHUNK).map(|chunk| self.localtime_many(chunk)).collect::<Result<Vec<_>, _>>()?;
+        Ok(chunks.into_iter().flatten().collect())
+    }
+
+    /// Like [`mktime_many`][Timezone::mktime_many] but splits the work across rayon's thread pool.
+    #[cfg(feature = "rayon")]
+    pub fn par_mktime_many(&self, tms: &[Tm]) -> Result<Vec<TimeT>, String> {
+        use rayon::prelude::*;
+        tms.par_iter().with_min_len(PAR_CHUNK).map(|tm| self.mktime(tm)).collect()
+    }
+
+    /// Convert local time [`Tm`] to system time, but only if it is already normalized.
+    ///
+    /// [`mktime`][Timezone::mktime] happily normalizes `Feb 30` into March and 02:30 on the day the clocks
+    /// spring forward into 03:30. `mktime_strict` returns an error instead whenever the result wouldn't have the
+    /// same wall clock time as `tm` (the seconds, minutes, hours, day, month and year). That happens when a
+    /// field is out of range (see [`Tm::validate`]), when the local time doesn't exist in this zone, and when a
+    /// non-negative `tm_isdst` doesn't match the zone's rules for that time. `tm_wday` and `tm_yday` are
+    /// ignored, like `mktime`.
+    pub fn mktime_strict(&self, tm: &Tm) -> Result<TimeT, String> {
+        let mut check = tm.clone();
+        (check.tm_wday, check.tm_yday) = (0, 0);
+        check.validate().map_err(|problems| problems.join(", "))?;
+        let time = self.mktime(tm)?;
+        let result = self.localtime(time)?;
+        let moved = tm.diff_fields(&result).into_iter()
+                                           .filter(|d| matches!(d.field, "tm_sec" | "tm_min" | "tm_hour" | "tm_mday" | "tm_mon" | "tm_year"))
+                                           .map(|d| d.to_string())
+                                           .collect::<Vec<_>>();
+        if !moved.is_empty() {
+            return Err(format!("Local time does not exist as specified ({})", moved.join(", ")));
         }
+        Ok(time)
+    }
+
+    /// Convert local time [`Tm`] to system time, choosing what happens when `tm_isdst` contradicts the zone.
+    ///
+    /// `mktime` silently shifts the result by the DST savings when `tm_isdst` is non-negative and doesn't
+    /// match the rules in effect at that local time, which is a common source of off-by-an-hour bugs. See
+    /// [`DstConflict`] for the options. A negative `tm_isdst` never conflicts. During an ambiguous hour (when
+    /// the clocks fall back) either flag value is valid and selects which of the two instants is returned.
+    pub fn mktime_dst(&self, tm: &Tm, conflict: DstConflict) -> Result<TimeT, MktimeError> {
+        let mut tm = tm.clone();
+        if conflict == DstConflict::Ignore {
+            tm.tm_isdst = -1;
+        }
+        let time = self.mktime(&tm).map_err(|_| MktimeError::InvalidDate)?;
+        if conflict == DstConflict::Error && tm.tm_isdst >= 0 {
+            let actual = self.localtime(time).map_err(|_| MktimeError::InvalidDate)?.tm_isdst;
+            if (actual > 0) != (tm.tm_isdst > 0) {
+                return Err(MktimeError::DstConflict{ requested: tm.tm_isdst, actual });
+            }
+        }
+        Ok(time)
+    }
+
+    /// Convert local time [`Tm`] to system time without relying on libtz's guess about DST.
+    ///
+    /// When `tm_isdst` is negative, C's `mktime` "attempts to divine whether daylight saving time is in
+    /// effect", and the answer for a repeated or skipped local time depends on its internal search order (and
+    /// may change between calls). This ignores `tm_isdst` entirely and instead finds every instant in the
+    /// zone's transition data whose local time matches `tm`'s wall clock fields (after normalizing them like
+    /// [`Tm::normalized`]). There is normally exactly one; when there are two or none `resolve` chooses what
+    /// happens. The result depends only on `tm` and the zone.
+    pub fn mktime_resolve(&self, tm: &Tm, resolve: Resolve) -> Result<TimeT, MktimeError> {
+        let wall = tm.wall_seconds();
+        let LocalCandidates{ times, gap } = self.local_candidates(wall).map_err(|_| MktimeError::InvalidDate)?;
+        resolve_candidates(wall, &times, gap, resolve)
+    }
+
+    /// Every reading of `tm`'s wall clock time in this zone, in time order: one normally, two when the clocks
+    /// fall back (so a UI can offer both), and none in a gap when they spring forward. Like
+    /// [`Timezone::mktime_resolve`], `tm_isdst` is ignored and the other fields are normalized.
+    ///
+    /// ```
+    /// use libtz::{Timezone, Tm};
+    /// let tz = Timezone::new("America/New_York").unwrap();
+    /// let offsets = tz.offsets_for_local(&Tm::from_ymd_hms(2024, 11, 3, 1, 30, 0)).unwrap();
+    /// assert_eq!(offsets.iter().map(|o| o.abbr.as_str()).collect::<Vec<_>>(), ["EDT", "EST"]);
+    /// assert_eq!(offsets[1].time - offsets[0].time, 3600);
+    /// assert!(tz.offsets_for_local(&Tm::from_ymd_hms(2024, 3, 10, 2, 30, 0)).unwrap().is_empty());
+    /// ```
+    pub fn offsets_for_local(&self, tm: &Tm) -> Result<Vec<OffsetInfo>, String> {
+        self.local_candidates(tm.wall_seconds())?.times.into_iter().map(|time| {
+            let tm = self.localtime(time)?;
+            Ok(OffsetInfo{ time, gmtoff: tm.tm_gmtoff, isdst: tm.tm_isdst > 0, abbr: tm.tm_zone.to_string() })
+        }).collect()
+    }
+
+    // The first instant whose local time is `wall`, or if a transition skipped over it, the instant it would be
+    // with the offset from before the transition (which lands after the transition, the way a wall clock that
+    // wasn't changed would). This is how RFC 5545 resolves local times and what people expect of alarms.
+    pub(crate) fn time_for_wall_forward(&self, wall: i64) -> Result<TimeT, String> {
+        let LocalCandidates{ times, gap } = self.local_candidates(wall)?;
+        times.first().copied().or_else(|| gap.map(|(before, _)| wall - before)).ok_or_else(|| "Invalid date specified".to_string())
+    }
+
+    /// How long from `now` until the wall clock in this zone reads `target`, for setting a timer.
+    ///
+    /// If `target` happens twice (when the clocks fall back) this is the first time. If it's skipped (when the
+    /// clocks spring forward) this is when a clock that wasn't changed would have read it, so 02:30 in a
+    /// 02:00-03:00 gap is 03:30. `tm_isdst`, `tm_wday` and `tm_yday` are ignored. Targets before `now` are an
+    /// error.
+    ///
+    /// ```
+    /// use libtz::{Timezone, Tm};
+    /// let tz = Timezone::new("Europe/London").unwrap();
+    /// let now = 1700000000; // 2023-11-14 22:13:20 GMT
+    /// let wake = tz.deadline(now, &Tm::from_ymd_hms(2023, 11, 15, 7, 0, 0)).unwrap();
+    /// assert_eq!(wake.as_secs(), 8 * 3600 + 46 * 60 + 40);
+    /// ```
+    pub fn deadline(&self, now: TimeT, target: &Tm) -> Result<std::time::Duration, String> {
+        let time = self.time_for_wall_forward(target.wall_seconds())?;
+        if time < now {
+            return Err("Deadline is in the past".to_string());
+        }
+        // time - now can overflow an i64, but never a u64.
+        Ok(std::time::Duration::from_secs(time.abs_diff(now)))
+    }
+
+    // Find every instant whose local time is `wall` (see Tm::wall_seconds()).
+    pub(crate) fn local_candidates(&self, wall: i64) -> Result<LocalCandidates, String> {
+        // tzcode's offsets are less than a week, so any offset that could apply is in effect within a week or so.
+        const WINDOW: TimeT = 8 * 24 * 60 * 60;
+        let (from, to) = wall.checked_sub(WINDOW).zip(wall.checked_add(WINDOW)).ok_or("Time out of range")?;
+        let transitions = self.transitions_between(from, to)?;
+        let mut offsets = vec![self.localtime(from)?.tm_gmtoff];
+        offsets.extend(transitions.iter().map(|(_, _, after)| after.tm_gmtoff));
+        let mut candidates = vec![];
+        for offset in offsets {
+            let Some(time) = wall.checked_sub(offset) else { continue };
+            if self.localtime(time)?.tm_gmtoff == offset && !candidates.contains(&time) {
+                candidates.push(time);
+            }
+        }
+        candidates.sort();
+        let gap = if candidates.is_empty() {
+            transitions.iter().find(|(at, before, after)| wall.saturating_sub(before.tm_gmtoff) >= *at && wall.saturating_sub(after.tm_gmtoff) < *at)
+                              .map(|(_, before, after)| (before.tm_gmtoff, after.tm_gmtoff))
+        } else {
+            None
+        };
+        Ok(LocalCandidates{ times: candidates, gap })
+    }
+
+    /// Convert `tm`, a local time in this zone, to the same instant's local time in `to`.
+    ///
+    /// ```
+    /// use libtz::{Timezone, Tm};
+    /// let (berlin, sydney) = (Timezone::new("Europe/Berlin").unwrap(), Timezone::new("Australia/Sydney").unwrap());
+    /// let nine_am = Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(2023, 11, 14, 9, 0, 0) };
+    /// let tm = berlin.convert(&nine_am, &sydney).expect("convert");
+    /// assert_eq!((tm.tm_mday, tm.tm_hour, &*tm.tm_zone), (14, 19, "AEDT"));
+    /// ```
+    ///
+    /// This uses [`Timezone::mktime`], so a local time that occurs twice or not at all is resolved however
+    /// libtz resolves it (depending on `tm_isdst`). Use [`Timezone::convert_resolve`] to choose.
+    pub fn convert(&self, tm: &Tm, to: &Timezone) -> Result<Tm, String> {
+        to.localtime(self.mktime(tm)?)
+    }
+
+    /// Like [`Timezone::convert`], but resolve local times that occur twice or not at all in this zone as
+    /// `resolve` says (see [`Timezone::mktime_resolve`]).
+    pub fn convert_resolve(&self, tm: &Tm, to: &Timezone, resolve: Resolve) -> Result<Tm, MktimeError> {
+        to.localtime(self.mktime_resolve(tm, resolve)?).map_err(|_| MktimeError::InvalidDate)
+    }
+
+    /// Add `duration` to `time` in this zone.
+    ///
+    /// The calendar units (years, months, weeks and days) move the local date and keep the local time of day,
+    /// so `P1D` from noon is noon the next day even if that day is 23 or 25 hours long. A day of the month
+    /// that doesn't exist in the new month is clamped (Jan 31 plus `P1M` is Feb 28 or 29). If the resulting
+    /// local time is repeated the first one is used, and if it's skipped it's moved forward (see
+    /// [`Timezone::deadline`]). Then the clock units (hours, minutes and seconds) are added as exact elapsed
+    /// seconds, so `PT24H` across a DST change isn't the same as `P1D`.
+    ///
+    /// ```
+    /// use libtz::{Timezone, CivilDuration};
+    /// let ny = Timezone::new("America/New_York").unwrap();
+    /// let noon = 1699027200; // 2023-11-03 12:00 EDT, two days before DST ends
+    /// let days = ny.add_duration(noon, &"P2D".parse().unwrap()).unwrap();
+    /// let hours = ny.add_duration(noon, &"PT48H".parse().unwrap()).unwrap();
+    /// assert_eq!((ny.localtime(days).unwrap().tm_hour, ny.localtime(hours).unwrap().tm_hour), (12, 11));
+    /// ```
+    pub fn add_duration(&self, time: TimeT, duration: &crate::CivilDuration) -> Result<TimeT, String> {
+        let overflow = || "Time out of range".to_string();
+        let mut time = time;
+        if duration.years != 0 || duration.months != 0 || duration.weeks != 0 || duration.days != 0 {
+            let tm = self.localtime(time)?;
+            let months = tm.tm_year as i64 * 12 + tm.tm_mon as i64 + duration.years as i64 * 12 + duration.months as i64;
+            let days = duration.weeks as i64 * 7 + duration.days as i64;
+            time = self.with_date(&tm, months.div_euclid(12) + 1900, months.rem_euclid(12) as i32, days, Policy::Clamp)?;
+        }
+        let secs = duration.hours as i64 * 3600 + duration.minutes as i64 * 60 + duration.seconds as i64;
+        time.checked_add(secs).ok_or_else(overflow)
+    }
+
+    /// The difference from `a` to `b` in calendar and clock units, the way people say "2 months, 3 days and 4
+    /// hours": the most whole months (shown as years and months) and then whole days that can be added to `a`
+    /// without passing `b`, and the rest as hours, minutes and seconds of elapsed time. It's the inverse of
+    /// [`Timezone::add_duration`]: adding the result to `a` gives `b`. If `b` is before `a` every unit is
+    /// negative (the difference from `b` to `a`, negated). Weeks are always 0.
+    ///
+    /// ```
+    /// use libtz::{Timezone, CivilDuration};
+    /// let tz = Timezone::new("Europe/London").unwrap();
+    /// let a = 1700000000; // 2023-11-14 22:13:20 GMT
+    /// let b = 1705582800; // 2024-01-18 13:00:00 GMT
+    /// let delta = tz.diff(a, b).unwrap();
+    /// assert_eq!(delta, CivilDuration{ months: 2, days: 3, hours: 14, minutes: 46, seconds: 40, ..Default::default() });
+    /// assert_eq!(tz.add_duration(a, &delta), Ok(b));
+    /// ```
+    pub fn diff(&self, a: TimeT, b: TimeT) -> Result<crate::CivilDuration, String> {
+        if b < a {
+            let d = self.diff(b, a)?;
+            return Ok(crate::CivilDuration{ years: -d.years, months: -d.months, weeks: 0, days: -d.days, hours: -d.hours,
+                                            minutes: -d.minutes, seconds: -d.seconds });
+        }
+        let out_of_range = || "Difference out of range".to_string();
+        let (ta, tb) = (self.localtime(a)?, self.localtime(b)?);
+        let mut months = (tb.tm_year as i64 - ta.tm_year as i64) * 12 + (tb.tm_mon - ta.tm_mon) as i64;
+        let add = |months: i64, days: i64| -> Result<TimeT, String> {
+            let months = i32::try_from(months).map_err(|_| out_of_range())?;
+            let days = i32::try_from(days).map_err(|_| out_of_range())?;
+            self.add_duration(a, &crate::CivilDuration{ months, days, ..Default::default() })
+        };
+        if add(months, 0)? > b {
+            months -= 1;
+        }
+        let tm = self.localtime(add(months, 0)?)?;
+        let day_number = |tm: &Tm| crate::calendar::days_from_civil(tm.tm_year as i64 + 1900, tm.tm_mon, tm.tm_mday as i64);
+        let mut days = day_number(&tb) - day_number(&tm);
+        if add(months, days)? > b {
+            days -= 1;
+        }
+        let secs = b - add(months, days)?;
+        Ok(crate::CivilDuration{ years: (months / 12) as i32, months: (months % 12) as i32, weeks: 0, days: days as i32,
+                                 hours: i32::try_from(secs / 3600).map_err(|_| out_of_range())?,
+                                 minutes: (secs / 60 % 60) as i32, seconds: (secs % 60) as i32 })
+    }
+
+    /// Add `months` calendar months (subtract, if negative) to `tm`, a local time in this zone, keeping the day
+    /// of the month and the time of day. `policy` says what happens when the day doesn't exist in the new month.
+    /// The local time is resolved like [`Timezone::add_duration`] does, and `tm_isdst` is ignored.
+    ///
+    /// ```
+    /// use libtz::{Timezone, Tm, Policy};
+    /// let tz = Timezone::new("Europe/Paris").unwrap();
+    /// let jan31 = Tm::from_ymd_hms(2024, 1, 31, 9, 0, 0);
+    /// let day = |policy| tz.add_months(&jan31, 1, policy).map(|tm| (tm.tm_mon + 1, tm.tm_mday));
+    /// assert_eq!(day(Policy::Clamp), Ok((2, 29)));
+    /// assert_eq!(day(Policy::Overflow), Ok((3, 2)));
+    /// assert!(day(Policy::Reject).is_err());
+    /// ```
+    pub fn add_months(&self, tm: &Tm, months: i32, policy: Policy) -> Result<Tm, String> {
+        let total = tm.tm_year as i64 * 12 + tm.tm_mon as i64 + months as i64;
+        self.localtime(self.with_date(tm, total.div_euclid(12) + 1900, total.rem_euclid(12) as i32, 0, policy)?)
+    }
+
+    /// Add `years` years (subtract, if negative) to `tm`, a local time in this zone, keeping the month, day and
+    /// time of day. `policy` says what happens to Feb 29 when the new year isn't a leap year: [`Policy::Clamp`]
+    /// gives Feb 28, [`Policy::Overflow`] gives Mar 1 and [`Policy::Reject`] is an error. Otherwise this is like
+    /// [`Timezone::add_months`].
+    ///
+    /// ```
+    /// use libtz::{Timezone, Tm, Policy};
+    /// let tz = Timezone::new("Europe/Paris").unwrap();
+    /// let leap_day = Tm::from_ymd_hms(2024, 2, 29, 9, 0, 0);
+    /// let day = |policy| tz.add_years(&leap_day, 1, policy).map(|tm| (tm.tm_mon + 1, tm.tm_mday));
+    /// assert_eq!(day(Policy::Clamp), Ok((2, 28)));
+    /// assert_eq!(day(Policy::Overflow), Ok((3, 1)));
+    /// assert!(day(Policy::Reject).is_err());
+    /// assert_eq!(tz.add_years(&leap_day, 4, Policy::Reject).map(|tm| tm.tm_mday), Ok(29));
+    /// ```
+    pub fn add_years(&self, tm: &Tm, years: i32, policy: Policy) -> Result<Tm, String> {
+        let total = tm.tm_year as i64 * 12 + tm.tm_mon as i64 + years as i64 * 12;
+        self.localtime(self.with_date(tm, total.div_euclid(12) + 1900, total.rem_euclid(12) as i32, 0, policy)?)
+    }
+
+    // `tm`'s time of day on `mday` of `month` (0 based) in `year`, then `days` days later, as a system time.
+    // Days past the end of the month are handled according to `policy` (before `days` are added).
+    pub(crate) fn with_date(&self, tm: &Tm, year: i64, month: i32, days: i64, policy: Policy) -> Result<TimeT, String> {
+        let last = crate::calendar::month_days(year, month);
+        let mday = match policy {
+            _ if tm.tm_mday <= last => tm.tm_mday,
+            Policy::Clamp           => last,
+            Policy::Overflow        => tm.tm_mday,
+            Policy::Reject          => return Err(format!("{} {} has no day {}", crate::MONTH_ABBRS[month as usize], year, tm.tm_mday)),
+        };
+        let days = crate::calendar::days_from_civil(year, month, mday as i64).checked_add(days).ok_or("Time out of range")?;
+        let secs = tm.tm_hour as i64 * 3600 + tm.tm_min as i64 * 60 + tm.tm_sec as i64;
+        let wall = days.checked_mul(86400).and_then(|d| d.checked_add(secs)).ok_or("Time out of range")?;
+        self.time_for_wall_forward(wall)
+    }
+
+    /// Summarize when daylight saving time starts and ends in `year` (the full year, like `2024`).
+    ///
+    /// Returns `None` if the zone doesn't both enter and leave daylight saving time during the year (including
+    /// when DST is in effect all year). Years whose end can't be represented (like `i32::MAX`) are an error.
+    pub fn dst_schedule(&self, year: i32) -> Result<Option<DstYear>, String> {
+        let transitions = self.transitions_between(year_start(year as i64)?, year_start(year as i64 + 1)?)?;
+        let start = transitions.iter().find(|(_, before, after)| before.tm_isdst == 0 && after.tm_isdst > 0);
+        let end   = transitions.iter().rev().find(|(_, before, after)| before.tm_isdst > 0 && after.tm_isdst == 0);
+        Ok(match (start, end) {
+            (Some((start, before, after)), Some((end, _, _))) => Some(DstYear{ start: *start,
+                                                                               end: *end,
+                                                                               savings: (after.tm_gmtoff - before.tm_gmtoff) as i32 }),
+            _ => None,
+        })
+    }
+
+    /// The standard time offset for the era around `at`: the offset itself when DST isn't
+    /// in effect, otherwise the one in effect before DST started (or, failing that, after it ends). Standard
+    /// offsets change too (Europe/Moscow has had several), so this depends on `at`.
+    ///
+    /// ```
+    /// let tz = libtz::Timezone::new("America/New_York").expect("timezone alloc");
+    /// let summer = 1688169600; // 2023-07-01
+    /// assert_eq!(tz.standard_offset(summer), Ok(-5 * 3600));
+    /// assert_eq!(tz.dst_savings(summer), Ok(3600));
+    /// assert_eq!(tz.dst_savings(1700000000), Ok(0)); // November
+    /// ```
+    pub fn standard_offset(&self, at: TimeT) -> Result<i64, String> {
+        // Far enough to get back through any DST period, even "double summer time" that lasted over a year.
+        const SEARCH: TimeT = 2 * 366 * 24 * 60 * 60;
+        let tm = self.localtime(at)?;
+        if tm.tm_isdst <= 0 {
+            return Ok(tm.tm_gmtoff);
+        }
+        let earlier = self.transitions_between(at.saturating_sub(SEARCH), at)?;
+        if let Some((_, before, _)) = earlier.into_iter().rev().find(|(_, before, _)| before.tm_isdst == 0) {
+            return Ok(before.tm_gmtoff);
+        }
+        match self.transitions_between(at, at.saturating_add(SEARCH))?.into_iter().find(|(_, _, after)| after.tm_isdst == 0) {
+            Some((_, _, after)) => Ok(after.tm_gmtoff),
+            None                => Err(format!("No standard time near {}", at)),
+        }
+    }
+
+    /// How far DST moves the clocks at `at` (the offset minus [`Timezone::standard_offset`]): 0 when DST isn't
+    /// in effect, usually 3600 when it is, but 1800 in Australia/Lord_Howe, 7200 for Britain's wartime "double
+    /// summer time", and negative in zones whose DST is in winter (like Europe/Dublin).
+    pub fn dst_savings(&self, at: TimeT) -> Result<i64, String> {
+        Ok(self.localtime(at)?.tm_gmtoff - self.standard_offset(at)?)
+    }
+
+    /// How long the offset, DST flag and abbreviation in effect at `now` stay in effect: the time from `now`
+    /// until the next change. `None` means they never change again.
+    ///
+    /// Transitions in the zone's data that don't change anything are skipped. Changes made by the zone's POSIX
+    /// rule (after its last transition) are found up to two years ahead, which covers every rule with DST.
+    pub fn time_until_next_transition(&self, now: TimeT) -> Result<Option<std::time::Duration>, String> {
+        const HORIZON: TimeT = 2 * 366 * 24 * 60 * 60;
+        let tm = self.localtime(now)?;
+        let mut from = now;
+        if let Ok(zi) = self.zone_info() {
+            for transition in zi.transitions.iter().filter(|t| t.time > now) {
+                let after = self.localtime(transition.time)?;
+                if (after.tm_gmtoff, after.tm_isdst, &after.tm_zone) != (tm.tm_gmtoff, tm.tm_isdst, &tm.tm_zone) {
+                    return Ok(Some(std::time::Duration::from_secs((transition.time - now) as u64)));
+                }
+                from = transition.time;
+            }
+        }
+        let next = self.transitions_between(from, from.saturating_add(HORIZON))?.first().map(|(time, _, _)| *time);
+        Ok(next.map(|time| std::time::Duration::from_secs((time - now) as u64)))
+    }
+
+    /// The first time from `from` on when this zone and `other` have different UTC offsets, or `None` if
+    /// they never will by their known rules: until then one can stand in for the other (their abbreviations and
+    /// DST flags may still differ).
+    ///
+    /// The offsets can only start to differ when one of the zones changes, so it checks their transitions and,
+    /// past the end of their transition tables, their POSIX rules. Rules that aren't the same are compared for 400
+    /// years, after which the Gregorian calendar repeats.
+    ///
+    /// ```
+    /// use libtz::Timezone;
+    /// let (detroit, new_york) = (Timezone::new("America/Detroit").unwrap(), Timezone::new("America/New_York").unwrap());
+    /// assert_eq!(detroit.equivalent_until(&new_york, 1700000000), Ok(None));
+    /// let (phoenix, denver) = (Timezone::new("America/Phoenix").unwrap(), Timezone::new("America/Denver").unwrap());
+    /// assert_eq!(phoenix.equivalent_until(&denver, 1700000000), Ok(Some(1710061200))); // Denver's next DST
+    /// ```
+    pub fn equivalent_until(&self, other: &Timezone, from: TimeT) -> Result<Option<TimeT>, String> {
+        const YEAR: TimeT = 366 * 24 * 60 * 60;
+        let differ = |t: TimeT| -> Result<bool, String> { Ok(self.localtime(t)?.tm_gmtoff != other.localtime(t)?.tm_gmtoff) };
+        if differ(from)? {
+            return Ok(Some(from));
+        }
+        let (a, b) = (self.zone_info().ok(), other.zone_info().ok());
+        let table_end = |zi: Option<&ZoneInfo>| zi.and_then(|zi| zi.transitions.last()).map_or(from, |t| t.time.max(from));
+        let (rules_start, rules_end) = (table_end(a).min(table_end(b)), table_end(a).max(table_end(b)));
+
+        // Before either zone is on its POSIX rule, the changes are all in the tables.
+        let mut changes: Vec<TimeT> = a.into_iter().chain(b).flat_map(|zi| zi.transitions.iter().map(|t| t.time))
+                                      .filter(|t| *t > from && *t <= rules_start).collect();
+        changes.sort_unstable();
+        for t in changes {
+            if differ(t)? {
+                return Ok(Some(t));
+            }
+        }
+
+        let same_rules = matches!((a, b), (Some(a), Some(b)) if a.footer == b.footer);
+        let horizon = rules_end.saturating_add(400 * YEAR);
+        let mut start = rules_start;
+        while start < horizon && !(same_rules && start >= rules_end) {
+            let end = start.saturating_add(YEAR);
+            let mut changes: Vec<TimeT> = self.transitions_between(start, end)?.into_iter().chain(other.transitions_between(start, end)?)
+                                              .map(|(t, _, _)| t).filter(|t| *t > from).collect();
+            changes.sort_unstable();
+            for t in changes {
+                if differ(t)? {
+                    return Ok(Some(t));
+                }
+            }
+            start = end;
+        }
+        Ok(None)
+    }
+
+    // Find every time in `[start, end)` where the offset, DST flag or abbreviation changes, along with the
+    // local time just before and at the change. The changes come from the zone's transitions and, past the last
+    // one, its POSIX rule. Zones without that data are probed instead (see transitions_probed()).
+    pub(crate) fn transitions_between(&self, start: TimeT, end: TimeT) -> Result<Vec<(TimeT, Tm, Tm)>, String> {
+        let Some(candidates) = self.transition_candidates(start, end) else { return self.transitions_probed(start, end) };
+        let mut transitions = vec![];
+        for t in candidates {
+            // libtz has the final say: some transitions (like ones that only change isstd) don't change anything.
+            let (before, after) = (self.localtime(t - 1)?, self.localtime(t)?);
+            if !same_local_type(&before, &after) {
+                transitions.push((t, before, after));
+            }
+        }
+        Ok(transitions)
+    }
+
+    // The times in `(start, end)` when the zone's transition table or its POSIX rule say it might change, in
+    // order. `None` if the data isn't there to say: zones with leap seconds, ones whose data can't be read, and
+    // DST rules without dates (which libtz takes from its `posixrules` file).
+    fn transition_candidates(&self, start: TimeT, end: TimeT) -> Option<Vec<TimeT>> {
+        let zi = self.zone_info().ok()?;
+        if !zi.leap_seconds.is_empty() {
+            return None;
+        }
+        let mut times: Vec<TimeT> = zi.transitions.iter().map(|t| t.time).filter(|t| *t > start && *t < end).collect();
+        let footer = match zi.footer.as_deref() {
+            Some(footer) => footer.parse::<crate::posix::PosixTz>().ok()?,
+            None         => return Some(times),
+        };
+        let Some(dst) = footer.dst else { return Some(times) };
+        let (dst_start, dst_end) = dst.rule?;
+        let from = zi.transitions.last().map_or(start, |last| last.time.max(start));
+        if from >= end {
+            return Some(times);
+        }
+        let year = |t: TimeT| crate::calendar::civil_from_days(t.div_euclid(86400)).0;
+        // Saturating, since times near the ends of TimeT land in years whose seconds don't fit.
+        let at = |year: i64, date: &crate::PosixRuleDate, offset: i32| crate::posix::rule_day(year, &date.day).saturating_mul(86400)
+                                                                         .saturating_add(date.time as i64 - offset as i64);
+        let mut rule_times: Vec<TimeT> = (year(from) - 1..=year(end) + 1)
+            .flat_map(|y| [at(y, &dst_start, footer.std_offset), at(y, &dst_end, dst.offset)])
+            .filter(|t| *t > from && *t < end).collect();
+        rule_times.sort_unstable();
+        times.extend(rule_times);
+        times.dedup();
+        Some(times)
+    }
+
+    // transitions_between() for zones whose data isn't available: probe localtime() a day at a time and then
+    // bisect down to the exact second. Changes that revert within a single day are not found.
+    fn transitions_probed(&self, start: TimeT, end: TimeT) -> Result<Vec<(TimeT, Tm, Tm)>, String> {
+        const STEP: TimeT = 24 * 60 * 60;
+        let mut transitions = vec![];
+        let (mut t, mut tm) = (start, self.localtime(start)?);
+        while t < end {
+            let next = (t + STEP).min(end);
+            let next_tm = self.localtime(next)?;
+            if !same_local_type(&tm, &next_tm) {
+                // Invariant: lo has the old state, hi has a different one.
+                let (mut lo, mut hi) = (t, next);
+                while hi - lo > 1 {
+                    let mid = lo + (hi - lo) / 2;
+                    if same_local_type(&tm, &self.localtime(mid)?) { lo = mid } else { hi = mid }
+                }
+                let before = self.localtime(lo)?;
+                let after = self.localtime(hi)?;
+                if hi < end {
+                    transitions.push((hi, before, after.clone()));
+                }
+                (t, tm) = (hi, after);
+                continue;
+            }
+            (t, tm) = (next, next_tm);
+        }
+        Ok(transitions)
+    }
+
+    /// Encode the zone's compiled data as a TZif file. See [`ZoneInfo::to_tzif`].
+    pub fn to_tzif(&self) -> Result<Vec<u8>, String> {
+        self.zone_info()?.to_tzif()
+    }
+
+    /// Summarize the zone's compiled data. This is cheap once the zone's data has been parsed (which happens
+    /// the first time it's needed).
+    pub fn stats(&self) -> Result<ZoneStats, String> {
+        let zi = self.zone_info()?;
+        Ok(ZoneStats{
+            transition_count: zi.transitions.len(),
+            type_count:       zi.types.len(),
+            abbr_count:       zi.abbreviations().len(),
+            has_leap_table:   !zi.leap_seconds.is_empty(),
+            earliest:         zi.transitions.first().map(|t| t.time),
+            latest:           zi.transitions.last().map(|t| t.time),
+        })
+    }
+
+    /// A `Debug` view of the zone as of `now`: its name, the offset, abbreviation and DST flag in effect, whether
+    /// it's leap-aware, and when the next transition is. Unlike the zone's own `Debug` output this does real work
+    /// (it loads the zone's data and searches for the next transition), so it's meant for dumping state, not for
+    /// hot paths.
+    ///
+    /// ```
+    /// # use libtz::Timezone;
+    /// let tz = Timezone::new("America/New_York").expect("timezone alloc");
+    /// assert_eq!(format!("{:?}", tz.debug_at(1700000000)),
+    ///            r#"Timezone { name: "America/New_York", offset: "-05:00", abbr: "EST", dst: false, leap_aware: false, next_transition: Some("2024-03-10 07:00:00 UTC") }"#);
+    /// ```
+    pub fn debug_at(&self, now: TimeT) -> impl std::fmt::Debug + '_ {
+        DebugAt(self, now)
+    }
+
+    /// Whether the zone's data has a leap second table, meaning its `time_t`s count leap seconds (like the
+    /// zones in `/usr/share/zoneinfo/right`). Use [`Timezone::time2posix`] to convert them to POSIX `time_t`s.
+    pub fn is_leap_aware(&self) -> bool {
+        self.zone_info().is_ok_and(|zi| !zi.leap_seconds.is_empty())
+    }
+
+    /// Whether the zone has the same offset, DST flag and abbreviation for all time, with no leap seconds, so
+    /// its local time is always UTC shifted by a constant (`Etc/GMT-5` or a POSIX rule without DST).
+    /// Code converting lots of times can then skip the zone and just add [`Tm::tm_gmtoff`] from any
+    /// conversion. Zones whose data can't be read are assumed not to be.
+    ///
+    /// ```
+    /// use libtz::Timezone;
+    /// assert!(Timezone::new("Etc/GMT-5").unwrap().is_fixed_offset());
+    /// assert!(Timezone::new("IST-5:30").unwrap().is_fixed_offset());
+    /// assert!(!Timezone::new("America/New_York").unwrap().is_fixed_offset());
+    /// ```
+    pub fn is_fixed_offset(&self) -> bool {
+        self.fixed_offset().is_some()
+    }
+
+    /// Whether the zone is UTC: a fixed offset (see [`Timezone::is_fixed_offset`]) of 0, whatever it's called
+    /// (`UTC`, `Etc/UTC`, `Etc/GMT`, `Zulu`, ...).
+    pub fn is_utc(&self) -> bool {
+        self.fixed_offset() == Some(0)
+    }
+
+    /// The zone for a fixed offset of `hours` east of UTC (from -12 to +14), without having to remember that
+    /// `Etc/GMT` zones' signs are backwards (see [`etc_gmt_name`]).
+    ///
+    /// ```
+    /// let tz = libtz::Timezone::for_utc_offset_hours(8).unwrap();
+    /// assert_eq!(tz.localtime(0).unwrap().tm_hour, 8);
+    /// ```
+    pub fn for_utc_offset_hours(hours: i32) -> Result<Timezone, String> {
+        let offset = crate::UtcOffset::from_hms(hours, 0, 0).ok_or_else(|| format!("Offset of {} hours is out of range", hours))?;
+        Timezone::new(&etc_gmt_name(offset)?)
+    }
+
+    /// The zone for a military (nautical) time zone letter, like `R` ("Romeo", -05:00) or `Z` ("Zulu", UTC), as
+    /// used in aviation and defense logs. See [`UtcOffset::from_military`](crate::UtcOffset::from_military) for
+    /// the letters. The zone is the `Etc/GMT` zone with the same offset, so times are labeled `-05` rather than `R`.
+    ///
+    /// ```
+    /// let tz = libtz::Timezone::from_military('R').unwrap();
+    /// assert_eq!(tz.localtime(0).unwrap().tm_hour, 19);
+    /// ```
+    pub fn from_military(letter: char) -> Result<Timezone, String> {
+        let offset = crate::UtcOffset::from_military(letter).ok_or_else(|| format!("{:?} isn't a military time zone letter", letter))?;
+        Timezone::new(&etc_gmt_name(offset)?)
+    }
+
+    /// The real offset of an `Etc/` zone (like `Etc/GMT-8`, which is +08:00, or `Etc/UTC`), read from its data
+    /// rather than its backwards name. `None` for zones that aren't in `Etc/` or aren't a fixed offset.
+    pub fn etc_offset(&self) -> Option<crate::UtcOffset> {
+        let name = self.provenance.name();
+        if !name.starts_with("Etc/") {
+            return None;
+        }
+        self.fixed_offset().and_then(|offset| crate::UtcOffset::from_seconds(offset as i64))
+    }
+
+    fn fixed_offset(&self) -> Option<i32> {
+        let zi = self.zone_info().ok()?;
+        if !zi.leap_seconds.is_empty() {
+            return None;
+        }
+        // Type 0 is in effect before the first transition.
+        let mut used = std::iter::once(0).chain(zi.transitions.iter().map(|t| t.local_time_type)).map(|i| zi.types.get(i));
+        let first = used.next()??;
+        if !used.all(|t| t.is_some_and(|t| (t.utoff, t.isdst, &t.abbr) == (first.utoff, first.isdst, &first.abbr))) {
+            return None;
+        }
+        // The rule for times after the last transition has to agree too.
+        let Some(footer) = zi.footer.as_deref().filter(|footer| !footer.is_empty()) else { return Some(first.utoff) };
+        let rule: crate::posix::PosixTz = footer.parse().ok()?;
+        let same = rule.dst.is_none() && rule.std_offset == first.utoff && rule.std_abbr == first.abbr && !first.isdst;
+        same.then_some(first.utoff)
+    }
+
+    /// The time from `a` to `b` (negative if `b` is earlier), both POSIX `time_t`s like the system clock gives.
+    ///
+    /// If the zone has a leap second table (see [`Timezone::is_leap_aware`]) the leap seconds between the two
+    /// times are counted in [`Elapsed::si`]. Otherwise there's nothing to count and it's the same as
+    /// [`Elapsed::posix`].
+    ///
+    /// ```
+    /// let tz = libtz::Timezone::new("right/UTC").expect("timezone alloc");
+    /// let elapsed = tz.elapsed(1483142400, 1483228800).expect("elapsed"); // The last day of 2016
+    /// assert_eq!((elapsed.si, elapsed.posix), (86401, 86400));
+    /// ```
+    pub fn elapsed(&self, a: TimeT, b: TimeT) -> Result<Elapsed, String> {
+        let posix = b.checked_sub(a).ok_or("Time out of range")?;
+        let si = match self.is_leap_aware() {
+            true  => self.posix2time(b).checked_sub(self.posix2time(a)).ok_or("Time out of range")?,
+            false => posix,
+        };
+        Ok(Elapsed{ si, posix })
     }
 
     /// Convert from leap-second to POSIX `time_t`s.
     ///
-    /// See [`libtz_sys::time2posix_z`] for details.
+    /// See [`libtz_sys::time2posix_z`](https://docs.rs/libtz-sys/latest/libtz_sys/fn.time2posix_z.html) for details.
     pub fn time2posix(&self, time: TimeT) -> TimeT {
         unsafe { time2posix_z(self.tz, time) }
     }
 
     /// Convert from POSIX to leap-second `time_t`s.
     ///
-    /// See [`libtz_sys::posix2time_z`] for details.
+    /// See [`libtz_sys::posix2time_z`](https://docs.rs/libtz-sys/latest/libtz_sys/fn.posix2time_z.html) for details.
     pub fn posix2time(&self, time: TimeT) -> TimeT {
         unsafe { posix2time_z(self.tz, time) }
     }
 }
 
+// Whether two local times have the same offset, DST flag and abbreviation.
+fn same_local_type(a: &Tm, b: &Tm) -> bool {
+    a.tm_gmtoff == b.tm_gmtoff && a.tm_isdst == b.tm_isdst && a.tm_zone == b.tm_zone
+}
+
+// The first second of `year` in UTC. The year has to fit in an `i32`, like the years `dst_schedule()` takes.
+fn year_start(year: i64) -> Result<TimeT, String> {
+    let tm_year = i32::try_from(year).ok().and_then(|year| year.checked_sub(1900)).ok_or("Year out of range")?;
+    crate::timegm(&Tm{ tm_sec: 0, tm_min: 0, tm_hour: 0, tm_mday: 1, tm_mon: 0, tm_year,
+                       tm_wday: 0, tm_yday: 0, tm_isdst: 0, tm_gmtoff: 0, tm_zone: "".into() })
+}
+
+// libtz's zone state is only written by tzalloc() and tzfree(). localtime_rz() and mktime_z() only read it (and
+// libtz is compiled with THREAD_SAFE for its shared globals) so a Timezone can be shared and sent between threads.
+unsafe impl Send for Timezone {}
+unsafe impl Sync for Timezone {}
+
 impl Drop for Timezone {
     fn drop(&mut self) {
         unsafe { tzfree(self.tz) };
     }
 }
 
+/// Parse a zone name with [`Timezone::new`], so `"Europe/Berlin".parse::<Timezone>()` works.
+impl std::str::FromStr for Timezone {
+    type Err = String;
+    fn from_str(name: &str) -> Result<Timezone, String> {
+        Timezone::new(name)
+    }
+}
+
+/// The zone's name, its offset, abbreviation and DST flag right now, whether it counts leap seconds and when
+/// its offset next changes (in UTC), for dumping application state:
+/// `Timezone { name: "America/New_York", offset: "-05:00", abbr: "EST", dst: false, leap_aware: false, next_transition: Some("2024-03-10 07:00:00 UTC") }`
+impl std::fmt::Debug for Timezone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Timezone").field("provenance", &self.provenance).finish()
+    }
+}
+
+// See Timezone::debug_at().
+struct DebugAt<'a>(&'a Timezone, TimeT);
+
+impl std::fmt::Debug for DebugAt<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let DebugAt(tz, now) = *self;
+        let mut out = f.debug_struct("Timezone");
+        out.field("name", &tz.provenance.name());
+        match tz.localtime(now) {
+            Ok(tm) => out.field("offset", &crate::format_offset(tm.tm_gmtoff, crate::OffsetFormat::Extended))
+                         .field("abbr", &tm.tm_zone)
+                         .field("dst", &(tm.tm_isdst > 0)),
+            Err(e) => out.field("error", &e),
+        };
+        let next = tz.time_until_next_transition(now).ok().flatten()
+                     .and_then(|d| crate::gmtime(now + d.as_secs() as TimeT).ok()).map(|tm| tm.strftime("%F %T UTC"));
+        out.field("leap_aware", &tz.is_leap_aware()).field("next_transition", &next).finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,23 +1745,635 @@ mod tests {
                           tm_yday   :0,
                           tm_isdst  :0,
                           tm_gmtoff :-28800,
-                          tm_zone   :"PST".to_string()});
+                          tm_zone   :"PST".into()});
         assert_eq!(tz.mktime(&tm).expect("unix time"), time); // Round trip
     }
 
     #[test]
     fn default() {
+        let _env = crate::tests::ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
         std::env::set_var("TZ", "Europe/Paris");
-        let tz = Timezone::default().expect("load from TZ");
+        let tz = Timezone::from_env().expect("load from TZ");
         let time = 915177600; // Tonight we're going to party...
         let tm;
         assert_eq!(tz.mktime({tm=tz.localtime(time).expect("localtime"); &tm}).expect("mktime"), time);
-        assert_eq!(tm.tm_zone, "CET");
+        assert_eq!(&*tm.tm_zone, "CET");
+
+        assert_eq!(tz.provenance(), &Provenance::File(PathBuf::from("/usr/share/zoneinfo/Europe/Paris")));
+
+        std::env::set_var("TZ", "");
+        let tz = Timezone::from_env().expect("empty TZ");
+        assert_eq!(tz.provenance(), &Provenance::Utc);
+        let tm = tz.localtime(time).expect("localtime");
+        assert_eq!((tm.tm_gmtoff, &*tm.tm_zone), (0, "UTC"));
+
+        std::env::set_var("TZ", ":Europe/Paris");
+        let tz = Timezone::from_env().expect("load from :TZ");
+        assert_eq!(tz.provenance(), &Provenance::File(PathBuf::from("/usr/share/zoneinfo/Europe/Paris")));
+
+        std::env::set_var("TZ", ":CET-1CEST,M3.5.0,M10.5.0/3");
+        assert!(Timezone::from_env().is_err(), "leading colon never parses a POSIX rule");
+        assert_eq!(Timezone::system().map(|tz| tz.provenance().clone()), Ok(Provenance::SystemDefault(PathBuf::from("/etc/localtime"))));
 
         std::env::remove_var("TZ");
-        let tz = Timezone::default().expect("load from /etc/localtime");
+        let tz = Timezone::from_env().expect("load from /etc/localtime");
         let time = 915177600; // Tonight we're going to party...
         assert_eq!(tz.mktime(&tz.localtime(time).expect("localtime")).expect("mktime"), time);
+        assert_eq!(tz.provenance(), &Provenance::SystemDefault(PathBuf::from("/etc/localtime")));
+        #[allow(deprecated)]
+        let old = Timezone::default().expect("default");
+        assert_eq!(old.provenance(), tz.provenance());
+    }
+
+    #[test]
+    fn many() {
+        let tz = Timezone::new("Europe/London").expect("timezone alloc");
+        let times: Vec<TimeT> = (0..100).map(|i| 1700000000 + i * 86400 * 5).collect();
+        let tms = tz.localtime_many(&times).expect("localtime_many");
+        assert_eq!(tms, times.iter().map(|t| tz.localtime(*t).expect("localtime")).collect::<Vec<_>>());
+        assert!(tms.iter().any(|tm| &*tm.tm_zone == "GMT") && tms.iter().any(|tm| &*tm.tm_zone == "BST"));
+        assert!(Arc::ptr_eq(&tms[0].tm_zone, &tz.localtime(times[0]).expect("localtime").tm_zone), "interned");
+        assert_eq!(tz.mktime_many(&tms).expect("mktime_many"), times);
+        assert_eq!(tz.localtime_many(&[]).expect("empty"), vec![]);
+
+        let mut tm = Tm::try_from(&tz.localtime_raw(0).expect("localtime_raw")).expect("tm");
+        for (time, expected) in times.iter().zip(tms.iter()) {
+            tz.localtime_into(*time, &mut tm).expect("localtime_into");
+            assert_eq!(&tm, expected);
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_many() {
+        let tz = Timezone::new("Europe/London").expect("timezone alloc");
+        let times: Vec<TimeT> = (0..20000).map(|i| 1700000000 + i * 3607).collect();
+        let tms = tz.par_localtime_many(&times).expect("par_localtime_many");
+        assert_eq!(tms, tz.localtime_many(&times).expect("localtime_many"));
+        assert_eq!(tz.par_mktime_many(&tms).expect("par_mktime_many"), times);
+    }
+
+    #[test]
+    fn mktime_minus_one() {
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        let tm = tz.localtime(-1).expect("localtime"); // 1969-12-31 18:59:59 EST
+        assert_eq!(tz.mktime(&tm), Ok(-1));
+        assert_eq!(tz.mktime_strict(&tm), Ok(-1));
+        assert_eq!(tz.mktime(&Tm{ tm_year: i32::MAX, tm_mon: i32::MAX, ..tm }), Err("Invalid date specified".to_string()));
+    }
+
+    #[test]
+    fn mktime_strict() {
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        let mut tm = tz.localtime(1709013600).expect("localtime"); // 2024-02-27 01:00 EST
+        assert_eq!(tz.mktime_strict(&tm), Ok(1709013600));
+        tm.tm_wday = 9; // ignored
+        assert_eq!(tz.mktime_strict(&tm), Ok(1709013600));
+        tm.tm_mday = 30;
+        assert_eq!(tz.mktime_strict(&tm), Err("Feb 30 invalid for 2024".to_string()));
+        tm.tm_mday = 10; tm.tm_mon = 2; tm.tm_hour = 2; tm.tm_isdst = 0; // Spring forward gap
+        assert!(tz.mktime(&tm).is_ok());
+        assert_eq!(tz.mktime_strict(&tm), Err("Local time does not exist as specified (tm_hour: 2 != 3)".to_string()));
+        tm.tm_hour = 1; tm.tm_isdst = 1; // Not DST yet
+        assert!(tz.mktime_strict(&tm).is_err());
+    }
+
+    #[test]
+    fn mktime_dst() {
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        let mut tm = tz.localtime(1705298400).expect("localtime"); // 2024-01-15 01:00 EST
+        tm.tm_isdst = 1;
+        assert_eq!(tz.mktime_dst(&tm, DstConflict::Honor), Ok(1705298400 - 3600));
+        assert_eq!(tz.mktime_dst(&tm, DstConflict::Ignore), Ok(1705298400));
+        assert_eq!(tz.mktime_dst(&tm, DstConflict::Error), Err(MktimeError::DstConflict{ requested: 1, actual: 0 }));
+
+        let mut tm = tz.localtime(1730611800).expect("localtime"); // 2024-11-03 01:30 EDT, the first of two 01:30s
+        assert_eq!(tz.mktime_dst(&tm, DstConflict::Error), Ok(1730611800));
+        tm.tm_isdst = 0;
+        assert_eq!(tz.mktime_dst(&tm, DstConflict::Error), Ok(1730611800 + 3600));
+    }
+
+    #[test]
+    fn mktime_resolve() {
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        let mut tm = tz.localtime(1730611800).expect("localtime"); // 2024-11-03 01:30 EDT, the first of two 01:30s
+        for isdst in [-1, 0, 1] {
+            tm.tm_isdst = isdst;
+            assert_eq!(tz.mktime_resolve(&tm, Resolve::Earlier), Ok(1730611800));
+            assert_eq!(tz.mktime_resolve(&tm, Resolve::Later), Ok(1730611800 + 3600));
+            assert_eq!(tz.mktime_resolve(&tm, Resolve::Error), Err(MktimeError::Ambiguous{ earlier: 1730611800, later: 1730611800 + 3600 }));
+        }
+
+        let mut tm = tz.localtime(1710050400).expect("localtime"); // 2024-03-10 01:00 EST
+        tm.tm_min = 90; // 02:30 doesn't exist
+        assert_eq!(tz.mktime_resolve(&tm, Resolve::Earlier), Ok(1710050400 + 1800)); // 01:30 EST
+        assert_eq!(tz.mktime_resolve(&tm, Resolve::Later), Ok(1710050400 + 5400)); // 03:30 EDT
+        assert_eq!(tz.mktime_resolve(&tm, Resolve::Error), Err(MktimeError::Nonexistent));
+        tm.tm_min = 0;
+        assert_eq!(tz.mktime_resolve(&tm, Resolve::Error), Ok(1710050400));
+    }
+
+    #[test]
+    fn clamped() {
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        let (min, max) = tz.supported_range().expect("supported_range");
+        assert!(tz.localtime(min).is_ok() && tz.localtime(max).is_ok());
+        assert!(tz.localtime(min - 1).is_err() && tz.localtime(max + 1).is_err());
+        assert_eq!(tz.localtime_clamped(TimeT::MIN), Ok(Clamped{ value: tz.localtime(min).expect("localtime"), clamped: true }));
+        assert_eq!(tz.localtime_clamped(max + 1).map(|c| c.value.tm_year), Ok(i32::MAX));
+        assert_eq!(tz.localtime_clamped(0).map(|c| c.clamped), Ok(false));
+        let extreme = Tm{ tm_year: i32::MAX, tm_mon: 12, ..Tm::unix_epoch() };
+        assert_eq!(tz.mktime_clamped(&extreme), Ok(Clamped{ value: max, clamped: true }));
+        assert_eq!(tz.mktime_clamped(&Tm{ tm_year: i32::MIN, tm_mon: -1, ..Tm::unix_epoch() }), Ok(Clamped{ value: min, clamped: true }));
+        assert_eq!(tz.mktime_clamped(&Tm::unix_epoch()), Ok(Clamped{ value: 18000, clamped: false }));
+        assert_eq!(tz.supported_range.get(), Some(&Ok((min, max)))); // Worked out once
+        let utc = Timezone::new("UTC").expect("timezone alloc");
+        assert_eq!(utc.supported_range(), Ok((-67768040609740800, 67768036191676799)));
+    }
+
+    #[test]
+    fn offsets_for_local() {
+        let tz = Timezone::new("Europe/London").expect("timezone alloc");
+        let bst = OffsetInfo{ time: 1698539400, gmtoff: 3600, isdst: true, abbr: "BST".to_string() };
+        let gmt = OffsetInfo{ time: 1698539400 + 3600, gmtoff: 0, isdst: false, abbr: "GMT".to_string() };
+        assert_eq!(tz.offsets_for_local(&Tm::from_ymd_hms(2023, 10, 29, 1, 30, 0)), Ok(vec![bst.clone(), gmt.clone()]));
+        // Not normalized yet, and the DST flag doesn't matter.
+        assert_eq!(tz.offsets_for_local(&Tm{ tm_isdst: 1, ..Tm::from_ymd_hms(2023, 10, 28, 24, 30, 3600) }), Ok(vec![bst.clone(), gmt.clone()]));
+        assert_eq!(tz.offsets_for_local(&Tm::from_ymd_hms(2023, 10, 29, 2, 30, 0)), Ok(vec![OffsetInfo{ time: gmt.time + 3600, ..gmt }]));
+        assert_eq!(tz.offsets_for_local(&Tm::from_ymd_hms(2023, 3, 26, 1, 30, 0)), Ok(vec![]));
+        assert_eq!(tz.offsets_for_local(&Tm::from_ymd_hms(2023, 7, 1, 12, 0, 0)).map(|o| o.len()), Ok(1));
+    }
+
+    #[test]
+    fn transitions_between() {
+        // The same answers as probing, across the end of the transition table and onto the POSIX rule.
+        let (start, end) = (1500000000, 2200000000);
+        for name in ["America/New_York", "Europe/Dublin", "Australia/Lord_Howe", "Africa/Casablanca", "Asia/Tokyo", "EST5EDT,M3.2.0,M11.1.0"] {
+            let tz = Timezone::new(name).expect("timezone alloc");
+            let times = |t: Vec<(TimeT, Tm, Tm)>| t.into_iter().map(|(t, before, after)| (t, before.tm_gmtoff, after.tm_gmtoff)).collect::<Vec<_>>();
+            assert_eq!(times(tz.transitions_between(start, end).expect("transitions")), times(tz.transitions_probed(start, end).expect("probed")), "{}", name);
+        }
+
+        // Changes that revert within a day, which probing a day at a time can't see.
+        let mut zi = ZoneInfo::from_posix(&"AAA0BBB-1,M3.5.0,M10.5.0".parse().expect("posix"));
+        zi.transitions = vec![crate::Transition{ time: 1000000, local_time_type: 1 }, crate::Transition{ time: 1003600, local_time_type: 0 }];
+        zi.footer = Some("AAA0".to_string());
+        let tz = Timezone::from_zone_info("Blip", zi).expect("from_zone_info");
+        assert_eq!(tz.transitions_between(0, 2000000).expect("transitions").iter().map(|(t, _, _)| *t).collect::<Vec<_>>(), [1000000, 1003600]);
+        assert_eq!(tz.transitions_probed(0, 2000000).expect("probed"), []);
+    }
+
+    #[test]
+    fn dst_schedule() {
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        assert_eq!(tz.dst_schedule(2024).expect("dst_schedule"), Some(DstYear{ start: 1710054000,   // 2024-03-10 07:00Z
+                                                                               end:   1730613600,   // 2024-11-03 06:00Z
+                                                                               savings: 3600 }));
+        let tz = Timezone::new("Australia/Lord_Howe").expect("timezone alloc");
+        let dst = tz.dst_schedule(2024).expect("dst_schedule").expect("has DST");
+        assert!(dst.end < dst.start, "southern hemisphere");
+        assert_eq!(dst.savings, 1800);
+        assert_eq!(Timezone::new("Asia/Tokyo").expect("timezone alloc").dst_schedule(2024).expect("dst_schedule"), None);
+        assert!(tz.dst_schedule(i32::MIN).is_err());
+        assert!(tz.dst_schedule(i32::MAX).is_err());
+    }
+
+    #[test]
+    fn stats() {
+        let stats = Timezone::new("America/Los_Angeles").expect("timezone alloc").stats().expect("stats");
+        assert_eq!((stats.type_count, stats.abbr_count, stats.has_leap_table), (6, 5, false));
+        assert_eq!(stats.earliest, Some(-2717640000)); // 1883-11-18 12:07:02 LMT -> PST
+        assert!(stats.transition_count > 100);
+
+        assert!(Timezone::new("right/UTC").expect("timezone alloc").stats().expect("stats").has_leap_table);
+        let tz = Timezone::new("Europe/Paris").expect("timezone alloc");
+        let zi = tz.zone_info().expect("zone_info");
+        let last = zi.transitions.last().expect("transitions");
+        assert_eq!(zi.types[last.local_time_type].abbr, &*tz.localtime(last.time).expect("localtime").tm_zone);
+        assert_eq!(zi.footer.as_deref(), Some("CET-1CEST,M3.5.0,M10.5.0/3"));
+
+        assert_eq!(Timezone::new("EST5EDT,M3.2.0,M11.1.0").expect("timezone alloc").stats().expect("stats"),
+                   ZoneStats{ transition_count: 0, type_count: 2, abbr_count: 2, has_leap_table: false, earliest: None, latest: None });
+    }
+
+    #[test]
+    fn provenance() {
+        assert_eq!(Timezone::new("").expect("empty").provenance(), &Provenance::Utc);
+        assert_eq!(Timezone::new("/usr/share/zoneinfo/Asia/Tokyo").expect("absolute").provenance(),
+                   &Provenance::File(PathBuf::from("/usr/share/zoneinfo/Asia/Tokyo")));
+        // Relative paths are relative to the zoneinfo directory, not the current directory.
+        assert_eq!(Timezone::new("../zoneinfo/Asia/Tokyo").expect("relative").provenance(),
+                   &Provenance::File(PathBuf::from("/usr/share/zoneinfo/../zoneinfo/Asia/Tokyo")));
+        assert_eq!(Timezone::new("EST5EDT,M3.2.0,M11.1.0").expect("posix").provenance(),
+                   &Provenance::PosixRule("EST5EDT,M3.2.0,M11.1.0".to_string()));
+        assert!(Timezone::new(":Not/A_Zone").is_err());
+        let data = std::fs::read("/usr/share/zoneinfo/Asia/Tokyo").expect("read");
+        assert_eq!(Timezone::from_tzif("Tokyo", &data).expect("from_tzif").provenance(), &Provenance::Synthesized("Tokyo".to_string()));
+    }
+
+    #[test]
+    fn new_in() {
+        let dir = std::env::temp_dir().join(format!("libtz-new-in-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("Fixture")).expect("mkdir");
+        std::fs::copy("/usr/share/zoneinfo/Asia/Tokyo", dir.join("Fixture/Tokyo")).expect("copy");
+        let tz = Timezone::new_in(&dir, "Fixture/Tokyo");
+        let missing = Timezone::new_in(&dir, ":Fixture/Missing");
+        let system = Timezone::new_in(&dir, "America/New_York");
+        std::fs::remove_dir_all(&dir).expect("remove");
+
+        let tz = tz.expect("new_in");
+        assert_eq!(tz.provenance(), &Provenance::File(dir.join("Fixture/Tokyo")));
+        assert_eq!(tz.localtime(0).expect("localtime").tm_gmtoff, 9 * 3600);
+        assert!(missing.is_err());
+        assert!(system.is_err(), "must not fall back to the system zone directory");
+
+        let rule = Timezone::new_in(&dir, "EST5EDT,M3.2.0,M11.1.0").expect("posix");
+        assert_eq!(rule.provenance(), &Provenance::PosixRule("EST5EDT,M3.2.0,M11.1.0".to_string()));
+        assert_eq!(rule.localtime(1690000000), Timezone::new("America/New_York").expect("timezone alloc").localtime(1690000000));
+        assert_eq!(Timezone::new_in(&dir, "").expect("utc").provenance(), &Provenance::Utc);
+    }
+
+    #[test]
+    fn layered_zone_dirs() {
+        let top = std::env::temp_dir().join(format!("libtz-zone-dirs-test-{}/top", std::process::id()));
+        let bottom = top.with_file_name("bottom");
+        std::fs::create_dir_all(top.join("Antarctica")).expect("mkdir");
+        std::fs::create_dir_all(bottom.join("Antarctica")).expect("mkdir");
+        std::fs::create_dir_all(bottom.join("Fixture")).expect("mkdir");
+        std::fs::copy("/usr/share/zoneinfo/Asia/Tokyo", top.join("Antarctica/Vostok")).expect("copy");
+        std::fs::copy("/usr/share/zoneinfo/Europe/Paris", bottom.join("Antarctica/Vostok")).expect("copy");
+        std::fs::copy("/usr/share/zoneinfo/Europe/Paris", bottom.join("Fixture/Layered")).expect("copy");
+        // Only names no other test uses, since this changes them for every thread.
+        set_zone_dirs([&top, &bottom]);
+        assert_eq!(zone_dirs(), [top.clone(), bottom.clone(), PathBuf::from(TZDIR)]);
+        let vostok = Timezone::new("Antarctica/Vostok");
+        let layered = Timezone::new("Fixture/Layered");
+        let name = vostok.as_ref().ok().map(|tz| tz.provenance().name());
+        set_zone_dirs(Vec::<PathBuf>::new());
+        let system = Timezone::new("Antarctica/Vostok");
+        std::fs::remove_dir_all(top.parent().expect("parent")).expect("remove");
+
+        let vostok = vostok.expect("new");
+        assert_eq!(vostok.provenance(), &Provenance::File(top.join("Antarctica/Vostok")));
+        assert_eq!(name.as_deref(), Some("Antarctica/Vostok"));
+        assert_eq!(vostok.localtime(0).expect("localtime").tm_gmtoff, 9 * 3600);
+        assert_eq!(layered.expect("new").localtime(0).expect("localtime").tm_gmtoff, 3600);
+        assert_eq!(system.expect("new").provenance(), &Provenance::File(PathBuf::from(TZDIR).join("Antarctica/Vostok")));
+        assert_eq!(zone_dirs(), [PathBuf::from(TZDIR)]);
+    }
+
+    #[test]
+    fn new_right() {
+        let tz = Timezone::new_right("Europe/Lisbon").expect("new_right");
+        assert_eq!(tz.provenance().name(), "right/Europe/Lisbon");
+        assert!(tz.is_leap_aware());
+        assert_eq!(tz.time2posix(1700000000), 1700000000 - 27);
+        assert!(Timezone::new_right("posix/Europe/Lisbon").expect("new_right").is_leap_aware());
+        assert!(!Timezone::new("Europe/Lisbon").expect("timezone alloc").is_leap_aware());
+        let rule = Timezone::new_right("EST5EDT,M3.2.0,M11.1.0").expect("new_right");
+        assert_eq!(rule.provenance(), &Provenance::PosixRule("EST5EDT,M3.2.0,M11.1.0".to_string()));
+        assert!(!rule.is_leap_aware());
+    }
+
+    #[test]
+    fn localtime_detailed() {
+        let tz = Timezone::new("America/Los_Angeles").expect("timezone alloc");
+        let (start, end) = tz.data_range().expect("data_range").expect("has transitions");
+        assert_eq!(tz.localtime_detailed(start - 1).expect("localtime").source, TmSource::Initial);
+        assert_eq!(&*tz.localtime_detailed(start - 1).expect("localtime").tm.tm_zone, "LMT");
+        assert_eq!(tz.localtime_detailed(start).expect("localtime").source, TmSource::Recorded);
+        assert_eq!(tz.localtime_detailed(end).expect("localtime").source, TmSource::Recorded);
+        let far = tz.localtime_detailed(end + 1).expect("localtime");
+        assert_eq!(far.source, TmSource::Rule);
+        assert_eq!(far.tm, tz.localtime(end + 1).expect("localtime"));
+        assert_eq!(Timezone::new("UTC").expect("timezone alloc").localtime_detailed(0).expect("localtime").source, TmSource::Rule);
+    }
+
+    #[test]
+    fn elapsed() {
+        let tz = Timezone::new("right/UTC").expect("timezone alloc");
+        assert_eq!(tz.elapsed(1483228800, 1483142400), Ok(Elapsed{ si: -86401, posix: -86400 }));
+        assert_eq!(tz.elapsed(0, 1700000000), Ok(Elapsed{ si: 1700000027, posix: 1700000000 }));
+        assert_eq!(tz.elapsed(1500000000, 1700000000), Ok(Elapsed{ si: 200000000, posix: 200000000 }));
+        assert_eq!(Timezone::new("UTC").expect("timezone alloc").elapsed(1483142400, 1483228800), Ok(Elapsed{ si: 86400, posix: 86400 }));
+        assert!(tz.elapsed(TimeT::MIN, 1).is_err());
+    }
+
+    #[test]
+    fn abbreviations() {
+        let tz = Timezone::new("America/Los_Angeles").expect("timezone alloc");
+        assert_eq!(tz.abbreviations().expect("abbreviations"), ["LMT", "PST", "PDT", "PWT", "PPT"]);
+        assert_eq!(Timezone::new("UTC").expect("timezone alloc").abbreviations().expect("abbreviations"), ["UTC"]);
+    }
+
+    #[test]
+    fn segment_cache() {
+        let tz = Timezone::new("Europe/London").expect("timezone alloc");
+        // Until the data is parsed a miss is just the plain conversion: no file I/O and nothing cached.
+        tz.localtime(1700000000).expect("localtime");
+        assert!(tz.zone_info.get().is_none() && tz.segment.read().unwrap().is_none());
+        // And a miss doesn't wait on the cache's lock.
+        tz.zone_info().expect("zone_info");
+        {
+            let _reader = tz.segment.read().unwrap();
+            assert_eq!(tz.localtime(1700000000).map(|tm| tm.tm_gmtoff), Ok(0));
+        }
+        assert!(tz.segment.read().unwrap().is_none());
+        tz.localtime(1700000000).expect("localtime");
+        assert!(tz.segment.read().unwrap().is_some());
+
+        let times: Vec<TimeT> = (-2_000_000_000..2_200_000_000).step_by(3_599_993).chain([1711846799, 1711846800, 1729990799, 1729990800]).collect();
+        let expected = tz.localtime_many(&times).expect("localtime_many");
+        for (time, expected) in times.iter().zip(expected.iter()) {
+            assert_eq!(&tz.localtime(*time).expect("localtime"), expected, "{}", time);
+            assert_eq!(&tz.localtime(*time).expect("localtime"), expected, "{} (cached)", time);
+        }
+        let right = Timezone::new("right/Europe/London").expect("timezone alloc");
+        for time in [1483228825, 1483228826, 1483228827] {
+            assert_eq!(right.localtime(time), right.localtime_many(&[time]).map(|tms| tms[0].clone()));
+        }
+    }
+
+    #[test]
+    fn standard_offset() {
+        let lord_howe = Timezone::new("Australia/Lord_Howe").expect("timezone alloc");
+        let january = 1704067200; // 2024-01-01, summer in the southern hemisphere
+        assert_eq!(lord_howe.standard_offset(january), Ok(10 * 3600 + 1800));
+        assert_eq!(lord_howe.dst_savings(january), Ok(1800));
+        assert_eq!(Timezone::new("XXX-100").expect("timezone alloc").standard_offset(0), Ok(100 * 3600));
+
+        let london = Timezone::new("Europe/London").expect("timezone alloc");
+        let bdst = -806976000; // 1944-06-01, during double summer time (GMT+2)
+        assert_eq!((london.standard_offset(bdst), london.dst_savings(bdst)), (Ok(0), Ok(7200)));
+        assert_eq!(london.dst_savings(1700000000), Ok(0));
+
+        let dublin = Timezone::new("Europe/Dublin").expect("timezone alloc");
+        let winter = dublin.localtime(january).expect("localtime");
+        if winter.tm_isdst > 0 { // tzdata's negative DST for Ireland (not in every build of the database)
+            assert_eq!((dublin.standard_offset(january), dublin.dst_savings(january)), (Ok(3600), Ok(-3600)));
+        }
+    }
+
+    #[test]
+    fn initial_era() {
+        let tz = Timezone::new("Europe/Amsterdam").expect("timezone alloc");
+        let first = tz.earliest_transition().expect("earliest_transition").expect("has transitions");
+        let lmt = tz.initial_local_time_type().expect("initial_local_time_type");
+        assert_eq!((lmt.abbr.as_str(), lmt.utoff, lmt.isdst), ("LMT", 1172, false));
+        assert_eq!(tz.localtime(first - 1).map(|tm| tm.tm_gmtoff), Ok(1172));
+        assert_eq!((tz.in_initial_era(first - 1), tz.in_initial_era(first)), (Ok(true), Ok(false)));
+
+        let utc = Timezone::new("UTC").expect("timezone alloc");
+        assert_eq!(utc.earliest_transition(), Ok(None));
+        assert_eq!(utc.initial_local_time_type().map(|t| t.utoff), Ok(0));
+        assert_eq!(utc.in_initial_era(1700000000), Ok(true));
+    }
+
+    #[test]
+    fn equivalent_until() {
+        let tz = |name| Timezone::new(name).expect("timezone alloc");
+        let (paris, berlin, london) = (tz("Europe/Paris"), tz("Europe/Berlin"), tz("Europe/London"));
+        assert_eq!(paris.equivalent_until(&berlin, 1700000000), Ok(None));
+        assert_eq!(paris.equivalent_until(&berlin, 0), Ok(Some(196819200))); // France's DST started in 1976, West Germany's in 1980
+        assert_eq!(paris.equivalent_until(&london, 0), Ok(Some(57722400))); // The end of British Standard Time, 1971-10-31
+        assert_eq!(paris.equivalent_until(&london, 1700000000), Ok(Some(1700000000)));
+        // Different rules that give the same offsets forever.
+        assert_eq!(tz("Asia/Tokyo").equivalent_until(&tz("Asia/Seoul"), 1700000000), Ok(None));
+        assert_eq!(tz("EST5EDT,M3.2.0,M11.1.0").equivalent_until(&tz("EST5EDT,M3.2.0/2,M11.1.0/2"), 1700000000), Ok(None));
+        // Rules that only differ in years when March has 5 Sundays.
+        assert_eq!(tz("EST5EDT,M3.4.0,M11.1.0").equivalent_until(&tz("EST5EDT,M3.5.0,M11.1.0"), 1700000000), Ok(Some(1711263600))); // 2024-03-24 02:00 EST
+    }
+
+    #[test]
+    fn zone_predicates() {
+        for name in ["UTC", "Etc/UTC", "Zulu", ""] {
+            let tz = Timezone::new(name).expect("timezone alloc");
+            assert!(tz.is_utc() && tz.is_fixed_offset() && !tz.is_leap_aware(), "{}", name);
+        }
+        for name in ["Etc/GMT+5", "<-05>5", "JST-9"] {
+            let tz = Timezone::new(name).expect("timezone alloc");
+            assert!(!tz.is_utc() && tz.is_fixed_offset(), "{}", name);
+        }
+        for name in ["America/New_York", "Asia/Tokyo", "EST5EDT,M3.2.0,M11.1.0", "right/UTC"] {
+            let tz = Timezone::new(name).expect("timezone alloc");
+            assert!(!tz.is_utc() && !tz.is_fixed_offset(), "{}", name);
+        }
+        assert!(Timezone::new("right/UTC").expect("timezone alloc").is_leap_aware());
+
+        for hours in -12..=14 {
+            let tz = Timezone::for_utc_offset_hours(hours).expect("for_utc_offset_hours");
+            assert_eq!(tz.etc_offset().map(|o| o.seconds()), Some(hours * 3600), "{}", hours);
+            assert_eq!(tz.localtime(0).map(|tm| tm.tm_gmtoff), Ok(hours as i64 * 3600));
+        }
+        assert!(Timezone::for_utc_offset_hours(15).is_err() && Timezone::for_utc_offset_hours(-13).is_err());
+        assert_eq!(Timezone::new("Etc/GMT+5").expect("timezone alloc").etc_offset().map(|o| o.to_string()), Some("-05:00".to_string()));
+        assert_eq!(Timezone::new("Etc/UTC").expect("timezone alloc").etc_offset(), Some(crate::UtcOffset::UTC));
+        assert_eq!(Timezone::new("JST-9").expect("timezone alloc").etc_offset(), None);
+
+        assert_eq!(Timezone::from_military('K').expect("from_military").etc_offset().map(|o| o.to_string()), Some("+10:00".to_string()));
+        assert_eq!(Timezone::from_military('z').expect("from_military").provenance().name(), "Etc/GMT");
+        assert!(Timezone::from_military('J').is_err());
+    }
+
+    #[test]
+    fn memory_footprint() {
+        let utc = Timezone::new("UTC").expect("timezone alloc");
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        let (utc_before, before) = (utc.memory_footprint(), tz.memory_footprint());
+        assert!(before > std::mem::size_of::<Timezone>());
+        utc.zone_info().expect("zone_info");
+        tz.zone_info().expect("zone_info");
+        tz.localtime(1700000000).expect("localtime");
+        assert!(tz.memory_footprint() > before + tz.zone_info().expect("zone_info").transitions.len() * std::mem::size_of::<crate::Transition>());
+        assert!(tz.memory_footprint() - before > utc.memory_footprint() - utc_before);
+    }
+
+    #[test]
+    fn localtime_many_sorted() {
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        let uncached = |time| tz.localtime_raw(time).and_then(|tztm| Ok(Tm::with_zone(&tztm, tz.intern(tztm.tm_zone)?)));
+        // Every ten minutes through 2023 plus the seconds around each transition, sorted and then reversed.
+        let mut times: Vec<TimeT> = (1672531200..1704067200).step_by(600).chain([1678604399, 1678604400, 1699163999, 1699164000]).collect();
+        times.sort();
+        for times in [times.clone(), times.into_iter().rev().collect()] {
+            let tms = tz.localtime_many(&times).expect("localtime_many");
+            for (time, tm) in times.iter().zip(tms) {
+                assert_eq!(Ok(tm), uncached(*time), "{}", time);
+            }
+        }
+    }
+
+    #[test]
+    fn data_range() {
+        let (start, end) = Timezone::new("America/Los_Angeles").expect("timezone alloc").data_range().expect("data_range").expect("has transitions");
+        assert_eq!(start, -2717640000); // 1883-11-18 20:00:00 UTC
+        assert!(end >= 1173607200);     // 2007-03-11, the last rule change
+        assert_eq!(Timezone::new("EST5EDT,M3.2.0,M11.1.0").expect("timezone alloc").data_range(), Ok(None));
+    }
+
+    #[test]
+    fn new_strict() {
+        for name in ["Not/AZone", "Europe/Berln", ":Europe/Berln", "Bogus", "EST5EDT,M3.2.0", "a\0b"] {
+            assert_eq!(Timezone::new_strict(name).err(), Some(ZoneError::UnknownZone(name.to_string())));
+        }
+        for (name, reason) in [("/nonexistent/zone", "No such file or directory (os error 2)"), (":/usr/share/zoneinfo", "it's a directory"),
+                               ("/etc/passwd", "not a TZif file")] {
+            let path = PathBuf::from(name.trim_start_matches(':'));
+            assert_eq!(Timezone::new_strict(name).err(), Some(ZoneError::BadFile{ path: path.clone(), reason: reason.to_string() }));
+            assert_eq!(Timezone::new(name).err(), Some(format!("Bad zone file {}: {}", path.display(), reason)));
+        }
+        for name in ["", "Europe/Berlin", ":Europe/Berlin", "/usr/share/zoneinfo/Asia/Tokyo", "EST5EDT,M3.2.0,M11.1.0", "<+0330>-3:30"] {
+            assert!(Timezone::new_strict(name).is_ok(), "{:?} should load", name);
+        }
+        assert_eq!(String::from(ZoneError::UnknownZone("Bogus".to_string())), "Unknown zone \"Bogus\"");
+    }
+
+    #[test]
+    fn raw() {
+        let tz = Timezone::new("Asia/Tokyo").expect("timezone alloc");
+        let tm = tz.localtime(0).expect("localtime");
+        let raw = tz.into_raw();
+        let tz = unsafe { Timezone::from_raw(raw) };
+        assert_eq!(tz.as_raw(), raw);
+        assert_eq!(tz.localtime(0), Ok(tm));
+        assert_eq!(tz.provenance(), &Provenance::Synthesized(String::new()));
+        assert!(tz.zone_info().is_err());
+    }
+
+    #[test]
+    fn from_str() {
+        let tz: Timezone = "Asia/Tokyo".parse().expect("parse");
+        assert_eq!(tz.provenance(), &Provenance::File(PathBuf::from("/usr/share/zoneinfo/Asia/Tokyo")));
+        assert!("Not/A_Zone".parse::<Timezone>().is_err());
+    }
+
+    #[test]
+    fn convert() {
+        let (ny, tokyo) = (Timezone::new("America/New_York").expect("timezone alloc"), Timezone::new("Asia/Tokyo").expect("timezone alloc"));
+        let tm = ny.localtime(1700000000).expect("localtime");
+        assert_eq!(ny.convert(&tm, &tokyo), tokyo.localtime(1700000000));
+        // 01:30 happens twice in New York on 2023-11-05.
+        let ambiguous = Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(2023, 11, 5, 1, 30, 0) };
+        assert_eq!(ny.convert_resolve(&ambiguous, &tokyo, Resolve::Earlier).map(|tm| tm.tm_hour), Ok(14));
+        assert_eq!(ny.convert_resolve(&ambiguous, &tokyo, Resolve::Later).map(|tm| tm.tm_hour), Ok(15));
+        assert!(matches!(ny.convert_resolve(&ambiguous, &tokyo, Resolve::Error), Err(MktimeError::Ambiguous{..})));
+    }
+
+    #[test]
+    fn debug() {
+        let ny = Timezone::new("America/New_York").expect("timezone alloc");
+        assert_eq!(format!("{:?}", ny.debug_at(1700000000)),
+                   r#"Timezone { name: "America/New_York", offset: "-05:00", abbr: "EST", dst: false, leap_aware: false, next_transition: Some("2024-03-10 07:00:00 UTC") }"#);
+        let tokyo = Timezone::new("right/Asia/Tokyo").expect("timezone alloc");
+        assert_eq!(format!("{:?}", tokyo.debug_at(1700000000)),
+                   r#"Timezone { name: "right/Asia/Tokyo", offset: "+09:00", abbr: "JST", dst: false, leap_aware: true, next_transition: None }"#);
+        assert_eq!(format!("{:?}", ny), format!("Timezone {{ provenance: {:?} }}", ny.provenance()));
+        assert_eq!(format!("{:?}", Timezone::new("EST5EDT,M3.2.0,M11.1.0").expect("timezone alloc")),
+                   r#"Timezone { provenance: PosixRule("EST5EDT,M3.2.0,M11.1.0") }"#);
+    }
+
+    #[test]
+    fn time_until_next_transition() {
+        let ny = Timezone::new("America/New_York").expect("timezone alloc");
+        // 2023-11-05 06:00 UTC is when New York leaves DST.
+        assert_eq!(ny.time_until_next_transition(1699164000 - 100), Ok(Some(std::time::Duration::from_secs(100))));
+        assert_eq!(ny.time_until_next_transition(1699164000), Ok(Some(std::time::Duration::from_secs(1710054000 - 1699164000))));
+        // Far past the last transition in the file, from the POSIX rule.
+        let next = ny.time_until_next_transition(4102444800).expect("next").expect("some"); // 2100-01-01
+        assert_eq!(ny.localtime(4102444800 + next.as_secs() as TimeT).expect("localtime").tm_isdst, 1);
+        assert_eq!(Timezone::new("Asia/Tokyo").expect("timezone alloc").time_until_next_transition(1700000000), Ok(None));
+        assert_eq!(Timezone::new("").expect("utc").time_until_next_transition(0), Ok(None));
+        assert_eq!(Timezone::new("EST5EDT,M3.2.0,M11.1.0").expect("posix").time_until_next_transition(1699164000 - 1),
+                   Ok(Some(std::time::Duration::from_secs(1))));
+    }
+
+    #[test]
+    fn deadline() {
+        let ny = Timezone::new("America/New_York").expect("timezone alloc");
+        let now = ny.mktime(&Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(2023, 3, 11, 12, 0, 0) }).expect("mktime");
+        // 02:30 on 2023-03-12 doesn't exist: the alarm goes off at 03:30 EDT, 14.5 hours (wall clock 15.5) later.
+        assert_eq!(ny.deadline(now, &Tm::from_ymd_hms(2023, 3, 12, 2, 30, 0)), Ok(std::time::Duration::from_secs(14 * 3600 + 1800)));
+        assert_eq!(ny.deadline(now, &Tm::from_ymd_hms(2023, 3, 12, 12, 0, 0)), Ok(std::time::Duration::from_secs(23 * 3600)));
+        assert_eq!(ny.deadline(now, &Tm::from_ymd_hms(2023, 3, 11, 12, 0, 0)), Ok(std::time::Duration::ZERO));
+        assert!(ny.deadline(now, &Tm::from_ymd_hms(2023, 3, 11, 11, 59, 59)).is_err());
+        // 01:30 on 2023-11-05 happens twice; the first one is in EDT.
+        let fall = ny.mktime(&Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(2023, 11, 5, 0, 0, 0) }).expect("mktime");
+        assert_eq!(ny.deadline(fall, &Tm::from_ymd_hms(2023, 11, 5, 1, 30, 0)), Ok(std::time::Duration::from_secs(5400)));
+        assert_eq!(ny.deadline(TimeT::MIN, &Tm::from_ymd_hms(2023, 11, 5, 1, 30, 0)), Ok(std::time::Duration::from_secs(fall as u64 + 5400 + (1 << 63))));
+    }
+
+    #[test]
+    fn add_duration() {
+        let ny = Timezone::new("America/New_York").expect("timezone alloc");
+        let add = |time, d: &str| ny.add_duration(time, &d.parse().expect("parse")).map(|t| ny.localtime(t).expect("localtime"));
+        let fields = |tm: Tm| (tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min);
+        let jan31 = ny.mktime(&Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(2024, 1, 31, 2, 30, 0) }).expect("mktime");
+        assert_eq!(add(jan31, "P1M").map(fields), Ok((2024, 2, 29, 2, 30)));
+        assert_eq!(add(jan31, "P1Y1M").map(fields), Ok((2025, 2, 28, 2, 30)));
+        // 02:30 on 2024-03-10 is skipped, so it moves forward to 03:30.
+        assert_eq!(add(jan31, "P1M10D").map(fields), Ok((2024, 3, 10, 3, 30)));
+        assert_eq!(add(jan31, "P5WT1H").map(fields), Ok((2024, 3, 6, 3, 30)));
+        assert_eq!(add(jan31, "-P1DT30M").map(fields), Ok((2024, 1, 30, 2, 0)));
+        assert_eq!(ny.add_duration(TimeT::MAX - 1, &"PT2S".parse().expect("parse")), Err("Time out of range".to_string()));
+    }
+
+    #[test]
+    fn diff() {
+        let ny = Timezone::new("America/New_York").expect("timezone alloc");
+        let at = |y, m, d, h, min| ny.mktime(&Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(y, m, d, h, min, 0) }).expect("mktime");
+        let diff = |a, b| ny.diff(a, b).expect("diff").format();
+        assert_eq!(diff(at(2024, 1, 31, 2, 30), at(2024, 3, 1, 2, 30)), "P1M1D"); // Jan 31 + 1 month is Feb 29
+        assert_eq!(diff(at(2024, 3, 1, 2, 30), at(2024, 1, 31, 2, 30)), "-P1M1D");
+        assert_eq!(diff(at(2020, 6, 15, 9, 0), at(2024, 1, 18, 13, 5)), "P3Y7M3DT4H5M");
+        // A day is a day, even when it's 25 hours long, but a 23 hour day that's one hour short isn't.
+        assert_eq!(diff(at(2023, 11, 4, 12, 0), at(2023, 11, 5, 12, 0)), "P1D");
+        assert_eq!(diff(at(2023, 3, 11, 12, 0), at(2023, 3, 12, 11, 0)), "PT22H");
+        assert_eq!(diff(1700000000, 1700000000), "PT0S");
+        for (a, b) in [(at(2024, 1, 31, 2, 30), at(2025, 2, 28, 1, 0)), (at(2023, 3, 11, 2, 30), at(2023, 3, 12, 3, 15))] {
+            assert_eq!(ny.add_duration(a, &ny.diff(a, b).expect("diff")), Ok(b));
+        }
+    }
+
+    #[test]
+    fn add_months() {
+        let ny = Timezone::new("America/New_York").expect("timezone alloc");
+        let fields = |tm: Tm| (tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min);
+        let mar31 = Tm::from_ymd_hms(2023, 3, 31, 2, 30, 0);
+        assert_eq!(ny.add_months(&mar31, 1, Policy::Clamp).map(fields), Ok((2023, 4, 30, 2, 30)));
+        assert_eq!(ny.add_months(&mar31, 1, Policy::Overflow).map(fields), Ok((2023, 5, 1, 2, 30)));
+        assert_eq!(ny.add_months(&mar31, -13, Policy::Clamp).map(fields), Ok((2022, 2, 28, 2, 30)));
+        assert_eq!(ny.add_months(&mar31, -13, Policy::Reject), Err("Feb 2022 has no day 31".to_string()));
+        assert_eq!(ny.add_months(&mar31, 12, Policy::Reject).map(fields), Ok((2024, 3, 31, 2, 30)));
+        // 02:30 on 2023-03-12 is skipped, so it moves forward to 03:30.
+        let feb12 = Tm::from_ymd_hms(2023, 2, 12, 2, 30, 0);
+        assert_eq!(ny.add_months(&feb12, 1, Policy::Reject).map(fields), Ok((2023, 3, 12, 3, 30)));
+    }
+
+    #[test]
+    fn add_years() {
+        let ny = Timezone::new("America/New_York").expect("timezone alloc");
+        let fields = |tm: Tm| (tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour);
+        let leap_day = Tm::from_ymd_hms(2000, 2, 29, 12, 0, 0);
+        assert_eq!(ny.add_years(&leap_day, 100, Policy::Clamp).map(fields), Ok((2100, 2, 28, 12)));
+        assert_eq!(ny.add_years(&leap_day, 100, Policy::Overflow).map(fields), Ok((2100, 3, 1, 12)));
+        assert_eq!(ny.add_years(&leap_day, 100, Policy::Reject), Err("Feb 2100 has no day 29".to_string()));
+        assert_eq!(ny.add_years(&leap_day, -400, Policy::Reject).map(fields), Ok((1600, 2, 29, 12)));
+        assert_eq!(ny.add_years(&Tm::from_ymd_hms(2023, 7, 4, 12, 0, 0), -1, Policy::Reject).map(fields), Ok((2022, 7, 4, 12)));
+    }
+
+    #[test]
+    fn android() {
+        let path = std::env::temp_dir().join(format!("libtz-android-tz-test-{}", std::process::id()));
+        std::fs::write(&path, crate::android::tests::build(&["Asia/Tokyo", "Europe/Dublin"])).expect("write");
+        let tz = Timezone::from_android(path.clone(), "Europe/Dublin".to_string());
+        std::fs::remove_file(&path).expect("remove");
+        let tz = tz.expect("from_android");
+        assert_eq!(tz.provenance(), &Provenance::AndroidTzdata(path, "Europe/Dublin".to_string()));
+        assert_eq!(tz.provenance().name(), "Europe/Dublin");
+        let dublin = Timezone::new("Europe/Dublin").expect("timezone alloc");
+        for time in [-1000000000, 0, 1690000000, 1700000000] {
+            assert_eq!(tz.localtime(time), dublin.localtime(time));
+        }
     }
 
     #[test]
@@ -176,7 +2394,7 @@ mod tests {
                           tm_yday   :364,
                           tm_isdst  :0,
                           tm_gmtoff :0,
-                          tm_zone   :"UTC".to_string()});
+                          tm_zone   :"UTC".into()});
         assert_eq!(tz.time2posix(time), posixtime); // Round Trip
     }
 }
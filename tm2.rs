@@ -0,0 +1,154 @@
+// A broken down time whose fields can't be out of range.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+//! A [`Tm`] whose fields are types that can only hold values in their normal ranges, for code that wants the
+//! compiler to keep garbage out instead of checking with [`crate::Tm::validate`].
+//!
+//! ```
+//! use libtz::tm2::{Tm, Second};
+//! let tm = Tm::try_from(&libtz::gmtime(1700000000).unwrap()).expect("valid");
+//! assert_eq!((tm.hour.get(), tm.mon, tm.wday), (22, libtz::Month::November, libtz::Weekday::Tuesday));
+//! assert!(Second::new(61).is_err());
+//! assert_eq!(libtz::timegm(&libtz::Tm::from(&tm)), Ok(1700000000));
+//! ```
+
+use core::fmt;
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+use crate::{Month, Weekday};
+
+/// An `i32` that's always in `[MIN, MAX]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ranged<const MIN: i32, const MAX: i32>(i32);
+
+impl<const MIN: i32, const MAX: i32> Ranged<MIN, MAX> {
+    /// The smallest value.
+    pub const MIN: Ranged<MIN, MAX> = Ranged(MIN);
+    /// The largest value.
+    pub const MAX: Ranged<MIN, MAX> = Ranged(MAX);
+
+    /// `value`, or an error if it's out of range.
+    pub fn new(value: i32) -> Result<Ranged<MIN, MAX>, String> {
+        if !(MIN..=MAX).contains(&value) {
+            return Err(format!("{} out of range [{}, {}]", value, MIN, MAX));
+        }
+        Ok(Ranged(value))
+    }
+
+    /// The value.
+    pub fn get(self) -> i32 {
+        self.0
+    }
+}
+
+impl<const MIN: i32, const MAX: i32> TryFrom<i32> for Ranged<MIN, MAX> {
+    type Error = String;
+    fn try_from(value: i32) -> Result<Ranged<MIN, MAX>, String> {
+        Ranged::new(value)
+    }
+}
+
+impl<const MIN: i32, const MAX: i32> From<Ranged<MIN, MAX>> for i32 {
+    fn from(value: Ranged<MIN, MAX>) -> i32 {
+        value.0
+    }
+}
+
+impl<const MIN: i32, const MAX: i32> fmt::Display for Ranged<MIN, MAX> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Seconds [0, 60] (60 is for leap seconds)
+pub type Second = Ranged<0, 60>;
+/// Minutes [0, 59]
+pub type Minute = Ranged<0, 59>;
+/// Hours [0, 23]
+pub type Hour = Ranged<0, 23>;
+/// Day of the month [1, 31]
+pub type MonthDay = Ranged<1, 31>;
+/// Day of the year [0, 365] (Jan/01 = 0)
+pub type YearDay = Ranged<0, 365>;
+
+/// A [`crate::Tm`] with range checked fields.
+///
+/// Each field is in range on its own, but the fields can still disagree with each other if they're set
+/// directly (like Feb 31, or a `wday` for the wrong day). Converting from a [`crate::Tm`] checks those too.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Tm {
+    /** Seconds */                                  pub sec    : Second,
+    /** Minutes */                                  pub min    : Minute,
+    /** Hour */                                     pub hour   : Hour,
+    /** Day of the month */                         pub mday   : MonthDay,
+    /** Month */                                    pub mon    : Month,
+    /** Year minus 1900 */                          pub year   : i32,
+    /** Day of the week */                          pub wday   : Weekday,
+    /** Day of the year */                          pub yday   : YearDay,
+    /** Daylight savings flag (`None` if unknown) */ pub isdst  : Option<bool>,
+
+    /** Seconds East of UTC */                      pub gmtoff : i64,
+    /** Timezone abbreviation */                    pub zone   : Arc<str>,
+}
+
+/// Fails with every problem [`crate::Tm::validate`] finds.
+impl TryFrom<&crate::Tm> for Tm {
+    type Error = Vec<String>;
+    fn try_from(tm: &crate::Tm) -> Result<Tm, Vec<String>> {
+        tm.validate()?;
+        Ok(Tm{
+            sec    : Ranged(tm.tm_sec),
+            min    : Ranged(tm.tm_min),
+            hour   : Ranged(tm.tm_hour),
+            mday   : Ranged(tm.tm_mday),
+            mon    : Month::from(tm.tm_mon),
+            year   : tm.tm_year,
+            wday   : Weekday::from(tm.tm_wday),
+            yday   : Ranged(tm.tm_yday),
+            isdst  : if tm.tm_isdst < 0 { None } else { Some(tm.tm_isdst > 0) },
+            gmtoff : tm.tm_gmtoff,
+            zone   : tm.tm_zone.clone(),
+        })
+    }
+}
+
+impl From<&Tm> for crate::Tm {
+    fn from(tm: &Tm) -> crate::Tm {
+        crate::Tm{
+            tm_sec    : tm.sec.get(),
+            tm_min    : tm.min.get(),
+            tm_hour   : tm.hour.get(),
+            tm_mday   : tm.mday.get(),
+            tm_mon    : tm.mon as i32,
+            tm_year   : tm.year,
+            tm_wday   : tm.wday as i32,
+            tm_yday   : tm.yday.get(),
+            tm_isdst  : tm.isdst.map_or(-1, |isdst| isdst as i32),
+            tm_gmtoff : tm.gmtoff,
+            tm_zone   : tm.zone.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tm2() {
+        assert_eq!(Second::new(60).map(Second::get), Ok(60));
+        assert_eq!(Second::new(61), Err("61 out of range [0, 60]".into()));
+        assert!(MonthDay::try_from(0).is_err());
+        assert_eq!((Hour::MIN.get(), Hour::MAX.get(), i32::from(YearDay::MAX)), (0, 23, 365));
+
+        let plain = crate::Tm::from_ymd_hms(2024, 2, 29, 23, 59, 60);
+        let tm = Tm::try_from(&plain).expect("valid");
+        assert_eq!((tm.mon, tm.wday, tm.yday.get(), tm.isdst), (Month::February, Weekday::Thursday, 59, Some(false)));
+        assert_eq!(crate::Tm::from(&tm), plain);
+        assert_eq!(crate::Tm::from(&Tm{ isdst: None, ..tm }).tm_isdst, -1);
+
+        assert_eq!(Tm::try_from(&crate::Tm{ tm_mday: 30, ..plain.clone() }), Err(vec!["Feb 30 invalid for 2024".into()]));
+        assert_eq!(Tm::try_from(&crate::Tm{ tm_hour: 24, tm_wday: 7, ..plain }).map_err(|e| e.len()), Err(2));
+    }
+}
@@ -0,0 +1,145 @@
+// Truncating and rounding times to local calendar boundaries.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use crate::{TimeT, Timezone, calendar};
+use crate::timezone::LocalCandidates;
+
+/// A local calendar granularity for [`Timezone::truncate`] and [`Timezone::round`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CalendarUnit {
+    /// A minute, starting at second 0.
+    Minute,
+    /// An hour, starting at minute 0.
+    Hour,
+    /// A day, starting at local midnight.
+    Day,
+    /// A week, starting at local midnight on Monday (like ISO 8601 weeks).
+    Week,
+    /// A month, starting at local midnight on its first day.
+    Month,
+    /// A year, starting at local midnight on January 1st.
+    Year,
+}
+
+// The local wall time (seconds since 1970-01-01 00:00 local) of the start of the `unit` containing `wall`.
+fn wall_start(wall: i64, unit: CalendarUnit) -> i64 {
+    let days = wall.div_euclid(86400);
+    let (year, month, _) = calendar::civil_from_days(days);
+    match unit {
+        CalendarUnit::Minute => wall - wall.rem_euclid(60),
+        CalendarUnit::Hour   => wall - wall.rem_euclid(3600),
+        CalendarUnit::Day    => days * 86400,
+        CalendarUnit::Week   => (days - (calendar::weekday_from_days(days) - 1).rem_euclid(7) as i64) * 86400,
+        CalendarUnit::Month  => calendar::days_from_civil(year, month, 1) * 86400,
+        CalendarUnit::Year   => calendar::days_from_civil(year, 0, 1) * 86400,
+    }
+}
+
+// The local wall time of the start of the `unit` after the one starting at `start`.
+fn wall_next(start: i64, unit: CalendarUnit) -> i64 {
+    let (year, month, _) = calendar::civil_from_days(start.div_euclid(86400));
+    match unit {
+        CalendarUnit::Minute => start + 60,
+        CalendarUnit::Hour   => start + 3600,
+        CalendarUnit::Day    => start + 86400,
+        CalendarUnit::Week   => start + 7 * 86400,
+        CalendarUnit::Month  => calendar::days_from_civil(year + (month == 11) as i64, (month + 1) % 12, 1) * 86400,
+        CalendarUnit::Year   => calendar::days_from_civil(year + 1, 0, 1) * 86400,
+    }
+}
+
+impl Timezone {
+    // The instants the wall time happens at, or if the clocks skipped it, the moment they did.
+    fn boundary_candidates(&self, wall: i64) -> Result<Vec<TimeT>, String> {
+        let LocalCandidates{ times, gap } = self.local_candidates(wall)?;
+        if !times.is_empty() {
+            return Ok(times);
+        }
+        let (before, after) = gap.ok_or("Time out of range")?;
+        Ok(self.transitions_between(wall - after, wall - before + 1)?.first().map(|(time, _, _)| *time).into_iter().collect())
+    }
+
+    /// The start of the local `unit` (minute, hour, day, ...) that `time` is in, for bucketing times by local
+    /// time.
+    ///
+    /// If the local time the unit starts at was skipped when the clocks sprang forward, the unit starts at the
+    /// moment they did (so the day of a midnight DST change starts at 01:00). If it happened twice when the
+    /// clocks fell back, it's the last one that isn't after `time` (so at 01:30 the second time round,
+    /// truncating to the hour gives the second 01:00).
+    ///
+    /// ```
+    /// use libtz::{Timezone, CalendarUnit};
+    /// let tz = Timezone::new("America/New_York").unwrap();
+    /// let time = 1700000000; // Tuesday 2023-11-14 17:13:20 EST
+    /// let local = |t| tz.localtime(t).unwrap().strftime("%F %T");
+    /// assert_eq!(local(tz.truncate(time, CalendarUnit::Hour).unwrap()), "2023-11-14 17:00:00");
+    /// assert_eq!(local(tz.truncate(time, CalendarUnit::Week).unwrap()), "2023-11-13 00:00:00");
+    /// assert_eq!(local(tz.truncate(time, CalendarUnit::Month).unwrap()), "2023-11-01 00:00:00");
+    /// ```
+    pub fn truncate(&self, time: TimeT, unit: CalendarUnit) -> Result<TimeT, String> {
+        let tm = self.localtime(time)?;
+        let start = wall_start(time + tm.tm_gmtoff, unit);
+        self.boundary_candidates(start)?.into_iter().rev().find(|t| *t <= time).ok_or_else(|| format!("No start of {:?} before {}", unit, time))
+    }
+
+    /// `time` rounded to the nearest start of a local `unit`: the start of the unit it's in
+    /// ([`Timezone::truncate`]) or the start of the next one, whichever is closer in elapsed time. Halfway
+    /// rounds up. The next unit's start follows the same rules as `truncate`, except that of two repeated
+    /// local times it's the first one after `time`.
+    ///
+    /// ```
+    /// use libtz::{Timezone, CalendarUnit};
+    /// let tz = Timezone::new("America/New_York").unwrap();
+    /// let time = 1700000000; // 2023-11-14 17:13:20 EST
+    /// let local = |t| tz.localtime(t).unwrap().strftime("%F %T");
+    /// assert_eq!(local(tz.round(time, CalendarUnit::Hour).unwrap()), "2023-11-14 17:00:00");
+    /// assert_eq!(local(tz.round(time, CalendarUnit::Day).unwrap()), "2023-11-15 00:00:00");
+    /// ```
+    pub fn round(&self, time: TimeT, unit: CalendarUnit) -> Result<TimeT, String> {
+        let down = self.truncate(time, unit)?;
+        let start = wall_start(time + self.localtime(time)?.tm_gmtoff, unit);
+        let mut candidates = self.boundary_candidates(start)?;
+        candidates.extend(self.boundary_candidates(wall_next(start, unit))?);
+        let up = candidates.into_iter().find(|t| *t > time).ok_or_else(|| format!("No start of {:?} after {}", unit, time))?;
+        Ok(if up - time <= time - down { up } else { down })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tm;
+
+    #[test]
+    fn truncate_and_round() {
+        let tz = Timezone::new("America/New_York").expect("timezone alloc");
+        let local = |t: Result<TimeT, String>| tz.localtime(t.expect("truncate")).expect("localtime").strftime("%F %T %Z");
+        let time = 1700000000; // Tuesday 2023-11-14 17:13:20 EST
+        assert_eq!(local(tz.truncate(time, CalendarUnit::Minute)), "2023-11-14 17:13:00 EST");
+        assert_eq!(local(tz.truncate(time, CalendarUnit::Day)), "2023-11-14 00:00:00 EST");
+        assert_eq!(local(tz.truncate(time, CalendarUnit::Year)), "2023-01-01 00:00:00 EST");
+        assert_eq!(local(tz.round(time, CalendarUnit::Minute)), "2023-11-14 17:13:00 EST");
+        assert_eq!(local(tz.round(time, CalendarUnit::Week)), "2023-11-13 00:00:00 EST");
+        assert_eq!(local(tz.round(time, CalendarUnit::Month)), "2023-11-01 00:00:00 EDT");
+        assert_eq!(local(tz.round(time, CalendarUnit::Year)), "2024-01-01 00:00:00 EST");
+        assert_eq!(local(tz.round(1700000000 - 13 * 60 - 20 + 1800, CalendarUnit::Hour)), "2023-11-14 18:00:00 EST"); // Halfway
+        let december = tz.mktime(&Tm::from_ymd_hms(2023, 12, 31, 23, 0, 0)).expect("mktime");
+        assert_eq!(local(tz.round(december, CalendarUnit::Month)), "2024-01-01 00:00:00 EST");
+
+        // 01:30 EST, the second time round on the day the clocks fall back.
+        let repeated = 1699165800;
+        assert_eq!(local(tz.truncate(repeated, CalendarUnit::Hour)), "2023-11-05 01:00:00 EST");
+        assert_eq!(local(tz.truncate(repeated - 3600, CalendarUnit::Hour)), "2023-11-05 01:00:00 EDT");
+        assert_eq!(local(tz.round(repeated - 3600 + 600, CalendarUnit::Hour)), "2023-11-05 01:00:00 EST"); // 01:40 EDT
+        assert_eq!(local(tz.truncate(repeated, CalendarUnit::Day)), "2023-11-05 00:00:00 EDT");
+
+        // Havana's clocks sprang forward from midnight to 01:00 on 2023-03-12, so the day started at 01:00.
+        let havana = Timezone::new("America/Havana").expect("timezone alloc");
+        let noon = havana.mktime(&Tm{ tm_isdst: -1, ..Tm::from_ymd_hms(2023, 3, 12, 12, 0, 0) }).expect("mktime");
+        let start = havana.truncate(noon, CalendarUnit::Day).expect("truncate");
+        assert_eq!(havana.localtime(start).map(|tm| (tm.tm_mday, tm.tm_hour)), Ok((12, 1)));
+        assert_eq!(havana.round(start - 1800, CalendarUnit::Day), Ok(start));
+    }
+}
@@ -0,0 +1,192 @@
+// Downloading, compiling and installing tzdata releases.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use crate::ZoneSource;
+
+/// The tz database source files that define zones, in a release's `tzdata` tarball.
+const SOURCE_FILES: &[&str] = &["africa", "antarctica", "asia", "australasia", "europe", "northamerica", "southamerica", "etcetera",
+                                "backward"];
+
+/// Tables that are copied along with the zones, for [`zone_tab`](crate::zone_tab) and friends.
+const TABLES: &[&str] = &["zone.tab", "zone1970.tab", "iso3166.tab"];
+
+// Run a command, turning a failure into an error that says what it was doing.
+fn run(command: &mut Command) -> Result<(), String> {
+    let output = command.output().map_err(|e| format!("{:?}: {}", command.get_program(), e))?;
+    if !output.status.success() {
+        return Err(format!("{:?} failed: {}", command.get_program(), String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+/// The fingerprint of the OpenPGP key that signs tzdata releases (Paul Eggert's, as given on IANA's
+/// [tz page](https://www.iana.org/time-zones)). [`download_tzdata`] needs it in `gpg`'s keyring
+/// (`gpg --recv-keys 7E3792A9D8ACF7D633BC1588ED97E90E62AA7E34`).
+pub const TZDATA_SIGNING_KEY: &str = "7E3792A9D8ACF7D633BC1588ED97E90E62AA7E34";
+
+// Check `tarball`'s detached OpenPGP `signature` with gpg, which has to say it was made by TZDATA_SIGNING_KEY.
+fn verify_signature(tarball: &Path, signature: &Path) -> Result<(), String> {
+    let output = Command::new("gpg").args(["--batch", "--status-fd", "1", "--verify"]).arg(signature).arg(tarball)
+                                    .output().map_err(|e| format!("gpg: {}", e))?;
+    // VALIDSIG's first field is the fingerprint of the key that made the signature and its last is the primary
+    // key's, in case a subkey made it.
+    let signed = String::from_utf8_lossy(&output.stdout).lines()
+        .filter_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+        .any(|fields| fields.split(' ').next() == Some(TZDATA_SIGNING_KEY) || fields.rsplit(' ').next() == Some(TZDATA_SIGNING_KEY));
+    if !output.status.success() || !signed {
+        return Err(format!("Couldn't verify {}'s signature (gpg needs the key {} in its keyring): {}", tarball.display(), TZDATA_SIGNING_KEY,
+                           String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+// A new directory in the temporary directory that only we can use. Unlike create_dir_all() it fails rather than
+// use a directory someone else made first, so it tries names until it gets one of its own.
+fn private_temp_dir() -> Result<PathBuf, String> {
+    use std::hash::{BuildHasher, Hasher};
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    for _ in 0..100 {
+        let random = std::collections::hash_map::RandomState::new().build_hasher().finish();
+        let dir = std::env::temp_dir().join(format!("libtz-tzdata-{}-{:016x}", std::process::id(), random));
+        match builder.create(&dir) {
+            Ok(())                                                   => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e)                                                   => return Err(format!("{}: {}", dir.display(), e)),
+        }
+    }
+    Err(format!("Couldn't make a temporary directory in {}", std::env::temp_dir().display()))
+}
+
+/// Download the IANA tzdata release `release` (like `2024a`, or `latest`) and unpack its sources into `dir`.
+/// This runs `curl`, `gpg` and `tar`, which must be installed. The release's signature is checked before it's
+/// unpacked, so `gpg` needs [`TZDATA_SIGNING_KEY`] in its keyring.
+pub fn download_tzdata(release: &str, dir: &Path) -> Result<(), String> {
+    let valid = release == "latest" || release.is_ascii() && release.len() >= 5 && release[..4].bytes().all(|b| b.is_ascii_digit())
+                                       && release[4..].bytes().all(|b| b.is_ascii_lowercase());
+    if !valid {
+        return Err(format!("Invalid tzdata release {:?}", release));
+    }
+    let url = match release {
+        "latest" => "https://data.iana.org/time-zones/tzdata-latest.tar.gz".to_string(),
+        release  => format!("https://data.iana.org/time-zones/releases/tzdata{}.tar.gz", release),
+    };
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let (tarball, signature) = (dir.join("tzdata.tar.gz"), dir.join("tzdata.tar.gz.asc"));
+    trace_event!(INFO, url = %url, "downloading tzdata");
+    let curl = || { let mut curl = Command::new("curl"); curl.args(["--fail", "--silent", "--show-error", "--location", "--output"]); curl };
+    let unpacked = run(curl().arg(&tarball).arg(&url))
+        .and_then(|_| run(curl().arg(&signature).arg(format!("{}.asc", url))))
+        .and_then(|_| verify_signature(&tarball, &signature))
+        .and_then(|_| run(Command::new("tar").arg("-xzf").arg(&tarball).arg("-C").arg(dir)));
+    let _ = (std::fs::remove_file(&tarball), std::fs::remove_file(&signature));
+    unpacked
+}
+
+/// Compile the tz database sources in `source` (an unpacked `tzdata` release, or a directory with a `tzdata.zi`)
+/// and install the zones in `dest`, ready to be used with [`set_zone_dirs`](crate::set_zone_dirs) or
+/// [`TimezoneBuilder::tzdir`](crate::TimezoneBuilder::tzdir). Links are installed as copies of their zones. The
+/// release's version goes in `+VERSION` and its `zone.tab` style tables are copied too.
+///
+/// Every file is written under a temporary name and renamed into place, so programs using `dest` while it's
+/// updated see either the old version of a zone or the new one. Returns the number of zones and links installed.
+pub fn install_tzdata(source: &Path, dest: &Path) -> Result<usize, String> {
+    let read = |name: &str| std::fs::read_to_string(source.join(name)).map_err(|e| format!("{}: {}", source.join(name).display(), e));
+    let text = match read("tzdata.zi") {
+        Ok(text) => text,
+        Err(_)   => SOURCE_FILES.iter().map(|name| read(name)).collect::<Result<Vec<_>, _>>()?.join("\n"),
+    };
+    let zones = ZoneSource::parse(&text).map_err(|e| format!("{}: {}", source.display(), e))?;
+    let version = read("version").ok().or_else(|| text.lines().next()?.strip_prefix("# version ").map(str::to_string));
+
+    let write = |name: &str, data: &[u8]| -> Result<(), String> {
+        let path = dest.join(name);
+        let tmp = PathBuf::from(format!("{}.tmp{}", path.display(), std::process::id()));
+        path.parent().map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&tmp, data))
+            .and_then(|_| std::fs::rename(&tmp, &path))
+            .map_err(|e| { let _ = std::fs::remove_file(&tmp); format!("{}: {}", path.display(), e) })
+    };
+    let names = zones.names();
+    let mut errors = vec![];
+    for name in names.iter() {
+        if name.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+            errors.push(format!("{}: bad zone name", name));
+            continue;
+        }
+        if let Err(e) = zones.compile(name).and_then(|zi| zi.to_tzif()).and_then(|data| write(name, &data)) {
+            errors.push(format!("{}: {}", name, e));
+        }
+    }
+    if !errors.is_empty() {
+        return Err(format!("Couldn't install {}", errors.join(", ")));
+    }
+    for table in TABLES {
+        if let Ok(data) = std::fs::read(source.join(table)) {
+            write(table, &data)?;
+        }
+    }
+    if let Some(version) = version {
+        write("+VERSION", format!("{}\n", version.trim()).as_bytes())?;
+    }
+    trace_event!(INFO, dest = %dest.display(), zones = names.len(), "installed tzdata");
+    Ok(names.len())
+}
+
+/// Download the tzdata release `release` (see [`download_tzdata`]) and install it in `dest` (see
+/// [`install_tzdata`]).
+///
+/// ```no_run
+/// libtz::download_and_install_tzdata("latest", "/var/lib/myapp/zoneinfo".as_ref()).expect("install");
+/// libtz::set_zone_dirs(["/var/lib/myapp/zoneinfo"]);
+/// ```
+pub fn download_and_install_tzdata(release: &str, dest: &Path) -> Result<usize, String> {
+    let source = private_temp_dir()?;
+    let installed = download_tzdata(release, &source).and_then(|_| install_tzdata(&source, dest));
+    let _ = std::fs::remove_dir_all(&source);
+    installed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Timezone;
+
+    #[test]
+    fn install() {
+        let (source, dest) = (std::env::temp_dir().join("libtz-install-source"), std::env::temp_dir().join("libtz-install-dest"));
+        let _ = (std::fs::remove_dir_all(&source), std::fs::remove_dir_all(&dest));
+        std::fs::create_dir_all(&source).expect("mkdir");
+        std::fs::write(source.join("version"), "2099z\n").expect("write");
+        std::fs::write(source.join("zone.tab"), "XX\t+0000+00000\tTest/Zone\n").expect("write");
+        for (name, text) in [("europe", "Rule Test 2000 max - Mar lastSun 1:00u 1:00 S\nRule Test 2000 max - Oct lastSun 1:00u 0 -\nZone Test/Zone 0:00 Test GMT/BST\n"),
+                             ("backward", "Link Test/Zone Test/Alias\n")] {
+            std::fs::write(source.join(name), text).expect("write");
+        }
+        assert!(install_tzdata(&source, &dest).is_err()); // Most of the source files are missing
+        for name in SOURCE_FILES.iter().filter(|n| !["europe", "backward"].contains(n)) {
+            std::fs::write(source.join(name), "").expect("write");
+        }
+        assert_eq!(install_tzdata(&source, &dest), Ok(2));
+        assert_eq!(std::fs::read_to_string(dest.join("+VERSION")).expect("read"), "2099z\n");
+        assert!(dest.join("zone.tab").exists());
+
+        let tz = Timezone::builder().tzdir(&dest).build("Test/Alias").expect("build");
+        assert_eq!(tz.localtime(1720000000).map(|tm| tm.tm_zone.to_string()), Ok("BST".to_string()));
+        assert!(download_tzdata("../etc", &source).is_err());
+        assert!(download_tzdata("202é", &source).is_err());
+        assert!(verify_signature(&dest.join("+VERSION"), &dest.join("zone.tab")).is_err());
+
+        let (a, b) = (private_temp_dir().expect("private_temp_dir"), private_temp_dir().expect("private_temp_dir"));
+        assert_ne!(a, b);
+        #[cfg(unix)]
+        assert_eq!(std::os::unix::fs::PermissionsExt::mode(&std::fs::metadata(&a).expect("metadata").permissions()) & 0o777, 0o700);
+        let _ = (std::fs::remove_dir(&a), std::fs::remove_dir(&b));
+        let _ = (std::fs::remove_dir_all(&source), std::fs::remove_dir_all(&dest));
+    }
+}
@@ -0,0 +1,104 @@
+// How up to date the installed zone data is.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use crate::zone_dirs;
+
+/// The tzdata releases known when this crate was built, oldest first. Pass a newer list (from IANA's
+/// `tz-announce` list or <https://data.iana.org/time-zones/releases/>, say) to [`tzdata_status_against`].
+pub const TZDATA_RELEASES: &[&str] = &[
+    "2016a", "2016b", "2016c", "2016d", "2016e", "2016f", "2016g", "2016h", "2016i", "2016j",
+    "2017a", "2017b", "2017c",
+    "2018a", "2018b", "2018c", "2018d", "2018e", "2018f", "2018g", "2018h", "2018i",
+    "2019a", "2019b", "2019c",
+    "2020a", "2020b", "2020c", "2020d", "2020e", "2020f",
+    "2021a", "2021b", "2021c", "2021d", "2021e",
+    "2022a", "2022b", "2022c", "2022d", "2022e", "2022f", "2022g",
+    "2023a", "2023b", "2023c", "2023d",
+    "2024a", "2024b",
+    "2025a", "2025b",
+];
+
+/// How the installed tzdata compares with the latest release, from [`tzdata_status`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TzdataStatus {
+    /// The installed version (like `2024a`), or `None` if it couldn't be found.
+    pub installed: Option<String>,
+    /// The newest release in the list it was compared with.
+    pub latest: Option<String>,
+    /// How many releases in the list are newer than the installed one, or `None` if the installed version is
+    /// unknown. Versions older than the whole list count every release in it, so this is a lower bound.
+    pub releases_behind: Option<usize>,
+}
+
+impl TzdataStatus {
+    /// Whether the installed version is the latest one (or newer).
+    pub fn is_current(&self) -> bool {
+        self.releases_behind == Some(0)
+    }
+
+    /// Whether the installed version is more than `releases` releases behind, or unknown, for alerting.
+    pub fn is_stale(&self, releases: usize) -> bool {
+        self.releases_behind.is_none_or(|behind| behind > releases)
+    }
+}
+
+/// The version of the installed tzdata: the `+VERSION` file or the `# version` line at the top of `tzdata.zi`,
+/// from the first of the [zone directories](zone_dirs) that has one.
+pub fn installed_tzdata_version() -> Option<String> {
+    zone_dirs().into_iter().find_map(|dir| {
+        let version = std::fs::read_to_string(dir.join("+VERSION")).ok().map(|v| v.trim().to_string());
+        version.or_else(|| {
+            let zi = std::fs::read_to_string(dir.join("tzdata.zi")).ok()?;
+            zi.lines().next()?.strip_prefix("# version ").map(|v| v.trim().to_string())
+        }).filter(|v| !v.is_empty())
+    })
+}
+
+/// How far behind the installed tzdata is, compared with the releases known when this crate was built
+/// ([`TZDATA_RELEASES`]), so monitoring can alert on hosts running rules that are several releases old.
+///
+/// ```
+/// let status = libtz::tzdata_status();
+/// if status.is_stale(2) {
+///     eprintln!("tzdata {:?} is {:?} releases behind {:?}", status.installed, status.releases_behind, status.latest);
+/// }
+/// ```
+pub fn tzdata_status() -> TzdataStatus {
+    tzdata_status_against(TZDATA_RELEASES)
+}
+
+/// Like [`tzdata_status`], but compared with `releases` (in any order), for a list fetched at run time.
+pub fn tzdata_status_against(releases: &[&str]) -> TzdataStatus {
+    status(installed_tzdata_version(), releases)
+}
+
+fn status(installed: Option<String>, releases: &[&str]) -> TzdataStatus {
+    // Release names sort in release order: the year, then the letter.
+    let latest = releases.iter().max().map(|v| v.to_string());
+    let releases_behind = installed.as_deref().map(|installed| releases.iter().filter(|r| **r > installed).count());
+    TzdataStatus{ installed, latest, releases_behind }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tzdata_status() {
+        assert!(TZDATA_RELEASES.windows(2).all(|w| w[0] < w[1]));
+        let installed = installed_tzdata_version().expect("installed_tzdata_version");
+        assert!(installed.len() == 5 && installed.starts_with("20"), "{}", installed);
+        assert_eq!(super::tzdata_status().installed.as_deref(), Some(installed.as_str()));
+
+        let old = status(Some("2023c".to_string()), &["2024a", "2023c", "2023d", "2023b"]);
+        assert_eq!(old, TzdataStatus{ installed: Some("2023c".to_string()), latest: Some("2024a".to_string()), releases_behind: Some(2) });
+        assert!(old.is_stale(1) && !old.is_stale(2) && !old.is_current());
+        let new = status(Some("2099a".to_string()), TZDATA_RELEASES);
+        assert!(new.is_current() && !new.is_stale(0));
+        let unknown = status(None, TZDATA_RELEASES);
+        assert!(unknown.is_stale(100) && !unknown.is_current());
+        assert_eq!(tzdata_status_against(&[]).latest, None);
+    }
+}
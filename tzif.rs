@@ -0,0 +1,684 @@
+// Parser for TZif (compiled zoneinfo) files.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use alloc::{format, vec, string::{String, ToString}, vec::Vec};
+use crate::TimeT;
+use crate::posix::PosixTz;
+
+/// The contents of a TZif file. Get one from [`Timezone::zone_info`][crate::Timezone::zone_info].
+///
+/// Reference: [RFC 8536](https://www.rfc-editor.org/rfc/rfc8536)
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZoneInfo {
+    /** TZif format version (1 to 4) */             pub version      : u8,
+    /** Transitions, in time order */               pub transitions  : Vec<Transition>,
+    /** Local time types */                         pub types        : Vec<LocalTimeType>,
+    /** Leap second corrections, in time order */   pub leap_seconds : Vec<LeapSecond>,
+    /** POSIX TZ rule for times after the last transition (version 2+) */ pub footer : Option<String>,
+}
+
+/// A time at which the zone switches to a different [`LocalTimeType`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transition {
+    /** When it happens */                          pub time            : TimeT,
+    /** Index into [`ZoneInfo::types`] */           pub local_time_type : usize,
+}
+
+/// An offset from UT along with its DST flag and abbreviation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalTimeType {
+    /** Seconds east of UT */                       pub utoff  : i32,
+    /** Daylight saving time flag */                pub isdst  : bool,
+    /** Abbreviation */                             pub abbr   : String,
+    /** Transition times were standard time (for POSIX rules without transitions) */ pub is_std : bool,
+    /** Transition times were UT (for POSIX rules without transitions) */            pub is_ut  : bool,
+}
+
+/// A leap second correction. `correction` is the total number of leap seconds in effect from `time` onwards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LeapSecond {
+    /** When it takes effect */                     pub time       : TimeT,
+    /** Total correction from then on */            pub correction : i32,
+}
+
+struct Header {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl Header {
+    fn data_len(&self, time_size: usize) -> usize {
+        self.timecnt * time_size + self.timecnt + self.typecnt * 6 + self.charcnt + self.leapcnt * (time_size + 4) + self.isstdcnt + self.isutcnt
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self.data.get(self.pos..self.pos + len).ok_or_else(|| format!("truncated at byte {}", self.data.len()))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+    fn u8(&mut self) -> Result<u8, String> { Ok(self.take(1)?[0]) }
+    fn i32(&mut self) -> Result<i32, String> { Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap())) }
+    fn i64(&mut self) -> Result<i64, String> { Ok(i64::from_be_bytes(self.take(8)?.try_into().unwrap())) }
+    fn time(&mut self, size: usize) -> Result<TimeT, String> { if size == 4 { Ok(self.i32()? as TimeT) } else { self.i64() } }
+
+    fn header(&mut self) -> Result<Header, String> {
+        if self.take(4)? != b"TZif" {
+            return Err("bad magic (not a TZif file)".to_string());
+        }
+        let version = match self.u8()? {
+            0 => 1,
+            v @ b'2'..=b'9' => v - b'0',
+            v => return Err(format!("unknown version {:#04x}", v)),
+        };
+        self.take(15)?;
+        let mut count = || -> Result<usize, String> {
+            let count = self.i32()?;
+            usize::try_from(count).map_err(|_| format!("negative count {}", count))
+        };
+        Ok(Header{ version, isutcnt: count()?, isstdcnt: count()?, leapcnt: count()?, timecnt: count()?, typecnt: count()?, charcnt: count()? })
+    }
+}
+
+impl ZoneInfo {
+    /// Parse the contents of a TZif file. For version 2+ files the 64 bit data block is used and the version 1
+    /// data block is skipped.
+    pub(crate) fn parse(data: &[u8]) -> Result<ZoneInfo, String> {
+        let mut r = Reader{ data, pos: 0 };
+        let mut header = r.header()?;
+        let mut time_size = 4;
+        if header.version >= 2 {
+            r.take(header.data_len(4))?;
+            header = r.header()?;
+            time_size = 8;
+        }
+        if header.typecnt == 0 {
+            return Err("no local time types".to_string());
+        }
+        if header.isstdcnt != 0 && header.isstdcnt != header.typecnt || header.isutcnt != 0 && header.isutcnt != header.typecnt {
+            return Err("isstdcnt and isutcnt must be zero or match typecnt".to_string());
+        }
+
+        let times = (0..header.timecnt).map(|_| r.time(time_size)).collect::<Result<Vec<_>, _>>()?;
+        let transitions = times.into_iter().map(|time| Ok(Transition{ time, local_time_type: r.u8()? as usize }))
+                                           .collect::<Result<Vec<_>, String>>()?;
+        let ttinfos = (0..header.typecnt).map(|_| Ok((r.i32()?, r.u8()?, r.u8()? as usize)))
+                                         .collect::<Result<Vec<_>, String>>()?;
+        let chars = r.take(header.charcnt)?;
+        let leap_seconds = (0..header.leapcnt).map(|_| Ok(LeapSecond{ time: r.time(time_size)?, correction: r.i32()? }))
+                                              .collect::<Result<Vec<_>, String>>()?;
+        let isstd = r.take(header.isstdcnt)?;
+        let isut = r.take(header.isutcnt)?;
+
+        if let Some(bad) = transitions.iter().find(|t| t.local_time_type >= header.typecnt) {
+            return Err(format!("transition at {} uses nonexistent local time type {}", bad.time, bad.local_time_type));
+        }
+        let types = ttinfos.into_iter().enumerate().map(|(i, (utoff, isdst, abbrind))| {
+            let abbr = chars.get(abbrind..).and_then(|rest| rest.split(|c| *c == 0).next().filter(|_| rest.contains(&0)))
+                            .ok_or_else(|| format!("local time type {} has an invalid abbreviation index {}", i, abbrind))?;
+            Ok(LocalTimeType{ utoff,
+                              isdst: isdst != 0,
+                              abbr: String::from_utf8(abbr.to_vec()).map_err(|_| format!("local time type {} has an invalid abbreviation", i))?,
+                              is_std: isstd.get(i).is_some_and(|b| *b != 0),
+                              is_ut: isut.get(i).is_some_and(|b| *b != 0) })
+        }).collect::<Result<Vec<_>, String>>()?;
+
+        let footer = if header.version >= 2 {
+            let rest = &data[r.pos..];
+            let footer = rest.strip_prefix(b"\n").and_then(|f| f.iter().position(|c| *c == b'\n').map(|end| &f[..end]))
+                             .ok_or("missing footer".to_string())?;
+            let footer = core::str::from_utf8(footer).map_err(|_| "footer is not valid utf8".to_string())?;
+            (!footer.is_empty()).then(|| footer.to_string())
+        } else {
+            None
+        };
+
+        Ok(ZoneInfo{ version: header.version, transitions, types, leap_seconds, footer })
+    }
+
+    /// Read and parse a TZif file.
+    #[cfg(any(feature = "std", test))]
+    pub(crate) fn load(path: &std::path::Path) -> Result<ZoneInfo, String> {
+        let data = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        trace_event!(DEBUG, path = %path.display(), bytes = data.len(), "loaded zone file");
+        ZoneInfo::parse(&data).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // Bytes allocated on the heap for the tables (not counting `size_of::<ZoneInfo>()` itself).
+    #[cfg(feature = "std")]
+    pub(crate) fn heap_size(&self) -> usize {
+        self.transitions.capacity() * core::mem::size_of::<Transition>()
+            + self.types.capacity() * core::mem::size_of::<LocalTimeType>()
+            + self.types.iter().map(|t| t.abbr.capacity()).sum::<usize>()
+            + self.leap_seconds.capacity() * core::mem::size_of::<LeapSecond>()
+            + self.footer.as_ref().map_or(0, String::capacity)
+    }
+
+    /// Build the equivalent of a TZif file with no transitions from a POSIX TZ rule string.
+    pub(crate) fn from_posix(rule: &PosixTz) -> ZoneInfo {
+        let mut types = vec![LocalTimeType{ utoff: rule.std_offset, isdst: false, abbr: rule.std_abbr.clone(), is_std: false, is_ut: false }];
+        if let Some(ref dst) = rule.dst {
+            types.push(LocalTimeType{ utoff: dst.offset, isdst: true, abbr: dst.abbr.clone(), is_std: false, is_ut: false });
+        }
+        ZoneInfo{ version: 2, transitions: vec![], types, leap_seconds: vec![], footer: Some(rule.to_string()) }
+    }
+
+    /// Encode as a TZif file (version 2 to 4: versions below 2 are written as 2, and later ones are an error).
+    ///
+    /// The version 1 data block gets the transitions and leap seconds that fit in 32 bits, plus one at the
+    /// earliest 32 bit time for any before that, so that old readers still work for times between 1901 and 2038.
+    pub fn to_tzif(&self) -> Result<Vec<u8>, String> {
+        if self.types.is_empty() || self.types.len() > 256 {
+            return Err(format!("need between 1 and 256 local time types, not {}", self.types.len()));
+        }
+        if let Some(bad) = self.transitions.iter().find(|t| t.local_time_type >= self.types.len()) {
+            return Err(format!("transition at {} uses nonexistent local time type {}", bad.time, bad.local_time_type));
+        }
+        if self.transitions.windows(2).any(|w| w[0].time >= w[1].time) || self.leap_seconds.windows(2).any(|w| w[0].time >= w[1].time) {
+            return Err("transitions and leap seconds must be in increasing time order".to_string());
+        }
+        if let Some(footer) = &self.footer {
+            if footer.contains('\n') {
+                return Err("footer contains a newline".to_string());
+            }
+        }
+        let mut chars: Vec<u8> = vec![];
+        let mut abbrinds = vec![];
+        for t in self.types.iter() {
+            if t.abbr.contains('\0') {
+                return Err(format!("abbreviation {:?} contains a null byte", t.abbr));
+            }
+            let abbr = [t.abbr.as_bytes(), b"\0"].concat();
+            let index = match chars.windows(abbr.len()).position(|w| w == abbr) {
+                Some(index) => index,
+                None        => { chars.extend_from_slice(&abbr); chars.len() - abbr.len() },
+            };
+            abbrinds.push(u8::try_from(index).map_err(|_| "abbreviations are too long".to_string())?);
+        }
+
+        let version = self.version.max(2);
+        if version > 4 {
+            return Err(format!("can't write TZif version {}", version));
+        }
+        let mut out = vec![];
+        let fits = |time: &TimeT| i32::try_from(*time).is_ok();
+        // Like zic, stand in for the transitions before 1901 with one at the earliest 32 bit time so v1 readers
+        // don't fall back to the first type (usually LMT) until the first transition that fits.
+        let early = self.transitions.iter().rev().find(|t| t.time < i32::MIN as TimeT)
+                        .filter(|_| self.transitions.iter().all(|t| t.time != i32::MIN as TimeT))
+                        .map(|t| Transition{ time: i32::MIN as TimeT, local_time_type: t.local_time_type });
+        let v1 = ZoneInfo{ transitions: early.into_iter().chain(self.transitions.iter().filter(|t| fits(&t.time)).copied()).collect(),
+                           leap_seconds: self.leap_seconds.iter().filter(|l| fits(&l.time)).copied().collect(),
+                           ..self.clone() };
+        v1.write_block(&mut out, version, 4, &chars, &abbrinds);
+        self.write_block(&mut out, version, 8, &chars, &abbrinds);
+        out.push(b'\n');
+        out.extend_from_slice(self.footer.as_deref().unwrap_or("").as_bytes());
+        out.push(b'\n');
+        Ok(out)
+    }
+
+    fn write_block(&self, out: &mut Vec<u8>, version: u8, time_size: usize, chars: &[u8], abbrinds: &[u8]) {
+        let time = |out: &mut Vec<u8>, time: TimeT| if time_size == 4 { out.extend_from_slice(&(time as i32).to_be_bytes()) }
+                                                    else { out.extend_from_slice(&time.to_be_bytes()) };
+        out.extend_from_slice(b"TZif");
+        out.push(b'0' + version);
+        out.extend_from_slice(&[0; 15]);
+        for count in [self.types.len(), self.types.len(), self.leap_seconds.len(), self.transitions.len(), self.types.len(), chars.len()] {
+            out.extend_from_slice(&(count as i32).to_be_bytes());
+        }
+        for t in self.transitions.iter() {
+            time(out, t.time);
+        }
+        out.extend(self.transitions.iter().map(|t| t.local_time_type as u8));
+        for (t, abbrind) in self.types.iter().zip(abbrinds) {
+            out.extend_from_slice(&t.utoff.to_be_bytes());
+            out.extend_from_slice(&[t.isdst as u8, *abbrind]);
+        }
+        out.extend_from_slice(chars);
+        for l in self.leap_seconds.iter() {
+            time(out, l.time);
+            out.extend_from_slice(&l.correction.to_be_bytes());
+        }
+        out.extend(self.types.iter().map(|t| t.is_std as u8));
+        out.extend(self.types.iter().map(|t| t.is_ut as u8));
+    }
+
+    /// The first and last transition times: the span where answers come straight from the transition table.
+    /// Before it the earliest local time type (usually LMT) is assumed, and after it the footer rule is
+    /// extrapolated. A zone with no transitions (like UTC, or a POSIX rule) has no table, so it's `None`: every
+    /// answer comes from its rule.
+    pub fn data_range(&self) -> Option<(TimeT, TimeT)> {
+        Some((self.transitions.first()?.time, self.transitions.last()?.time))
+    }
+
+    /// The distinct abbreviations used by the local time types, in order of first use.
+    pub fn abbreviations(&self) -> Vec<&str> {
+        let mut abbrs: Vec<&str> = vec![];
+        for t in self.types.iter() {
+            if !abbrs.contains(&t.abbr.as_str()) {
+                abbrs.push(&t.abbr);
+            }
+        }
+        abbrs
+    }
+
+    // The distinct abbreviations in the order they come into use.
+    pub(crate) fn abbreviations_chronological(&self) -> Vec<&str> {
+        abbreviations_chronological(&self.transitions, self.types.len(), |i| self.types[i].abbr.as_str())
+    }
+}
+
+// The distinct abbreviations of a zone with `types` local time types (whose abbreviations come from `abbr`) in the
+// order they come into use: the one before the first transition, then the transitions' and finally those of any
+// types that aren't used by a transition (like a footer rule's DST).
+pub(crate) fn abbreviations_chronological<'a>(transitions: &[Transition], types: usize, abbr: impl Fn(usize) -> &'a str) -> Vec<&'a str> {
+    let mut abbrs: Vec<&str> = vec![];
+    let order = (0..types.min(1)).chain(transitions.iter().map(|t| t.local_time_type)).chain(0..types);
+    for abbr in order.map(abbr) {
+        if !abbrs.contains(&abbr) {
+            abbrs.push(abbr);
+        }
+    }
+    abbrs
+}
+
+/// A TZif file parsed in place: the tables are read straight out of the borrowed bytes, nothing is copied and
+/// nothing is allocated. It's checked once, when it's made, so the accessors can't fail.
+///
+/// This is the zero-copy counterpart of [`ZoneInfo`]: see `MappedZone` (with the `mmap` feature) for one backed by a
+/// memory mapped file.
+#[derive(Clone, Copy, Debug)]
+pub struct TzifView<'a> {
+    version: u8,
+    time_size: usize,
+    times: &'a [u8],
+    indices: &'a [u8],
+    ttinfos: &'a [u8],
+    chars: &'a [u8],
+    leap_count: usize,
+    footer: Option<&'a str>,
+}
+
+impl<'a> TzifView<'a> {
+    /// Check the contents of a TZif file and find its tables. For version 2+ files the 64 bit data block is used.
+    pub fn parse(data: &'a [u8]) -> Result<TzifView<'a>, String> {
+        let mut r = Reader{ data, pos: 0 };
+        let mut header = r.header()?;
+        let mut time_size = 4;
+        if header.version >= 2 {
+            r.take(header.data_len(4))?;
+            header = r.header()?;
+            time_size = 8;
+        }
+        if header.typecnt == 0 {
+            return Err("no local time types".to_string());
+        }
+        let times = r.take(header.timecnt * time_size)?;
+        let indices = r.take(header.timecnt)?;
+        let ttinfos = r.take(header.typecnt * 6)?;
+        let chars = r.take(header.charcnt)?;
+        r.take(header.leapcnt * (time_size + 4) + header.isstdcnt + header.isutcnt)?;
+
+        if let Some(bad) = indices.iter().position(|i| *i as usize >= header.typecnt) {
+            return Err(format!("transition {} uses nonexistent local time type {}", bad, indices[bad]));
+        }
+        for (i, ttinfo) in ttinfos.chunks(6).enumerate() {
+            let abbr = chars.get(ttinfo[5] as usize..).and_then(|rest| rest.split(|c| *c == 0).next().filter(|_| rest.contains(&0)))
+                            .ok_or_else(|| format!("local time type {} has an invalid abbreviation index {}", i, ttinfo[5]))?;
+            core::str::from_utf8(abbr).map_err(|_| format!("local time type {} has an invalid abbreviation", i))?;
+        }
+        let view = TzifView{ version: header.version, time_size, times, indices, ttinfos, chars, leap_count: header.leapcnt, footer: None };
+        if (1..view.transition_count()).any(|i| view.time(i - 1) >= view.time(i)) {
+            return Err("transitions must be in increasing time order".to_string());
+        }
+
+        let footer = if header.version >= 2 {
+            let rest = &data[r.pos..];
+            let footer = rest.strip_prefix(b"\n").and_then(|f| f.iter().position(|c| *c == b'\n').map(|end| &f[..end]))
+                             .ok_or("missing footer".to_string())?;
+            let footer = core::str::from_utf8(footer).map_err(|_| "footer is not valid utf8".to_string())?;
+            (!footer.is_empty()).then_some(footer)
+        } else {
+            None
+        };
+        Ok(TzifView{ footer, ..view })
+    }
+
+    fn time(&self, i: usize) -> TimeT {
+        let bytes = &self.times[i * self.time_size..(i + 1) * self.time_size];
+        if self.time_size == 4 { i32::from_be_bytes(bytes.try_into().unwrap()) as TimeT } else { i64::from_be_bytes(bytes.try_into().unwrap()) }
+    }
+
+    /// TZif format version (1 to 4).
+    pub fn version(&self) -> u8 { self.version }
+    /// The number of transitions.
+    pub fn transition_count(&self) -> usize { self.indices.len() }
+    /// The number of local time types.
+    pub fn type_count(&self) -> usize { self.ttinfos.len() / 6 }
+    /// The number of leap second records. The view doesn't read them, so [`type_at`](Self::type_at) doesn't
+    /// account for them.
+    pub fn leap_count(&self) -> usize { self.leap_count }
+    /// The POSIX TZ rule for times after the last transition (version 2+).
+    pub fn footer(&self) -> Option<&'a str> { self.footer }
+
+    /// Transition `i`, or `None` if there aren't that many.
+    pub fn transition(&self, i: usize) -> Option<Transition> {
+        (i < self.transition_count()).then(|| Transition{ time: self.time(i), local_time_type: self.indices[i] as usize })
+    }
+
+    /// The transitions, in time order.
+    pub fn transitions(&self) -> impl Iterator<Item = Transition> + 'a {
+        let view = *self;
+        (0..view.transition_count()).map(move |i| Transition{ time: view.time(i), local_time_type: view.indices[i] as usize })
+    }
+
+    /// The offset (seconds east of UT), DST flag and abbreviation of local time type `i`, or `None` if there
+    /// aren't that many.
+    pub fn local_time_type(&self, i: usize) -> Option<(i32, bool, &'a str)> {
+        let ttinfo = self.ttinfos.get(i * 6..i * 6 + 6)?;
+        let abbr = self.chars[ttinfo[5] as usize..].split(|c| *c == 0).next().unwrap_or_default();
+        Some((i32::from_be_bytes(ttinfo[..4].try_into().unwrap()), ttinfo[4] != 0, core::str::from_utf8(abbr).unwrap_or_default()))
+    }
+
+    /// The index of the local time type in effect at `time`, found with a binary search of the transitions.
+    /// Before the first transition it's the first type. It's `None` after the last transition when the
+    /// [`footer`](Self::footer) rule applies instead.
+    pub fn type_at(&self, time: TimeT) -> Option<usize> {
+        let (mut lo, mut hi) = (0, self.transition_count());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.time(mid) <= time { lo = mid + 1 } else { hi = mid }
+        }
+        if lo == self.transition_count() && self.footer.is_some() {
+            return None;
+        }
+        Some(lo.checked_sub(1).map_or(0, |last| self.indices[last] as usize))
+    }
+}
+
+/// What [`validate_tzif`] found in a TZif file that's usable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TzifReport {
+    /// TZif format version (1 to 4).
+    pub version: u8,
+    /// The number of transitions.
+    pub transition_count: usize,
+    /// The number of local time types.
+    pub type_count: usize,
+    /// The number of leap second records.
+    pub leap_count: usize,
+    /// The distinct abbreviations, in order of first use.
+    pub abbreviations: Vec<String>,
+    /// The POSIX TZ rule for times after the last transition, if there is one.
+    pub footer: Option<String>,
+    /// Things that are allowed but probably not intended, like a version 1 file (which can't describe times
+    /// after 2038) or a footer that disagrees with the last transition.
+    pub warnings: Vec<String>,
+}
+
+/// Why [`validate_tzif`] rejected a TZif file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TzifError {
+    /// The data doesn't start with `TZif`.
+    BadMagic,
+    /// The version byte isn't one this understands.
+    UnknownVersion(u8),
+    /// The data ends before the header says it should.
+    Truncated {
+        /// The length of the data.
+        len: u64,
+        /// The length the headers call for.
+        needed: u64,
+    },
+    /// Transition `index` (at `time`) isn't later than the one before it.
+    NotMonotonic {
+        /// The index of the out of order transition.
+        index: usize,
+        /// Its time.
+        time: TimeT,
+    },
+    /// The footer isn't a valid POSIX TZ rule.
+    BadFooter(String),
+    /// Something else is wrong (a count, an index, a leap second record...).
+    Invalid(String),
+}
+
+impl core::fmt::Display for TzifError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TzifError::BadMagic                 => write!(f, "bad magic (not a TZif file)"),
+            TzifError::UnknownVersion(v)        => write!(f, "unknown version {:#04x}", v),
+            TzifError::Truncated{ len, needed } => write!(f, "truncated: {} bytes long but the headers call for {}", len, needed),
+            TzifError::NotMonotonic{ index, time } => write!(f, "transition {} (at {}) is out of order", index, time),
+            TzifError::BadFooter(why)           => write!(f, "bad footer: {}", why),
+            TzifError::Invalid(why)             => write!(f, "{}", why),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TzifError {}
+
+impl From<TzifError> for String {
+    fn from(err: TzifError) -> String {
+        err.to_string()
+    }
+}
+
+/// Check that `data` is a well formed TZif file before trusting it (with
+/// [`Timezone::from_tzif`][crate::Timezone::from_tzif], say).
+///
+/// On top of what parsing checks this makes sure the lengths the headers claim fit in the data, that the
+/// transitions and leap seconds are in order, that the leap second corrections step by one, and that the footer
+/// is a valid POSIX TZ rule.
+///
+/// ```
+/// let data = std::fs::read("/usr/share/zoneinfo/Europe/Paris").unwrap();
+/// let report = libtz::validate_tzif(&data).expect("valid");
+/// assert_eq!(report.footer.as_deref(), Some("CET-1CEST,M3.5.0,M10.5.0/3"));
+/// assert!(matches!(libtz::validate_tzif(&data[..100]), Err(libtz::TzifError::Truncated{ len: 100, .. })));
+/// ```
+pub fn validate_tzif(data: &[u8]) -> Result<TzifReport, TzifError> {
+    let len = data.len() as u64;
+    let mut pos = 0;
+    let mut version = 0;
+    for block in 0..2 {
+        let header = data.get(pos..pos + 44).ok_or(TzifError::Truncated{ len, needed: pos as u64 + 44 })?;
+        if &header[..4] != b"TZif" {
+            return Err(if block == 0 { TzifError::BadMagic } else { TzifError::Invalid("bad magic in second header".to_string()) });
+        }
+        version = match header[4] {
+            0 => 1,
+            v @ b'2'..=b'9' => v - b'0',
+            v => return Err(TzifError::UnknownVersion(v)),
+        };
+        let count = |i: usize| u32::from_be_bytes(header[20 + i * 4..24 + i * 4].try_into().unwrap());
+        let [isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt] = [0, 1, 2, 3, 4, 5].map(|i| count(i) as u64);
+        if [isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt].iter().any(|c| *c > i32::MAX as u64) {
+            return Err(TzifError::Invalid("negative count".to_string()));
+        }
+        let time_size = if block == 0 { 4 } else { 8 };
+        let needed = pos as u64 + 44 + timecnt * time_size + timecnt + typecnt * 6 + charcnt + leapcnt * (time_size + 4) + isstdcnt + isutcnt;
+        if needed > len {
+            return Err(TzifError::Truncated{ len, needed });
+        }
+        pos = needed as usize;
+        if version < 2 {
+            break;
+        }
+    }
+
+    let zi = ZoneInfo::parse(data).map_err(TzifError::Invalid)?;
+    if let Some(index) = (1..zi.transitions.len()).find(|i| zi.transitions[*i].time <= zi.transitions[i - 1].time) {
+        return Err(TzifError::NotMonotonic{ index, time: zi.transitions[index].time });
+    }
+    if let Some(i) = zi.types.iter().position(|t| t.utoff == i32::MIN) {
+        return Err(TzifError::Invalid(format!("local time type {} has an offset of -2^31", i)));
+    }
+    let mut previous = 0;
+    for (i, leap) in zi.leap_seconds.iter().enumerate() {
+        if i > 0 && leap.time <= zi.leap_seconds[i - 1].time {
+            return Err(TzifError::Invalid(format!("leap second {} is out of order", i)));
+        }
+        let change = leap.correction as i64 - previous as i64;
+        if change.unsigned_abs() != 1 && !(i == 0 && version >= 4) {
+            return Err(TzifError::Invalid(format!("leap second {} changes the correction by {}", i, change)));
+        }
+        previous = leap.correction;
+    }
+    let footer = zi.footer.as_deref().map(|f| f.parse::<PosixTz>().map_err(TzifError::BadFooter)).transpose()?;
+
+    let mut warnings = vec![];
+    if version < 2 {
+        warnings.push("version 1 files have no 64 bit data or footer, so they can't describe times after 2038".to_string());
+    } else if footer.is_none() {
+        warnings.push("there's no footer, so the last local time type applies forever".to_string());
+    }
+    if let (Some(footer), Some(last)) = (&footer, zi.transitions.last()) {
+        let last = &zi.types[last.local_time_type];
+        let agrees = match footer.dst {
+            None                       => last.utoff == footer.std_offset && last.abbr == footer.std_abbr,
+            Some(ref dst) if last.isdst => last.utoff == dst.offset && last.abbr == dst.abbr,
+            Some(_)                     => last.utoff == footer.std_offset && last.abbr == footer.std_abbr,
+        };
+        if !agrees {
+            warnings.push(format!("the footer ({}) disagrees with the last transition ({} at {})", zi.footer.as_deref().unwrap_or(""), last.abbr, last.utoff));
+        }
+    }
+    let footer_len = zi.footer.as_ref().map_or(0, |f| f.len());
+    if version >= 2 && data.len() > pos + footer_len + 2 {
+        warnings.push(format!("there are {} bytes of junk after the footer", data.len() - (pos + footer_len + 2)));
+    }
+
+    Ok(TzifReport{ version,
+                   transition_count: zi.transitions.len(),
+                   type_count: zi.types.len(),
+                   leap_count: zi.leap_seconds.len(),
+                   abbreviations: zi.abbreviations_chronological().into_iter().map(|a| a.to_string()).collect(),
+                   footer: zi.footer.clone(),
+                   warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse() {
+        let zi = ZoneInfo::load(std::path::Path::new("/usr/share/zoneinfo/America/Los_Angeles")).expect("load");
+        assert!(zi.version >= 2);
+        assert_eq!(zi.footer.as_deref(), Some("PST8PDT,M3.2.0,M11.1.0"));
+        assert_eq!(zi.transitions[0].time, -2717640000); // 1883-11-18 20:00:00 UTC
+        assert_eq!(zi.types[zi.transitions[0].local_time_type].abbr, "PST");
+        assert_eq!(zi.types[0], LocalTimeType{ utoff: -28378, isdst: false, abbr: "LMT".to_string(), is_std: false, is_ut: false });
+        assert_eq!(zi.abbreviations(), ["LMT", "PDT", "PST", "PWT", "PPT"]);
+        assert!(zi.leap_seconds.is_empty());
+        assert_eq!(zi.data_range().map(|(start, _)| start), Some(-2717640000));
+        assert_eq!(ZoneInfo::from_posix(&"EST5EDT,M3.2.0,M11.1.0".parse().expect("posix")).data_range(), None);
+
+        let zi = ZoneInfo::load(std::path::Path::new("/usr/share/zoneinfo/right/UTC")).expect("load");
+        assert_eq!(zi.leap_seconds[0], LeapSecond{ time: 78796800, correction: 1 });
+
+        assert!(ZoneInfo::parse(b"TZif2").is_err());
+        assert!(ZoneInfo{ types: vec![], ..zi.clone() }.to_tzif().is_err());
+        assert!(ZoneInfo{ version: 10, ..zi.clone() }.to_tzif().is_err());
+        assert!(ZoneInfo{ version: 4, ..zi }.to_tzif().is_ok());
+    }
+
+    #[test]
+    fn view() {
+        let data = std::fs::read("/usr/share/zoneinfo/America/Los_Angeles").expect("read");
+        let (view, zi) = (TzifView::parse(&data).expect("parse"), ZoneInfo::parse(&data).expect("parse"));
+        assert_eq!((view.version(), view.type_count(), view.footer()), (zi.version, zi.types.len(), zi.footer.as_deref()));
+        assert!(view.transitions().eq(zi.transitions.iter().copied()));
+        assert!((0..view.type_count()).all(|i| view.local_time_type(i) == Some((zi.types[i].utoff, zi.types[i].isdst, zi.types[i].abbr.as_str()))));
+        let last = zi.transitions.last().expect("transitions");
+        assert_eq!((view.type_at(TimeT::MIN), view.type_at(last.time - 1), view.type_at(last.time)), (Some(0), Some(zi.transitions[zi.transitions.len() - 2].local_time_type), None));
+        assert_eq!((view.transition(view.transition_count()), view.leap_count()), (None, 0));
+        assert!(TzifView::parse(&std::fs::read("/usr/share/zoneinfo/right/UTC").expect("read")).expect("parse").leap_count() > 0);
+
+        let rule = ZoneInfo::from_posix(&"JST-9".parse().expect("posix")).to_tzif().expect("to_tzif");
+        assert_eq!(TzifView::parse(&rule).expect("parse").type_at(0), None);
+        assert!(TzifView::parse(&data[..data.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn validate() {
+        let data = std::fs::read("/usr/share/zoneinfo/America/Los_Angeles").expect("read");
+        let report = validate_tzif(&data).expect("validate");
+        assert_eq!((report.version >= 2, report.type_count, report.leap_count), (true, 6, 0));
+        assert_eq!(report.abbreviations, ["LMT", "PST", "PDT", "PWT", "PPT"]);
+        assert_eq!(report.warnings, Vec::<String>::new());
+        assert!(validate_tzif(&std::fs::read("/usr/share/zoneinfo/right/UTC").expect("read")).is_ok());
+
+        assert_eq!(validate_tzif(b"TZjf"), Err(TzifError::Truncated{ len: 4, needed: 44 }));
+        assert_eq!(validate_tzif(&[b"TZjf", &data[4..]].concat()), Err(TzifError::BadMagic));
+        assert_eq!(validate_tzif(&[b"TZif\x01", &data[5..]].concat()), Err(TzifError::UnknownVersion(1)));
+        assert!(matches!(validate_tzif(&data[..data.len() - 40]), Err(TzifError::Truncated{ .. })));
+
+        // to_tzif() won't write transitions out of order, so swap two in the 64 bit block.
+        let mut zi = ZoneInfo::parse(&data).expect("parse");
+        let mut swapped = data.clone();
+        let find = |time: TimeT| data.windows(8).rposition(|w| w == time.to_be_bytes()).expect("find");
+        let (a, b) = (find(zi.transitions[3].time), find(zi.transitions[4].time));
+        swapped[a..a + 8].copy_from_slice(&zi.transitions[4].time.to_be_bytes());
+        swapped[b..b + 8].copy_from_slice(&zi.transitions[3].time.to_be_bytes());
+        assert_eq!(validate_tzif(&swapped), Err(TzifError::NotMonotonic{ index: 4, time: zi.transitions[3].time }));
+        zi.footer = Some("PST8PDT,M3".to_string());
+        assert!(matches!(validate_tzif(&zi.to_tzif().expect("to_tzif")), Err(TzifError::BadFooter(_))));
+        zi.footer = Some("EST5EDT,M3.2.0,M11.1.0".to_string());
+        assert_eq!(validate_tzif(&zi.to_tzif().expect("to_tzif")).expect("validate").warnings.len(), 1);
+        let mut junk = ZoneInfo{ footer: None, ..zi.clone() }.to_tzif().expect("to_tzif");
+        junk.extend(b"junk");
+        assert_eq!(validate_tzif(&junk).expect("validate").warnings.len(), 2);
+
+        let mut zi = ZoneInfo::load(std::path::Path::new("/usr/share/zoneinfo/right/UTC")).expect("load");
+        zi.leap_seconds[1].correction = 5;
+        assert!(matches!(validate_tzif(&zi.to_tzif().expect("to_tzif")), Err(TzifError::Invalid(_))));
+        zi.leap_seconds[1].correction = i32::MIN;
+        assert!(matches!(validate_tzif(&zi.to_tzif().expect("to_tzif")), Err(TzifError::Invalid(_))));
+    }
+
+    #[test]
+    fn write() {
+        for name in ["America/Los_Angeles", "right/UTC", "Australia/Lord_Howe"] {
+            let zi = ZoneInfo::load(&std::path::Path::new("/usr/share/zoneinfo").join(name)).expect("load");
+            let data = zi.to_tzif().expect("to_tzif");
+            assert_eq!(ZoneInfo::parse(&data).expect("parse"), zi, "{}", name);
+
+            let v1 = ZoneInfo::parse(&[b"TZif\0", &data[5..]].concat()).expect("parse v1");
+            assert!(v1.transitions.iter().all(|t| zi.transitions.contains(t) || t.time == i32::MIN as TimeT));
+            assert_eq!(v1.types, zi.types);
+            if name == "America/Los_Angeles" {
+                // The transition to PST in 1883 doesn't fit, so v1 readers get PST from the earliest 32 bit time, not LMT.
+                assert_eq!(v1.transitions[0].time, i32::MIN as TimeT);
+                assert_eq!(v1.types[v1.transitions[0].local_time_type].abbr, "PST");
+            }
+        }
+
+        // Something synthesized should be usable by libtz.
+        #[cfg(feature = "std")] {
+            let zi = ZoneInfo::from_posix(&"AAA-1BBB,M3.5.0,M10.5.0/3".parse().expect("posix"));
+            let path = std::env::temp_dir().join(format!("libtz-write-test-{}", std::process::id()));
+            std::fs::write(&path, zi.to_tzif().expect("to_tzif")).expect("write");
+            let tz = crate::Timezone::new(path.to_str().expect("path")).expect("timezone alloc");
+            assert_eq!(&*tz.localtime(1719835200).expect("localtime").tm_zone, "BBB");
+            assert_eq!(tz.zone_info().expect("zone_info"), &zi);
+            std::fs::remove_file(&path).expect("remove");
+        }
+        assert!(ZoneInfo::parse(b"not a zone file").is_err());
+    }
+}
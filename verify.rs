@@ -0,0 +1,117 @@
+// Self-consistency checks for a zone's conversions.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
+use crate::{Tm, TimeT, Timezone, UtcOffset};
+
+/// The first problem [`verify_zone`] found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    /** The instant that failed */                  pub time    : TimeT,
+    /** What went wrong */                          pub problem : String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.time, self.problem)
+    }
+}
+
+impl std::error::Error for Violation {}
+
+// The fields of `tm` that say what the wall clock reads.
+fn wall(tm: &Tm) -> (i32, i32, i32, i32, i32, i32) {
+    (tm.tm_year, tm.tm_mon, tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
+
+/// Check that `tz`'s conversions agree with each other over `range`, for running in CI against each new tzdata
+/// release to catch regressions early. Returns the first [`Violation`] found.
+///
+/// It looks at every `step` through the range (a zero `step` counts as a second) and at each transition in it
+/// and the second before, checking that:
+///
+/// - `localtime()` works and gives a valid offset (less than 26 hours) whose wall clock time is the time plus
+///   the offset (less any leap seconds).
+/// - `mktime(localtime(t)) == t`. The only exception is a repeated time where the offset changes but the DST
+///   flag doesn't (like a zone moving to a new standard time): `mktime()` has nothing to tell the two apart by, so
+///   it's fine for it to give the other instant with the same wall clock time.
+/// - The offset only changes across a transition the zone reports (the ones [`diff_zones`](crate::diff_zones) and
+///   [`upcoming_transitions`](crate::upcoming_transitions) find).
+///
+/// ```
+/// use libtz::{Timezone, verify_zone};
+/// let tz = Timezone::new("Europe/Dublin").unwrap();
+/// verify_zone(&tz, 0..2000000000, std::time::Duration::from_secs(86400)).unwrap();
+/// ```
+pub fn verify_zone(tz: &Timezone, range: Range<TimeT>, step: Duration) -> Result<(), Violation> {
+    let violation = |time, problem: String| Violation{ time, problem };
+    let step = TimeT::try_from(step.as_secs().max(1)).unwrap_or(TimeT::MAX);
+    let transitions = tz.transitions_between(range.start, range.end).map_err(|e| violation(range.start, format!("transitions: {}", e)))?;
+
+    let mut times: Vec<TimeT> = transitions.iter().flat_map(|(t, _, _)| [*t - 1, *t]).filter(|t| range.contains(t)).collect();
+    let mut t = range.start;
+    while t < range.end {
+        times.push(t);
+        t = t.saturating_add(step);
+    }
+    times.sort_unstable();
+    times.dedup();
+
+    let mut last: Option<(TimeT, i64)> = None;
+    let mut transitions = transitions.iter().map(|(t, _, _)| *t).peekable();
+    for time in times {
+        let tm = tz.localtime(time).map_err(|e| violation(time, format!("localtime: {}", e)))?;
+        if UtcOffset::new(tm.tm_gmtoff).is_err() {
+            return Err(violation(time, format!("offset {} is out of range", tm.tm_gmtoff)));
+        }
+        // Leap seconds (in the `right/` zones) aren't on the wall clock.
+        let posix = tz.time2posix(time);
+        match crate::timegm(&Tm{ tm_sec: tm.tm_sec.min(59), ..tm.clone() }) {
+            Ok(local) if local - posix == tm.tm_gmtoff => {},
+            Ok(local) => return Err(violation(time, format!("wall clock {} is {}s from UTC but the offset is {}", tm.strftime("%F %T"), local - posix, tm.tm_gmtoff))),
+            Err(e)    => return Err(violation(time, format!("timegm: {}", e))),
+        }
+
+        let back = tz.mktime(&tm).map_err(|e| violation(time, format!("mktime({}): {}", tm.strftime("%F %T %Z"), e)))?;
+        if back != time {
+            let other = tz.localtime(back).map_err(|e| violation(back, format!("localtime: {}", e)))?;
+            if wall(&other) != wall(&tm) || (other.tm_isdst > 0) != (tm.tm_isdst > 0) {
+                return Err(violation(time, format!("mktime({}) gave {}, not {}", tm.strftime("%F %T %Z"), back, time)));
+            }
+        }
+
+        let mut crossed = false;
+        while transitions.next_if(|t| *t <= time).is_some() {
+            crossed = true;
+        }
+        if let Some((prev, offset)) = last {
+            if offset != tm.tm_gmtoff && !crossed {
+                return Err(violation(time, format!("offset changed from {} to {} since {} without a transition", offset, tm.tm_gmtoff, prev)));
+            }
+        }
+        last = Some((time, tm.tm_gmtoff));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify() {
+        let day = Duration::from_secs(86400);
+        for zone in ["America/New_York", "Europe/Dublin", "Australia/Lord_Howe", "Pacific/Apia", "Asia/Kolkata", "UTC", "right/UTC", "EST5EDT,M3.2.0,M11.1.0"] {
+            let tz = Timezone::new(zone).expect("timezone alloc");
+            assert_eq!(verify_zone(&tz, -2500000000..2500000000, day), Ok(()), "{}", zone);
+        }
+        let tz = Timezone::new("Europe/London").expect("timezone alloc");
+        assert_eq!(verify_zone(&tz, 0..0, day), Ok(()));
+        assert_eq!(verify_zone(&tz, 1700000000..1700000010, Duration::ZERO), Ok(()));
+        assert_eq!(Violation{ time: 5, problem: "wrong".to_string() }.to_string(), "5: wrong");
+    }
+}
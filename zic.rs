@@ -0,0 +1,529 @@
+// Compiler for tz database source files (the input to zic).
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use crate::{Timezone, ZoneInfo, Transition, LocalTimeType, calendar};
+use crate::posix::{PosixTz, PosixDst, PosixRuleDate, PosixRuleDay};
+
+const MONTHS: [&str; 12] = ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October",
+                            "November", "December"];
+const WEEKDAYS: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+
+// Rules that go on forever are expanded up to this year (like zic's default), after which the zone's POSIX
+// rule takes over.
+const HORIZON_YEAR: i64 = 2037;
+
+// Years have to be in this range (apart from "min" and "max"), which keeps the date arithmetic from overflowing
+// and the expansion of rules to a few thousand years.
+const YEARS: core::ops::RangeInclusive<i64> = -9999..=9999;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Clock {
+    Wall,
+    Standard,
+    Universal,
+}
+
+// The ON field of a Rule (and the day in a Zone's UNTIL).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum On {
+    Day(i64),
+    Last(i32),              // lastSun
+    AtOrAfter(i32, i64),    // Sun>=8
+    AtOrBefore(i32, i64),   // Sun<=25
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    name: String,
+    from: i64,
+    to: i64,
+    month: i32, // [0, 11]
+    on: On,
+    at: i64,
+    at_clock: Clock,
+    save: i64,
+    isdst: bool,
+    letter: String,
+}
+
+#[derive(Clone, Debug)]
+enum Rules {
+    Fixed(i64, bool), // "-" is Fixed(0, false)
+    Named(String),
+}
+
+#[derive(Clone, Debug)]
+struct ZoneLine {
+    stdoff: i64,
+    rules: Rules,
+    format: String,
+    until: Option<(i64, Clock)>, // Local wall seconds
+}
+
+/// Zones compiled from tz database source: the `Rule`, `Zone` and `Link` lines that `zic` reads (including
+/// the condensed `tzdata.zi` form).
+///
+/// This lets you try out proposed tz database changes or define your own zones without installing `zic`:
+///
+/// ```
+/// let source = libtz::ZoneSource::parse("
+///     Rule  Mars  2020  max  -  Mar  lastSun  2:00  1:00  S
+///     Rule  Mars  2020  max  -  Oct  lastSun  3:00  0     -
+///     Zone  Mars/Olympus_Mons  1:00  Mars  OM%sT
+///     Link  Mars/Olympus_Mons  Mars/Tharsis
+/// ").expect("parse");
+/// let tz = source.timezone("Mars/Tharsis").expect("compile");
+/// assert_eq!(&*tz.localtime(1719835200).expect("localtime").tm_zone, "OMST");
+/// ```
+///
+/// The compiler follows `zic`'s rules, with a few limits: `Leap` and `Expires` lines aren't supported, years
+/// have to be between -9999 and 9999 (or `min`/`max`), times and offsets have to fit in 32 bits, and rules that
+/// go on forever are expanded up to 2037 and then described by a POSIX TZ rule in the TZif footer
+/// (when the rule can be expressed as one; if not the last offset simply continues).
+///
+/// Reference: [`zic(8)`](https://man7.org/linux/man-pages/man8/zic.8.html)
+#[derive(Clone, Debug, Default)]
+pub struct ZoneSource {
+    rules: Vec<Rule>,
+    zones: Vec<(String, Vec<ZoneLine>)>,
+    links: Vec<(String, String)>, // (target, name)
+}
+
+// Case insensitive keyword lookup, allowing unambiguous abbreviations like zic does.
+fn lookup(word: &str, table: &[&str]) -> Option<usize> {
+    if word.is_empty() {
+        return None;
+    }
+    if let Some(i) = table.iter().position(|t| t.eq_ignore_ascii_case(word)) {
+        return Some(i);
+    }
+    let matches: Vec<usize> = (0..table.len()).filter(|i| table[*i].len() > word.len() && table[*i][..word.len()].eq_ignore_ascii_case(word)).collect();
+    match matches[..] { [i] => Some(i), _ => None }
+}
+
+fn parse_month(s: &str) -> Result<i32, String> {
+    lookup(s, &MONTHS).map(|m| m as i32).ok_or_else(|| format!("Invalid month {:?}", s))
+}
+
+fn parse_weekday(s: &str) -> Result<i32, String> {
+    lookup(s, &WEEKDAYS).map(|d| d as i32).ok_or_else(|| format!("Invalid weekday {:?}", s))
+}
+
+// [-]h[:mm[:ss]] with an optional suffix letter from `suffixes`. "-" is zero.
+fn parse_time(s: &str, suffixes: &str) -> Result<(i64, Option<char>), String> {
+    let err = || format!("Invalid time {:?}", s);
+    if s == "-" {
+        return Ok((0, None));
+    }
+    let (s, suffix) = match s.chars().last() {
+        Some(c) if c.is_ascii_alphabetic() => {
+            if !suffixes.contains(c.to_ascii_lowercase()) { return Err(err()) }
+            (&s[..s.len() - 1], Some(c.to_ascii_lowercase()))
+        },
+        _ => (s, None),
+    };
+    let (sign, s) = match s.strip_prefix('-') { Some(s) => (-1, s), None => (1, s) };
+    let mut secs: i64 = 0;
+    for (i, part) in s.split(':').enumerate() {
+        let part = if i == 2 { part.split('.').next().unwrap() } else { part }; // zic ignores fractional seconds
+        if i > 2 || part.is_empty() || !part.bytes().all(|c| c.is_ascii_digit()) {
+            return Err(err());
+        }
+        let n: i64 = part.parse().map_err(|_| err())?;
+        if i > 0 && n > 59 {
+            return Err(err());
+        }
+        secs = n.checked_mul([3600, 60, 1][i]).and_then(|n| secs.checked_add(n)).ok_or_else(err)?;
+    }
+    // Offsets end up as 32 bit TZif offsets, so nothing bigger makes sense (and sums of them can't overflow).
+    if secs > i32::MAX as i64 {
+        return Err(err());
+    }
+    Ok((sign * secs, suffix))
+}
+
+fn parse_clock_time(s: &str) -> Result<(i64, Clock), String> {
+    let (time, suffix) = parse_time(s, "wsugz")?;
+    Ok((time, match suffix { Some('s') => Clock::Standard, Some('u' | 'g' | 'z') => Clock::Universal, _ => Clock::Wall }))
+}
+
+fn parse_on(s: &str) -> Result<On, String> {
+    let err = || format!("Invalid day {:?}", s);
+    if let Some(day) = s.strip_prefix("last").or_else(|| s.strip_prefix("Last")) {
+        return Ok(On::Last(parse_weekday(day.trim_start_matches('-'))?));
+    }
+    for (op, make) in [(">=", On::AtOrAfter as fn(i32, i64) -> On), ("<=", On::AtOrBefore)] {
+        if let Some((weekday, day)) = s.split_once(op) {
+            return Ok(make(parse_weekday(weekday)?, day.parse().ok().filter(|d| (1..=31).contains(d)).ok_or_else(err)?));
+        }
+    }
+    s.parse().ok().filter(|d| (1..=31).contains(d)).map(On::Day).ok_or_else(err)
+}
+
+fn parse_year(s: &str, extra: &[&str]) -> Result<i64, String> {
+    match lookup(s, extra) {
+        Some(i) => Ok([i64::MIN, i64::MAX, 0][i]),
+        None    => s.parse().map_err(|_| format!("Invalid year {:?}", s))
+                     .and_then(|year| if YEARS.contains(&year) { Ok(year) } else { Err(format!("Year out of range {:?}", s)) }),
+    }
+}
+
+fn parse_until(fields: &[&str]) -> Result<(i64, Clock), String> {
+    let year = parse_year(fields[0], &[])?;
+    let month = fields.get(1).map_or(Ok(0), |m| parse_month(m))?;
+    let day = match fields.get(2) { Some(on) => parse_on(on)?.day(year, month), None => calendar::days_from_civil(year, month, 1) };
+    let (time, clock) = fields.get(3).map_or(Ok((0, Clock::Wall)), |t| parse_clock_time(t))?;
+    if fields.len() > 4 {
+        return Err("Too many fields".to_string());
+    }
+    Ok((day * 86400 + time, clock))
+}
+
+fn parse_zone_line(fields: &[&str]) -> Result<ZoneLine, String> {
+    if fields.len() < 3 {
+        return Err("Zone line needs STDOFF, RULES and FORMAT".to_string());
+    }
+    let stdoff = parse_time(fields[0], "")?.0;
+    let rules = match fields[1] {
+        "-" => Rules::Fixed(0, false),
+        r if r.starts_with(|c: char| c.is_ascii_digit() || c == '-') => {
+            let (save, suffix) = parse_time(r, "sd")?;
+            Rules::Fixed(save, suffix.map_or(save != 0, |s| s == 'd'))
+        },
+        name => Rules::Named(name.to_string()),
+    };
+    let until = if fields.len() > 3 { Some(parse_until(&fields[3..])?) } else { None };
+    Ok(ZoneLine{ stdoff, rules, format: fields[2].to_string(), until })
+}
+
+impl On {
+    // Days since 1970-01-01 for this day in `year` and `month` ([0, 11]).
+    fn day(&self, year: i64, month: i32) -> i64 {
+        let first = calendar::days_from_civil(year, month, 1);
+        let weekday_of = |day: i64| calendar::weekday_from_days(day);
+        match *self {
+            On::Day(day)                 => first + day - 1,
+            On::Last(weekday)            => {
+                let last = first + calendar::month_days(year, month) as i64 - 1;
+                last - (weekday_of(last) - weekday).rem_euclid(7) as i64
+            },
+            On::AtOrAfter(weekday, day)  => { let d = first + day - 1; d + (weekday - weekday_of(d)).rem_euclid(7) as i64 },
+            On::AtOrBefore(weekday, day) => { let d = first + day - 1; d - (weekday_of(d) - weekday).rem_euclid(7) as i64 },
+        }
+    }
+}
+
+// The abbreviation for a zone line's FORMAT.
+fn abbreviation(format: &str, letter: &str, utoff: i64, isdst: bool) -> String {
+    if let Some((std, dst)) = format.split_once('/') {
+        return (if isdst { dst } else { std }).to_string();
+    }
+    format.replace("%s", letter).replace("%z", &crate::format_offset(utoff, crate::OffsetFormat::Abbreviation))
+}
+
+// A rule's POSIX TZ equivalent, given the save in effect before it. This follows zic's stringrule().
+fn posix_rule_date(rule: &Rule, stdoff: i64, save_before: i64) -> Option<PosixRuleDate> {
+    let mut time = match rule.at_clock {
+        Clock::Wall      => rule.at,
+        Clock::Standard  => rule.at + save_before,
+        Clock::Universal => rule.at + stdoff + save_before,
+    };
+    let leap_days = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+    let (week, mut weekday) = match rule.on {
+        On::Day(day) => {
+            if rule.month == 1 && day == 29 {
+                return None;
+            }
+            let yday = (0..rule.month as usize).map(|m| calendar::month_days(1970, m as i32) as i64).sum::<i64>() + day;
+            return Some(PosixRuleDate{ day: PosixRuleDay::Julian1(yday as u16), time: i32::try_from(time).ok()? });
+        },
+        On::Last(weekday) => (5, weekday),
+        On::AtOrAfter(weekday, day) => {
+            let offset = (day - 1) % 7;
+            time += offset * 86400;
+            (1 + (day - 1) / 7, weekday - offset as i32)
+        },
+        On::AtOrBefore(weekday, day) if day == leap_days[rule.month as usize] => (5, weekday),
+        On::AtOrBefore(weekday, day) => {
+            let offset = day % 7;
+            time += offset * 86400;
+            (day / 7, weekday - offset as i32)
+        },
+    };
+    weekday = weekday.rem_euclid(7);
+    if !(1..=5).contains(&week) || time.abs() > 167 * 3600 {
+        return None;
+    }
+    Some(PosixRuleDate{ day: PosixRuleDay::MonthWeekDay{ month: rule.month as u8 + 1, week: week as u8, weekday: weekday as u8 }, time: i32::try_from(time).ok()? })
+}
+
+impl ZoneSource {
+    /// Parse tz database source text. It can be any mix of `Rule`, `Zone` and `Link` lines (keywords, months and
+    /// weekdays can be abbreviated, as in `tzdata.zi`).
+    pub fn parse(text: &str) -> Result<ZoneSource, String> {
+        let mut source = ZoneSource::default();
+        let mut continuation = false;
+        for (n, line) in text.lines().enumerate() {
+            let err = |e: String| format!("line {}: {}", n + 1, e);
+            let line = line.split('#').next().unwrap();
+            let fields: Vec<&str> = line.split_whitespace().map(|f| f.trim_matches('"')).collect();
+            if fields.is_empty() {
+                continue;
+            }
+            if continuation {
+                let zone = parse_zone_line(&fields).map_err(err)?;
+                continuation = zone.until.is_some();
+                source.zones.last_mut().unwrap().1.push(zone);
+                continue;
+            }
+            match lookup(fields[0], &["Rule", "Zone", "Link"]) {
+                Some(0) => {
+                    let [_, name, from, to, _, month, on, at, save, letter] = fields[..] else {
+                        return Err(err("Rule line needs 10 fields".to_string()));
+                    };
+                    let from = parse_year(from, &["minimum", "maximum"]).map_err(err)?;
+                    let to = match parse_year(to, &["minimum", "maximum", "only"]).map_err(err)? { 0 => from, to => to };
+                    let (at, at_clock) = parse_clock_time(at).map_err(err)?;
+                    let (save, suffix) = parse_time(save, "sd").map_err(err)?;
+                    source.rules.push(Rule{ name: name.to_string(), from, to, month: parse_month(month).map_err(err)?, on: parse_on(on).map_err(err)?,
+                                            at, at_clock, save, isdst: suffix.map_or(save != 0, |s| s == 'd'),
+                                            letter: if letter == "-" { String::new() } else { letter.to_string() } });
+                },
+                Some(1) => {
+                    if fields.len() < 2 {
+                        return Err(err("Zone line needs a name".to_string()));
+                    }
+                    let zone = parse_zone_line(&fields[2..]).map_err(err)?;
+                    continuation = zone.until.is_some();
+                    source.zones.push((fields[1].to_string(), vec![zone]));
+                },
+                Some(2) => {
+                    let [_, target, name] = fields[..] else { return Err(err("Link line needs 3 fields".to_string())) };
+                    source.links.push((target.to_string(), name.to_string()));
+                },
+                _ => return Err(err(format!("Unknown line type {:?}", fields[0]))),
+            }
+        }
+        if continuation {
+            return Err("Zone continuation line expected at end of input".to_string());
+        }
+        Ok(source)
+    }
+
+    /// The names of the zones and links defined, in the order they were defined.
+    pub fn names(&self) -> Vec<&str> {
+        self.zones.iter().map(|(name, _)| name.as_str()).chain(self.links.iter().map(|(_, name)| name.as_str())).collect()
+    }
+
+    fn zone_lines(&self, name: &str) -> Result<&[ZoneLine], String> {
+        let mut name = name;
+        for _ in 0..32 {
+            if let Some((_, lines)) = self.zones.iter().find(|(n, _)| n == name) {
+                return Ok(lines);
+            }
+            name = &self.links.iter().find(|(_, n)| n == name).ok_or_else(|| format!("Unknown zone {:?}", name))?.0;
+        }
+        Err(format!("Too many levels of links for {:?}", name))
+    }
+
+    /// Compile a zone (or link) into TZif data.
+    pub fn compile(&self, name: &str) -> Result<ZoneInfo, String> {
+        let lines = self.zone_lines(name)?;
+        let mut types: Vec<LocalTimeType> = vec![];
+        let mut type_index = |utoff: i64, isdst: bool, abbr: String| -> Result<usize, String> {
+            let t = LocalTimeType{ utoff: i32::try_from(utoff).map_err(|_| "Offset out of range")?, isdst, abbr, is_std: false, is_ut: false };
+            Ok(types.iter().position(|u| *u == t).unwrap_or_else(|| { types.push(t); types.len() - 1 }))
+        };
+        let mut transitions: Vec<Transition> = vec![];
+        let push = |transitions: &mut Vec<Transition>, time: i64, local_time_type: usize| {
+            while transitions.last().is_some_and(|t| t.time >= time) {
+                transitions.pop();
+            }
+            transitions.push(Transition{ time, local_time_type });
+        };
+
+        let mut start: Option<i64> = None; // UT; None for the first line
+        for line in lines {
+            let stdoff = line.stdoff;
+            let until_ut = |save: i64| line.until.map(|(until, clock)| match clock {
+                Clock::Wall      => until - stdoff - save,
+                Clock::Standard  => until - stdoff,
+                Clock::Universal => until,
+            });
+            let end;
+            match line.rules {
+                Rules::Fixed(save, isdst) => {
+                    let t = type_index(stdoff + save, isdst, abbreviation(&line.format, "", stdoff + save, isdst))?;
+                    if let Some(start) = start {
+                        push(&mut transitions, start, t);
+                    }
+                    end = until_ut(save);
+                },
+                Rules::Named(ref name) => {
+                    let rules: Vec<&Rule> = self.rules.iter().filter(|r| r.name == *name).collect();
+                    if rules.is_empty() {
+                        return Err(format!("Unknown rule {:?}", name));
+                    }
+                    // Before any rule applies it's standard time, using the letter of the earliest standard time rule.
+                    let std_letter = rules.iter().filter(|r| r.save == 0).min_by_key(|r| (r.from, r.month)).map_or("", |r| r.letter.as_str());
+                    let (mut save, mut isdst, mut letter) = (0, false, std_letter);
+                    // Rules from before the line starts still decide its initial state.
+                    let first_year = match (rules.iter().map(|r| r.from).min().unwrap(), start) {
+                        (i64::MIN, Some(start)) => calendar::civil_from_days(start.div_euclid(86400)).0 - 1,
+                        (i64::MIN, None)        => 1800,
+                        (from, _)               => from,
+                    };
+                    let last_year = match line.until {
+                        Some((until, _)) => calendar::civil_from_days(until.div_euclid(86400)).0 + 1,
+                        // Rules that end are always expanded in full.
+                        None => rules.iter().map(|r| if r.to == i64::MAX { r.from } else { r.to }).max().unwrap().max(HORIZON_YEAR),
+                    };
+                    let mut occurrences: Vec<(i64, &Rule)> = vec![];
+                    for year in first_year..=last_year {
+                        for rule in rules.iter().filter(|r| r.from <= year && year <= r.to) {
+                            occurrences.push((rule.on.day(year, rule.month) * 86400 + rule.at, rule));
+                        }
+                    }
+                    // Order by standard time, which is close enough since rules are months apart.
+                    occurrences.sort_by_key(|(time, rule)| if rule.at_clock == Clock::Universal { time + stdoff } else { *time });
+
+                    let mut started = start.is_none();
+                    if started {
+                        type_index(stdoff, false, abbreviation(&line.format, letter, stdoff, false))?; // Type 0
+                    }
+                    for (time, rule) in occurrences {
+                        let ut = match rule.at_clock {
+                            Clock::Wall      => time - stdoff - save,
+                            Clock::Standard  => time - stdoff,
+                            Clock::Universal => time,
+                        };
+                        if until_ut(save).is_some_and(|until| ut >= until) {
+                            break;
+                        }
+                        if !started && ut > start.unwrap() {
+                            let t = type_index(stdoff + save, isdst, abbreviation(&line.format, letter, stdoff + save, isdst))?;
+                            push(&mut transitions, start.unwrap(), t);
+                            started = true;
+                        }
+                        (save, isdst, letter) = (rule.save, rule.isdst, &rule.letter);
+                        if started {
+                            let t = type_index(stdoff + save, isdst, abbreviation(&line.format, letter, stdoff + save, isdst))?;
+                            push(&mut transitions, ut, t);
+                        }
+                    }
+                    if !started {
+                        let t = type_index(stdoff + save, isdst, abbreviation(&line.format, letter, stdoff + save, isdst))?;
+                        push(&mut transitions, start.unwrap(), t);
+                    }
+                    end = until_ut(save);
+                },
+            }
+            start = end;
+        }
+
+        // Like zic, merge a transition into the one before it if it happens no later in local time (this is what
+        // makes a rule change right at a line's start happen at the start) and drop ones that change nothing.
+        let mut merged: Vec<Transition> = vec![];
+        for t in transitions {
+            if let Some(last) = merged.last() {
+                let before = if merged.len() > 1 { merged[merged.len() - 2].local_time_type } else { 0 };
+                if t.time + types[last.local_time_type].utoff as i64 <= last.time + types[before].utoff as i64 {
+                    merged.last_mut().unwrap().local_time_type = t.local_time_type;
+                    continue;
+                }
+            }
+            if merged.last().map_or(0, |last| last.local_time_type) != t.local_time_type {
+                merged.push(t);
+            }
+        }
+        let transitions = merged;
+        let footer = self.footer(lines.last().unwrap(), transitions.last().map_or(&types[0], |t| &types[t.local_time_type]));
+        Ok(ZoneInfo{ version: 2, transitions, types, leap_seconds: vec![], footer: footer.map(|f| f.to_string()) })
+    }
+
+    // The POSIX TZ rule for times after the last transition, if there is one.
+    fn footer(&self, last: &ZoneLine, last_type: &LocalTimeType) -> Option<PosixTz> {
+        let rule = match last.rules {
+            Rules::Named(ref name) => {
+                let ongoing: Vec<&Rule> = self.rules.iter().filter(|r| r.name == *name && r.to == i64::MAX).collect();
+                match ongoing[..] {
+                    [] => None,
+                    [a, b] if a.save == 0 || b.save == 0 => {
+                        // Even with a negative save (like Ireland's winter), the rule without a save is standard time.
+                        let (std, dst) = if a.save == 0 { (a, b) } else { (b, a) };
+                        let abbr = |r: &Rule| abbreviation(&last.format, &r.letter, last.stdoff + r.save, r.isdst);
+                        Some(PosixTz{ std_abbr: abbr(std), std_offset: i32::try_from(last.stdoff + std.save).ok()?,
+                                      dst: Some(PosixDst{ abbr: abbr(dst), offset: i32::try_from(last.stdoff + dst.save).ok()?,
+                                                          rule: Some((posix_rule_date(dst, last.stdoff, std.save)?,
+                                                                      posix_rule_date(std, last.stdoff, dst.save)?)) }) })
+                    },
+                    _ => return None,
+                }
+            },
+            Rules::Fixed(..) => None,
+        };
+        let rule = rule.unwrap_or_else(|| PosixTz{ std_abbr: last_type.abbr.clone(), std_offset: last_type.utoff, dst: None });
+        // Not every abbreviation can be written in a POSIX rule.
+        (rule.to_string().parse::<PosixTz>().ok()? == rule).then_some(rule)
+    }
+
+    /// Compile a zone (or link) and load it. The [`Timezone`]'s [`Provenance`][crate::Provenance] is
+    /// [`Synthesized`][crate::Provenance::Synthesized] with the name given.
+    pub fn timezone(&self, name: &str) -> Result<Timezone, String> {
+        Timezone::from_zone_info(name, self.compile(name)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_at(zi: &ZoneInfo, time: i64) -> &LocalTimeType {
+        match zi.transitions.partition_point(|t| t.time <= time) {
+            0 => &zi.types[0],
+            i => &zi.types[zi.transitions[i - 1].local_time_type],
+        }
+    }
+
+    #[test]
+    fn tzdata() {
+        // Compiling the system's own source should give the same results as the system's compiled zones.
+        let source = ZoneSource::parse(&std::fs::read_to_string("/usr/share/zoneinfo/tzdata.zi").expect("read")).expect("parse");
+        for name in source.names() {
+            let zi = source.compile(name).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            let Ok(system) = ZoneInfo::load(&std::path::Path::new("/usr/share/zoneinfo").join(name)) else { continue };
+            for t in system.transitions.iter().filter(|t| t.time > -2208988800) { // 1900
+                for time in [t.time - 1, t.time] {
+                    let (ours, theirs) = (type_at(&zi, time), type_at(&system, time));
+                    assert_eq!((ours.utoff, ours.isdst, &ours.abbr), (theirs.utoff, theirs.isdst, &theirs.abbr), "{} at {}", name, time);
+                }
+            }
+            assert_eq!(zi.footer, system.footer, "{}", name);
+        }
+    }
+
+    #[test]
+    fn custom() {
+        let source = ZoneSource::parse("
+            # A comment
+            Rule  Test  2000  max  -  Apr  Sun>=1  2:00   1:00  D
+            Rule  Test  2000  max  -  Oct  lastSun 2:00   0     S
+            Zone  Test/Zone  -5:00  -  XST  2001 Jan 1
+                             -5:00  Test E%sT
+        ").expect("parse");
+        let zi = source.compile("Test/Zone").expect("compile");
+        assert_eq!(zi.footer.as_deref(), Some("EST5EDT,M4.1.0,M10.5.0"));
+        let tz = source.timezone("Test/Zone").expect("timezone");
+        assert_eq!(&*tz.localtime(946684800).expect("localtime").tm_zone, "XST"); // 2000-01-01
+        assert_eq!(&*tz.localtime(994000000).expect("localtime").tm_zone, "EDT"); // 2001-07-01
+        assert_eq!(&*tz.localtime(4102444800).expect("localtime").tm_zone, "EST"); // 2100-01-01
+
+        for bad in ["Rule Test 2000", "Zone Test/Zone -5:00 Nope XST", "Zone A 1 - X 2000\n", "Bogus line", "Zone A 1:61 - X",
+                    "Zone A 99999999999999999:00 - X", "Zone A 9999999:00 - X", "Zone A 1:00 - X 999999999999999\n 1:00 - X",
+                    "Rule R 999999999999999 max - Jan 1 0:00 0 -\nZone A 1:00 R X", "Rule R 2000 99999999 - Jan 1 0:00 0 -\nZone A 1:00 R X"] {
+            assert!(ZoneSource::parse(bad).and_then(|s| s.compile("A")).is_err(), "{:?} should fail", bad);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+// Localized zone names from the Unicode CLDR.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use crate::{Locale, Timezone};
+
+/// Which of a zone's names [`Timezone::display_name`] gives.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NameStyle {
+    /// The name for standard time ("Pacific Standard Time").
+    Standard,
+    /// The name for daylight saving time ("Pacific Daylight Time").
+    Daylight,
+    /// The name for the zone as a whole, whatever time of year it is ("Pacific Time").
+    Generic,
+}
+
+// CLDR groups zones that share names into "metazones". This maps zones (and their common aliases) to the
+// metazone they're in now. London and Dublin have their own summer time names so they get their own entries.
+const METAZONES: &[(&str, &str)] = &[
+    ("America/Los_Angeles", "America_Pacific"), ("America/Vancouver", "America_Pacific"), ("America/Tijuana", "America_Pacific"),
+    ("US/Pacific", "America_Pacific"), ("PST8PDT", "America_Pacific"),
+    ("America/Denver", "America_Mountain"), ("America/Phoenix", "America_Mountain"), ("America/Boise", "America_Mountain"),
+    ("America/Edmonton", "America_Mountain"), ("US/Mountain", "America_Mountain"), ("US/Arizona", "America_Mountain"),
+    ("MST7MDT", "America_Mountain"),
+    ("America/Chicago", "America_Central"), ("America/Winnipeg", "America_Central"), ("America/Mexico_City", "America_Central"),
+    ("America/Regina", "America_Central"), ("US/Central", "America_Central"), ("CST6CDT", "America_Central"),
+    ("America/New_York", "America_Eastern"), ("America/Toronto", "America_Eastern"), ("America/Detroit", "America_Eastern"),
+    ("America/Indiana/Indianapolis", "America_Eastern"), ("America/Jamaica", "America_Eastern"), ("America/Panama", "America_Eastern"),
+    ("US/Eastern", "America_Eastern"), ("EST5EDT", "America_Eastern"),
+    ("America/Anchorage", "Alaska"), ("America/Juneau", "Alaska"), ("US/Alaska", "Alaska"),
+    ("Pacific/Honolulu", "Hawaii_Aleutian"), ("America/Adak", "Hawaii_Aleutian"), ("US/Hawaii", "Hawaii_Aleutian"),
+    ("America/Halifax", "Atlantic"), ("America/Puerto_Rico", "Atlantic"), ("Atlantic/Bermuda", "Atlantic"),
+    ("Europe/London", "British"), ("GB", "British"),
+    ("Europe/Dublin", "Irish"), ("Eire", "Irish"),
+    ("Etc/GMT", "GMT"), ("GMT", "GMT"), ("Atlantic/Reykjavik", "GMT"), ("Africa/Abidjan", "GMT"), ("Africa/Accra", "GMT"),
+    ("UTC", "UTC"), ("Etc/UTC", "UTC"), ("Etc/UCT", "UTC"), ("Etc/Universal", "UTC"), ("Etc/Zulu", "UTC"),
+    ("Universal", "UTC"), ("Zulu", "UTC"),
+    ("Europe/Lisbon", "Europe_Western"), ("Atlantic/Canary", "Europe_Western"), ("Atlantic/Madeira", "Europe_Western"),
+    ("Atlantic/Faroe", "Europe_Western"), ("Portugal", "Europe_Western"),
+    ("Europe/Berlin", "Europe_Central"), ("Europe/Paris", "Europe_Central"), ("Europe/Madrid", "Europe_Central"),
+    ("Europe/Rome", "Europe_Central"), ("Europe/Amsterdam", "Europe_Central"), ("Europe/Brussels", "Europe_Central"),
+    ("Europe/Vienna", "Europe_Central"), ("Europe/Zurich", "Europe_Central"), ("Europe/Stockholm", "Europe_Central"),
+    ("Europe/Oslo", "Europe_Central"), ("Europe/Copenhagen", "Europe_Central"), ("Europe/Warsaw", "Europe_Central"),
+    ("Europe/Prague", "Europe_Central"), ("Europe/Budapest", "Europe_Central"), ("Europe/Belgrade", "Europe_Central"),
+    ("Europe/Luxembourg", "Europe_Central"), ("CET", "Europe_Central"),
+    ("Europe/Helsinki", "Europe_Eastern"), ("Europe/Athens", "Europe_Eastern"), ("Europe/Kyiv", "Europe_Eastern"),
+    ("Europe/Kiev", "Europe_Eastern"), ("Europe/Bucharest", "Europe_Eastern"), ("Europe/Sofia", "Europe_Eastern"),
+    ("Europe/Riga", "Europe_Eastern"), ("Europe/Tallinn", "Europe_Eastern"), ("Europe/Vilnius", "Europe_Eastern"),
+    ("Africa/Cairo", "Europe_Eastern"), ("Asia/Beirut", "Europe_Eastern"), ("EET", "Europe_Eastern"),
+    ("Asia/Kolkata", "India"), ("Asia/Calcutta", "India"),
+    ("Asia/Tokyo", "Japan"), ("Japan", "Japan"),
+    ("Asia/Shanghai", "China"), ("Asia/Chongqing", "China"), ("PRC", "China"),
+    ("Australia/Sydney", "Australia_Eastern"), ("Australia/Melbourne", "Australia_Eastern"),
+    ("Australia/Brisbane", "Australia_Eastern"), ("Australia/Hobart", "Australia_Eastern"),
+    ("Australia/Adelaide", "Australia_Central"), ("Australia/Darwin", "Australia_Central"),
+    ("Australia/Perth", "Australia_Western"),
+    ("Pacific/Auckland", "New_Zealand"), ("NZ", "New_Zealand"),
+];
+
+// (metazone, locale, [standard, daylight, generic]). Metazones without daylight saving time use the standard
+// name as the generic one, like CLDR does.
+type Names = [Option<&'static str>; 3];
+const NAMES: &[(&str, Locale, Names)] = &[
+    ("America_Pacific",   Locale::En, [Some("Pacific Standard Time"), Some("Pacific Daylight Time"), Some("Pacific Time")]),
+    ("America_Mountain",  Locale::En, [Some("Mountain Standard Time"), Some("Mountain Daylight Time"), Some("Mountain Time")]),
+    ("America_Central",   Locale::En, [Some("Central Standard Time"), Some("Central Daylight Time"), Some("Central Time")]),
+    ("America_Eastern",   Locale::En, [Some("Eastern Standard Time"), Some("Eastern Daylight Time"), Some("Eastern Time")]),
+    ("Alaska",            Locale::En, [Some("Alaska Standard Time"), Some("Alaska Daylight Time"), Some("Alaska Time")]),
+    ("Hawaii_Aleutian",   Locale::En, [Some("Hawaii-Aleutian Standard Time"), Some("Hawaii-Aleutian Daylight Time"), Some("Hawaii-Aleutian Time")]),
+    ("Atlantic",          Locale::En, [Some("Atlantic Standard Time"), Some("Atlantic Daylight Time"), Some("Atlantic Time")]),
+    ("British",           Locale::En, [Some("Greenwich Mean Time"), Some("British Summer Time"), Some("United Kingdom Time")]),
+    ("Irish",             Locale::En, [Some("Greenwich Mean Time"), Some("Irish Standard Time"), Some("Ireland Time")]),
+    ("GMT",               Locale::En, [Some("Greenwich Mean Time"), None, Some("Greenwich Mean Time")]),
+    ("UTC",               Locale::En, [Some("Coordinated Universal Time"), None, Some("Coordinated Universal Time")]),
+    ("Europe_Western",    Locale::En, [Some("Western European Standard Time"), Some("Western European Summer Time"), Some("Western European Time")]),
+    ("Europe_Central",    Locale::En, [Some("Central European Standard Time"), Some("Central European Summer Time"), Some("Central European Time")]),
+    ("Europe_Eastern",    Locale::En, [Some("Eastern European Standard Time"), Some("Eastern European Summer Time"), Some("Eastern European Time")]),
+    ("India",             Locale::En, [Some("India Standard Time"), None, Some("India Standard Time")]),
+    ("Japan",             Locale::En, [Some("Japan Standard Time"), Some("Japan Daylight Time"), Some("Japan Time")]),
+    ("China",             Locale::En, [Some("China Standard Time"), Some("China Daylight Time"), Some("China Time")]),
+    ("Australia_Eastern", Locale::En, [Some("Australian Eastern Standard Time"), Some("Australian Eastern Daylight Time"), Some("Eastern Australia Time")]),
+    ("Australia_Central", Locale::En, [Some("Australian Central Standard Time"), Some("Australian Central Daylight Time"), Some("Central Australia Time")]),
+    ("Australia_Western", Locale::En, [Some("Australian Western Standard Time"), Some("Australian Western Daylight Time"), Some("Western Australia Time")]),
+    ("New_Zealand",       Locale::En, [Some("New Zealand Standard Time"), Some("New Zealand Daylight Time"), Some("New Zealand Time")]),
+
+    ("America_Pacific",   Locale::De, [Some("Nordamerikanische Westküsten-Normalzeit"), Some("Nordamerikanische Westküsten-Sommerzeit"), Some("Nordamerikanische Westküstenzeit")]),
+    ("America_Mountain",  Locale::De, [Some("Rocky-Mountain-Normalzeit"), Some("Rocky-Mountain-Sommerzeit"), Some("Rocky-Mountain-Zeit")]),
+    ("America_Central",   Locale::De, [Some("Nordamerikanische Zentral-Normalzeit"), Some("Nordamerikanische Zentral-Sommerzeit"), Some("Nordamerikanische Zentralzeit")]),
+    ("America_Eastern",   Locale::De, [Some("Nordamerikanische Ostküsten-Normalzeit"), Some("Nordamerikanische Ostküsten-Sommerzeit"), Some("Nordamerikanische Ostküstenzeit")]),
+    ("British",           Locale::De, [Some("Mittlere Greenwich-Zeit"), Some("Britische Sommerzeit"), None]),
+    ("Irish",             Locale::De, [Some("Mittlere Greenwich-Zeit"), Some("Irische Sommerzeit"), None]),
+    ("GMT",               Locale::De, [Some("Mittlere Greenwich-Zeit"), None, Some("Mittlere Greenwich-Zeit")]),
+    ("UTC",               Locale::De, [Some("Koordinierte Weltzeit"), None, Some("Koordinierte Weltzeit")]),
+    ("Europe_Western",    Locale::De, [Some("Westeuropäische Normalzeit"), Some("Westeuropäische Sommerzeit"), Some("Westeuropäische Zeit")]),
+    ("Europe_Central",    Locale::De, [Some("Mitteleuropäische Normalzeit"), Some("Mitteleuropäische Sommerzeit"), Some("Mitteleuropäische Zeit")]),
+    ("Europe_Eastern",    Locale::De, [Some("Osteuropäische Normalzeit"), Some("Osteuropäische Sommerzeit"), Some("Osteuropäische Zeit")]),
+    ("India",             Locale::De, [Some("Indische Normalzeit"), None, Some("Indische Normalzeit")]),
+    ("Japan",             Locale::De, [Some("Japanische Normalzeit"), Some("Japanische Sommerzeit"), Some("Japanische Zeit")]),
+    ("China",             Locale::De, [Some("Chinesische Normalzeit"), Some("Chinesische Sommerzeit"), Some("Chinesische Zeit")]),
+    ("Australia_Eastern", Locale::De, [Some("Ostaustralische Normalzeit"), Some("Ostaustralische Sommerzeit"), Some("Ostaustralische Zeit")]),
+
+    ("America_Pacific",   Locale::Fr, [Some("heure normale du Pacifique nord-américain"), Some("heure d’été du Pacifique nord-américain"), Some("heure du Pacifique nord-américain")]),
+    ("America_Eastern",   Locale::Fr, [Some("heure normale de l’Est nord-américain"), Some("heure d’été de l’Est nord-américain"), Some("heure de l’Est nord-américain")]),
+    ("British",           Locale::Fr, [Some("heure moyenne de Greenwich"), Some("heure d’été britannique"), None]),
+    ("GMT",               Locale::Fr, [Some("heure moyenne de Greenwich"), None, Some("heure moyenne de Greenwich")]),
+    ("UTC",               Locale::Fr, [Some("temps universel coordonné"), None, Some("temps universel coordonné")]),
+    ("Europe_Western",    Locale::Fr, [Some("heure normale d’Europe de l’Ouest"), Some("heure d’été d’Europe de l’Ouest"), Some("heure d’Europe de l’Ouest")]),
+    ("Europe_Central",    Locale::Fr, [Some("heure normale d’Europe centrale"), Some("heure d’été d’Europe centrale"), Some("heure d’Europe centrale")]),
+    ("Europe_Eastern",    Locale::Fr, [Some("heure normale d’Europe de l’Est"), Some("heure d’été d’Europe de l’Est"), Some("heure d’Europe de l’Est")]),
+    ("Japan",             Locale::Fr, [Some("heure normale du Japon"), Some("heure d’été du Japon"), Some("heure du Japon")]),
+];
+
+// The CLDR name of zone `id`, if there is one.
+fn display_name(id: &str, locale: Locale, style: NameStyle) -> Option<&'static str> {
+    let id = id.strip_prefix("right/").or_else(|| id.strip_prefix("posix/")).unwrap_or(id);
+    let metazone = METAZONES.iter().find(|(zone, _)| *zone == id)?.1;
+    let names = NAMES.iter().find(|(m, l, _)| *m == metazone && *l == locale)?.2;
+    names[style as usize]
+}
+
+impl Timezone {
+    /// The zone's name for people, from the Unicode CLDR ("Mitteleuropäische Zeit", "Pacific Daylight Time"),
+    /// looked up by the zone's IANA name. Only the most widely used zones are covered, and not every zone has
+    /// names in every [`Locale`]; this is `None` when there isn't one (CLDR suggests falling back to the UTC
+    /// offset, see [`format_offset`][crate::format_offset]).
+    ///
+    /// ```
+    /// use libtz::{Timezone, Locale, NameStyle};
+    /// let data = std::fs::read("/usr/share/zoneinfo/Europe/Berlin").expect("read");
+    /// let tz = Timezone::from_tzif("Europe/Berlin", &data).expect("from_tzif");
+    /// assert_eq!(tz.display_name(Locale::De, NameStyle::Generic), Some("Mitteleuropäische Zeit"));
+    /// let data = std::fs::read("/usr/share/zoneinfo/America/Los_Angeles").expect("read");
+    /// let tz = Timezone::from_tzif("America/Los_Angeles", &data).expect("from_tzif");
+    /// assert_eq!(tz.display_name(Locale::En, NameStyle::Daylight), Some("Pacific Daylight Time"));
+    /// ```
+    pub fn display_name(&self, locale: Locale, style: NameStyle) -> Option<&'static str> {
+        #[cfg(feature = "std")]
+        return display_name(&self.provenance().name(), locale, style);
+        #[cfg(not(feature = "std"))]
+        return display_name(self.name(), locale, style);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_names() {
+        assert_eq!(display_name("Europe/London", Locale::En, NameStyle::Daylight), Some("British Summer Time"));
+        assert_eq!(display_name("Europe/London", Locale::De, NameStyle::Standard), Some("Mittlere Greenwich-Zeit"));
+        assert_eq!(display_name("right/Asia/Tokyo", Locale::Fr, NameStyle::Standard), Some("heure normale du Japon"));
+        assert_eq!(display_name("Asia/Kolkata", Locale::En, NameStyle::Generic), Some("India Standard Time"));
+        assert_eq!(display_name("Asia/Kolkata", Locale::En, NameStyle::Daylight), None);
+        assert_eq!(display_name("Asia/Kolkata", Locale::Ja, NameStyle::Standard), None);
+        assert_eq!(display_name("Antarctica/Troll", Locale::En, NameStyle::Standard), None);
+        for (zone, _) in METAZONES {
+            assert!(display_name(zone, Locale::En, NameStyle::Standard).is_some(), "{} has no English name", zone);
+        }
+        #[cfg(feature = "std")] {
+            let tz = Timezone::new("America/New_York").expect("timezone alloc");
+            assert_eq!(tz.display_name(Locale::En, NameStyle::Standard), Some("Eastern Standard Time"));
+            assert_eq!(Timezone::new("EST5EDT,M3.2.0,M11.1.0").expect("timezone alloc").display_name(Locale::En, NameStyle::Generic), None);
+        }
+    }
+}
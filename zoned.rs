@@ -0,0 +1,222 @@
+// A time together with its zone.
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::{Tm, TimeT, Timezone, TimeSpec, Resolve, MktimeError, Clock, CivilDuration, Date, TimeOfDay, Weekday, UtcOffset, CalendarUnit};
+
+/// An instant and the zone it's seen in, with its local time worked out: the batteries included way to handle
+/// times in application code, without juggling [`TimeT`]s, [`Tm`]s and [`Timezone`]s separately.
+///
+/// The zone is shared (an [`Arc`]), so cloning is cheap and many times can use one zone.
+///
+/// ```
+/// use libtz::{Timezone, ZonedDateTime};
+/// let tz = Timezone::new("America/New_York").unwrap();
+/// let t = ZonedDateTime::new(tz, 1700000000).unwrap();
+/// assert_eq!((t.year(), t.month(), t.day(), t.hour(), t.abbrev()), (2023, 11, 14, 17, "EST"));
+/// let later = t.add(&"P6M".parse().unwrap()).unwrap();
+/// assert_eq!(later.to_string(), "2024-05-14T17:13:20-04:00[America/New_York]");
+/// assert_eq!(later.with_timezone(Timezone::new("Asia/Tokyo").unwrap()).unwrap().strftime("%F %R %Z"), "2024-05-15 06:13 JST");
+/// ```
+#[derive(Clone)]
+pub struct ZonedDateTime {
+    tz: Arc<Timezone>,
+    time: TimeT,
+    tm: Tm,
+}
+
+impl ZonedDateTime {
+    /// The system time `time` in the zone `tz`.
+    pub fn new(tz: impl Into<Arc<Timezone>>, time: TimeT) -> Result<ZonedDateTime, String> {
+        let tz = tz.into();
+        let tm = tz.localtime(time)?;
+        Ok(ZonedDateTime{ tz, time, tm })
+    }
+
+    /// The local time `tm` in the zone `tz`. `resolve` decides what happens to repeated and skipped times, like
+    /// [`Timezone::mktime_resolve`].
+    pub fn from_local(tz: impl Into<Arc<Timezone>>, tm: &Tm, resolve: Resolve) -> Result<ZonedDateTime, MktimeError> {
+        let tz = tz.into();
+        let time = tz.mktime_resolve(tm, resolve)?;
+        ZonedDateTime::new(tz, time).map_err(|_| MktimeError::InvalidDate)
+    }
+
+    /// The current time in the zone `tz`, according to `clock`.
+    pub fn now(tz: impl Into<Arc<Timezone>>, clock: &impl Clock) -> Result<ZonedDateTime, String> {
+        ZonedDateTime::new(tz, clock.now())
+    }
+
+    /// Parse an RFC 9557 timestamp with a zone annotation, like `2024-03-10T03:30:00-07:00[America/Los_Angeles]`
+    /// (see [`parse_ixdtf`](crate::parse_ixdtf)). Fractions of a second are dropped.
+    pub fn parse(s: &str) -> Result<ZonedDateTime, String> {
+        let ixdtf = crate::parse_ixdtf(s)?;
+        let tz = ixdtf.zone.ok_or_else(|| format!("{:?} has no zone annotation", s))?;
+        ZonedDateTime::new(tz, ixdtf.time.sec)
+    }
+
+    /// The zone.
+    pub fn timezone(&self) -> &Arc<Timezone> { &self.tz }
+    /// The system time.
+    pub fn time(&self) -> TimeT { self.time }
+    /// The local time.
+    pub fn tm(&self) -> &Tm { &self.tm }
+
+    /// The year (astronomical numbering, like [`Date`]).
+    pub fn year(&self) -> i32 { self.tm.tm_year + 1900 }
+    /// The month [1, 12].
+    pub fn month(&self) -> i32 { self.tm.tm_mon + 1 }
+    /// The day of the month [1, 31].
+    pub fn day(&self) -> i32 { self.tm.tm_mday }
+    /// The hour [0, 23].
+    pub fn hour(&self) -> i32 { self.tm.tm_hour }
+    /// The minute [0, 59].
+    pub fn minute(&self) -> i32 { self.tm.tm_min }
+    /// The second [0, 60].
+    pub fn second(&self) -> i32 { self.tm.tm_sec }
+    /// The day of the week.
+    pub fn weekday(&self) -> Weekday { self.tm.weekday() }
+    /// The zone abbreviation (like `EST`).
+    pub fn abbrev(&self) -> &str { &self.tm.tm_zone }
+    /// Whether daylight saving time is in effect.
+    pub fn is_dst(&self) -> bool { self.tm.tm_isdst > 0 }
+
+    /// The UTC offset in effect. It's an error if it's outside [`UtcOffset`]'s range, which zones made from
+    /// POSIX TZ strings (like `XXX-100`) can be.
+    pub fn offset(&self) -> Result<UtcOffset, String> {
+        UtcOffset::new(self.tm.tm_gmtoff)
+    }
+
+    /// The local date.
+    pub fn date(&self) -> Date {
+        Date::try_from(&self.tm).expect("localtime gives valid dates")
+    }
+
+    /// The local time of day. A leap second is 23:59:59.
+    pub fn time_of_day(&self) -> TimeOfDay {
+        TimeOfDay::new(self.tm.tm_hour, self.tm.tm_min, self.tm.tm_sec.min(59)).expect("localtime gives valid times")
+    }
+
+    /// Format the local time like C's `strftime()` (see [`Tm::strftime`]).
+    pub fn strftime(&self, format: &str) -> String {
+        self.tm.strftime(format)
+    }
+
+    /// The same instant in another zone.
+    pub fn with_timezone(&self, tz: impl Into<Arc<Timezone>>) -> Result<ZonedDateTime, String> {
+        ZonedDateTime::new(tz, self.time)
+    }
+
+    /// Add a calendar duration, like [`Timezone::add_duration`].
+    pub fn add(&self, duration: &CivilDuration) -> Result<ZonedDateTime, String> {
+        ZonedDateTime::new(self.tz.clone(), self.tz.add_duration(self.time, duration)?)
+    }
+
+    /// Add `seconds` of elapsed time (subtract, if negative).
+    pub fn add_seconds(&self, seconds: i64) -> Result<ZonedDateTime, String> {
+        ZonedDateTime::new(self.tz.clone(), self.time.checked_add(seconds).ok_or("Time out of range")?)
+    }
+
+    /// The calendar difference from this time to `other`, in this zone, like [`Timezone::diff`].
+    pub fn until(&self, other: &ZonedDateTime) -> Result<CivilDuration, String> {
+        self.tz.diff(self.time, other.time)
+    }
+
+    /// The elapsed time from this time to `other`, or `None` if `other` is earlier.
+    pub fn elapsed_until(&self, other: &ZonedDateTime) -> Option<Duration> {
+        (other.time >= self.time).then(|| Duration::from_secs(other.time.abs_diff(self.time)))
+    }
+
+    /// The start of the local `unit` this time is in, like [`Timezone::truncate`].
+    pub fn truncate(&self, unit: CalendarUnit) -> Result<ZonedDateTime, String> {
+        ZonedDateTime::new(self.tz.clone(), self.tz.truncate(self.time, unit)?)
+    }
+
+    /// This time rounded to the nearest start of a local `unit`, like [`Timezone::round`].
+    pub fn round(&self, unit: CalendarUnit) -> Result<ZonedDateTime, String> {
+        ZonedDateTime::new(self.tz.clone(), self.tz.round(self.time, unit)?)
+    }
+}
+
+impl fmt::Debug for ZonedDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ZonedDateTime({})", self)
+    }
+}
+
+/// An RFC 9557 timestamp, from [`Timezone::to_ixdtf`].
+impl fmt::Display for ZonedDateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.tz.to_ixdtf(TimeSpec{ sec: self.time, nsec: 0 }).map_err(|_| fmt::Error)?)
+    }
+}
+
+impl std::str::FromStr for ZonedDateTime {
+    type Err = String;
+    fn from_str(s: &str) -> Result<ZonedDateTime, String> {
+        ZonedDateTime::parse(s)
+    }
+}
+
+/// Times are the same if they're the same instant, whatever their zones.
+impl PartialEq for ZonedDateTime {
+    fn eq(&self, other: &ZonedDateTime) -> bool {
+        self.time == other.time
+    }
+}
+
+impl Eq for ZonedDateTime {}
+
+impl PartialOrd for ZonedDateTime {
+    fn partial_cmp(&self, other: &ZonedDateTime) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ZonedDateTime {
+    fn cmp(&self, other: &ZonedDateTime) -> std::cmp::Ordering {
+        self.time.cmp(&other.time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FixedClock;
+
+    #[test]
+    fn zoned_date_time() {
+        let ny = Arc::new(Timezone::new("America/New_York").expect("timezone alloc"));
+        let t = ZonedDateTime::now(ny.clone(), &FixedClock(1700000000)).expect("now");
+        assert_eq!((t.year(), t.month(), t.day(), t.hour(), t.minute(), t.second()), (2023, 11, 14, 17, 13, 20));
+        assert_eq!((t.weekday(), t.abbrev(), t.is_dst(), t.offset().map(|o| o.to_string())), (Weekday::Tuesday, "EST", false, Ok("-05:00".to_string())));
+        assert_eq!((t.date().to_string(), t.time_of_day().to_string()), ("2023-11-14".to_string(), "17:13:20".to_string()));
+        assert!(Arc::ptr_eq(t.timezone(), &ny));
+        let far = ZonedDateTime::new(Timezone::new("XXX-100").expect("timezone alloc"), 0).expect("new");
+        assert_eq!((far.hour(), far.offset().is_err()), (4, true));
+
+        let parsed: ZonedDateTime = "2023-11-14T17:13:20-05:00[America/New_York]".parse().expect("parse");
+        assert_eq!(parsed, t);
+        assert_eq!(format!("{:?}", parsed), "ZonedDateTime(2023-11-14T17:13:20-05:00[America/New_York])");
+        assert!(ZonedDateTime::parse("2023-11-14T22:13:20Z").is_err()); // No zone
+
+        let tokyo = t.with_timezone(Timezone::new("Asia/Tokyo").expect("timezone alloc")).expect("with_timezone");
+        assert_eq!((tokyo.day(), tokyo.hour()), (15, 7));
+        assert_eq!(tokyo, t); // Same instant
+
+        // A day is a calendar day, even across the change from DST.
+        let before = ZonedDateTime::from_local(ny.clone(), &Tm::from_ymd_hms(2023, 11, 4, 12, 0, 0), Resolve::Error).expect("from_local");
+        let after = before.add(&"P1D".parse().expect("parse")).expect("add");
+        assert_eq!((after.hour(), before.elapsed_until(&after)), (12, Some(Duration::from_secs(25 * 3600))));
+        assert_eq!(before.until(&after).map(|d| d.format()), Ok("P1D".to_string()));
+        assert_eq!(after.elapsed_until(&before), None);
+        assert!(before < after);
+        assert_eq!(before.add_seconds(3600).map(|t| t.hour()), Ok(13));
+        assert_eq!(t.truncate(CalendarUnit::Day).map(|t| t.to_string()), Ok("2023-11-14T00:00:00-05:00[America/New_York]".to_string()));
+        assert_eq!(t.round(CalendarUnit::Hour).map(|t| t.hour()), Ok(17));
+        assert!(ZonedDateTime::from_local(ny, &Tm::from_ymd_hms(2024, 3, 10, 2, 30, 0), Resolve::Error).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+// The tz database's table of zones by country (zone1970.tab).
+//
+// Copyright © 2023 David Caldwell <david@porkrind.org>
+// License: MIT (see LICENSE.md file)
+
+use std::path::Path;
+use crate::timezone::TZDIR;
+
+/// A line of the tz database's `zone1970.tab`: a zone, where it's centered and the countries that use it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZoneTabEntry {
+    /** ISO 3166 alpha-2 country codes */           pub countries : Vec<String>,
+    /** Degrees north of the equator */             pub latitude  : f64,
+    /** Degrees east of Greenwich */                pub longitude : f64,
+    /** Zone name, like `Europe/Paris` */           pub zone      : String,
+    /** Which part of the countries it covers */    pub comment   : Option<String>,
+}
+
+// ±DDMM[SS] or ±DDDMM[SS], returned in degrees.
+fn parse_coordinate(s: &str, degree_digits: usize) -> Option<f64> {
+    let sign = match s.as_bytes().first()? { b'+' => 1.0, b'-' => -1.0, _ => return None };
+    let digits = &s[1..];
+    if !digits.bytes().all(|c| c.is_ascii_digit()) || !(digits.len() == degree_digits + 2 || digits.len() == degree_digits + 4) {
+        return None;
+    }
+    let n = |range: std::ops::Range<usize>| digits.get(range).map_or(0.0, |d| d.parse::<f64>().unwrap());
+    Some(sign * (n(0..degree_digits) + n(degree_digits..degree_digits + 2) / 60.0 + n(degree_digits + 2..degree_digits + 4) / 3600.0))
+}
+
+fn parse_line(line: &str) -> Result<ZoneTabEntry, String> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let (3 | 4) = fields.len() else { return Err("expected 3 or 4 tab separated fields".to_string()) };
+    let coordinates = fields[1];
+    let split = coordinates.get(1..).ok_or("bad coordinates")?.find(['+', '-']).map(|i| i + 1).ok_or("bad coordinates")?;
+    let (latitude, longitude) = (parse_coordinate(&coordinates[..split], 2).ok_or("bad latitude")?,
+                                 parse_coordinate(&coordinates[split..], 3).ok_or("bad longitude")?);
+    Ok(ZoneTabEntry{ countries: fields[0].split(',').map(str::to_string).collect(),
+                     latitude, longitude,
+                     zone: fields[2].to_string(),
+                     comment: fields.get(3).map(|c| c.to_string()) })
+}
+
+pub(crate) fn load(path: &Path) -> Result<Vec<ZoneTabEntry>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    text.lines().enumerate().filter(|(_, line)| !line.starts_with('#') && !line.is_empty())
+        .map(|(i, line)| parse_line(line).map_err(|e| format!("{}:{}: {}", path.display(), i + 1, e)))
+        .collect()
+}
+
+/// Read the system's table of zones by country (`/usr/share/zoneinfo/zone1970.tab`). This lists one zone for
+/// each region whose clocks have agreed since 1970, not every zone (it leaves out links and most zones that
+/// differ only before 1970).
+pub fn zone_tab() -> Result<Vec<ZoneTabEntry>, String> {
+    load(&Path::new(TZDIR).join("zone1970.tab"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse() {
+        let e = parse_line("AE,OM,RE,SC,TF\t+2518+05518\tAsia/Dubai\tCrozet").expect("parse");
+        assert_eq!(e, ZoneTabEntry{ countries: vec!["AE".into(), "OM".into(), "RE".into(), "SC".into(), "TF".into()],
+                                    latitude: 25.3, longitude: 55.3, zone: "Asia/Dubai".into(), comment: Some("Crozet".into()) });
+        let e = parse_line("US\t+404251-0740023\tAmerica/New_York\tEastern (most areas)").expect("parse");
+        assert!((e.latitude - 40.714167).abs() < 1e-6 && (e.longitude + 74.006389).abs() < 1e-6);
+        for bad in ["US", "US\t+4042\tAmerica/New_York", "US\t+4042-07400x\tAmerica/New_York", "US\t\tAmerica/New_York", "US\té+4042\tAmerica/New_York"] {
+            assert!(parse_line(bad).is_err(), "{:?} should fail", bad);
+        }
+
+        let tab = zone_tab().expect("zone_tab");
+        assert!(tab.iter().any(|e| e.zone == "Australia/Sydney" && e.countries.contains(&"AU".to_string())));
+    }
+}